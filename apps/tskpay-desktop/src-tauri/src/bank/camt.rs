@@ -0,0 +1,535 @@
+use super::xml::{child, children, find, text};
+use super::{assign_ids, normalize_iban, BankStatement, BankTransaction, ParsedTransaction, StatementBalance};
+use roxmltree::{Document, Node};
+
+/// Namespace prefix of ISO 20022 messages, followed by the message type, e.g. `camt.053.001.02`
+const ISO20022_NAMESPACE: &str = "urn:iso:std:iso:20022:tech:xsd:";
+/// Banks send this instead of leaving out an unknown end-to-end id
+pub(super) const NOT_PROVIDED: &str = "NOTPROVIDED";
+/// Balance type codes of the opening balance: booked, or the previous day's closing balance
+const OPENING_BALANCE_CODES: &[&str] = &["OPBD", "PRCD"];
+const CLOSING_BALANCE_CODE: &str = "CLBD";
+
+/// The three bank-to-customer cash management messages. Their entries (`Ntry`) share
+/// one structure; only the wrapping elements differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CamtMessage {
+    /// camt.052, intraday account report
+    Report,
+    /// camt.053, end-of-day statement
+    Statement,
+    /// camt.054, debit/credit notification
+    Notification,
+}
+
+impl CamtMessage {
+    const ALL: [CamtMessage; 3] = [CamtMessage::Report, CamtMessage::Statement, CamtMessage::Notification];
+
+    fn name(self) -> &'static str {
+        match self {
+            CamtMessage::Report => "camt.052",
+            CamtMessage::Statement => "camt.053",
+            CamtMessage::Notification => "camt.054",
+        }
+    }
+
+    /// Child of `Document` holding the message
+    fn message_element(self) -> &'static str {
+        match self {
+            CamtMessage::Report => "BkToCstmrAcctRpt",
+            CamtMessage::Statement => "BkToCstmrStmt",
+            CamtMessage::Notification => "BkToCstmrDbtCdtNtfctn",
+        }
+    }
+
+    /// Repeated per-account element inside the message
+    fn account_element(self) -> &'static str {
+        match self {
+            CamtMessage::Report => "Rpt",
+            CamtMessage::Statement => "Stmt",
+            CamtMessage::Notification => "Ntfctn",
+        }
+    }
+}
+
+fn missing(message_type: &str, path: &str) -> String {
+    format!("Datoteka ni veljaven izpisek {}: manjka element {}.", message_type, path)
+}
+
+fn entry_error(number: usize, message: &str) -> String {
+    format!("Napaka v postavki {} izpiska: {}.", number, message)
+}
+
+/// Message kind and full type of the document, e.g. `camt.053.001.02`, detected from
+/// the namespace. Files without a namespace are recognised by their message element.
+fn detect_message(root: Node) -> Result<(CamtMessage, String), String> {
+    if root.tag_name().name() != "Document" {
+        return Err(format!(
+            "Datoteka ni bančni izpisek ISO 20022 (korenski element je {}).",
+            root.tag_name().name()
+        ));
+    }
+    match root.tag_name().namespace() {
+        None => CamtMessage::ALL
+            .into_iter()
+            .find(|message| child(root, message.message_element()).is_some())
+            .map(|message| (message, message.name().to_string()))
+            .ok_or_else(|| "Datoteka ni bančni izpisek camt.052, camt.053 ali camt.054.".to_string()),
+        Some(namespace) => {
+            let message_type = namespace.strip_prefix(ISO20022_NAMESPACE).unwrap_or(namespace);
+            CamtMessage::ALL
+                .into_iter()
+                .find(|message| message_type.starts_with(message.name()))
+                .map(|message| (message, message_type.to_string()))
+                .ok_or_else(|| {
+                    format!("Datoteka ni bančni izpisek camt.052, camt.053 ali camt.054, ampak {}.", message_type)
+                })
+        }
+    }
+}
+
+/// Non-negative decimal such as `45.00`
+fn parse_decimal(raw: &str) -> Option<f64> {
+    let is_decimal = !raw.is_empty()
+        && raw.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && raw.chars().filter(|c| *c == '.').count() <= 1;
+    raw.parse::<f64>().ok().filter(|_| is_decimal)
+}
+
+fn parse_amount(node: Node, number: usize) -> Result<f64, String> {
+    let raw = node.text().unwrap_or("").trim();
+    parse_decimal(raw).ok_or_else(|| entry_error(number, &format!("neveljaven znesek \"{}\"", raw)))
+}
+
+/// Date of a `BookgDt`/`ValDt`/`Dt` element, from `Dt` or the date part of `DtTm`
+fn parse_date(parent: Node, name: &str) -> Result<Option<String>, String> {
+    let Some(node) = child(parent, name) else {
+        return Ok(None);
+    };
+    let Some(raw) = text(node, &["Dt"]).or_else(|| text(node, &["DtTm"])) else {
+        return Ok(None);
+    };
+    let date = raw.get(..10).unwrap_or(&raw);
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map(|_| Some(date.to_string()))
+        .map_err(|_| format!("neveljaven datum \"{}\" v {}", raw, name))
+}
+
+/// Sum of the charges on an entry or transaction; v2 puts amounts in `Chrgs/Amt`, v4 and later in `Chrgs/Rcrd/Amt`
+fn parse_charges(node: Node, number: usize) -> Result<Option<f64>, String> {
+    let mut amounts = Vec::new();
+    for charges in children(node, "Chrgs") {
+        amounts.extend(children(charges, "Amt"));
+        amounts.extend(children(charges, "Rcrd").filter_map(|record| child(record, "Amt")));
+    }
+    if amounts.is_empty() {
+        return Ok(None);
+    }
+    let mut total = 0.0;
+    for amount in amounts {
+        total += parse_amount(amount, number)?;
+    }
+    Ok(Some((total * 100.0).round() / 100.0))
+}
+
+/// The `Bal` of an account element with one of the given type codes, signed by `CdtDbtInd`
+fn parse_balance(account: Node, codes: &[&str]) -> Result<Option<StatementBalance>, String> {
+    let Some(balance) = children(account, "Bal").find(|balance| {
+        text(*balance, &["Tp", "CdOrPrtry", "Cd"]).is_some_and(|code| codes.contains(&code.as_str()))
+    }) else {
+        return Ok(None);
+    };
+    let amount_node = child(balance, "Amt").ok_or_else(|| "Napaka v stanju računa: manjka znesek (Amt).".to_string())?;
+    let raw = amount_node.text().unwrap_or("").trim();
+    let amount = parse_decimal(raw).ok_or_else(|| format!("Napaka v stanju računa: neveljaven znesek \"{}\".", raw))?;
+    let date = parse_date(balance, "Dt").map_err(|e| format!("Napaka v stanju računa: {}.", e))?;
+    Ok(Some(StatementBalance {
+        amount: if text(balance, &["CdtDbtInd"]).as_deref() == Some("DBIT") { -amount } else { amount },
+        currency: amount_node.attribute("Ccy").unwrap_or("EUR").to_string(),
+        date,
+    }))
+}
+
+/// Unstructured remittance lines joined, else the structured additional information
+fn parse_description(details: Node) -> Option<String> {
+    let remittance = child(details, "RmtInf")?;
+    let lines: Vec<String> = children(remittance, "Ustrd")
+        .filter_map(|line| line.text())
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+    if !lines.is_empty() {
+        return Some(lines.join(" "));
+    }
+    children(remittance, "Strd").find_map(|structured| text(structured, &["AddtlRmtInf"]))
+}
+
+/// Creditor reference (`SI12 ...`, `RF...`), else the end-to-end id
+fn parse_reference(details: Node) -> Option<String> {
+    child(details, "RmtInf")
+        .and_then(|remittance| {
+            children(remittance, "Strd").find_map(|structured| text(structured, &["CdtrRefInf", "Ref"]))
+        })
+        .or_else(|| text(details, &["Refs", "EndToEndId"]).filter(|id| id != NOT_PROVIDED))
+}
+
+/// One `Ntry`, split into a transaction per `TxDtls` for batch bookings
+fn parse_entry(entry: Node, number: usize, account_currency: Option<&str>) -> Result<Vec<ParsedTransaction>, String> {
+    let amount_node = child(entry, "Amt").ok_or_else(|| entry_error(number, "manjka znesek (Amt)"))?;
+    let amount = parse_amount(amount_node, number)?;
+    let currency = amount_node
+        .attribute("Ccy")
+        .or(account_currency)
+        .unwrap_or("EUR")
+        .to_string();
+    let is_credit = match text(entry, &["CdtDbtInd"]).as_deref() {
+        Some("CRDT") => true,
+        Some("DBIT") => false,
+        other => {
+            return Err(entry_error(
+                number,
+                &format!("neveljavna smer knjižbe (CdtDbtInd) \"{}\"", other.unwrap_or("")),
+            ))
+        }
+    };
+    let status = text(entry, &["Sts", "Cd"]).or_else(|| text(entry, &["Sts"]));
+    let value_date = parse_date(entry, "ValDt").map_err(|e| entry_error(number, &e))?;
+    let booking_date = parse_date(entry, "BookgDt")
+        .map_err(|e| entry_error(number, &e))?
+        .or_else(|| value_date.clone())
+        .ok_or_else(|| entry_error(number, "manjka datum knjiženja (BookgDt)"))?;
+    let entry_reference = text(entry, &["AcctSvcrRef"]);
+    let entry_fee = parse_charges(entry, number)?;
+
+    let details: Vec<Node> = children(entry, "NtryDtls")
+        .flat_map(|entry_details| children(entry_details, "TxDtls"))
+        .collect();
+    let transaction = BankTransaction {
+        id: String::new(),
+        bank_reference: entry_reference.clone(),
+        amount,
+        currency,
+        is_credit,
+        status,
+        booking_date,
+        value_date,
+        payer_name: None,
+        payer_iban: None,
+        description: text(entry, &["AddtlNtryInf"]),
+        reference: None,
+        bank_fee: entry_fee,
+    };
+    if details.is_empty() {
+        return Ok(vec![ParsedTransaction {
+            transaction,
+            id_reference: entry_reference,
+        }]);
+    }
+
+    let is_batch = details.len() > 1;
+    let (party, party_account) = if is_credit { ("Dbtr", "DbtrAcct") } else { ("Cdtr", "CdtrAcct") };
+    let mut parsed = Vec::with_capacity(details.len());
+    for (index, tx) in details.into_iter().enumerate() {
+        let amount = match child(tx, "Amt").or_else(|| find(tx, &["AmtDtls", "TxAmt", "Amt"])) {
+            Some(node) => parse_amount(node, number)?,
+            None if !is_batch => amount,
+            None => return Err(entry_error(number, "manjka znesek posamezne transakcije")),
+        };
+        let parties = child(tx, "RltdPties");
+        // camt.052.001.08 wraps the name in Pty
+        let payer_name = parties.and_then(|p| text(p, &[party, "Nm"]).or_else(|| text(p, &[party, "Pty", "Nm"])));
+        let payer_iban = parties
+            .and_then(|p| text(p, &[party_account, "Id", "IBAN"]))
+            .map(|iban| normalize_iban(&iban));
+        let own_reference = text(tx, &["Refs", "AcctSvcrRef"]);
+        let id_reference = match &own_reference {
+            Some(reference) => Some(reference.clone()),
+            None if is_batch => entry_reference.as_ref().map(|reference| format!("{}|{}", reference, index + 1)),
+            None => entry_reference.clone(),
+        };
+        // Entry-level charges of a batch go with its first transaction, so the total stays right
+        let bank_fee = match parse_charges(tx, number)? {
+            Some(fee) => Some(fee),
+            None if index == 0 => entry_fee,
+            None => None,
+        };
+
+        parsed.push(ParsedTransaction {
+            transaction: BankTransaction {
+                bank_reference: own_reference.or_else(|| entry_reference.clone()),
+                amount,
+                payer_name,
+                payer_iban,
+                description: parse_description(tx)
+                    .or_else(|| text(tx, &["AddtlTxInf"]))
+                    .or_else(|| transaction.description.clone()),
+                reference: parse_reference(tx),
+                bank_fee,
+                ..transaction.clone()
+            },
+            id_reference,
+        });
+    }
+    Ok(parsed)
+}
+
+/// Parse a camt.052 report, camt.053 statement or camt.054 notification; the message
+/// type and version are detected from the namespace.
+///
+/// Elements are matched by namespace, so default and prefixed namespaces of every
+/// version work. All entries are returned, credits and debits; a message split over
+/// several account elements is merged, as long as they are for one account. The
+/// opening balance comes from the first, the closing balance from the last one.
+pub fn parse_camt(content: &str) -> Result<BankStatement, String> {
+    let document = Document::parse(content).map_err(|e| format!("Datoteka ni veljaven XML: {}", e))?;
+    let root = document.root_element();
+    let (message, message_type) = detect_message(root)?;
+    let missing = |path: &str| missing(&message_type, path);
+
+    let body = child(root, message.message_element()).ok_or_else(|| missing(message.message_element()))?;
+    let message_id = text(body, &["GrpHdr", "MsgId"]).ok_or_else(|| missing("GrpHdr/MsgId"))?;
+    let creation_date_time = text(body, &["GrpHdr", "CreDtTm"]).ok_or_else(|| missing("GrpHdr/CreDtTm"))?;
+
+    let mut account_iban: Option<String> = None;
+    let mut account_owner = None;
+    let mut opening_balance = None;
+    let mut closing_balance = None;
+    let mut parsed = Vec::new();
+    let mut entry_number = 0;
+    for account in children(body, message.account_element()) {
+        let iban = text(account, &["Acct", "Id", "IBAN"])
+            .map(|iban| normalize_iban(&iban))
+            .ok_or_else(|| missing(&format!("{}/Acct/Id/IBAN", message.account_element())))?;
+        if let Some(first) = &account_iban {
+            if *first != iban {
+                return Err(format!("Datoteka vsebuje izpiske za več računov ({} in {}).", first, iban));
+            }
+        }
+        account_iban = Some(iban);
+        account_owner = account_owner.or_else(|| text(account, &["Acct", "Ownr", "Nm"]));
+        if opening_balance.is_none() {
+            opening_balance = parse_balance(account, OPENING_BALANCE_CODES)?;
+        }
+        closing_balance = parse_balance(account, &[CLOSING_BALANCE_CODE])?.or(closing_balance);
+
+        let account_currency = text(account, &["Acct", "Ccy"]);
+        for entry in children(account, "Ntry") {
+            entry_number += 1;
+            parsed.extend(parse_entry(entry, entry_number, account_currency.as_deref())?);
+        }
+    }
+    let account_iban = account_iban.ok_or_else(|| missing(message.account_element()))?;
+
+    Ok(BankStatement {
+        message_type,
+        message_id,
+        creation_date_time,
+        transactions: assign_ids(&account_iban, parsed),
+        account_iban,
+        account_owner,
+        opening_balance,
+        closing_balance,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V02_DEFAULT_NAMESPACE: &str =
+        include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/bank/camt052-v02-default-ns.xml"));
+    const V08_PREFIXED: &str =
+        include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/bank/camt052-v08-prefixed.xml"));
+    const STATEMENT: &str =
+        include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/bank/camt053-v02-statement.xml"));
+    const NOTIFICATION: &str =
+        include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/bank/camt054-v02-notification.xml"));
+    const TRUNCATED: &str =
+        include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/bank/camt052-truncated.xml"));
+
+    #[test]
+    fn parses_default_namespace_report() {
+        let statement = parse_camt(V02_DEFAULT_NAMESPACE).unwrap();
+        assert_eq!(statement.message_type, "camt.052.001.02");
+        assert_eq!(statement.message_id, "20250314-0000482915");
+        assert_eq!(statement.creation_date_time, "2025-03-14T16:05:12");
+        assert_eq!(statement.account_iban, "SI56020100012345678");
+        assert_eq!(statement.account_owner.as_deref(), Some("TENIŠKI KLUB SONČEK"));
+        assert_eq!(statement.transactions.len(), 3);
+
+        let first = &statement.transactions[0];
+        assert_eq!(first.bank_reference.as_deref(), Some("2025031400123456"));
+        assert_eq!(first.amount, 45.0);
+        assert_eq!(first.currency, "EUR");
+        assert!(first.is_credit);
+        assert_eq!(first.status.as_deref(), Some("BOOK"));
+        assert_eq!(first.booking_date, "2025-03-14");
+        assert_eq!(first.payer_name.as_deref(), Some("NOVAK MOJCA"));
+        assert_eq!(first.payer_iban.as_deref(), Some("SI56031001000012345"));
+        assert_eq!(first.description.as_deref(), Some("VADNINA MAREC LUKA NOVAK"));
+        assert_eq!(first.reference.as_deref(), Some("SI12 1000-2025031"));
+        assert_eq!(first.bank_fee, None);
+
+        let second = &statement.transactions[1];
+        assert_eq!(second.bank_reference, None);
+        assert_eq!(second.value_date.as_deref(), Some("2025-03-13"));
+        assert_eq!(second.payer_name.as_deref(), Some("Kovač Žiga in Špela"));
+        assert_eq!(second.description.as_deref(), Some("Članarina 2025 Eva Kovač"));
+        assert_eq!(second.reference, None, "NOTPROVIDED is not a reference");
+        assert_eq!(second.bank_fee, Some(0.39));
+
+        let debit = &statement.transactions[2];
+        assert!(!debit.is_credit);
+        assert_eq!(debit.payer_name.as_deref(), Some("Telekom d.d."));
+        assert_eq!(debit.payer_iban.as_deref(), Some("SI56290000055555555"));
+    }
+
+    #[test]
+    fn parses_prefixed_namespace_report() {
+        let statement = parse_camt(V08_PREFIXED).unwrap();
+        assert_eq!(statement.message_type, "camt.052.001.08");
+        assert_eq!(statement.account_iban, "SI56290000001234567");
+        assert_eq!(statement.transactions.len(), 5);
+
+        let first = &statement.transactions[0];
+        assert_eq!(first.booking_date, "2025-04-01");
+        assert_eq!(first.payer_name.as_deref(), Some("Horvat Ana"));
+        assert_eq!(first.description.as_deref(), Some("Priprave Poreč Tim Horvat"));
+        assert_eq!(first.reference.as_deref(), Some("RF18539007547034"));
+        assert_eq!(first.bank_fee, Some(0.25));
+        assert_eq!(statement.transactions[1].value_date, None);
+        assert_eq!(statement.transactions[1].booking_date, "2025-04-01");
+    }
+
+    #[test]
+    fn splits_batch_entries() {
+        let statement = parse_camt(V08_PREFIXED).unwrap();
+        let batch = &statement.transactions[3..];
+        assert_eq!(batch[0].amount, 100.0);
+        assert_eq!(batch[0].bank_reference.as_deref(), Some("BATCH-7781-1"));
+        assert_eq!(batch[0].description.as_deref(), Some("Sofinanciranje 1/2"));
+        assert_eq!(batch[1].amount, 50.0);
+        assert_eq!(batch[1].bank_reference.as_deref(), Some("BATCH-7781-2"));
+    }
+
+    #[test]
+    fn ids_are_deterministic_and_unique() {
+        let first = parse_camt(V08_PREFIXED).unwrap();
+        let again = parse_camt(V08_PREFIXED).unwrap();
+        let ids: Vec<&str> = first.transactions.iter().map(|t| t.id.as_str()).collect();
+        let ids_again: Vec<&str> = again.transactions.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, ids_again);
+
+        // Entries 2 and 3 are identical payments without a bank reference
+        let mut unique = ids.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), ids.len());
+        assert!(ids.iter().all(|id| id.starts_with("txn-") && id.len() == 28));
+    }
+
+    #[test]
+    fn id_of_referenced_entry_ignores_content() {
+        let statement = parse_camt(V02_DEFAULT_NAMESPACE).unwrap();
+        let edited = parse_camt(&V02_DEFAULT_NAMESPACE.replace("VADNINA MAREC", "VADNINA")).unwrap();
+        assert_eq!(statement.transactions[0].id, edited.transactions[0].id);
+    }
+
+    #[test]
+    fn repeated_reference_gets_its_own_id() {
+        // The pending entry is reported again once booked, with the same AcctSvcrRef
+        let start = V02_DEFAULT_NAMESPACE.find("<Ntry>").unwrap();
+        let end = V02_DEFAULT_NAMESPACE.find("</Ntry>").unwrap() + "</Ntry>".len();
+        let pending = V02_DEFAULT_NAMESPACE[start..end].replace("<Sts>BOOK</Sts>", "<Sts>PDNG</Sts>");
+        let report = format!("{}{}{}", &V02_DEFAULT_NAMESPACE[..start], pending, &V02_DEFAULT_NAMESPACE[start..]);
+
+        let original = parse_camt(V02_DEFAULT_NAMESPACE).unwrap();
+        let statement = parse_camt(&report).unwrap();
+        assert_eq!(statement.transactions[0].status.as_deref(), Some("PDNG"));
+        assert_eq!(statement.transactions[0].id, original.transactions[0].id);
+        assert_ne!(statement.transactions[1].id, statement.transactions[0].id);
+    }
+
+    #[test]
+    fn parses_statement_with_balances() {
+        let statement = parse_camt(STATEMENT).unwrap();
+        assert_eq!(statement.message_type, "camt.053.001.02");
+        assert_eq!(statement.message_id, "STMT-20250331-0042");
+        assert_eq!(statement.transactions.len(), 2);
+
+        let opening = statement.opening_balance.unwrap();
+        assert_eq!(opening.amount, 1250.4);
+        assert_eq!(opening.date.as_deref(), Some("2025-03-30"));
+        let closing = statement.closing_balance.unwrap();
+        assert_eq!(closing.amount, 1348.5);
+        assert_eq!(closing.currency, "EUR");
+
+        let fee = &statement.transactions[1];
+        assert!(!fee.is_credit);
+        assert_eq!(fee.description.as_deref(), Some("Nadomestilo za vodenje računa"));
+    }
+
+    #[test]
+    fn parses_notification() {
+        let statement = parse_camt(NOTIFICATION).unwrap();
+        assert_eq!(statement.message_type, "camt.054.001.02");
+        assert_eq!(statement.account_iban, "SI56020100012345678");
+        assert!(statement.opening_balance.is_none() && statement.closing_balance.is_none());
+
+        let credit = &statement.transactions[0];
+        assert_eq!(credit.amount, 60.0);
+        assert_eq!(credit.payer_name.as_deref(), Some("Mlakar Tomaž"));
+        assert_eq!(credit.payer_iban.as_deref(), Some("SI56330000001111222"));
+        assert_eq!(credit.reference.as_deref(), Some("E2E-4471-2025"));
+    }
+
+    #[test]
+    fn detects_message_without_namespace() {
+        let plain = STATEMENT.replace(" xmlns=\"urn:iso:std:iso:20022:tech:xsd:camt.053.001.02\"", "");
+        let statement = parse_camt(&plain).unwrap();
+        assert_eq!(statement.message_type, "camt.053");
+        assert_eq!(statement.transactions.len(), 2);
+    }
+
+    #[test]
+    fn signs_debit_balances() {
+        let overdrawn = STATEMENT.replacen(
+            "<Amt Ccy=\"EUR\">1348.50</Amt>\n        <CdtDbtInd>CRDT</CdtDbtInd>",
+            "<Amt Ccy=\"EUR\">1348.50</Amt>\n        <CdtDbtInd>DBIT</CdtDbtInd>",
+            1,
+        );
+        let statement = parse_camt(&overdrawn).unwrap();
+        assert_eq!(statement.closing_balance.unwrap().amount, -1348.5);
+    }
+
+    #[test]
+    fn rejects_malformed_xml() {
+        let error = parse_camt(TRUNCATED).unwrap_err();
+        assert!(error.starts_with("Datoteka ni veljaven XML"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_other_message_types() {
+        let pain = V02_DEFAULT_NAMESPACE.replace("camt.052.001.02", "pain.001.001.03");
+        let error = parse_camt(&pain).unwrap_err();
+        assert!(error.contains("pain.001.001.03"), "{}", error);
+
+        let error = parse_camt("<?xml version=\"1.0\"?><Invoice/>").unwrap_err();
+        assert!(error.contains("Invoice"), "{}", error);
+    }
+
+    #[test]
+    fn reports_invalid_amount_with_entry_number() {
+        let broken = V02_DEFAULT_NAMESPACE.replace(">90.00<", ">90,00<");
+        let error = parse_camt(&broken).unwrap_err();
+        assert_eq!(error, "Napaka v postavki 2 izpiska: neveljaven znesek \"90,00\".");
+    }
+
+    #[test]
+    fn reports_missing_account() {
+        let broken = V02_DEFAULT_NAMESPACE.replace("<IBAN>SI56020100012345678</IBAN>", "");
+        let error = parse_camt(&broken).unwrap_err();
+        assert!(error.contains("Rpt/Acct/Id/IBAN"), "{}", error);
+    }
+}
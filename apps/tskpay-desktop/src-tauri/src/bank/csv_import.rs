@@ -0,0 +1,419 @@
+use super::{assign_ids, normalize_iban, BankStatement, BankTransaction, ParsedTransaction, UnparsedLine};
+use serde::{Deserialize, Serialize};
+
+/// Rows shown in the preview when the caller doesn't ask for a number
+pub const DEFAULT_PREVIEW_ROWS: usize = 10;
+
+/// Which CSV column holds each transaction field: the header text, or the 1-based
+/// column number for files without a header row
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CsvColumns {
+    pub booking_date: Option<String>,
+    pub value_date: Option<String>,
+    /// Signed amount, debits negative. Leave empty when the bank uses separate credit and debit columns.
+    pub amount: Option<String>,
+    pub credit: Option<String>,
+    pub debit: Option<String>,
+    pub currency: Option<String>,
+    pub payer_name: Option<String>,
+    pub payer_iban: Option<String>,
+    pub description: Option<String>,
+    pub reference: Option<String>,
+    pub bank_reference: Option<String>,
+}
+
+/// How to read one bank's CSV export; saved profiles live in `csv_import_profiles`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CsvProfile {
+    /// `None` for a profile that has not been saved yet
+    pub id: Option<String>,
+    pub name: String,
+    pub bank: Option<String>,
+    /// IBAN of the exported account; the exports themselves rarely contain it
+    pub account_iban: String,
+    /// A single character, or `\t` for tab-separated files
+    pub delimiter: String,
+    /// Encoding label, e.g. `windows-1250` or `utf-8`
+    pub encoding: String,
+    /// `1.234,56` instead of `1,234.56`
+    pub decimal_comma: bool,
+    /// chrono format of the date columns, e.g. `%d.%m.%Y`
+    pub date_format: String,
+    /// Lines before the header row (or before the data, without a header)
+    pub skip_rows: usize,
+    pub has_header: bool,
+    pub columns: CsvColumns,
+}
+
+impl Default for CsvProfile {
+    fn default() -> Self {
+        Self {
+            id: None,
+            name: String::new(),
+            bank: None,
+            account_iban: String::new(),
+            delimiter: ";".to_string(),
+            encoding: "windows-1250".to_string(),
+            decimal_comma: true,
+            date_format: "%d.%m.%Y".to_string(),
+            skip_rows: 0,
+            has_header: true,
+            columns: CsvColumns::default(),
+        }
+    }
+}
+
+/// A CSV file read with a profile
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CsvStatement {
+    pub headers: Vec<String>,
+    /// Raw data rows, in file order
+    pub rows: Vec<Vec<String>>,
+    pub statement: BankStatement,
+    pub unparsed_lines: Vec<UnparsedLine>,
+}
+
+/// The first rows of a CSV file and how they would be imported, for checking a mapping
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CsvPreview {
+    pub source_path: String,
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    pub transactions: Vec<BankTransaction>,
+    pub total_rows: usize,
+    pub total_transactions: usize,
+    pub unparsed_lines: Vec<UnparsedLine>,
+}
+
+impl CsvStatement {
+    pub fn preview(self, source_path: String, limit: usize) -> CsvPreview {
+        CsvPreview {
+            source_path,
+            headers: self.headers,
+            total_rows: self.rows.len(),
+            rows: self.rows.into_iter().take(limit).collect(),
+            total_transactions: self.statement.transactions.len(),
+            transactions: self.statement.transactions.into_iter().take(limit).collect(),
+            unparsed_lines: self.unparsed_lines,
+        }
+    }
+}
+
+/// Decode a CSV file with the profile's encoding; a byte order mark overrides it
+pub fn decode_csv(bytes: &[u8], encoding: &str) -> Result<String, String> {
+    let encoding = encoding_rs::Encoding::for_label(encoding.trim().as_bytes())
+        .ok_or_else(|| format!("Neznano kodiranje znakov \"{}\".", encoding))?;
+    let (text, used, had_errors) = encoding.decode(bytes);
+    if had_errors {
+        return Err(format!("Datoteka ni v kodiranju {}.", used.name()));
+    }
+    Ok(text.into_owned())
+}
+
+fn delimiter_byte(delimiter: &str) -> Result<u8, String> {
+    match delimiter {
+        "\\t" | "\t" | "tab" => Ok(b'\t'),
+        d if d.len() == 1 => Ok(d.as_bytes()[0]),
+        _ => Err(format!("Ločilo \"{}\" ni en sam znak.", delimiter)),
+    }
+}
+
+/// Column index of a mapping: a header (case and whitespace insensitive), else a 1-based number
+fn resolve_column(headers: &[String], field: &str, column: &Option<String>) -> Result<Option<usize>, String> {
+    let Some(column) = column.as_deref().map(str::trim).filter(|c| !c.is_empty()) else {
+        return Ok(None);
+    };
+    if let Some(index) = headers.iter().position(|h| h.trim().eq_ignore_ascii_case(column)) {
+        return Ok(Some(index));
+    }
+    match column.parse::<usize>() {
+        Ok(number) if number >= 1 => Ok(Some(number - 1)),
+        _ => Err(format!("Stolpca \"{}\" za polje {} ni v datoteki.", column, field)),
+    }
+}
+
+struct ColumnIndexes {
+    booking_date: usize,
+    value_date: Option<usize>,
+    amount: Option<usize>,
+    credit: Option<usize>,
+    debit: Option<usize>,
+    currency: Option<usize>,
+    payer_name: Option<usize>,
+    payer_iban: Option<usize>,
+    description: Option<usize>,
+    reference: Option<usize>,
+    bank_reference: Option<usize>,
+}
+
+impl ColumnIndexes {
+    fn resolve(headers: &[String], columns: &CsvColumns) -> Result<Self, String> {
+        let resolve = |field: &str, column: &Option<String>| resolve_column(headers, field, column);
+        let indexes = Self {
+            booking_date: resolve("datum knjiženja", &columns.booking_date)?
+                .ok_or_else(|| "Profil nima določenega stolpca z datumom knjiženja.".to_string())?,
+            value_date: resolve("datum valute", &columns.value_date)?,
+            amount: resolve("znesek", &columns.amount)?,
+            credit: resolve("dobro", &columns.credit)?,
+            debit: resolve("breme", &columns.debit)?,
+            currency: resolve("valuta", &columns.currency)?,
+            payer_name: resolve("plačnik", &columns.payer_name)?,
+            payer_iban: resolve("IBAN plačnika", &columns.payer_iban)?,
+            description: resolve("namen", &columns.description)?,
+            reference: resolve("referenca", &columns.reference)?,
+            bank_reference: resolve("referenca banke", &columns.bank_reference)?,
+        };
+        if indexes.amount.is_none() && indexes.credit.is_none() && indexes.debit.is_none() {
+            return Err("Profil nima določenega stolpca z zneskom (znesek ali dobro/breme).".to_string());
+        }
+        Ok(indexes)
+    }
+}
+
+/// Amount in the profile's number format; `None` for an empty cell.
+/// Accepts thousands separators, currency symbols, and a leading or trailing minus.
+fn parse_amount(raw: &str, decimal_comma: bool) -> Result<Option<f64>, String> {
+    let mut value: String = raw
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '\u{a0}' && *c != '€')
+        .collect();
+    value = value.trim_end_matches("EUR").to_string();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    let negative = value.starts_with('-') || value.ends_with('-');
+    let digits = value.trim_matches('-');
+    let normalized = if decimal_comma {
+        digits.replace('.', "").replace(',', ".")
+    } else {
+        digits.replace(',', "")
+    };
+    let amount: f64 = normalized
+        .parse()
+        .map_err(|_| format!("neveljaven znesek \"{}\"", raw))?;
+    Ok(Some(if negative { -amount } else { amount }))
+}
+
+fn parse_date(raw: &str, format: &str) -> Result<String, String> {
+    let raw = raw.trim();
+    chrono::NaiveDate::parse_from_str(raw, format)
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(raw, format).map(|dt| dt.date()))
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .map_err(|_| format!("neveljaven datum \"{}\" (pričakovana oblika {})", raw, format))
+}
+
+fn cell(record: &[String], index: Option<usize>) -> Option<String> {
+    index
+        .and_then(|i| record.get(i))
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+fn parse_row(record: &[String], columns: &ColumnIndexes, profile: &CsvProfile) -> Result<BankTransaction, String> {
+    let booking_date = cell(record, Some(columns.booking_date)).ok_or_else(|| "manjka datum knjiženja".to_string())?;
+    let booking_date = parse_date(&booking_date, &profile.date_format)?;
+    let value_date = cell(record, columns.value_date)
+        .map(|date| parse_date(&date, &profile.date_format))
+        .transpose()?;
+    let amount = |index| {
+        cell(record, index)
+            .map(|raw| parse_amount(&raw, profile.decimal_comma))
+            .transpose()
+            .map(Option::flatten)
+    };
+
+    let (amount, is_credit) = match columns.amount {
+        Some(_) => {
+            let amount = amount(columns.amount)?.ok_or_else(|| "manjka znesek".to_string())?;
+            (amount.abs(), amount >= 0.0)
+        }
+        None => match (amount(columns.credit)?.filter(|a| *a != 0.0), amount(columns.debit)?.filter(|a| *a != 0.0)) {
+            (Some(credit), None) => (credit.abs(), true),
+            (None, Some(debit)) => (debit.abs(), false),
+            (None, None) => return Err("manjka znesek".to_string()),
+            (Some(_), Some(_)) => return Err("znesek je v stolpcu dobro in v stolpcu breme".to_string()),
+        },
+    };
+
+    Ok(BankTransaction {
+        id: String::new(),
+        bank_reference: cell(record, columns.bank_reference),
+        amount,
+        currency: cell(record, columns.currency).unwrap_or_else(|| "EUR".to_string()),
+        is_credit,
+        status: Some("BOOK".to_string()),
+        booking_date,
+        value_date,
+        payer_name: cell(record, columns.payer_name),
+        payer_iban: cell(record, columns.payer_iban).map(|iban| normalize_iban(&iban)),
+        description: cell(record, columns.description),
+        reference: cell(record, columns.reference),
+        bank_fee: None,
+    })
+}
+
+/// Read a decoded CSV export with a profile. Rows that can't be read (totals, notes,
+/// malformed values) are returned as unparsed lines instead of failing the file.
+pub fn parse_csv(text: &str, profile: &CsvProfile) -> Result<CsvStatement, String> {
+    let delimiter = delimiter_byte(&profile.delimiter)?;
+    let body: String = text
+        .split_inclusive('\n')
+        .skip(profile.skip_rows)
+        .collect();
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(body.as_bytes());
+
+    let mut records = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Napaka pri branju CSV datoteke: {}", e))?;
+        // The reader's line count lags behind on CRLF files, where a record's offset is the
+        // `\n` ending the previous line, so count line breaks up to and including that byte
+        let offset = record.position().map(|p| p.byte() as usize).unwrap_or(0);
+        let preceding = &body.as_bytes()[..(offset + 1).min(body.len())];
+        let line_number = preceding.iter().filter(|b| **b == b'\n').count() + 1 + profile.skip_rows;
+        let values: Vec<String> = record.iter().map(str::to_string).collect();
+        if values.iter().all(|v| v.trim().is_empty()) {
+            continue;
+        }
+        records.push((line_number, values));
+    }
+
+    let headers = if profile.has_header && !records.is_empty() {
+        records.remove(0).1.into_iter().map(|h| h.trim().to_string()).collect()
+    } else {
+        Vec::new()
+    };
+    let columns = ColumnIndexes::resolve(&headers, &profile.columns)?;
+
+    let mut parsed = Vec::new();
+    let mut unparsed_lines = Vec::new();
+    for (line_number, record) in &records {
+        match parse_row(record, &columns, profile) {
+            Ok(transaction) => parsed.push(ParsedTransaction {
+                id_reference: transaction.bank_reference.clone(),
+                transaction,
+            }),
+            Err(reason) => unparsed_lines.push(UnparsedLine {
+                line_number: *line_number,
+                text: record.join(&(delimiter as char).to_string()),
+                reason,
+            }),
+        }
+    }
+
+    let account_iban = normalize_iban(&profile.account_iban);
+    let last_date = parsed.iter().map(|p| p.transaction.booking_date.clone()).max();
+    let name = profile.bank.as_deref().unwrap_or(&profile.name);
+    Ok(CsvStatement {
+        headers,
+        rows: records.into_iter().map(|(_, record)| record).collect(),
+        statement: BankStatement {
+            message_type: if name.is_empty() { "CSV".to_string() } else { format!("CSV {}", name) },
+            message_id: String::new(),
+            creation_date_time: last_date.unwrap_or_default(),
+            transactions: assign_ids(&account_iban, parsed),
+            account_iban,
+            account_owner: None,
+            opening_balance: None,
+            closing_balance: None,
+        },
+        unparsed_lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(columns: CsvColumns) -> CsvProfile {
+        CsvProfile {
+            name: "Test".to_string(),
+            account_iban: "SI56 0201 0001 2345 678".to_string(),
+            columns,
+            ..CsvProfile::default()
+        }
+    }
+
+    #[test]
+    fn reads_windows_1250_export_with_credit_and_debit_columns() {
+        let text = "Promet na računu;;;;;\r\n\
+                    Datum;Valuta;Plačnik;IBAN;Namen;Dobro;Breme\r\n\
+                    14.03.2025;14.03.2025;NOVAK MOJCA;SI56 0310 0100 0012 345;\"Vadnina; marec\";45,00;\r\n\
+                    14.03.2025;14.03.2025;Telekom d.d.;;Račun;;12,80\r\n\
+                    Skupaj;;;;;45,00;12,80\r\n";
+        let (bytes, _, _) = encoding_rs::WINDOWS_1250.encode(text);
+        let decoded = decode_csv(&bytes, "windows-1250").unwrap();
+
+        let mut profile = profile(CsvColumns {
+            booking_date: Some("datum".to_string()),
+            value_date: Some("Valuta".to_string()),
+            payer_name: Some("Plačnik".to_string()),
+            payer_iban: Some("IBAN".to_string()),
+            description: Some("Namen".to_string()),
+            credit: Some("Dobro".to_string()),
+            debit: Some("Breme".to_string()),
+            ..CsvColumns::default()
+        });
+        profile.skip_rows = 1;
+        let csv = parse_csv(&decoded, &profile).unwrap();
+
+        assert_eq!(csv.headers[2], "Plačnik");
+        assert_eq!(csv.rows.len(), 3);
+        let transactions = &csv.statement.transactions;
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[0].booking_date, "2025-03-14");
+        assert_eq!(transactions[0].amount, 45.0);
+        assert!(transactions[0].is_credit);
+        assert_eq!(transactions[0].payer_iban.as_deref(), Some("SI56031001000012345"));
+        assert_eq!(transactions[0].description.as_deref(), Some("Vadnina; marec"));
+        assert!(!transactions[1].is_credit);
+        assert_eq!(csv.statement.account_iban, "SI56020100012345678");
+
+        assert_eq!(csv.unparsed_lines.len(), 1);
+        assert_eq!(csv.unparsed_lines[0].line_number, 5);
+    }
+
+    #[test]
+    fn reads_signed_amounts_by_column_number() {
+        let text = "2025-04-01,\"1,250.00\",Horvat Ana,SI00 1000-0042\n2025-04-02,-3.50,Provizija,\n";
+        let mut profile = profile(CsvColumns {
+            booking_date: Some("1".to_string()),
+            amount: Some("2".to_string()),
+            payer_name: Some("3".to_string()),
+            reference: Some("4".to_string()),
+            ..CsvColumns::default()
+        });
+        profile.delimiter = ",".to_string();
+        profile.decimal_comma = false;
+        profile.date_format = "%Y-%m-%d".to_string();
+        profile.has_header = false;
+
+        let csv = parse_csv(text, &profile).unwrap();
+        let transactions = &csv.statement.transactions;
+        assert_eq!(transactions[0].amount, 1250.0);
+        assert_eq!(transactions[0].reference.as_deref(), Some("SI00 1000-0042"));
+        assert_eq!(transactions[1].amount, 3.5);
+        assert!(!transactions[1].is_credit);
+        assert_ne!(transactions[0].id, transactions[1].id);
+    }
+
+    #[test]
+    fn rejects_unknown_columns_and_encodings() {
+        let error = parse_csv("Datum;Znesek\n", &profile(CsvColumns {
+            booking_date: Some("Datum".to_string()),
+            amount: Some("Iznos".to_string()),
+            ..CsvColumns::default()
+        }))
+        .unwrap_err();
+        assert!(error.contains("Iznos"), "{}", error);
+        assert!(decode_csv(b"a;b", "klingon").is_err());
+    }
+}
@@ -0,0 +1,768 @@
+use super::{decode_reference, normalize_iban, stored_references, ReferenceKind};
+use crate::database::{record_change, ChangeEvent};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Candidates scoring below this are not offered
+const MIN_SCORE: u32 = 20;
+const HIGH_SCORE: u32 = 70;
+const MEDIUM_SCORE: u32 = 40;
+/// A runner-up this close makes the best candidate one confidence level less certain
+const AMBIGUOUS_MARGIN: u32 = 10;
+/// Name similarity from which a payer name counts as the same person
+const NAME_THRESHOLD: f64 = 0.75;
+
+const PARENT_IBAN_POINTS: u32 = 60;
+const KNOWN_IBAN_POINTS: u32 = 50;
+/// A structured reference with valid check digits, issued for the parent or one of their costs
+const ISSUED_REFERENCE_POINTS: u32 = 80;
+const KNOWN_REFERENCE_POINTS: u32 = 40;
+const PARENT_NAME_POINTS: f64 = 40.0;
+const MEMBER_NAME_POINTS: f64 = 30.0;
+const LAST_NAME_POINTS: u32 = 15;
+const CHILD_FULL_NAME_POINTS: u32 = 30;
+const CHILD_FIRST_NAME_POINTS: u32 = 10;
+const OPEN_COST_POINTS: u32 = 20;
+const OPEN_TOTAL_POINTS: u32 = 15;
+
+/// The values `bank_transactions.match_confidence` allows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchConfidence {
+    Low,
+    Medium,
+    High,
+}
+
+impl MatchConfidence {
+    fn from_score(score: u32) -> Self {
+        match score {
+            s if s >= HIGH_SCORE => MatchConfidence::High,
+            s if s >= MEDIUM_SCORE => MatchConfidence::Medium,
+            _ => MatchConfidence::Low,
+        }
+    }
+
+    fn lowered(self) -> Self {
+        match self {
+            MatchConfidence::High => MatchConfidence::Medium,
+            _ => MatchConfidence::Low,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MatchConfidence::High => "high",
+            MatchConfidence::Medium => "medium",
+            MatchConfidence::Low => "low",
+        }
+    }
+}
+
+/// A parent a bank transaction may come from, with the evidence for it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchCandidate {
+    pub parent_id: String,
+    pub parent_name: String,
+    /// The child the payment is most likely for, if the evidence points to one
+    pub member_id: Option<String>,
+    pub member_name: Option<String>,
+    /// An open cost whose amount the payment equals
+    pub cost_id: Option<String>,
+    /// 0–100
+    pub score: u32,
+    pub confidence: MatchConfidence,
+    /// One sentence per piece of evidence, for the review screen
+    pub reasons: Vec<String>,
+}
+
+/// Outcome of matching stored transactions
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchRun {
+    /// Transactions that got a parent
+    pub matched: usize,
+    /// Transactions without a candidate, left for manual matching
+    pub unmatched: usize,
+}
+
+/// The fields of a bank transaction the engine looks at
+#[derive(Debug, Clone, Default)]
+pub struct MatchInput {
+    pub amount: f64,
+    pub payer_name: String,
+    pub payer_iban: String,
+    pub description: String,
+    pub reference: Option<String>,
+}
+
+struct ParentRecord {
+    id: String,
+    first_name: String,
+    last_name: String,
+    iban: Option<String>,
+}
+
+struct MemberRecord {
+    id: String,
+    first_name: String,
+    last_name: String,
+    parent_ids: Vec<String>,
+}
+
+struct OpenCost {
+    id: String,
+    member_id: String,
+    title: String,
+    /// Amount less what is already allocated, in cents
+    open_cents: i64,
+}
+
+/// A cost with a payment reference of its own
+struct ReferencedCost {
+    id: String,
+    member_id: String,
+    title: String,
+}
+
+/// Everything the engine compares transactions with, read once per run
+pub struct MatchData {
+    parents: Vec<ParentRecord>,
+    members: Vec<MemberRecord>,
+    open_costs: Vec<OpenCost>,
+    /// Payer IBANs of confirmed transactions, with the parent they were confirmed for
+    known_ibans: HashMap<String, String>,
+    /// References of confirmed transactions and payments, with their parent
+    known_references: HashMap<String, String>,
+    /// Numbers of issued parent references, see [`decode_reference`]
+    parent_references: HashMap<u64, String>,
+    /// Numbers of issued cost references
+    cost_references: HashMap<u64, ReferencedCost>,
+}
+
+fn cents(amount: f64) -> i64 {
+    (amount * 100.0).round() as i64
+}
+
+/// Lower case without diacritics, so `Kovač` and `KOVAC` compare equal
+fn fold_char(c: char) -> char {
+    match c {
+        'č' | 'ć' | 'ç' => 'c',
+        'š' | 'ś' => 's',
+        'ž' | 'ź' | 'ż' => 'z',
+        'đ' | 'ď' => 'd',
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ă' | 'ą' => 'a',
+        'è' | 'é' | 'ê' | 'ë' | 'ě' | 'ę' => 'e',
+        'ì' | 'í' | 'î' | 'ï' => 'i',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ő' => 'o',
+        'ù' | 'ú' | 'û' | 'ü' | 'ů' | 'ű' => 'u',
+        'ñ' | 'ň' | 'ń' => 'n',
+        'ý' | 'ÿ' => 'y',
+        'ł' | 'ľ' | 'ĺ' => 'l',
+        'ř' | 'ŕ' => 'r',
+        'ť' => 't',
+        'ß' => 's',
+        c => c,
+    }
+}
+
+/// Words of `text` folded to lower-case ASCII letters and digits, see [`fold_char`]
+pub fn name_tokens(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .chars()
+        .map(fold_char)
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .filter(|token| token.chars().count() >= 2)
+        .map(str::to_string)
+        .collect()
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// 1 for equal words, falling towards 0 with every edit
+fn token_similarity(a: &str, b: &str) -> f64 {
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 0.0;
+    }
+    1.0 - levenshtein(a, b) as f64 / longest as f64
+}
+
+/// How well `text` contains the name made of `name` words, in any order: the average of
+/// each name word's best similarity to a word of the text
+pub fn name_similarity(text: &[String], name: &[String]) -> f64 {
+    if text.is_empty() || name.is_empty() {
+        return 0.0;
+    }
+    let total: f64 = name
+        .iter()
+        .map(|word| text.iter().map(|t| token_similarity(t, word)).fold(0.0, f64::max))
+        .sum();
+    total / name.len() as f64
+}
+
+fn percent(similarity: f64) -> u32 {
+    (similarity * 100.0).round() as u32
+}
+
+/// Read parents, their children, open costs and confirmed matches
+pub fn load_match_data(conn: &Connection) -> rusqlite::Result<MatchData> {
+    let mut stmt = conn.prepare("SELECT id, first_name, last_name, iban FROM parents")?;
+    let parents = stmt
+        .query_map([], |row| {
+            Ok(ParentRecord {
+                id: row.get(0)?,
+                first_name: row.get(1)?,
+                last_name: row.get(2)?,
+                iban: row.get::<_, Option<String>>(3)?.map(|iban| normalize_iban(&iban)).filter(|iban| !iban.is_empty()),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut links: HashMap<String, Vec<String>> = HashMap::new();
+    let mut stmt = conn.prepare(
+        "SELECT member_id, parent_id FROM member_parents
+         UNION SELECT id, parent_id FROM members",
+    )?;
+    for link in stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))? {
+        let (member_id, parent_id) = link?;
+        links.entry(member_id).or_default().push(parent_id);
+    }
+    let mut stmt = conn.prepare("SELECT id, first_name, last_name FROM members WHERE status != 'archived'")?;
+    let members = stmt
+        .query_map([], |row| {
+            Ok(MemberRecord {
+                id: row.get(0)?,
+                first_name: row.get(1)?,
+                last_name: row.get(2)?,
+                parent_ids: Vec::new(),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .into_iter()
+        .map(|member| MemberRecord {
+            parent_ids: links.remove(&member.id).unwrap_or_default(),
+            ..member
+        })
+        .collect();
+
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.member_id, c.title, c.amount - COALESCE(SUM(pa.allocated_amount), 0)
+         FROM costs c
+         LEFT JOIN payment_allocations pa ON pa.cost_id = c.id
+         WHERE c.status = 'pending'
+         GROUP BY c.id",
+    )?;
+    let open_costs = stmt
+        .query_map([], |row| {
+            Ok(OpenCost {
+                id: row.get(0)?,
+                member_id: row.get(1)?,
+                title: row.get(2)?,
+                open_cents: cents(row.get(3)?),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .into_iter()
+        .filter(|cost| cost.open_cents > 0)
+        .collect();
+
+    let mut known_ibans = HashMap::new();
+    let mut known_references = HashMap::new();
+    let mut stmt = conn.prepare(
+        "SELECT account_number, reference, matched_parent_id FROM bank_transactions
+         WHERE status = 'confirmed' AND matched_parent_id IS NOT NULL
+         ORDER BY transaction_date",
+    )?;
+    for row in stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, String>(2)?))
+    })? {
+        let (account, reference, parent_id) = row?;
+        let iban = normalize_iban(&account);
+        if !iban.is_empty() {
+            known_ibans.insert(iban, parent_id.clone());
+        }
+        if let Some(reference) = reference.map(|r| normalize_iban(&r)).filter(|r| !r.is_empty()) {
+            known_references.insert(reference, parent_id);
+        }
+    }
+    let mut stmt = conn.prepare(
+        "SELECT reference_number, parent_id FROM payments
+         WHERE reference_number IS NOT NULL AND reference_number != '' AND parent_id IS NOT NULL",
+    )?;
+    for row in stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))? {
+        let (reference, parent_id) = row?;
+        known_references.entry(normalize_iban(&reference)).or_insert(parent_id);
+    }
+
+    let parent_references = stored_references(conn, ReferenceKind::Parent)?.into_iter().collect();
+    let mut stmt = conn.prepare("SELECT id, member_id, title, payment_reference FROM costs WHERE payment_reference IS NOT NULL")?;
+    let cost_references = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(3)?,
+                ReferencedCost {
+                    id: row.get(0)?,
+                    member_id: row.get(1)?,
+                    title: row.get(2)?,
+                },
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .into_iter()
+        .filter_map(|(reference, cost)| {
+            let decoded = decode_reference(&reference).filter(|decoded| decoded.kind == ReferenceKind::Cost)?;
+            Some((decoded.number, cost))
+        })
+        .collect();
+
+    Ok(MatchData {
+        parents,
+        members,
+        open_costs,
+        known_ibans,
+        known_references,
+        parent_references,
+        cost_references,
+    })
+}
+
+/// Evidence gathered for one parent
+#[derive(Default)]
+struct Evidence {
+    score: u32,
+    reasons: Vec<String>,
+    /// Child the evidence points to, with how strongly
+    member: Option<(String, u32)>,
+    cost_id: Option<String>,
+}
+
+impl Evidence {
+    fn add(&mut self, points: u32, reason: String) {
+        self.score += points;
+        self.reasons.push(reason);
+    }
+
+    fn point_to_member(&mut self, member_id: &str, points: u32) {
+        if self.member.as_ref().is_none_or(|(_, best)| points > *best) {
+            self.member = Some((member_id.to_string(), points));
+        }
+    }
+}
+
+/// Score every parent against a transaction and return those with enough evidence,
+/// best first. Signals: the parent's IBAN or an IBAN confirmed for them before, an SI12
+/// or RF reference issued to them or one of their costs (check digits validated), else a
+/// reference used by them before, the payer name against parent and child names
+/// (ignoring diacritics and word order), child names in the description, and an amount
+/// equal to one open cost or to everything the parent owes.
+pub fn rank_candidates(data: &MatchData, input: &MatchInput) -> Vec<MatchCandidate> {
+    let mut evidence: BTreeMap<&str, Evidence> = BTreeMap::new();
+    let payer_iban = normalize_iban(&input.payer_iban);
+    let payer_tokens = name_tokens(&input.payer_name);
+    let description_tokens = name_tokens(&input.description);
+    let amount_cents = cents(input.amount);
+
+    for parent in &data.parents {
+        let entry = evidence.entry(parent.id.as_str()).or_default();
+        if !payer_iban.is_empty() && parent.iban.as_deref() == Some(payer_iban.as_str()) {
+            entry.add(PARENT_IBAN_POINTS, format!("IBAN plačnika {} je IBAN starša.", payer_iban));
+        } else if !payer_iban.is_empty() && data.known_ibans.get(&payer_iban) == Some(&parent.id) {
+            entry.add(KNOWN_IBAN_POINTS, format!("Z IBAN {} je ta starš že plačal.", payer_iban));
+        }
+
+        let full_name = name_tokens(&format!("{} {}", parent.first_name, parent.last_name));
+        let similarity = name_similarity(&payer_tokens, &full_name);
+        if similarity >= NAME_THRESHOLD {
+            entry.add(
+                (PARENT_NAME_POINTS * similarity).round() as u32,
+                format!(
+                    "Ime plačnika »{}« se ujema z imenom starša {} {} ({} %).",
+                    input.payer_name.trim(),
+                    parent.first_name,
+                    parent.last_name,
+                    percent(similarity)
+                ),
+            );
+        } else if name_similarity(&payer_tokens, &name_tokens(&parent.last_name)) >= 0.9 {
+            entry.add(
+                LAST_NAME_POINTS,
+                format!("Ime plačnika »{}« vsebuje priimek {}.", input.payer_name.trim(), parent.last_name),
+            );
+        }
+    }
+
+    let decoded = input.reference.as_deref().and_then(decode_reference);
+    match decoded {
+        Some(decoded) if decoded.kind == ReferenceKind::Parent => {
+            if let Some(entry) = data
+                .parent_references
+                .get(&decoded.number)
+                .and_then(|parent_id| evidence.get_mut(parent_id.as_str()))
+            {
+                let reference = input.reference.as_deref().unwrap_or_default().trim();
+                entry.add(ISSUED_REFERENCE_POINTS, format!("Sklic {} je sklic tega starša.", reference));
+            }
+        }
+        Some(decoded) => {
+            if let Some(cost) = data.cost_references.get(&decoded.number) {
+                let reference = input.reference.as_deref().unwrap_or_default().trim();
+                let parent_ids = data
+                    .members
+                    .iter()
+                    .find(|member| member.id == cost.member_id)
+                    .map(|member| member.parent_ids.as_slice())
+                    .unwrap_or_default();
+                for parent_id in parent_ids {
+                    if let Some(entry) = evidence.get_mut(parent_id.as_str()) {
+                        entry.add(
+                            ISSUED_REFERENCE_POINTS,
+                            format!("Sklic {} je sklic stroška »{}«.", reference, cost.title),
+                        );
+                        entry.cost_id = Some(cost.id.clone());
+                        entry.point_to_member(&cost.member_id, ISSUED_REFERENCE_POINTS);
+                    }
+                }
+            }
+        }
+        None => {}
+    }
+
+    if let Some(reference) = input.reference.as_deref().map(normalize_iban).filter(|r| !r.is_empty() && decoded.is_none()) {
+        if let Some(entry) = data
+            .known_references
+            .get(&reference)
+            .and_then(|parent_id| evidence.get_mut(parent_id.as_str()))
+        {
+            entry.add(KNOWN_REFERENCE_POINTS, format!("Sklic {} je ta starš že uporabil.", reference));
+        }
+    }
+
+    for member in &data.members {
+        let member_name = format!("{} {}", member.first_name, member.last_name);
+        let full_name = name_tokens(&member_name);
+        let payer_similarity = name_similarity(&payer_tokens, &full_name);
+        let first_name = name_tokens(&member.first_name);
+        let in_description = name_similarity(&description_tokens, &full_name);
+        for parent_id in &member.parent_ids {
+            let Some(entry) = evidence.get_mut(parent_id.as_str()) else {
+                continue;
+            };
+            if payer_similarity >= NAME_THRESHOLD {
+                let points = (MEMBER_NAME_POINTS * payer_similarity).round() as u32;
+                entry.add(
+                    points,
+                    format!(
+                        "Ime plačnika »{}« se ujema z imenom člana {} ({} %).",
+                        input.payer_name.trim(),
+                        member_name,
+                        percent(payer_similarity)
+                    ),
+                );
+                entry.point_to_member(&member.id, points);
+            }
+            if in_description >= 0.85 {
+                entry.add(CHILD_FULL_NAME_POINTS, format!("Namen plačila omenja člana {}.", member_name));
+                entry.point_to_member(&member.id, CHILD_FULL_NAME_POINTS);
+            } else if first_name.first().is_some_and(|first| first.len() >= 3 && description_tokens.contains(first)) {
+                entry.add(CHILD_FIRST_NAME_POINTS, format!("Namen plačila omenja ime {}.", member.first_name));
+                entry.point_to_member(&member.id, CHILD_FIRST_NAME_POINTS);
+            }
+        }
+    }
+
+    let parents: HashMap<&str, &ParentRecord> = data.parents.iter().map(|p| (p.id.as_str(), p)).collect();
+    let members: HashMap<&str, &MemberRecord> = data.members.iter().map(|m| (m.id.as_str(), m)).collect();
+    for (parent_id, entry) in evidence.iter_mut() {
+        // Many parents owe the same amounts, so an amount only backs up other evidence
+        if entry.score == 0 {
+            continue;
+        }
+        let costs: Vec<&OpenCost> = data
+            .open_costs
+            .iter()
+            .filter(|cost| {
+                members
+                    .get(cost.member_id.as_str())
+                    .is_some_and(|member| member.parent_ids.iter().any(|id| id == parent_id))
+            })
+            .collect();
+        // Prefer a cost of the child the other evidence points to
+        let equal_cost = costs.iter().filter(|cost| cost.open_cents == amount_cents).max_by_key(|cost| {
+            (
+                entry.cost_id.as_deref() == Some(cost.id.as_str()),
+                entry.member.as_ref().is_some_and(|(member_id, _)| *member_id == cost.member_id),
+            )
+        });
+        if let Some(cost) = equal_cost {
+            let member_name = members
+                .get(cost.member_id.as_str())
+                .map(|m| format!("{} {}", m.first_name, m.last_name))
+                .unwrap_or_default();
+            entry.add(
+                OPEN_COST_POINTS,
+                format!("Znesek je enak odprtemu strošku »{}« ({}).", cost.title, member_name),
+            );
+            // A cost named by the reference stays, even if another one has this amount
+            entry.cost_id.get_or_insert_with(|| cost.id.clone());
+            entry.point_to_member(&cost.member_id, OPEN_COST_POINTS);
+        } else if costs.len() > 1 && costs.iter().map(|cost| cost.open_cents).sum::<i64>() == amount_cents {
+            entry.add(OPEN_TOTAL_POINTS, "Znesek je enak vsem odprtim stroškom starša.".to_string());
+        }
+    }
+
+    let mut candidates: Vec<MatchCandidate> = evidence
+        .into_iter()
+        .filter(|(_, entry)| entry.score >= MIN_SCORE)
+        .filter_map(|(parent_id, entry)| {
+            let parent = parents.get(parent_id)?;
+            let score = entry.score.min(100);
+            let member = entry.member.and_then(|(member_id, _)| members.get(member_id.as_str()).copied());
+            Some(MatchCandidate {
+                parent_id: parent.id.clone(),
+                parent_name: format!("{} {}", parent.first_name, parent.last_name),
+                member_id: member.map(|m| m.id.clone()),
+                member_name: member.map(|m| format!("{} {}", m.first_name, m.last_name)),
+                cost_id: entry.cost_id,
+                score,
+                confidence: MatchConfidence::from_score(score),
+                reasons: entry.reasons,
+            })
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.parent_name.cmp(&b.parent_name)));
+
+    if let [best, runner_up, ..] = candidates.as_mut_slice() {
+        if best.score - runner_up.score < AMBIGUOUS_MARGIN {
+            best.confidence = best.confidence.lowered();
+        }
+    }
+    candidates
+}
+
+fn read_input(conn: &Connection, transaction_id: &str) -> rusqlite::Result<Option<MatchInput>> {
+    conn.query_row(
+        "SELECT amount, payer_name, account_number, description, reference FROM bank_transactions WHERE id = ?1",
+        [transaction_id],
+        |row| {
+            Ok(MatchInput {
+                amount: row.get(0)?,
+                payer_name: row.get(1)?,
+                payer_iban: row.get(2)?,
+                description: row.get(3)?,
+                reference: row.get(4)?,
+            })
+        },
+    )
+    .optional()
+}
+
+/// Ranked candidates for a stored transaction
+pub fn transaction_candidates(conn: &Connection, transaction_id: &str) -> Result<Vec<MatchCandidate>, String> {
+    let input = read_input(conn, transaction_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Bančna transakcija ne obstaja.".to_string())?;
+    let data = load_match_data(conn).map_err(|e| e.to_string())?;
+    Ok(rank_candidates(&data, &input))
+}
+
+/// Match the unmatched transactions of a statement, or of all statements, and write the
+/// best candidate to `matched_parent_id`/`match_confidence`. Confirmed transactions and
+/// ones already matched by hand are left alone.
+pub fn match_transactions(
+    conn: &Connection,
+    statement_id: Option<&str>,
+) -> rusqlite::Result<(MatchRun, Vec<ChangeEvent>)> {
+    let data = load_match_data(conn)?;
+    let mut stmt = conn.prepare(
+        "SELECT id, bank_statement_id FROM bank_transactions
+         WHERE status = 'unmatched' AND (?1 IS NULL OR bank_statement_id = ?1)
+         ORDER BY transaction_date, id",
+    )?;
+    let pending = stmt
+        .query_map([statement_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut run = MatchRun::default();
+    let mut matched_ids = Vec::new();
+    let mut statements: Vec<String> = Vec::new();
+    for (id, statement) in pending {
+        let Some(input) = read_input(conn, &id)? else {
+            continue;
+        };
+        match rank_candidates(&data, &input).into_iter().next() {
+            Some(best) => {
+                conn.execute(
+                    "UPDATE bank_transactions SET matched_parent_id = ?2, match_confidence = ?3, status = 'matched'
+                     WHERE id = ?1",
+                    params![id, best.parent_id, best.confidence.as_str()],
+                )?;
+                run.matched += 1;
+                matched_ids.push(id);
+                if !statements.contains(&statement) {
+                    statements.push(statement);
+                }
+            }
+            None => run.unmatched += 1,
+        }
+    }
+
+    let mut changes = Vec::new();
+    if !matched_ids.is_empty() {
+        for statement in &statements {
+            conn.execute(
+                "UPDATE bank_statements SET
+                    matched_transactions = (SELECT COUNT(*) FROM bank_transactions WHERE bank_statement_id = ?1 AND status != 'unmatched'),
+                    unmatched_transactions = (SELECT COUNT(*) FROM bank_transactions WHERE bank_statement_id = ?1 AND status = 'unmatched')
+                 WHERE id = ?1",
+                [statement],
+            )?;
+        }
+        changes.push(record_change(conn, "bank_transactions", "update", &matched_ids)?);
+        changes.push(record_change(conn, "bank_statements", "update", &statements)?);
+    }
+    Ok((run, changes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../database/schema.sql")).unwrap();
+        conn.execute_batch(
+            "INSERT INTO coaches (id, name) VALUES ('coach-1', 'Trener');
+             INSERT INTO groups (id, name, coach_id) VALUES ('grp-1', 'Začetniki', 'coach-1');
+             INSERT INTO cost_types (id, name) VALUES ('ct-1', 'Vadnina');
+             INSERT INTO parents (id, first_name, last_name, iban) VALUES
+                ('par-novak', 'Mojca', 'Novak', 'SI56 0310 0100 0012 345'),
+                ('par-kovac', 'Žiga', 'Kovač', NULL),
+                ('par-horvat', 'Ana', 'Horvat', NULL);
+             INSERT INTO members (id, first_name, last_name, date_of_birth, status, parent_id, group_id) VALUES
+                ('mem-luka', 'Luka', 'Novak', '2015-04-02', 'active', 'par-novak', 'grp-1'),
+                ('mem-eva', 'Eva', 'Kovač', '2016-09-12', 'active', 'par-kovac', 'grp-1'),
+                ('mem-tim', 'Tim', 'Horvat', '2014-01-20', 'active', 'par-horvat', 'grp-1');
+             INSERT INTO costs (id, member_id, title, amount, cost_type_id, status) VALUES
+                ('cost-eva', 'mem-eva', 'Vadnina marec', 45, 'ct-1', 'pending'),
+                ('cost-tim', 'mem-tim', 'Vadnina marec', 45, 'ct-1', 'pending');
+             UPDATE parents SET payment_reference = 'SI12 10000429' WHERE id = 'par-horvat';
+             UPDATE costs SET payment_reference = 'RF06 2000 007' WHERE id = 'cost-eva';",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn input(payer_name: &str, payer_iban: &str, description: &str, amount: f64) -> MatchInput {
+        MatchInput {
+            amount,
+            payer_name: payer_name.to_string(),
+            payer_iban: payer_iban.to_string(),
+            description: description.to_string(),
+            reference: None,
+        }
+    }
+
+    #[test]
+    fn compares_names_without_diacritics_or_word_order() {
+        assert_eq!(name_tokens("KOVAČ ŽIGA in Špela"), ["kovac", "ziga", "in", "spela"]);
+        let similarity = name_similarity(&name_tokens("KOVAC ZIGA"), &name_tokens("Žiga Kovač"));
+        assert_eq!(similarity, 1.0);
+        let typo = name_similarity(&name_tokens("NOVAK MOJCKA"), &name_tokens("Mojca Novak"));
+        assert!(typo > NAME_THRESHOLD && typo < 1.0, "{}", typo);
+        assert!(name_similarity(&name_tokens("HORVAT ANA"), &name_tokens("Mojca Novak")) < 0.5);
+    }
+
+    #[test]
+    fn ranks_iban_above_name_and_explains() {
+        let data = load_match_data(&database()).unwrap();
+        let candidates = rank_candidates(&data, &input("M. NOVAK", "SI56031001000012345", "VADNINA LUKA NOVAK", 45.0));
+        let best = &candidates[0];
+        assert_eq!(best.parent_id, "par-novak");
+        assert_eq!(best.member_id.as_deref(), Some("mem-luka"));
+        assert_eq!(best.confidence, MatchConfidence::High);
+        assert!(best.reasons.iter().any(|r| r.contains("IBAN")), "{:?}", best.reasons);
+        assert!(best.reasons.iter().any(|r| r.contains("Luka Novak")), "{:?}", best.reasons);
+    }
+
+    #[test]
+    fn uses_child_names_and_open_costs() {
+        let data = load_match_data(&database()).unwrap();
+        let candidates = rank_candidates(&data, &input("KOVAC ZIGA IN SPELA", "", "Clanarina Eva", 45.0));
+        let best = &candidates[0];
+        assert_eq!(best.parent_id, "par-kovac");
+        assert_eq!(best.member_id.as_deref(), Some("mem-eva"));
+        assert_eq!(best.cost_id.as_deref(), Some("cost-eva"));
+
+        // An amount alone is not evidence
+        let candidates = rank_candidates(&data, &input("NEZNANI PLACNIK", "", "", 45.0));
+        assert!(candidates.is_empty(), "{:?}", candidates);
+    }
+
+    #[test]
+    fn decodes_issued_references() {
+        let data = load_match_data(&database()).unwrap();
+        let mut paid = input("NEZNANI PLACNIK", "", "", 30.0);
+        paid.reference = Some("SI12 1000-0429".to_string());
+        let candidates = rank_candidates(&data, &paid);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].parent_id, "par-horvat");
+        assert_eq!(candidates[0].confidence, MatchConfidence::High);
+
+        paid.reference = Some("RF062000007".to_string());
+        let best = &rank_candidates(&data, &paid)[0];
+        assert_eq!(best.parent_id, "par-kovac");
+        assert_eq!(best.member_id.as_deref(), Some("mem-eva"));
+        assert_eq!(best.cost_id.as_deref(), Some("cost-eva"));
+
+        // A wrong check digit is not the parent's reference
+        paid.reference = Some("SI12 10000428".to_string());
+        assert!(rank_candidates(&data, &paid).is_empty());
+    }
+
+    #[test]
+    fn writes_best_candidate_to_unmatched_transactions() {
+        let conn = database();
+        conn.execute_batch(
+            "INSERT INTO bank_statements (id, file_name, file_type, total_transactions, unmatched_transactions)
+                VALUES ('stmt-1', 'a.xml', 'xml', 2, 2);
+             INSERT INTO bank_transactions (id, bank_statement_id, transaction_date, amount, description, account_number, payer_name)
+                VALUES ('txn-1', 'stmt-1', '2025-03-14', 45, 'Vadnina Tim', '', 'Ana Horvat'),
+                       ('txn-2', 'stmt-1', '2025-03-14', 12, 'Donacija', '', 'Neznan');",
+        )
+        .unwrap();
+        let (run, changes) = match_transactions(&conn, Some("stmt-1")).unwrap();
+        assert_eq!((run.matched, run.unmatched), (1, 1));
+        assert_eq!(changes.len(), 2);
+
+        let (parent, confidence): (String, String) = conn
+            .query_row(
+                "SELECT matched_parent_id, match_confidence FROM bank_transactions WHERE id = 'txn-1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(parent, "par-horvat");
+        assert_eq!(confidence, "high");
+        let matched: i64 = conn
+            .query_row("SELECT matched_transactions FROM bank_statements WHERE id = 'stmt-1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(matched, 1);
+    }
+}
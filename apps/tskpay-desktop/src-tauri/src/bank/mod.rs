@@ -0,0 +1,435 @@
+mod camt;
+mod csv_import;
+mod matching;
+mod mt940;
+mod pdf;
+mod reference;
+mod store;
+mod xml;
+pub use camt::*;
+pub use csv_import::*;
+pub use matching::*;
+pub use mt940::*;
+pub use pdf::*;
+pub use reference::*;
+pub use store::*;
+
+use crate::database::{ask_open_path, check_input_path, emit_change, get_connection};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// A bank statement, report or notification, as read from a statement file
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BankStatement {
+    /// Message type from the file's namespace, e.g. `camt.053.001.02`, `MT940`, or `PDF <bank>` / `CSV <bank>` for other formats
+    pub message_type: String,
+    pub message_id: String,
+    pub creation_date_time: String,
+    /// IBAN of the club's account, without spaces
+    pub account_iban: String,
+    pub account_owner: Option<String>,
+    pub transactions: Vec<BankTransaction>,
+    /// Balance at the start of the period, kept for reconciliation
+    pub opening_balance: Option<StatementBalance>,
+    pub closing_balance: Option<StatementBalance>,
+}
+
+/// An account balance; negative when the account is overdrawn
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatementBalance {
+    pub amount: f64,
+    pub currency: String,
+    pub date: Option<String>,
+}
+
+/// One booked movement on the account
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BankTransaction {
+    /// Derived from the bank reference or, when the bank sends none, from the
+    /// transaction's content, so importing the same file twice yields the same ids
+    pub id: String,
+    /// The bank's own reference (`AcctSvcrRef`), when present
+    pub bank_reference: Option<String>,
+    /// Always positive; the direction is in `is_credit`
+    pub amount: f64,
+    pub currency: String,
+    pub is_credit: bool,
+    /// Entry status, e.g. `BOOK` or `PDNG`
+    pub status: Option<String>,
+    /// `YYYY-MM-DD`
+    pub booking_date: String,
+    pub value_date: Option<String>,
+    /// The other party: the payer of a credit, the payee of a debit
+    pub payer_name: Option<String>,
+    pub payer_iban: Option<String>,
+    pub description: Option<String>,
+    /// Payment reference, e.g. `SI12 1000-2025031` or `RF18539007547034`
+    pub reference: Option<String>,
+    pub bank_fee: Option<f64>,
+}
+
+/// `txn-` followed by the first 24 hex digits of the SHA-256 of `key`
+fn transaction_id(key: &str) -> String {
+    let digest = hex::encode(Sha256::digest(key.as_bytes()));
+    format!("txn-{}", &digest[..24])
+}
+
+/// A parsed transaction with the bank reference its id is derived from, if any
+struct ParsedTransaction {
+    transaction: BankTransaction,
+    id_reference: Option<String>,
+}
+
+/// Give every transaction its id. Transactions with a bank reference are keyed on it;
+/// the rest on their content plus a counter, so identical payments on the same day
+/// still get distinct ids that stay the same when the file is imported again. A reference
+/// repeated in the file, e.g. a pending entry followed by the booked one, is counted too.
+fn assign_ids(account_iban: &str, parsed: Vec<ParsedTransaction>) -> Vec<BankTransaction> {
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    parsed
+        .into_iter()
+        .map(|ParsedTransaction { mut transaction, id_reference }| {
+            let key = match id_reference {
+                Some(reference) => {
+                    let key = format!("ref|{}|{}", account_iban, reference);
+                    let occurrence = occurrences.entry(key.clone()).or_insert(0);
+                    *occurrence += 1;
+                    match *occurrence {
+                        1 => key,
+                        n => format!("{}|{}", key, n),
+                    }
+                }
+                None => {
+                    let content = format!(
+                        "content|{}|{}|{:.2}|{}|{}|{}|{}|{}",
+                        account_iban,
+                        transaction.booking_date,
+                        transaction.amount,
+                        if transaction.is_credit { "C" } else { "D" },
+                        transaction.payer_name.as_deref().unwrap_or(""),
+                        transaction.payer_iban.as_deref().unwrap_or(""),
+                        transaction.description.as_deref().unwrap_or(""),
+                        transaction.reference.as_deref().unwrap_or(""),
+                    );
+                    let occurrence = occurrences.entry(content.clone()).or_insert(0);
+                    *occurrence += 1;
+                    format!("{}|{}", content, occurrence)
+                }
+            };
+            transaction.id = transaction_id(&key);
+            transaction
+        })
+        .collect()
+}
+
+/// IBAN without spaces, upper case
+fn normalize_iban(iban: &str) -> String {
+    iban.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_uppercase()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// SHA-256 of the file's bytes, stored with the statement to recognise a repeated import
+fn file_sha256(path: &Path) -> Result<String, String> {
+    let bytes = fs::read(path)
+        .map_err(|e| format!("Napaka pri branju datoteke {}: {}", path.display(), e))?;
+    Ok(hex::encode(Sha256::digest(&bytes)))
+}
+
+/// Store a statement read from `path`, see [`import_statement`]
+fn import_file(app: &AppHandle, path: &Path, file_type: &str, statement: &BankStatement) -> Result<StatementImport, String> {
+    import_statement(app, &file_name(path), file_type, &file_sha256(path)?, statement)
+}
+
+/// Read a statement file as UTF-8 text, dropping a byte order mark
+pub fn read_statement_file(path: &Path) -> Result<String, String> {
+    check_input_path(path)?;
+    let bytes = fs::read(path)
+        .map_err(|e| format!("Napaka pri branju datoteke {}: {}", path.display(), e))?;
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&bytes);
+    String::from_utf8(bytes.to_vec())
+        .map_err(|_| format!("Datoteka {} ni v kodiranju UTF-8.", path.display()))
+}
+
+/// Parse the ISO 20022 bank file at `path`, see [`parse_camt`]
+pub fn parse_camt_file(path: &Path) -> Result<BankStatement, String> {
+    let content = read_statement_file(path)?;
+    parse_camt(&content)
+}
+
+async fn ask_camt_path(app: &AppHandle) -> Result<PathBuf, String> {
+    ask_open_path(
+        app,
+        "Izberi bančni izpisek (camt.052, camt.053, camt.054)",
+        &[("XML", &["xml"]), ("All Files", &["*"])],
+        "Uvoz je bil preklican.",
+    )
+    .await
+}
+
+/// Parse a user-selected camt.052/053/054 file without importing it
+#[tauri::command]
+pub async fn bank_parse_xml(app: AppHandle) -> Result<BankStatement, String> {
+    let file_path = ask_camt_path(&app).await?;
+    parse_camt_file(&file_path)
+}
+
+/// Parse the camt.052/053/054 file at an explicit path without importing it
+#[tauri::command]
+pub fn bank_parse_xml_from(path: String) -> Result<BankStatement, String> {
+    parse_camt_file(Path::new(&path))
+}
+
+/// Read the text of the PDF statement at `path` and parse it, see [`parse_pdf_text`]
+pub fn parse_pdf_file(path: &Path) -> Result<PdfStatement, String> {
+    check_input_path(path)?;
+    let bytes = fs::read(path)
+        .map_err(|e| format!("Napaka pri branju datoteke {}: {}", path.display(), e))?;
+    parse_pdf_text(&extract_pdf_text(&bytes)?)
+}
+
+async fn ask_pdf_path(app: &AppHandle) -> Result<PathBuf, String> {
+    ask_open_path(
+        app,
+        "Izberi bančni izpisek (PDF)",
+        &[("PDF", &["pdf"]), ("All Files", &["*"])],
+        "Uvoz je bil preklican.",
+    )
+    .await
+}
+
+/// Store a parsed PDF statement; unparsed lines are passed on for review
+fn import_pdf_statement(app: &AppHandle, path: &Path, pdf: PdfStatement) -> Result<StatementImport, String> {
+    let mut summary = import_file(app, path, "pdf", &pdf.statement)?;
+    summary.unparsed_lines = pdf.unparsed_lines;
+    Ok(summary)
+}
+
+/// Import a user-selected camt.052/053/054 file, see [`import_statement`]
+#[tauri::command]
+pub async fn bank_import_xml(app: AppHandle) -> Result<StatementImport, String> {
+    let file_path = ask_camt_path(&app).await?;
+    let statement = parse_camt_file(&file_path)?;
+    import_file(&app, &file_path, "xml", &statement)
+}
+
+/// Import the camt.052/053/054 file at an explicit path, without a dialog
+#[tauri::command]
+pub fn bank_import_xml_from(app: AppHandle, path: String) -> Result<StatementImport, String> {
+    let path = Path::new(&path);
+    let statement = parse_camt_file(path)?;
+    import_file(&app, path, "xml", &statement)
+}
+
+/// Parse a user-selected PDF statement without importing it
+#[tauri::command]
+pub async fn bank_parse_pdf(app: AppHandle) -> Result<PdfStatement, String> {
+    let file_path = ask_pdf_path(&app).await?;
+    parse_pdf_file(&file_path)
+}
+
+/// Parse the PDF statement at an explicit path without importing it
+#[tauri::command]
+pub fn bank_parse_pdf_from(path: String) -> Result<PdfStatement, String> {
+    parse_pdf_file(Path::new(&path))
+}
+
+/// Import a user-selected PDF statement
+#[tauri::command]
+pub async fn bank_import_pdf(app: AppHandle) -> Result<StatementImport, String> {
+    let file_path = ask_pdf_path(&app).await?;
+    let pdf = parse_pdf_file(&file_path)?;
+    import_pdf_statement(&app, &file_path, pdf)
+}
+
+/// Import the PDF statement at an explicit path, without a dialog
+#[tauri::command]
+pub fn bank_import_pdf_from(app: AppHandle, path: String) -> Result<StatementImport, String> {
+    let path = Path::new(&path);
+    let pdf = parse_pdf_file(path)?;
+    import_pdf_statement(&app, path, pdf)
+}
+
+/// Read the MT940 file at `path` and parse it, see [`parse_mt940`]. Banks write these
+/// in UTF-8 or, for the Slovenian letters, Windows-1250.
+pub fn parse_mt940_file(path: &Path) -> Result<BankStatement, String> {
+    check_input_path(path)?;
+    let bytes = fs::read(path)
+        .map_err(|e| format!("Napaka pri branju datoteke {}: {}", path.display(), e))?;
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&bytes);
+    let content = match std::str::from_utf8(bytes) {
+        Ok(content) => content.to_string(),
+        Err(_) => encoding_rs::WINDOWS_1250.decode(bytes).0.into_owned(),
+    };
+    parse_mt940(&content)
+}
+
+async fn ask_mt940_path(app: &AppHandle) -> Result<PathBuf, String> {
+    ask_open_path(
+        app,
+        "Izberi bančni izpisek (MT940)",
+        &[("MT940", &["sta", "mt940", "940", "txt"]), ("All Files", &["*"])],
+        "Uvoz je bil preklican.",
+    )
+    .await
+}
+
+/// Parse a user-selected MT940 file without importing it
+#[tauri::command]
+pub async fn bank_parse_mt940(app: AppHandle) -> Result<BankStatement, String> {
+    let file_path = ask_mt940_path(&app).await?;
+    parse_mt940_file(&file_path)
+}
+
+/// Parse the MT940 file at an explicit path without importing it
+#[tauri::command]
+pub fn bank_parse_mt940_from(path: String) -> Result<BankStatement, String> {
+    parse_mt940_file(Path::new(&path))
+}
+
+/// Import a user-selected MT940 file, see [`import_statement`]
+#[tauri::command]
+pub async fn bank_import_mt940(app: AppHandle) -> Result<StatementImport, String> {
+    let file_path = ask_mt940_path(&app).await?;
+    let statement = parse_mt940_file(&file_path)?;
+    import_file(&app, &file_path, "mt940", &statement)
+}
+
+/// Import the MT940 file at an explicit path, without a dialog
+#[tauri::command]
+pub fn bank_import_mt940_from(app: AppHandle, path: String) -> Result<StatementImport, String> {
+    let path = Path::new(&path);
+    let statement = parse_mt940_file(path)?;
+    import_file(&app, path, "mt940", &statement)
+}
+
+/// Read the CSV export at `path` with a column-mapping profile, see [`parse_csv`]
+pub fn parse_csv_file(path: &Path, profile: &CsvProfile) -> Result<CsvStatement, String> {
+    check_input_path(path)?;
+    let bytes = fs::read(path)
+        .map_err(|e| format!("Napaka pri branju datoteke {}: {}", path.display(), e))?;
+    parse_csv(&decode_csv(&bytes, &profile.encoding)?, profile)
+}
+
+async fn ask_csv_path(app: &AppHandle) -> Result<PathBuf, String> {
+    ask_open_path(
+        app,
+        "Izberi bančni izpisek (CSV)",
+        &[("CSV", &["csv", "txt"]), ("All Files", &["*"])],
+        "Uvoz je bil preklican.",
+    )
+    .await
+}
+
+/// Show the first rows of a CSV export and the transactions the profile reads from them.
+/// Without a path the user picks the file; pass the returned `sourcePath` to [`bank_import_csv`].
+#[tauri::command]
+pub async fn bank_csv_preview(
+    app: AppHandle,
+    path: Option<String>,
+    profile: CsvProfile,
+    limit: Option<usize>,
+) -> Result<CsvPreview, String> {
+    let file_path = match path {
+        Some(path) => PathBuf::from(path),
+        None => ask_csv_path(&app).await?,
+    };
+    let csv = parse_csv_file(&file_path, &profile)?;
+    Ok(csv.preview(file_path.display().to_string(), limit.unwrap_or(DEFAULT_PREVIEW_ROWS)))
+}
+
+/// Import the CSV export at `path` with a column-mapping profile
+#[tauri::command]
+pub fn bank_import_csv(app: AppHandle, path: String, profile: CsvProfile) -> Result<StatementImport, String> {
+    if normalize_iban(&profile.account_iban).is_empty() {
+        return Err("Profil mora imeti IBAN računa, s katerega je izvoz.".to_string());
+    }
+    let path = Path::new(&path);
+    let csv = parse_csv_file(path, &profile)?;
+    let mut summary = import_file(&app, path, "csv", &csv.statement)?;
+    summary.unparsed_lines = csv.unparsed_lines;
+    Ok(summary)
+}
+
+/// Saved CSV column-mapping profiles
+#[tauri::command]
+pub fn bank_csv_profiles(app: AppHandle) -> Result<Vec<CsvProfile>, String> {
+    let conn = get_connection(&app).map_err(|e| e.to_string())?;
+    list_csv_profiles(&conn).map_err(|e| e.to_string())
+}
+
+/// Save a new or changed CSV profile and return it as stored
+#[tauri::command]
+pub fn bank_csv_profile_save(app: AppHandle, profile: CsvProfile) -> Result<CsvProfile, String> {
+    let conn = get_connection(&app).map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let (saved, change) = save_csv_profile(&tx, &profile)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    emit_change(&app, &change);
+    Ok(saved)
+}
+
+#[tauri::command]
+pub fn bank_csv_profile_delete(app: AppHandle, id: String) -> Result<(), String> {
+    let conn = get_connection(&app).map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let change = delete_csv_profile(&tx, &id).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    emit_change(&app, &change);
+    Ok(())
+}
+
+/// Parents a stored bank transaction may come from, best first, with the reasons for each
+#[tauri::command]
+pub fn bank_match_candidates(
+    app: AppHandle,
+    transaction_id: String,
+    limit: Option<usize>,
+) -> Result<Vec<MatchCandidate>, String> {
+    let conn = get_connection(&app).map_err(|e| e.to_string())?;
+    let mut candidates = transaction_candidates(&conn, &transaction_id)?;
+    candidates.truncate(limit.unwrap_or(5));
+    Ok(candidates)
+}
+
+/// Give parents without a payment reference, and with `include_costs` open costs without
+/// one, the next free SI12 or RF reference
+#[tauri::command]
+pub fn bank_assign_references(
+    app: AppHandle,
+    model: ReferenceModel,
+    include_costs: bool,
+) -> Result<ReferenceAssignment, String> {
+    let conn = get_connection(&app).map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let (assignment, changes) = assign_references(&tx, model, include_costs).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    for change in &changes {
+        emit_change(&app, change);
+    }
+    Ok(assignment)
+}
+
+/// Match the unmatched transactions of one statement, or of all statements when no id is given
+#[tauri::command]
+pub fn bank_match_transactions(app: AppHandle, statement_id: Option<String>) -> Result<MatchRun, String> {
+    let conn = get_connection(&app).map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let (run, changes) = match_transactions(&tx, statement_id.as_deref()).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    for change in &changes {
+        emit_change(&app, change);
+    }
+    Ok(run)
+}
@@ -0,0 +1,475 @@
+use super::camt::NOT_PROVIDED;
+use super::{assign_ids, normalize_iban, BankStatement, BankTransaction, ParsedTransaction, StatementBalance};
+use chrono::{Datelike, NaiveDate};
+use regex::Regex;
+
+/// `:61:` statement line: value date, optional entry date, debit/credit mark, optional
+/// funds code, amount, transaction type, customer reference and optional `//` bank reference
+const STATEMENT_LINE: &str = r"^(\d{6})(\d{4})?(RC|RD|C|D)([A-Z])?(\d[\d,]*)([NSF][A-Z0-9]{3})(.*)$";
+/// `:60F:`/`:62F:` balance: mark, date, currency and amount
+const BALANCE: &str = r"^([CD])(\d{6})([A-Z]{3})(\d[\d,]*)$";
+/// Keywords of `/KEY/value` style `:86:` fields
+const SLASH_KEYWORDS: &[&str] = &[
+    "ORDP", "BENM", "NAME", "ADDR", "IBAN", "ACCT", "BIC", "REF", "EREF", "CREF", "KREF", "CDTRREF", "REMI", "PURP",
+    "ULTD", "ULTC", "MARF", "CSID", "RTRN", "TRCD",
+];
+/// Keywords of SEPA purpose text in `?20`–`?29`, e.g. `EREF+SI12 1000-2025031SVWZ+Vadnina`
+const SEPA_KEYWORDS: &[&str] = &["EREF", "KREF", "MREF", "CRED", "DEBT", "SVWZ", "ABWA", "ABWE", "IBAN", "BIC", "PURP"];
+
+/// One `:TAG:` field with its continuation lines
+struct Field {
+    tag: String,
+    lines: Vec<String>,
+    line_number: usize,
+}
+
+/// A `:61:` line with the `:86:` that follows it
+struct Entry {
+    line: Field,
+    information: Option<Field>,
+}
+
+/// Fields from one `:20:` to the next
+#[derive(Default)]
+struct Block {
+    reference: String,
+    /// `:28C:` statement and sequence number, e.g. `00052/001`
+    statement_number: Option<String>,
+    account: Option<String>,
+    opening_balance: Option<StatementBalance>,
+    closing_balance: Option<StatementBalance>,
+    entries: Vec<Entry>,
+}
+
+/// What the `:86:` field says about the other party and the payment
+#[derive(Debug, Default)]
+struct Information {
+    payer_name: Option<String>,
+    payer_iban: Option<String>,
+    reference: Option<String>,
+    description: Option<String>,
+}
+
+fn line_error(line_number: usize, message: &str) -> String {
+    format!("Napaka v vrstici {} izpiska MT940: {}.", line_number, message)
+}
+
+/// Split the file into tag fields. Text outside the `{4:` block of a SWIFT envelope,
+/// header lines before the first tag and `-` message terminators are dropped.
+fn fields(content: &str, tag: &Regex) -> Vec<Field> {
+    let mut fields: Vec<Field> = Vec::new();
+    for (index, raw) in content.lines().enumerate() {
+        let mut line = raw.trim_end();
+        if line.starts_with('{') {
+            match line.find("{4:") {
+                Some(start) => line = &line[start + 3..],
+                None => continue,
+            }
+        }
+        if line.is_empty() || line == "-" || line.starts_with("-}") {
+            continue;
+        }
+        match tag.captures(line) {
+            Some(captures) => fields.push(Field {
+                tag: captures[1].to_string(),
+                lines: vec![captures[2].to_string()],
+                line_number: index + 1,
+            }),
+            None => {
+                if let Some(field) = fields.last_mut() {
+                    field.lines.push(line.to_string());
+                }
+            }
+        }
+    }
+    fields
+}
+
+/// `1234,56` with a decimal comma, as SWIFT writes amounts
+fn parse_amount(raw: &str) -> Option<f64> {
+    if raw.chars().filter(|c| *c == ',').count() != 1 {
+        return None;
+    }
+    raw.replace(',', ".").trim_end_matches('.').parse().ok()
+}
+
+fn parse_date(raw: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(raw, "%y%m%d").ok()
+}
+
+fn parse_balance(field: &Field, pattern: &Regex) -> Result<StatementBalance, String> {
+    let raw = field.lines[0].trim();
+    let invalid = || line_error(field.line_number, &format!("neveljavno stanje računa \"{}\"", raw));
+    let captures = pattern.captures(raw).ok_or_else(invalid)?;
+    let date = parse_date(&captures[2]).ok_or_else(invalid)?;
+    let amount = parse_amount(&captures[4]).ok_or_else(invalid)?;
+    Ok(StatementBalance {
+        amount: if &captures[1] == "D" { -amount } else { amount },
+        currency: captures[3].to_string(),
+        date: Some(date.format("%Y-%m-%d").to_string()),
+    })
+}
+
+/// The entry date `MMDD` in the year closest to the value date, for entries booked across New Year
+fn entry_date(value_date: NaiveDate, raw: &str) -> Option<NaiveDate> {
+    let month = raw[..2].parse().ok()?;
+    let day = raw[2..].parse().ok()?;
+    (value_date.year() - 1..=value_date.year() + 1)
+        .filter_map(|year| NaiveDate::from_ymd_opt(year, month, day))
+        .min_by_key(|date| (*date - value_date).num_days().abs())
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim().trim_end_matches('/').trim();
+    (!value.is_empty() && value != NOT_PROVIDED).then(|| value.to_string())
+}
+
+/// Values of `keywords` in `text`, in order of appearance. A keyword starts where
+/// `prefix` + keyword + `suffix` occurs and runs to the start of the next one.
+fn keyword_values(text: &str, keywords: &[&str], prefix: &str, suffix: &str) -> Vec<(String, String)> {
+    let mut starts: Vec<(usize, usize, &str)> = Vec::new();
+    for keyword in keywords {
+        let marker = format!("{}{}{}", prefix, keyword, suffix);
+        starts.extend(text.match_indices(&marker).map(|(at, _)| (at, at + marker.len(), *keyword)));
+    }
+    // The longest marker wins where two start together; one inside another marker is text
+    starts.sort_by_key(|(at, end, _)| (*at, std::cmp::Reverse(*end)));
+    let mut kept: Vec<(usize, usize, &str)> = Vec::new();
+    for start in starts {
+        if kept.last().is_none_or(|last| start.0 >= last.1) {
+            kept.push(start);
+        }
+    }
+    kept.iter()
+        .enumerate()
+        .map(|(index, (_, value_start, keyword))| {
+            let end = kept.get(index + 1).map(|next| next.0).unwrap_or(text.len());
+            (keyword.to_string(), text[*value_start..end].to_string())
+        })
+        .collect()
+}
+
+fn value_of(values: &[(String, String)], keywords: &[&str]) -> Option<String> {
+    keywords.iter().find_map(|keyword| {
+        values
+            .iter()
+            .filter(|(key, _)| key == keyword)
+            .find_map(|(_, value)| non_empty(value))
+    })
+}
+
+/// `?`-numbered subfields, as German and Austrian banks and several Slovenian ones send:
+/// `?20`–`?29` and `?60`–`?63` purpose, `?31` account, `?32`–`?33` name
+fn parse_numbered(text: &str) -> Information {
+    let mut purpose = String::new();
+    let mut name = String::new();
+    let mut account = String::new();
+    let mut posting_text = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('?') {
+        let code = rest.get(start + 1..start + 3).filter(|code| code.chars().all(|c| c.is_ascii_digit()));
+        let Some(code) = code else {
+            rest = &rest[start + 1..];
+            continue;
+        };
+        let value_start = start + 3;
+        let value_end = rest[value_start..]
+            .match_indices('?')
+            .map(|(at, _)| value_start + at)
+            .find(|at| rest.get(at + 1..at + 3).is_some_and(|c| c.chars().all(|c| c.is_ascii_digit())))
+            .unwrap_or(rest.len());
+        let value = &rest[value_start..value_end];
+        match code.parse::<u8>().unwrap_or(0) {
+            0 => posting_text.push_str(value),
+            20..=29 | 60..=63 => purpose.push_str(value),
+            31 => account.push_str(value),
+            32 | 33 => name.push_str(value),
+            _ => {}
+        }
+        rest = &rest[value_end..];
+    }
+
+    let sepa = keyword_values(&purpose, SEPA_KEYWORDS, "", "+");
+    let description = if sepa.is_empty() {
+        non_empty(&purpose)
+    } else {
+        value_of(&sepa, &["SVWZ"])
+    };
+    Information {
+        payer_name: non_empty(&name),
+        payer_iban: non_empty(&account).or_else(|| value_of(&sepa, &["IBAN"])),
+        reference: value_of(&sepa, &["EREF", "KREF"]),
+        description: description.or_else(|| non_empty(&posting_text)),
+    }
+}
+
+/// `/KEY/value` subfields, e.g. `/ORDP//NAME/Novak Mojca/IBAN/SI56.../REMI/USTD//Vadnina/`
+fn parse_slashed(text: &str) -> Information {
+    let values = keyword_values(text, SLASH_KEYWORDS, "/", "/");
+    let remittance = value_of(&values, &["REMI"]);
+    let (structured_reference, description) = match remittance.as_deref() {
+        Some(remi) if remi.starts_with("STRD/") => (
+            // STRD/CUR/<creditor reference>
+            remi.rsplit('/').next().and_then(non_empty),
+            None,
+        ),
+        Some(remi) => (None, non_empty(remi.trim_start_matches("USTD").trim_start_matches('/'))),
+        None => (None, None),
+    };
+    Information {
+        payer_name: value_of(&values, &["NAME", "ORDP", "BENM"]),
+        payer_iban: value_of(&values, &["IBAN", "ACCT"]),
+        reference: value_of(&values, &["CDTRREF", "REF"])
+            .or(structured_reference)
+            .or_else(|| value_of(&values, &["EREF", "CREF", "KREF"])),
+        description: description.or_else(|| value_of(&values, &["PURP"])),
+    }
+}
+
+/// Read the `:86:` field in whichever structure the bank uses; anything else is the description
+fn parse_information(field: &Field, numbered: &Regex) -> Information {
+    let joined = field.lines.concat();
+    let is_numbered = numbered.is_match(&joined);
+    let information = if is_numbered {
+        parse_numbered(&joined)
+    } else if joined.starts_with('/') && !keyword_values(&joined, SLASH_KEYWORDS, "/", "/").is_empty() {
+        parse_slashed(&joined)
+    } else {
+        Information {
+            description: non_empty(&field.lines.iter().map(|line| line.trim()).collect::<Vec<_>>().join(" ")),
+            ..Information::default()
+        }
+    };
+    Information {
+        payer_iban: information.payer_iban.map(|iban| normalize_iban(&iban)),
+        ..information
+    }
+}
+
+fn parse_entry(entry: &Entry, pattern: &Regex, numbered: &Regex, currency: &str) -> Result<ParsedTransaction, String> {
+    let line = &entry.line;
+    let raw = line.lines[0].trim();
+    let error = |message: &str| line_error(line.line_number, message);
+    let captures = pattern
+        .captures(raw)
+        .ok_or_else(|| error(&format!("neveljavna postavka \"{}\"", raw)))?;
+    let value_date = parse_date(&captures[1]).ok_or_else(|| error(&format!("neveljaven datum valute \"{}\"", &captures[1])))?;
+    let booking_date = match captures.get(2) {
+        Some(entry) => entry_date(value_date, entry.as_str())
+            .ok_or_else(|| error(&format!("neveljaven datum knjiženja \"{}\"", entry.as_str())))?,
+        None => value_date,
+    };
+    // A reversed debit puts money back on the account
+    let is_credit = matches!(&captures[3], "C" | "RD");
+    let amount = parse_amount(&captures[5]).ok_or_else(|| error(&format!("neveljaven znesek \"{}\"", &captures[5])))?;
+    let bank_reference = captures[7].split_once("//").and_then(|(_, reference)| non_empty(reference));
+
+    let information = entry
+        .information
+        .as_ref()
+        .map(|field| parse_information(field, numbered))
+        .unwrap_or_default();
+    let supplementary = line.lines.get(1).and_then(|details| non_empty(details));
+    Ok(ParsedTransaction {
+        id_reference: bank_reference.clone(),
+        transaction: BankTransaction {
+            id: String::new(),
+            bank_reference,
+            amount,
+            currency: currency.to_string(),
+            is_credit,
+            status: Some("BOOK".to_string()),
+            booking_date: booking_date.format("%Y-%m-%d").to_string(),
+            value_date: Some(value_date.format("%Y-%m-%d").to_string()),
+            payer_name: information.payer_name,
+            payer_iban: information.payer_iban,
+            description: information.description.or(supplementary),
+            reference: information.reference,
+            bank_fee: None,
+        },
+    })
+}
+
+/// `:25:` is the IBAN, or bank code and account number as `BIC/ACCOUNT`
+fn account_iban(raw: &str) -> String {
+    normalize_iban(raw.rsplit('/').next().unwrap_or(raw))
+}
+
+/// Parse a SWIFT MT940 customer statement: tags `:20:`, `:25:`, `:28C:`, `:60F:`/`:60M:`,
+/// `:61:`, `:86:` and `:62F:`/`:62M:`, with or without the SWIFT `{1:…}{4:` envelope.
+///
+/// A file with several statements (one per `:20:`) is merged, as long as they are for
+/// one account, like a camt message with several account elements. The opening balance
+/// comes from the first statement, the closing balance from the last one. The message id
+/// is the `:28C:` statement number, or the `:20:` reference without one.
+pub fn parse_mt940(content: &str) -> Result<BankStatement, String> {
+    let tag = Regex::new(r"^:(\d{2}[A-Z]?):(.*)$").expect("Invalid MT940 pattern");
+    let statement_line = Regex::new(STATEMENT_LINE).expect("Invalid MT940 pattern");
+    let balance = Regex::new(BALANCE).expect("Invalid MT940 pattern");
+    // `:86:` with `?20`-style numbered subfields, optionally after a 3-digit transaction code
+    let numbered = Regex::new(r"^\d{0,3}\?\d{2}").expect("Invalid MT940 pattern");
+
+    let mut blocks: Vec<Block> = Vec::new();
+    for field in fields(content, &tag) {
+        if field.tag == "20" {
+            blocks.push(Block {
+                reference: field.lines[0].trim().to_string(),
+                ..Block::default()
+            });
+            continue;
+        }
+        let Some(block) = blocks.last_mut() else {
+            return Err(line_error(field.line_number, &format!("polje :{}: pred poljem :20:", field.tag)));
+        };
+        let tag = field.tag.clone();
+        match tag.as_str() {
+            "25" => block.account = Some(account_iban(field.lines[0].trim())),
+            "28C" => block.statement_number = non_empty(field.lines[0].trim()),
+            "60F" | "60M" => block.opening_balance = Some(parse_balance(&field, &balance)?),
+            "62F" | "62M" => block.closing_balance = Some(parse_balance(&field, &balance)?),
+            "61" => block.entries.push(Entry { line: field, information: None }),
+            // An :86: before the first :61: describes the whole statement
+            "86" => {
+                if let Some(entry) = block.entries.last_mut().filter(|entry| entry.information.is_none()) {
+                    entry.information = Some(field);
+                }
+            }
+            _ => {}
+        }
+    }
+    if blocks.is_empty() {
+        return Err("Datoteka ni izpisek MT940: manjka polje :20:.".to_string());
+    }
+
+    let mut iban: Option<String> = None;
+    let mut opening_balance = None;
+    let mut closing_balance = None;
+    let mut parsed = Vec::new();
+    for block in &blocks {
+        let account = block
+            .account
+            .clone()
+            .ok_or_else(|| format!("Izpisek MT940 {} nima polja :25: (račun).", block.reference))?;
+        if let Some(first) = &iban {
+            if *first != account {
+                return Err(format!("Datoteka vsebuje izpiske za več računov ({} in {}).", first, account));
+            }
+        }
+        iban = Some(account);
+        if opening_balance.is_none() {
+            opening_balance = block.opening_balance.clone();
+        }
+        closing_balance = block.closing_balance.clone().or(closing_balance);
+
+        let currency = block
+            .opening_balance
+            .as_ref()
+            .or(block.closing_balance.as_ref())
+            .map(|balance| balance.currency.as_str())
+            .unwrap_or("EUR");
+        for entry in &block.entries {
+            parsed.push(parse_entry(entry, &statement_line, &numbered, currency)?);
+        }
+    }
+    let account_iban = iban.unwrap_or_default();
+
+    let last_date = parsed.iter().map(|p| p.transaction.booking_date.clone()).max();
+    let block_id = |block: &Block| block.statement_number.clone().unwrap_or_else(|| block.reference.clone());
+    let message_id = match blocks.as_slice() {
+        [first, .., last] => format!("{} - {}", block_id(first), block_id(last)),
+        _ => block_id(&blocks[0]),
+    };
+    Ok(BankStatement {
+        message_type: "MT940".to_string(),
+        message_id,
+        creation_date_time: closing_balance
+            .as_ref()
+            .and_then(|balance| balance.date.clone())
+            .or(last_date)
+            .unwrap_or_default(),
+        transactions: assign_ids(&account_iban, parsed),
+        account_iban,
+        account_owner: None,
+        opening_balance,
+        closing_balance,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATEMENT: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/bank/mt940-statement.sta"));
+
+    #[test]
+    fn parses_statement_with_balances_and_subfields() {
+        let statement = parse_mt940(STATEMENT).unwrap();
+        assert_eq!(statement.message_type, "MT940");
+        assert_eq!(statement.message_id, "00052/001 - 00053/001");
+        assert_eq!(statement.account_iban, "SI56020100012345678");
+        assert_eq!(statement.creation_date_time, "2025-03-17");
+
+        let opening = statement.opening_balance.as_ref().unwrap();
+        assert_eq!(opening.amount, 1520.3);
+        assert_eq!(opening.date.as_deref(), Some("2025-03-13"));
+        let closing = statement.closing_balance.as_ref().unwrap();
+        assert_eq!(closing.amount, 1592.5, "closing balance of the second statement");
+        assert_eq!(closing.date.as_deref(), Some("2025-03-17"));
+
+        let transactions = &statement.transactions;
+        assert_eq!(transactions.len(), 4);
+
+        let numbered = &transactions[0];
+        assert_eq!(numbered.bank_reference.as_deref(), Some("2025031400123456"));
+        assert_eq!(numbered.amount, 45.0);
+        assert!(numbered.is_credit);
+        assert_eq!(numbered.booking_date, "2025-03-14");
+        assert_eq!(numbered.payer_name.as_deref(), Some("NOVAK MOJCA"));
+        assert_eq!(numbered.payer_iban.as_deref(), Some("SI56031001000012345"));
+        assert_eq!(numbered.reference.as_deref(), Some("SI12 1000-2025031"));
+        assert_eq!(numbered.description.as_deref(), Some("VADNINA MAREC LUKA NOVAK"));
+
+        let slashed = &transactions[1];
+        assert_eq!(slashed.payer_name.as_deref(), Some("Kovač Žiga"));
+        assert_eq!(slashed.payer_iban.as_deref(), Some("SI56290000001234567"));
+        assert_eq!(slashed.reference.as_deref(), Some("RF18539007547034"));
+        assert_eq!(slashed.description.as_deref(), Some("Članarina 2025 Eva Kovač"));
+        assert_eq!(slashed.bank_reference, None);
+
+        let debit = &transactions[2];
+        assert!(!debit.is_credit);
+        assert_eq!(debit.amount, 12.8);
+        assert_eq!(debit.description.as_deref(), Some("Račun za telefon marec"));
+
+        let reversal = &transactions[3];
+        assert!(reversal.is_credit, "RD reverses a debit");
+        assert_eq!(reversal.booking_date, "2025-03-17");
+        assert_eq!(reversal.value_date.as_deref(), Some("2025-03-15"));
+    }
+
+    #[test]
+    fn names_the_statement_by_its_number() {
+        let statement = parse_mt940(":20:REF1\n:25:SI56020100012345678\n:28C:00052/001\n").unwrap();
+        assert_eq!(statement.message_id, "00052/001");
+        let statement = parse_mt940(":20:REF1\n:25:SI56020100012345678\n").unwrap();
+        assert_eq!(statement.message_id, "REF1");
+    }
+
+    #[test]
+    fn reads_entry_dates_across_new_year() {
+        let value_date = NaiveDate::from_ymd_opt(2024, 12, 31).unwrap();
+        assert_eq!(entry_date(value_date, "0102"), NaiveDate::from_ymd_opt(2025, 1, 2));
+        let value_date = NaiveDate::from_ymd_opt(2025, 1, 2).unwrap();
+        assert_eq!(entry_date(value_date, "1231"), NaiveDate::from_ymd_opt(2024, 12, 31));
+    }
+
+    #[test]
+    fn rejects_malformed_statement_lines_and_mixed_accounts() {
+        let error = parse_mt940(":20:A\n:25:SI56020100012345678\n:61:2503X\n").unwrap_err();
+        assert!(error.contains("vrstici 3"), "{}", error);
+
+        let error = parse_mt940(":20:A\n:25:SI56020100012345678\n:20:B\n:25:SI56290000001234567\n").unwrap_err();
+        assert!(error.contains("več računov"), "{}", error);
+        assert!(parse_mt940("<Document/>").is_err());
+    }
+}
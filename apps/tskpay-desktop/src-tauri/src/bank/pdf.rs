@@ -0,0 +1,487 @@
+use super::{assign_ids, normalize_iban, BankStatement, BankTransaction, ParsedTransaction, StatementBalance};
+use regex::Regex;
+use serde::Serialize;
+
+/// `14.03.2025` or `14.03.25`, optionally with spaces after the dots
+const DATE: &str = r"\d{1,2}\.\s?\d{1,2}\.\s?(?:\d{4}|\d{2})";
+/// Slovenian number format, e.g. `1.234,56` or `-45,00`
+const AMOUNT: &str = r"-?\d{1,3}(?:\.\d{3})*,\d{2}";
+const OPENING_BALANCE_LABELS: &[&str] = &["začetno stanje", "prejšnje stanje", "predhodno stanje"];
+const CLOSING_BALANCE_LABELS: &[&str] = &["končno stanje", "novo stanje"];
+
+/// How a layout prints the amount of a transaction row
+#[derive(Debug, Clone, Copy)]
+enum AmountColumns {
+    /// Separate columns, debit ("Breme") before credit ("Dobro")
+    DebitCredit,
+    /// Separate columns, credit before debit
+    CreditDebit,
+    /// One column with debits negative, optionally followed by the running balance
+    Signed,
+}
+
+/// The text layout of one bank's PDF statements
+struct PdfLayout {
+    bank: &'static str,
+    /// Text that identifies the bank's statements, usually its BIC
+    markers: &'static [&'static str],
+    amounts: AmountColumns,
+    purpose_labels: &'static [&'static str],
+    reference_labels: &'static [&'static str],
+}
+
+const LAYOUTS: &[PdfLayout] = &[
+    PdfLayout {
+        bank: "NLB",
+        markers: &["LJBASI2X", "NLB d.d."],
+        amounts: AmountColumns::DebitCredit,
+        purpose_labels: &["Namen:"],
+        reference_labels: &["Referenca:", "Sklic:"],
+    },
+    PdfLayout {
+        bank: "NKBM/OTP",
+        markers: &["KBMASI2X", "OTP banka", "Nova KBM"],
+        amounts: AmountColumns::Signed,
+        purpose_labels: &["Namen plačila:", "Namen:"],
+        reference_labels: &["Sklic:", "Referenca:"],
+    },
+    PdfLayout {
+        bank: "SKB",
+        markers: &["SKBASI2X", "SKB banka", "SKB d.d."],
+        amounts: AmountColumns::CreditDebit,
+        purpose_labels: &["Namen:"],
+        reference_labels: &["Referenca prejemnika:", "Referenca:"],
+    },
+    PdfLayout {
+        bank: "Delavska hranilnica",
+        markers: &["HDELSI22", "Delavska hranilnica"],
+        amounts: AmountColumns::DebitCredit,
+        purpose_labels: &["Namen:"],
+        reference_labels: &["Sklic prejemnika:", "Sklic:"],
+    },
+];
+
+/// A line of the statement that looked like a transaction but could not be read
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnparsedLine {
+    /// 1-based line number in the extracted text
+    pub line_number: usize,
+    pub text: String,
+    pub reason: String,
+}
+
+/// A PDF statement, with the lines that need checking by hand
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfStatement {
+    /// Bank whose layout was recognised, e.g. `NLB`
+    pub bank: String,
+    pub statement: BankStatement,
+    pub unparsed_lines: Vec<UnparsedLine>,
+}
+
+struct Patterns {
+    row: Regex,
+    iban: Regex,
+    reference: Regex,
+    amount: Regex,
+    date: Regex,
+    statement_number: Regex,
+    statement_date: Regex,
+    owner: Regex,
+    page: Regex,
+}
+
+impl Patterns {
+    fn new() -> Self {
+        let build = |pattern: &str| Regex::new(pattern).expect("Invalid PDF statement pattern");
+        Self {
+            // Booking date, optional value date, payer, then one or two amounts
+            row: build(&format!(r"^({DATE})\s+(?:({DATE})\s+)?(.*?)\s*({AMOUNT})(?:\s+({AMOUNT}))?$")),
+            iban: build(r"\b[A-Z]{2}\d{2}(?:\s?[A-Z0-9]{4}){3,7}(?:\s?[A-Z0-9]{1,3})?\b"),
+            reference: build(r"^(?:SI\d{2}\s?[0-9-]{1,22}|RF\d{2}\s?[A-Z0-9 ]{1,25})$"),
+            amount: build(AMOUNT),
+            date: build(DATE),
+            statement_number: build(r"(?i)izpisek\s+(?:št\.|številka)?\s*:?\s*([A-Z0-9][A-Z0-9/.-]*)"),
+            statement_date: build(&format!(r"(?i)datum\s+izpiska\s*:?\s*({DATE})")),
+            owner: build(r"(?i)(?:imetnik|lastnik)(?:\s+ra[čc]una)?\s*:\s*(.+)"),
+            page: build(r"(?i)^stran\s+\d+|\bstran\s+\d+\s*(?:od|/)\s*\d+"),
+        }
+    }
+}
+
+/// `14.03.2025` or `14.03.25` as `2025-03-14`
+fn parse_date(raw: &str) -> Option<String> {
+    let compact: String = raw.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut parts = compact.split('.');
+    let (day, month, year) = (parts.next()?, parts.next()?, parts.next()?);
+    let year = match year.len() {
+        2 => format!("20{}", year),
+        4 => year.to_string(),
+        _ => return None,
+    };
+    let date = chrono::NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)?;
+    Some(date.format("%Y-%m-%d").to_string())
+}
+
+/// `1.234,56` as `1234.56`
+fn parse_amount(raw: &str) -> Option<f64> {
+    raw.replace('.', "").replace(',', ".").parse().ok()
+}
+
+/// Amount and direction of a row, `None` when the columns are ambiguous
+fn row_amount(columns: AmountColumns, first: f64, second: Option<f64>) -> Option<(f64, bool)> {
+    let (debit, credit) = match (columns, second) {
+        (AmountColumns::Signed, _) => return Some((first.abs(), first >= 0.0)),
+        (AmountColumns::DebitCredit, Some(second)) => (first, second),
+        (AmountColumns::CreditDebit, Some(second)) => (second, first),
+        // A blank column can't be told apart from the other one
+        (_, None) => return None,
+    };
+    match (debit != 0.0, credit != 0.0) {
+        (true, false) => Some((debit.abs(), false)),
+        (false, true) => Some((credit.abs(), true)),
+        _ => None,
+    }
+}
+
+fn strip_label<'a>(line: &'a str, labels: &[&str]) -> Option<&'a str> {
+    labels.iter().find_map(|label| {
+        line.get(..label.len())
+            .filter(|start| start.eq_ignore_ascii_case(label))
+            .map(|_| line[label.len()..].trim())
+    })
+}
+
+fn has_label(line: &str, labels: &[&str]) -> bool {
+    let lower = line.to_lowercase();
+    labels.iter().any(|label| lower.starts_with(label))
+}
+
+/// Page headers and footers repeated inside the transaction list
+fn is_page_furniture(patterns: &Patterns, line: &str) -> bool {
+    let lower = line.to_lowercase();
+    patterns.page.is_match(line)
+        || (lower.contains("datum") && ["breme", "dobro", "znesek"].iter().any(|column| lower.contains(column)))
+}
+
+fn append(target: &mut Option<String>, text: &str) {
+    match target {
+        Some(existing) => {
+            existing.push(' ');
+            existing.push_str(text);
+        }
+        None => *target = Some(text.to_string()),
+    }
+}
+
+/// First IBAN in `line`. RF references look like IBANs, and Slovenian IBANs have 19 characters.
+fn find_iban<'a>(patterns: &Patterns, line: &'a str) -> Option<regex::Match<'a>> {
+    patterns.iban.find_iter(line).find(|candidate| {
+        let iban = normalize_iban(candidate.as_str());
+        !iban.starts_with("RF") && (!iban.starts_with("SI") || iban.len() == 19)
+    })
+}
+
+/// Add a continuation line (payer, IBAN, purpose, reference) to the open transaction.
+/// Returns false for lines that can't belong to a transaction.
+fn add_detail(patterns: &Patterns, layout: &PdfLayout, transaction: &mut BankTransaction, line: &str) -> bool {
+    if let Some(reference) = strip_label(line, layout.reference_labels) {
+        transaction.reference = Some(reference.to_string()).filter(|r| !r.is_empty());
+        return true;
+    }
+    if let Some(purpose) = strip_label(line, layout.purpose_labels) {
+        append(&mut transaction.description, purpose);
+        return true;
+    }
+    if transaction.payer_iban.is_none() {
+        if let Some(iban) = find_iban(patterns, line) {
+            transaction.payer_iban = Some(normalize_iban(iban.as_str()));
+            let rest = format!("{} {}", &line[..iban.start()], &line[iban.end()..]);
+            let rest = rest.trim();
+            if !rest.is_empty() && transaction.payer_name.is_none() {
+                transaction.payer_name = Some(rest.to_string());
+            }
+            return true;
+        }
+    }
+    if patterns.reference.is_match(line) && transaction.reference.is_none() {
+        transaction.reference = Some(line.to_string());
+        return true;
+    }
+    // Amounts outside the amount columns mean the layout was not understood
+    if patterns.amount.is_match(line) {
+        return false;
+    }
+    if transaction.payer_name.is_none() {
+        transaction.payer_name = Some(line.to_string());
+    } else {
+        append(&mut transaction.description, line);
+    }
+    true
+}
+
+fn detect_layout(text: &str) -> Result<&'static PdfLayout, String> {
+    LAYOUTS
+        .iter()
+        .find(|layout| layout.markers.iter().any(|marker| text.contains(marker)))
+        .ok_or_else(|| {
+            "Banke izpiska ni bilo mogoče prepoznati. Podprti so izpiski NLB, NKBM/OTP, SKB in Delavske hranilnice."
+                .to_string()
+        })
+}
+
+fn parse_balance(patterns: &Patterns, line: &str) -> Option<StatementBalance> {
+    let amount = patterns.amount.find_iter(line).last()?;
+    Some(StatementBalance {
+        amount: parse_amount(amount.as_str())?,
+        currency: "EUR".to_string(),
+        date: patterns.date.find(line).and_then(|date| parse_date(date.as_str())),
+    })
+}
+
+/// Text of a PDF, page by page. Scanned statements have no text and are refused.
+pub fn extract_pdf_text(bytes: &[u8]) -> Result<String, String> {
+    // pdf-extract panics on some malformed files instead of returning an error
+    let text = std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem(bytes))
+        .map_err(|_| "Datoteke PDF ni bilo mogoče prebrati.".to_string())?
+        .map_err(|e| format!("Datoteka ni veljaven PDF: {}", e))?;
+    if text.trim().is_empty() {
+        return Err("PDF ne vsebuje besedila. Skeniranih izpiskov ni mogoče uvoziti.".to_string());
+    }
+    Ok(text)
+}
+
+/// Parse the text of a PDF statement.
+///
+/// The bank is recognised by its BIC or name. Each transaction starts with a row
+/// holding the booking date, optional value date, payer and amounts; the following
+/// lines add the payer's IBAN, purpose and reference. Rows that start with a date
+/// but don't fit the layout, and stray amounts, are returned as unparsed lines.
+pub fn parse_pdf_text(text: &str) -> Result<PdfStatement, String> {
+    let layout = detect_layout(text)?;
+    let patterns = Patterns::new();
+
+    let mut account_iban = None;
+    let mut account_owner = None;
+    let mut message_id = None;
+    let mut statement_date = None;
+    let mut opening_balance = None;
+    let mut closing_balance = None;
+    let mut parsed: Vec<ParsedTransaction> = Vec::new();
+    let mut unparsed_lines = Vec::new();
+    // Whether the last row was a transaction, so continuation lines belong to it
+    let mut in_transaction = false;
+
+    for (index, raw_line) in text.lines().enumerate() {
+        let line = raw_line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() || is_page_furniture(&patterns, &line) {
+            continue;
+        }
+        let mut unparsed = |reason: &str| {
+            unparsed_lines.push(UnparsedLine {
+                line_number: index + 1,
+                text: line.clone(),
+                reason: reason.to_string(),
+            })
+        };
+
+        if has_label(&line, OPENING_BALANCE_LABELS) {
+            opening_balance = opening_balance.or_else(|| parse_balance(&patterns, &line));
+            in_transaction = false;
+            continue;
+        }
+        if has_label(&line, CLOSING_BALANCE_LABELS) {
+            closing_balance = parse_balance(&patterns, &line).or(closing_balance);
+            in_transaction = false;
+            continue;
+        }
+
+        if let Some(row) = patterns.row.captures(&line) {
+            let booking_date = parse_date(&row[1]);
+            let value_date = row.get(2).and_then(|date| parse_date(date.as_str()));
+            let first = parse_amount(&row[4]);
+            let second = row.get(5).and_then(|amount| parse_amount(amount.as_str()));
+            let amount = first.and_then(|first| row_amount(layout.amounts, first, second));
+            let (Some(booking_date), Some((amount, is_credit))) = (booking_date, amount) else {
+                unparsed("znesek ali datum ni v pričakovani obliki");
+                in_transaction = false;
+                continue;
+            };
+            let mut transaction = BankTransaction {
+                id: String::new(),
+                bank_reference: None,
+                amount,
+                currency: "EUR".to_string(),
+                is_credit,
+                status: Some("BOOK".to_string()),
+                booking_date,
+                value_date,
+                payer_name: None,
+                payer_iban: None,
+                description: None,
+                reference: None,
+                bank_fee: None,
+            };
+            let payer = row[3].trim();
+            if !payer.is_empty() {
+                add_detail(&patterns, layout, &mut transaction, payer);
+            }
+            parsed.push(ParsedTransaction {
+                transaction,
+                id_reference: None,
+            });
+            in_transaction = true;
+            continue;
+        }
+        // Header lines such as "14.03.2025 Izpisek št. 62" may start with a date too
+        let starts_with_date = patterns.date.find(&line).is_some_and(|date| date.start() == 0);
+        if starts_with_date && (!parsed.is_empty() || patterns.amount.is_match(&line)) {
+            unparsed("vrstica z datumom ni prepoznana kot transakcija");
+            in_transaction = false;
+            continue;
+        }
+
+        match parsed.last_mut() {
+            Some(last) if in_transaction => {
+                if !add_detail(&patterns, layout, &mut last.transaction, &line) {
+                    unparsed("znesek izven stolpcev za znesek");
+                }
+            }
+            _ => {
+                // Statement header before the first transaction
+                if account_iban.is_none() {
+                    account_iban = find_iban(&patterns, &line).map(|iban| normalize_iban(iban.as_str()));
+                }
+                if message_id.is_none() {
+                    message_id = patterns.statement_number.captures(&line).map(|c| c[1].to_string());
+                }
+                if statement_date.is_none() {
+                    statement_date = patterns.statement_date.captures(&line).and_then(|c| parse_date(&c[1]));
+                }
+                if account_owner.is_none() {
+                    account_owner = patterns.owner.captures(&line).map(|c| c[1].trim().to_string());
+                }
+            }
+        }
+    }
+
+    let account_iban = account_iban.ok_or_else(|| "V izpisku ni bilo mogoče najti IBAN računa.".to_string())?;
+    let creation_date_time = statement_date
+        .or_else(|| closing_balance.as_ref().and_then(|balance| balance.date.clone()))
+        .or_else(|| parsed.last().map(|last| last.transaction.booking_date.clone()))
+        .unwrap_or_default();
+
+    Ok(PdfStatement {
+        bank: layout.bank.to_string(),
+        statement: BankStatement {
+            message_type: format!("PDF {}", layout.bank),
+            message_id: message_id.unwrap_or_default(),
+            creation_date_time,
+            transactions: assign_ids(&account_iban, parsed),
+            account_iban,
+            account_owner,
+            opening_balance,
+            closing_balance,
+        },
+        unparsed_lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NLB_PDF: &[u8] =
+        include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/bank/pdf-nlb-statement.pdf"));
+
+    #[test]
+    fn parses_nlb_pdf() {
+        let pdf = parse_pdf_text(&extract_pdf_text(NLB_PDF).unwrap()).unwrap();
+        assert_eq!(pdf.bank, "NLB");
+        let statement = &pdf.statement;
+        assert_eq!(statement.message_type, "PDF NLB");
+        assert_eq!(statement.message_id, "62");
+        assert_eq!(statement.creation_date_time, "2025-03-31");
+        assert_eq!(statement.account_iban, "SI56020100012345678");
+        assert_eq!(statement.account_owner.as_deref(), Some("Teniški klub Žabnica"));
+        assert_eq!(statement.opening_balance.as_ref().unwrap().amount, 1250.4);
+        assert_eq!(statement.closing_balance.as_ref().unwrap().date.as_deref(), Some("2025-03-31"));
+        assert_eq!(statement.transactions.len(), 2);
+
+        let credit = &statement.transactions[0];
+        assert!(credit.is_credit);
+        assert_eq!(credit.amount, 120.0);
+        assert_eq!(credit.booking_date, "2025-03-31");
+        assert_eq!(credit.payer_name.as_deref(), Some("KRAJNC PETRA"));
+        assert_eq!(credit.payer_iban.as_deref(), Some("SI56101000055512345"));
+        assert_eq!(credit.description.as_deref(), Some("Vadnina april Jan Krajnc"));
+        assert_eq!(credit.reference.as_deref(), Some("SI00 1000-0042"));
+
+        let debit = &statement.transactions[1];
+        assert!(!debit.is_credit);
+        assert_eq!(debit.amount, 21.9);
+
+        // A single amount in a two-column layout can't be assigned to a side
+        assert_eq!(pdf.unparsed_lines.len(), 1);
+        assert_eq!(pdf.unparsed_lines[0].text, "31.03.2025 HORVAT ANA 45,00");
+    }
+
+    #[test]
+    fn parses_signed_layout() {
+        let text = "OTP banka d.d. KBMASI2X\n\
+                    Izpisek št.: 2025/14\n\
+                    Račun SI56 0400 0012 3456 789\n\
+                    02.04.25 ZUPAN MARKO SI56 0475 0000 1234 561 60,00 1.410,40\n\
+                    Namen plačila: Turnir Maribor\n\
+                    Sklic: RF18539007547034\n\
+                    02.04.25 Zavarovalnica d.d. -1.200,00 210,40\n";
+        let pdf = parse_pdf_text(text).unwrap();
+        assert_eq!(pdf.bank, "NKBM/OTP");
+        assert_eq!(pdf.statement.message_id, "2025/14");
+        assert_eq!(pdf.statement.account_iban, "SI56040000123456789");
+
+        let credit = &pdf.statement.transactions[0];
+        assert_eq!(credit.amount, 60.0);
+        assert_eq!(credit.booking_date, "2025-04-02");
+        assert_eq!(credit.payer_name.as_deref(), Some("ZUPAN MARKO"));
+        assert_eq!(credit.payer_iban.as_deref(), Some("SI56047500001234561"));
+        assert_eq!(credit.reference.as_deref(), Some("RF18539007547034"));
+
+        let debit = &pdf.statement.transactions[1];
+        assert!(!debit.is_credit);
+        assert_eq!(debit.amount, 1200.0);
+    }
+
+    #[test]
+    fn parses_credit_first_layout() {
+        let text = "SKB banka d.d. SKBASI2X\nIBAN SI56 0310 0100 0012 345\n\
+                    05.04.2025 05.04.2025 MLAKAR TOMAŽ 35,50 0,00\n\
+                    RF18539007547034\n\
+                    Stran 1 od 2\n\
+                    Datum Valuta Naziv Dobro Breme\n\
+                    05.04.2025 05.04.2025 Elektro d.d. 0,00 12,30\n\
+                    Provizija 0,39\n";
+        let pdf = parse_pdf_text(text).unwrap();
+        assert_eq!(pdf.bank, "SKB");
+        assert_eq!(pdf.statement.transactions.len(), 2);
+        assert!(pdf.statement.transactions[0].is_credit);
+        assert_eq!(pdf.statement.transactions[0].reference.as_deref(), Some("RF18539007547034"));
+        assert_eq!(pdf.statement.transactions[0].payer_iban, None);
+        assert!(!pdf.statement.transactions[1].is_credit);
+        assert_eq!(pdf.unparsed_lines.len(), 1);
+        assert_eq!(pdf.unparsed_lines[0].line_number, 8);
+    }
+
+    #[test]
+    fn rejects_unknown_banks_and_empty_files() {
+        let error = parse_pdf_text("Neznana banka\nIBAN SI56 0310 0100 0012 345\n").unwrap_err();
+        assert!(error.starts_with("Banke izpiska ni bilo mogoče prepoznati"), "{}", error);
+
+        let error = parse_pdf_text("Delavska hranilnica d.d.\n01.04.2025 NOVAK 0,00 5,00\n").unwrap_err();
+        assert!(error.contains("IBAN"), "{}", error);
+
+        assert!(extract_pdf_text(b"not a pdf").is_err());
+    }
+}
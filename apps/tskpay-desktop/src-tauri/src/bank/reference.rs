@@ -0,0 +1,284 @@
+use crate::database::{record_change, ChangeEvent};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+/// Structured payment reference ("sklic") models the club hands out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReferenceModel {
+    /// Slovenian model 12: one number ending in a mod-11 check digit, e.g. `SI12 10000429`
+    Si12,
+    /// ISO 11649 creditor reference, e.g. `RF811000042`
+    Rf,
+}
+
+/// What a reference number identifies; the first digit of the number
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReferenceKind {
+    Parent,
+    Cost,
+}
+
+impl ReferenceKind {
+    fn digit(self) -> char {
+        match self {
+            ReferenceKind::Parent => '1',
+            ReferenceKind::Cost => '2',
+        }
+    }
+
+    fn table(self) -> &'static str {
+        match self {
+            ReferenceKind::Parent => "parents",
+            ReferenceKind::Cost => "costs",
+        }
+    }
+}
+
+/// A reference read back to the parent or cost number it was issued for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodedReference {
+    pub kind: ReferenceKind,
+    pub number: u64,
+}
+
+/// References handed out by one assignment run
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferenceAssignment {
+    pub parents: usize,
+    pub costs: usize,
+}
+
+/// Digits a reference number is built from: kind digit and the number, at least six digits
+fn reference_body(kind: ReferenceKind, number: u64) -> String {
+    format!("{}{:06}", kind.digit(), number)
+}
+
+/// Model 11/12 check digit: digits weighted 2, 3, 4 … from the right, 11 less the sum
+/// mod 11, and 0 where that gives 10 or 11
+pub fn mod11_check_digit(digits: &str) -> Option<u32> {
+    let mut sum = 0;
+    for (i, c) in digits.chars().rev().enumerate() {
+        sum += c.to_digit(10)? * (2 + (i as u32 % 12));
+    }
+    match 11 - sum % 11 {
+        10 | 11 => Some(0),
+        check => Some(check),
+    }
+}
+
+/// ISO 7064 mod 97 of an alphanumeric string, letters counting as 10 (A) to 35 (Z)
+fn mod97(text: &str) -> Option<u32> {
+    let mut remainder = 0u32;
+    for c in text.chars() {
+        let value = c.to_digit(36)?;
+        remainder = if value < 10 { remainder * 10 + value } else { remainder * 100 + value } % 97;
+    }
+    Some(remainder)
+}
+
+/// ISO 11649 check digits for `body`: 98 less the mod 97 of `body` followed by `RF00`
+pub fn rf_check_digits(body: &str) -> Option<u32> {
+    Some(98 - mod97(&format!("{}RF00", body))?)
+}
+
+/// The reference for parent or cost number `number`, printable as it should be paid with
+pub fn format_reference(model: ReferenceModel, kind: ReferenceKind, number: u64) -> String {
+    let body = reference_body(kind, number);
+    match model {
+        ReferenceModel::Si12 => {
+            let check = mod11_check_digit(&body).unwrap_or(0);
+            format!("SI12 {}{}", body, check)
+        }
+        ReferenceModel::Rf => {
+            let check = rf_check_digits(&body).unwrap_or(0);
+            format!("RF{:02}{}", check, body)
+        }
+    }
+}
+
+/// Read an `SI12` or `RF` reference, as it comes in `CdtrRefInf/Ref` or is typed on a
+/// slip, back to the parent or cost it was issued for. Spaces and hyphens are ignored;
+/// references with a wrong check digit or not issued by [`format_reference`] give `None`.
+pub fn decode_reference(reference: &str) -> Option<DecodedReference> {
+    let compact: String = reference
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect::<String>()
+        .to_uppercase();
+
+    let body = if let Some(number) = compact.strip_prefix("SI12") {
+        if number.len() < 2 || !number.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let (body, check) = number.split_at(number.len() - 1);
+        if mod11_check_digit(body)? != check.parse::<u32>().ok()? {
+            return None;
+        }
+        body.to_string()
+    } else if let Some(rest) = compact.strip_prefix("RF") {
+        if rest.len() < 3 || rest.len() > 23 || !rest.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+        let (check, body) = rest.split_at(2);
+        if mod97(&format!("{}RF{}", body, check))? != 1 {
+            return None;
+        }
+        body.to_string()
+    } else {
+        return None;
+    };
+
+    if body.len() < 2 || !body.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let kind = match body.chars().next()? {
+        '1' => ReferenceKind::Parent,
+        '2' => ReferenceKind::Cost,
+        _ => return None,
+    };
+    Some(DecodedReference {
+        kind,
+        number: body[1..].parse().ok()?,
+    })
+}
+
+/// Numbers of the references stored in `kind`'s table, with the row ids
+pub fn stored_references(conn: &Connection, kind: ReferenceKind) -> rusqlite::Result<Vec<(u64, String)>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, payment_reference FROM {} WHERE payment_reference IS NOT NULL",
+        kind.table()
+    ))?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(id, reference)| {
+            decode_reference(&reference)
+                .filter(|decoded| decoded.kind == kind)
+                .map(|decoded| (decoded.number, id))
+        })
+        .collect())
+}
+
+fn assign_kind(
+    conn: &Connection,
+    model: ReferenceModel,
+    kind: ReferenceKind,
+    filter: &str,
+) -> rusqlite::Result<Vec<String>> {
+    let next = stored_references(conn, kind)?.iter().map(|(number, _)| *number).max().unwrap_or(0) + 1;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id FROM {} WHERE payment_reference IS NULL {} ORDER BY created_at, id",
+        kind.table(),
+        filter
+    ))?;
+    let ids = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (id, number) in ids.iter().zip(next..) {
+        conn.execute(
+            &format!("UPDATE {} SET payment_reference = ?2 WHERE id = ?1", kind.table()),
+            params![id, format_reference(model, kind, number)],
+        )?;
+    }
+    Ok(ids)
+}
+
+/// Give every parent without a reference the next free one and, with `include_costs`,
+/// every open cost as well. Existing references are kept, whatever their model.
+pub fn assign_references(
+    conn: &Connection,
+    model: ReferenceModel,
+    include_costs: bool,
+) -> rusqlite::Result<(ReferenceAssignment, Vec<ChangeEvent>)> {
+    let mut assignment = ReferenceAssignment::default();
+    let mut changes = Vec::new();
+
+    let parents = assign_kind(conn, model, ReferenceKind::Parent, "")?;
+    assignment.parents = parents.len();
+    if !parents.is_empty() {
+        changes.push(record_change(conn, "parents", "update", &parents)?);
+    }
+    if include_costs {
+        let costs = assign_kind(conn, model, ReferenceKind::Cost, "AND status = 'pending'")?;
+        assignment.costs = costs.len();
+        if !costs.is_empty() {
+            changes.push(record_change(conn, "costs", "update", &costs)?);
+        }
+    }
+    Ok((assignment, changes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_check_digits() {
+        // 0·2 + 9·3 + 8·4 + … + 1·11 = 255, 255 mod 11 = 2
+        assert_eq!(mod11_check_digit("1234567890"), Some(9));
+        // A sum divisible by 11 would give 11
+        assert_eq!(mod11_check_digit("0"), Some(0));
+        // ISO 11649 example
+        assert_eq!(rf_check_digits("539007547034"), Some(18));
+        assert_eq!(mod11_check_digit("12a"), None);
+    }
+
+    #[test]
+    fn formats_and_decodes_both_models() {
+        for model in [ReferenceModel::Si12, ReferenceModel::Rf] {
+            for (kind, number) in [(ReferenceKind::Parent, 42), (ReferenceKind::Cost, 1_234_567)] {
+                let reference = format_reference(model, kind, number);
+                assert_eq!(decode_reference(&reference), Some(DecodedReference { kind, number }), "{}", reference);
+            }
+        }
+        assert_eq!(format_reference(ReferenceModel::Si12, ReferenceKind::Parent, 42), "SI12 10000429");
+        assert_eq!(format_reference(ReferenceModel::Rf, ReferenceKind::Parent, 42), "RF811000042");
+        // As banks send it in CdtrRefInf/Ref
+        assert_eq!(
+            decode_reference("si12 1000-0429"),
+            Some(DecodedReference { kind: ReferenceKind::Parent, number: 42 })
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_check_digits_and_foreign_references() {
+        assert_eq!(decode_reference("SI12 10000428"), None);
+        assert_eq!(decode_reference("RF821000042"), None);
+        // Valid, but not one of ours
+        assert_eq!(decode_reference("RF18539007547034"), None);
+        assert_eq!(decode_reference("SI00 12345"), None);
+        assert_eq!(decode_reference("NOTPROVIDED"), None);
+    }
+
+    #[test]
+    fn assigns_the_next_free_numbers() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../database/schema.sql")).unwrap();
+        conn.execute_batch(
+            "INSERT INTO parents (id, first_name, last_name, payment_reference, created_at) VALUES
+                ('par-1', 'Mojca', 'Novak', 'RF811000042', '2025-01-01'),
+                ('par-2', 'Žiga', 'Kovač', NULL, '2025-01-02'),
+                ('par-3', 'Ana', 'Horvat', NULL, '2025-01-03');",
+        )
+        .unwrap();
+        let (assignment, changes) = assign_references(&conn, ReferenceModel::Si12, false).unwrap();
+        assert_eq!((assignment.parents, assignment.costs), (2, 0));
+        assert_eq!(changes.len(), 1);
+
+        let references: Vec<(u64, String)> = stored_references(&conn, ReferenceKind::Parent).unwrap();
+        assert_eq!(references.len(), 3);
+        let kovac: String = conn
+            .query_row("SELECT payment_reference FROM parents WHERE id = 'par-2'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(kovac, format_reference(ReferenceModel::Si12, ReferenceKind::Parent, 43));
+
+        let (assignment, changes) = assign_references(&conn, ReferenceModel::Si12, true).unwrap();
+        assert_eq!((assignment.parents, assignment.costs), (0, 0));
+        assert!(changes.is_empty());
+    }
+}
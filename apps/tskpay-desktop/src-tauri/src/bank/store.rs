@@ -0,0 +1,529 @@
+use super::{match_transactions, normalize_iban, BankStatement, BankTransaction, CsvProfile, UnparsedLine};
+use crate::database::{emit_change, generate_id, get_connection, record_change, ChangeEvent};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use std::collections::HashSet;
+use tauri::AppHandle;
+
+/// Outcome of importing a statement file
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatementImport {
+    /// Id of the new `bank_statements` row
+    pub statement_id: String,
+    pub message_type: String,
+    /// Incoming payments written to `bank_transactions`
+    pub imported: usize,
+    /// Imported payments the matching engine assigned to a parent
+    pub matched: usize,
+    /// Outgoing payments in the file; only incoming payments are matched to parents, so these are not stored
+    pub skipped_debits: usize,
+    /// Incoming payments that are already stored, e.g. from an overlapping report
+    pub skipped_duplicates: Vec<SkippedDuplicate>,
+    /// Lines of a PDF or CSV statement that could not be read and need checking by hand
+    pub unparsed_lines: Vec<UnparsedLine>,
+}
+
+/// How an incoming payment was recognised as one that is already stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DuplicateMatch {
+    /// The same transaction id, i.e. the same entry of an earlier import of this account
+    Id,
+    /// The same bank reference (`AcctSvcrRef`)
+    BankReference,
+    /// No bank reference; the same booking date, amount, payer IBAN and payment reference
+    Fingerprint,
+}
+
+/// An incoming payment of the file that was not stored again
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedDuplicate {
+    pub booking_date: String,
+    pub amount: f64,
+    pub payer_name: Option<String>,
+    pub reference: Option<String>,
+    /// The `bank_transactions` row it repeats
+    pub existing_id: String,
+    pub matched_on: DuplicateMatch,
+}
+
+/// Refuse a file whose exact bytes were imported before
+pub fn check_not_imported(conn: &Connection, file_sha256: &str) -> Result<(), String> {
+    let previous: Option<(String, String)> = conn
+        .query_row(
+            "SELECT file_name, imported_at FROM bank_statements WHERE file_sha256 = ?1 ORDER BY imported_at LIMIT 1",
+            [file_sha256],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    match previous {
+        Some((file_name, imported_at)) => Err(format!("Ta datoteka je bila že uvožena ({}, {}).", file_name, imported_at)),
+        None => Ok(()),
+    }
+}
+
+/// Stored transaction of the club's account `account_iban` that `transaction` repeats, if
+/// any: by bank reference, else by fingerprint. Bank references are only unique within
+/// one bank, so other accounts' statements are not searched. Rows in `claimed` were
+/// already matched to another transaction of the file, so two identical payments on one
+/// day, or the parts of a batch booking that share the entry's reference, are all kept
+/// the first time.
+fn find_duplicate(
+    conn: &Connection,
+    account_iban: &str,
+    transaction: &BankTransaction,
+    claimed: &HashSet<String>,
+) -> rusqlite::Result<Option<(String, DuplicateMatch)>> {
+    let (ids, matched_on) = match &transaction.bank_reference {
+        Some(bank_reference) => {
+            let mut stmt = conn.prepare_cached(
+                "SELECT t.id FROM bank_transactions t
+                 JOIN bank_statements s ON s.id = t.bank_statement_id
+                 WHERE s.account_iban = ?1 AND t.bank_reference = ?2
+                 ORDER BY t.created_at, t.id",
+            )?;
+            let ids = stmt
+                .query_map(params![account_iban, bank_reference], |row| row.get::<_, String>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            (ids, DuplicateMatch::BankReference)
+        }
+        None => {
+            let mut stmt = conn.prepare_cached(
+                "SELECT t.id FROM bank_transactions t
+                 JOIN bank_statements s ON s.id = t.bank_statement_id
+                 WHERE s.account_iban = ?1 AND t.transaction_date = ?2
+                   AND round(t.amount * 100) = round(?3 * 100)
+                   AND t.account_number = ?4 AND coalesce(t.reference, '') = ?5
+                 ORDER BY t.created_at, t.id",
+            )?;
+            let ids = stmt
+                .query_map(
+                    params![
+                        account_iban,
+                        transaction.booking_date,
+                        transaction.amount,
+                        transaction.payer_iban.as_deref().unwrap_or(""),
+                        transaction.reference.as_deref().unwrap_or(""),
+                    ],
+                    |row| row.get::<_, String>(0),
+                )?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            (ids, DuplicateMatch::Fingerprint)
+        }
+    };
+    Ok(ids.into_iter().find(|id| !claimed.contains(id)).map(|id| (id, matched_on)))
+}
+
+/// Write a parsed statement as a `bank_statements` row with a `bank_transactions` row
+/// per incoming payment that is not stored yet. Returns the import summary and the
+/// changes to report.
+pub fn store_statement(
+    conn: &Connection,
+    file_name: &str,
+    file_type: &str,
+    file_sha256: &str,
+    statement: &BankStatement,
+) -> rusqlite::Result<(StatementImport, Vec<ChangeEvent>)> {
+    let statement_id = generate_id("bank_statements");
+
+    // Look up all duplicates before inserting, so the file is only compared with earlier
+    // imports. A stored row with the same id is the same transaction; only the rest are
+    // compared by bank reference or fingerprint, so an id is never inserted twice.
+    let incoming: Vec<&BankTransaction> = statement.transactions.iter().filter(|t| t.is_credit).collect();
+    let mut claimed = HashSet::new();
+    let mut existing = Vec::with_capacity(incoming.len());
+    {
+        let mut stmt = conn.prepare("SELECT 1 FROM bank_transactions WHERE id = ?1")?;
+        for transaction in &incoming {
+            let stored = stmt.exists([&transaction.id])?;
+            if stored {
+                claimed.insert(transaction.id.clone());
+            }
+            existing.push(stored);
+        }
+    }
+
+    let mut credits = Vec::new();
+    let mut skipped_duplicates = Vec::new();
+    for (transaction, stored) in incoming.into_iter().zip(existing) {
+        let duplicate = if stored {
+            Some((transaction.id.clone(), DuplicateMatch::Id))
+        } else {
+            find_duplicate(conn, &statement.account_iban, transaction, &claimed)?
+        };
+        match duplicate {
+            Some((existing_id, matched_on)) => {
+                claimed.insert(existing_id.clone());
+                skipped_duplicates.push(SkippedDuplicate {
+                    booking_date: transaction.booking_date.clone(),
+                    amount: transaction.amount,
+                    payer_name: transaction.payer_name.clone(),
+                    reference: transaction.reference.clone(),
+                    existing_id,
+                    matched_on,
+                });
+            }
+            None => credits.push(transaction),
+        }
+    }
+
+    conn.execute(
+        "INSERT INTO bank_statements (
+            id, file_name, file_type, status, total_transactions, matched_transactions, unmatched_transactions,
+            message_type, message_id, account_iban,
+            opening_balance, opening_balance_date, closing_balance, closing_balance_date, file_sha256
+         ) VALUES (?1, ?2, ?3, 'completed', ?4, 0, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            statement_id,
+            file_name,
+            file_type,
+            credits.len() as i64,
+            statement.message_type,
+            statement.message_id,
+            statement.account_iban,
+            statement.opening_balance.as_ref().map(|b| b.amount),
+            statement.opening_balance.as_ref().and_then(|b| b.date.clone()),
+            statement.closing_balance.as_ref().map(|b| b.amount),
+            statement.closing_balance.as_ref().and_then(|b| b.date.clone()),
+            file_sha256,
+        ],
+    )?;
+
+    let mut stmt = conn.prepare(
+        "INSERT INTO bank_transactions (
+            id, bank_statement_id, transaction_date, amount, description, reference,
+            account_number, payer_name, bank_reference, status
+         ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, 'unmatched')",
+    )?;
+    for transaction in &credits {
+        stmt.execute(params![
+            transaction.id,
+            statement_id,
+            transaction.booking_date,
+            transaction.amount,
+            transaction.description.as_deref().unwrap_or(""),
+            transaction.reference,
+            transaction.payer_iban.as_deref().unwrap_or(""),
+            transaction.payer_name.as_deref().unwrap_or(""),
+            transaction.bank_reference,
+        ])?;
+    }
+
+    let mut changes = vec![record_change(conn, "bank_statements", "insert", std::slice::from_ref(&statement_id))?];
+    if !credits.is_empty() {
+        let ids: Vec<String> = credits.iter().map(|t| t.id.clone()).collect();
+        changes.push(record_change(conn, "bank_transactions", "insert", &ids)?);
+    }
+
+    let summary = StatementImport {
+        statement_id,
+        message_type: statement.message_type.clone(),
+        imported: credits.len(),
+        matched: 0,
+        skipped_debits: statement.transactions.iter().filter(|t| !t.is_credit).count(),
+        skipped_duplicates,
+        unparsed_lines: Vec::new(),
+    };
+    Ok((summary, changes))
+}
+
+/// Store a parsed statement in one transaction and notify views, see [`store_statement`].
+/// A file that was imported before is refused.
+pub fn import_statement(
+    app: &AppHandle,
+    file_name: &str,
+    file_type: &str,
+    file_sha256: &str,
+    statement: &BankStatement,
+) -> Result<StatementImport, String> {
+    let conn = get_connection(app).map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    check_not_imported(&tx, file_sha256)?;
+    let (mut summary, mut changes) = store_statement(&tx, file_name, file_type, file_sha256, statement)
+        .map_err(|e| format!("Napaka pri shranjevanju izpiska: {}", e))?;
+    let (run, match_changes) = match_transactions(&tx, Some(&summary.statement_id))
+        .map_err(|e| format!("Napaka pri povezovanju plačil: {}", e))?;
+    summary.matched = run.matched;
+    changes.extend(match_changes);
+    tx.commit().map_err(|e| e.to_string())?;
+    for change in &changes {
+        emit_change(app, change);
+    }
+    Ok(summary)
+}
+
+const CSV_PROFILE_COLUMNS: &str =
+    "id, name, bank, account_iban, delimiter, encoding, decimal_comma, date_format, skip_rows, has_header, columns";
+
+fn csv_profile_from_row(row: &Row) -> rusqlite::Result<CsvProfile> {
+    let columns: String = row.get(10)?;
+    Ok(CsvProfile {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        bank: row.get(2)?,
+        account_iban: row.get(3)?,
+        delimiter: row.get(4)?,
+        encoding: row.get(5)?,
+        decimal_comma: row.get(6)?,
+        date_format: row.get(7)?,
+        skip_rows: row.get::<_, i64>(8)?.max(0) as usize,
+        has_header: row.get(9)?,
+        // A mapping that no longer parses is shown empty rather than hiding the profile
+        columns: serde_json::from_str(&columns).unwrap_or_default(),
+    })
+}
+
+/// Saved CSV column mappings, by name
+pub fn list_csv_profiles(conn: &Connection) -> rusqlite::Result<Vec<CsvProfile>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM csv_import_profiles ORDER BY name COLLATE NOCASE",
+        CSV_PROFILE_COLUMNS
+    ))?;
+    let profiles = stmt.query_map([], csv_profile_from_row)?.collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(profiles)
+}
+
+/// Insert a profile without an id, or update the one with its id. Returns the stored profile.
+pub fn save_csv_profile(conn: &Connection, profile: &CsvProfile) -> Result<(CsvProfile, ChangeEvent), String> {
+    let name = profile.name.trim();
+    if name.is_empty() {
+        return Err("Profil mora imeti ime.".to_string());
+    }
+    // Duplicates are only looked for among statements of the same account
+    let account_iban = normalize_iban(&profile.account_iban);
+    if account_iban.is_empty() {
+        return Err("Profil mora imeti IBAN računa, s katerega je izvoz.".to_string());
+    }
+    let columns = serde_json::to_string(&profile.columns).map_err(|e| e.to_string())?;
+    let bank = profile.bank.as_deref().map(str::trim).filter(|b| !b.is_empty());
+    let (id, operation, sql) = match &profile.id {
+        Some(id) => (
+            id.clone(),
+            "update",
+            "UPDATE csv_import_profiles SET
+                name = ?2, bank = ?3, account_iban = ?4, delimiter = ?5, encoding = ?6,
+                decimal_comma = ?7, date_format = ?8, skip_rows = ?9, has_header = ?10, columns = ?11
+             WHERE id = ?1",
+        ),
+        None => (
+            generate_id("csv_import_profiles"),
+            "insert",
+            "INSERT INTO csv_import_profiles (
+                id, name, bank, account_iban, delimiter, encoding, decimal_comma, date_format, skip_rows, has_header, columns
+             ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        ),
+    };
+    let written = conn
+        .execute(
+            sql,
+            params![
+                id,
+                name,
+                bank,
+                account_iban,
+                profile.delimiter,
+                profile.encoding.trim(),
+                profile.decimal_comma,
+                profile.date_format,
+                profile.skip_rows as i64,
+                profile.has_header,
+                columns,
+            ],
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::SqliteFailure(f, _) if f.code == rusqlite::ErrorCode::ConstraintViolation => {
+                format!("Profil z imenom \"{}\" že obstaja.", name)
+            }
+            e => format!("Napaka pri shranjevanju profila: {}", e),
+        })?;
+    if written == 0 {
+        return Err("Profil ne obstaja več.".to_string());
+    }
+
+    let change = record_change(conn, "csv_import_profiles", operation, std::slice::from_ref(&id)).map_err(|e| e.to_string())?;
+    let saved = conn
+        .query_row(
+            &format!("SELECT {} FROM csv_import_profiles WHERE id = ?1", CSV_PROFILE_COLUMNS),
+            [&id],
+            csv_profile_from_row,
+        )
+        .map_err(|e| e.to_string())?;
+    Ok((saved, change))
+}
+
+/// Delete a saved profile
+pub fn delete_csv_profile(conn: &Connection, id: &str) -> rusqlite::Result<ChangeEvent> {
+    conn.execute("DELETE FROM csv_import_profiles WHERE id = ?1", [id])?;
+    record_change(conn, "csv_import_profiles", "delete", &[id.to_string()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../database/schema.sql")).unwrap();
+        conn
+    }
+
+    fn credit(id: &str, bank_reference: Option<&str>, booking_date: &str, amount: f64) -> BankTransaction {
+        BankTransaction {
+            id: id.to_string(),
+            bank_reference: bank_reference.map(str::to_string),
+            amount,
+            currency: "EUR".to_string(),
+            is_credit: true,
+            status: Some("BOOK".to_string()),
+            booking_date: booking_date.to_string(),
+            value_date: None,
+            payer_name: Some("NOVAK MOJCA".to_string()),
+            payer_iban: Some("SI56031001000012345".to_string()),
+            description: Some("Vadnina".to_string()),
+            reference: Some("SI12 1000-2025031".to_string()),
+            bank_fee: None,
+        }
+    }
+
+    fn statement(transactions: Vec<BankTransaction>) -> BankStatement {
+        BankStatement {
+            message_type: "camt.052.001.02".to_string(),
+            message_id: "1".to_string(),
+            creation_date_time: "2025-03-14T16:00:00".to_string(),
+            account_iban: "SI56020100012345678".to_string(),
+            account_owner: None,
+            transactions,
+            opening_balance: None,
+            closing_balance: None,
+        }
+    }
+
+    #[test]
+    fn refuses_a_file_imported_before() {
+        let conn = database();
+        store_statement(&conn, "porocilo.xml", "xml", "abc123", &statement(Vec::new())).unwrap();
+        let error = check_not_imported(&conn, "abc123").unwrap_err();
+        assert!(error.contains("porocilo.xml"), "{}", error);
+        assert!(check_not_imported(&conn, "def456").is_ok());
+    }
+
+    #[test]
+    fn skips_transactions_of_an_overlapping_report() {
+        let conn = database();
+        let first = statement(vec![
+            credit("txn-1", Some("REF-1"), "2025-03-13", 45.0),
+            credit("txn-2", None, "2025-03-14", 30.0),
+        ]);
+        let (summary, _) = store_statement(&conn, "13.xml", "xml", "a", &first).unwrap();
+        assert_eq!(summary.imported, 2);
+
+        // The next day's report repeats both, with a second equal payment and a new one
+        let second = statement(vec![
+            credit("txn-1", Some("REF-1"), "2025-03-13", 45.0),
+            credit("txn-2", None, "2025-03-14", 30.0),
+            credit("txn-3", None, "2025-03-14", 30.0),
+            credit("txn-4", Some("REF-4"), "2025-03-15", 45.0),
+        ]);
+        let (summary, _) = store_statement(&conn, "14.xml", "xml", "b", &second).unwrap();
+        assert_eq!(summary.imported, 2, "the second equal payment and the new one");
+        assert_eq!(summary.skipped_duplicates.len(), 2);
+        assert_eq!(summary.skipped_duplicates[0].existing_id, "txn-1");
+        assert_eq!(summary.skipped_duplicates[0].matched_on, DuplicateMatch::Id);
+        assert_eq!(summary.skipped_duplicates[1].existing_id, "txn-2");
+        assert_eq!(summary.skipped_duplicates[1].matched_on, DuplicateMatch::Id);
+
+        let stored: i64 = conn.query_row("SELECT COUNT(*) FROM bank_transactions", [], |row| row.get(0)).unwrap();
+        assert_eq!(stored, 4);
+    }
+
+    #[test]
+    fn falls_back_to_bank_reference_and_fingerprint() {
+        let conn = database();
+        let first = statement(vec![
+            credit("txn-1", Some("REF-1"), "2025-03-13", 45.0),
+            credit("txn-2", None, "2025-03-14", 30.0),
+        ]);
+        store_statement(&conn, "13.xml", "xml", "a", &first).unwrap();
+
+        // Another export of the same payments whose ids came out differently
+        let second = statement(vec![
+            credit("txn-1b", Some("REF-1"), "2025-03-13", 45.0),
+            credit("txn-2b", None, "2025-03-14", 30.0),
+        ]);
+        let (summary, _) = store_statement(&conn, "13.csv", "csv", "b", &second).unwrap();
+        assert_eq!(summary.imported, 0);
+        assert_eq!(summary.skipped_duplicates[0].existing_id, "txn-1");
+        assert_eq!(summary.skipped_duplicates[0].matched_on, DuplicateMatch::BankReference);
+        assert_eq!(summary.skipped_duplicates[1].existing_id, "txn-2");
+        assert_eq!(summary.skipped_duplicates[1].matched_on, DuplicateMatch::Fingerprint);
+    }
+
+    #[test]
+    fn payment_with_the_same_fingerprint_does_not_take_a_stored_id() {
+        let conn = database();
+        let first = statement(vec![credit("txn-b", None, "2025-03-14", 30.0)]);
+        store_statement(&conn, "14.xml", "xml", "a", &first).unwrap();
+
+        // C differs from B only in the purpose text, so it has its own id but B's fingerprint
+        let mut c = credit("txn-c", None, "2025-03-14", 30.0);
+        c.description = Some("Vadnina april".to_string());
+        let second = statement(vec![c, credit("txn-b", None, "2025-03-14", 30.0)]);
+        let (summary, _) = store_statement(&conn, "15.xml", "xml", "b", &second).unwrap();
+        assert_eq!(summary.imported, 1);
+        assert_eq!(summary.skipped_duplicates.len(), 1);
+        assert_eq!(summary.skipped_duplicates[0].existing_id, "txn-b");
+        assert_eq!(summary.skipped_duplicates[0].matched_on, DuplicateMatch::Id);
+
+        let ids: Vec<String> = conn
+            .prepare("SELECT id FROM bank_transactions ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(ids, vec!["txn-b", "txn-c"]);
+    }
+
+    #[test]
+    fn compares_only_with_the_same_account() {
+        let conn = database();
+        let first = statement(vec![
+            credit("txn-1", Some("REF-1"), "2025-03-13", 45.0),
+            credit("txn-2", None, "2025-03-14", 30.0),
+        ]);
+        store_statement(&conn, "trr.xml", "xml", "a", &first).unwrap();
+
+        // Another bank reuses the reference, and the same parent pays the same amount into the second account
+        let mut other = statement(vec![
+            credit("txn-3", Some("REF-1"), "2025-03-13", 45.0),
+            credit("txn-4", None, "2025-03-14", 30.0),
+        ]);
+        other.account_iban = "SI56191000000123438".to_string();
+        let (summary, _) = store_statement(&conn, "drugi-trr.xml", "xml", "b", &other).unwrap();
+        assert_eq!(summary.imported, 2);
+        assert!(summary.skipped_duplicates.is_empty());
+
+        let (summary, _) = store_statement(&conn, "drugi-trr-2.xml", "xml", "c", &other).unwrap();
+        assert_eq!(summary.imported, 0);
+        assert_eq!(summary.skipped_duplicates[0].existing_id, "txn-3");
+        assert_eq!(summary.skipped_duplicates[1].existing_id, "txn-4");
+    }
+
+    #[test]
+    fn csv_profile_needs_the_account_iban() {
+        let conn = database();
+        let mut profile = CsvProfile {
+            name: "NLB".to_string(),
+            ..CsvProfile::default()
+        };
+        let error = save_csv_profile(&conn, &profile).unwrap_err();
+        assert!(error.contains("IBAN"), "{}", error);
+
+        profile.account_iban = "si56 0201 0001 2345 678".to_string();
+        let (saved, _) = save_csv_profile(&conn, &profile).unwrap();
+        assert_eq!(saved.account_iban, "SI56020100012345678");
+    }
+}
@@ -0,0 +1,35 @@
+use roxmltree::Node;
+
+/// Whether `candidate` is an element called `name` in the namespace of `parent`.
+///
+/// Bank files use both a default namespace and prefixes (`ns2:`, `camt:`), so
+/// elements are matched by local name and namespace URI, never by prefix.
+fn is_element(parent: Node, candidate: Node, name: &str) -> bool {
+    candidate.is_element()
+        && candidate.tag_name().name() == name
+        && candidate.tag_name().namespace() == parent.tag_name().namespace()
+}
+
+/// First child element with the given local name, see [`is_element`]
+pub fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|c| is_element(node, *c, name))
+}
+
+/// All child elements with the given local name, see [`is_element`]
+pub fn children<'a, 'input: 'a>(node: Node<'a, 'input>, name: &'a str) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children().filter(move |c| is_element(node, *c, name))
+}
+
+/// Element at `path` below `node`, following the first match at every step
+pub fn find<'a, 'input>(node: Node<'a, 'input>, path: &[&str]) -> Option<Node<'a, 'input>> {
+    path.iter().try_fold(node, |current, name| child(current, name))
+}
+
+/// Trimmed text of the element at `path`, `None` when missing or empty
+pub fn text(node: Node, path: &[&str]) -> Option<String> {
+    find(node, path)
+        .and_then(|n| n.text())
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+}
@@ -0,0 +1,297 @@
+use crate::database::{replace_database_file, sha256_hex, snapshot_database_file, SCHEMA_VERSION};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{AesMode, CompressionMethod, ZipArchive, ZipWriter};
+
+/// File extension of tskpay backup archives
+pub const ARCHIVE_EXTENSION: &str = "tskpay";
+const ARCHIVE_FORMAT: &str = "tskpay-archive";
+const ARCHIVE_FORMAT_VERSION: u32 = 1;
+const MANIFEST_ENTRY: &str = "manifest.json";
+const DATABASE_ENTRY: &str = "database.db";
+
+/// Contents of `manifest.json`. The manifest is never encrypted, so an archive can be
+/// identified before the password is known; apart from the club name it holds no club data.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveManifest {
+    pub format: String,
+    pub format_version: u32,
+    pub app_version: String,
+    pub schema_version: i32,
+    pub created_at: String,
+    pub club_name: String,
+    pub encrypted: bool,
+    pub files: Vec<ArchiveFile>,
+}
+
+/// A file inside the archive with its SHA-256 checksum
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveFile {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Whether a path should be treated as a `.tskpay` archive rather than a plain SQLite file
+pub fn is_archive_path(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case(ARCHIVE_EXTENSION))
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".partial");
+    PathBuf::from(name)
+}
+
+/// Write a `.tskpay` archive: the database snapshot, which carries the attachments in
+/// `attachment_blobs`, and a manifest with checksums. With a password the database entry
+/// is AES-256 encrypted.
+pub fn write_archive_file(
+    db_path: &Path,
+    dest: &Path,
+    club_name: Option<&str>,
+    password: Option<&str>,
+    app_version: &str,
+) -> Result<ArchiveManifest, String> {
+    let password = password.filter(|p| !p.is_empty());
+
+    let snapshot_path = db_path.with_file_name("tskpay-archive-export.db.partial");
+    snapshot_database_file(db_path, &snapshot_path)?;
+    let result = write_archive_from_snapshot(app_version, &snapshot_path, dest, club_name, password);
+    let _ = fs::remove_file(&snapshot_path);
+    result
+}
+
+fn write_archive_from_snapshot(
+    app_version: &str,
+    snapshot_path: &Path,
+    dest: &Path,
+    club_name: Option<&str>,
+    password: Option<&str>,
+) -> Result<ArchiveManifest, String> {
+    let database = fs::read(snapshot_path)
+        .map_err(|e| format!("Napaka pri branju datoteke {}: {}", snapshot_path.display(), e))?;
+    let files = vec![ArchiveFile {
+        path: DATABASE_ENTRY.to_string(),
+        size: database.len() as u64,
+        sha256: sha256_hex(&database),
+    }];
+    let entries: Vec<(&str, &[u8])> = vec![(DATABASE_ENTRY, &database)];
+
+    let manifest = ArchiveManifest {
+        format: ARCHIVE_FORMAT.to_string(),
+        format_version: ARCHIVE_FORMAT_VERSION,
+        app_version: app_version.to_string(),
+        schema_version: SCHEMA_VERSION,
+        created_at: chrono::Local::now().to_rfc3339(),
+        club_name: club_name.unwrap_or("tskpay").to_string(),
+        encrypted: password.is_some(),
+        files,
+    };
+    let manifest_json = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| format!("Napaka pri pretvorbi manifesta: {}", e))?;
+
+    let tmp_path = partial_path(dest);
+    let file = fs::File::create(&tmp_path)
+        .map_err(|e| format!("Napaka pri ustvarjanju datoteke {}: {}", tmp_path.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let write_error = |e: zip::result::ZipError| format!("Napaka pri pisanju arhiva: {}", e);
+
+    zip.start_file(MANIFEST_ENTRY, options).map_err(write_error)?;
+    zip.write_all(&manifest_json).map_err(|e| format!("Napaka pri pisanju arhiva: {}", e))?;
+
+    for (name, data) in entries {
+        let entry_options = match password {
+            Some(password) => options.with_aes_encryption(AesMode::Aes256, password),
+            None => options,
+        };
+        zip.start_file(name, entry_options).map_err(write_error)?;
+        zip.write_all(data).map_err(|e| format!("Napaka pri pisanju arhiva: {}", e))?;
+    }
+    zip.finish().map_err(write_error)?;
+
+    fs::rename(&tmp_path, dest)
+        .map_err(|e| format!("Napaka pri premikanju datoteke {} v {}: {}", tmp_path.display(), dest.display(), e))?;
+
+    Ok(manifest)
+}
+
+fn open_archive(path: &Path) -> Result<ZipArchive<fs::File>, String> {
+    let file = fs::File::open(path)
+        .map_err(|e| format!("Napaka pri branju datoteke {}: {}", path.display(), e))?;
+    ZipArchive::new(file)
+        .map_err(|e| format!("Datoteka {} ni veljaven tskpay arhiv: {}", path.display(), e))
+}
+
+fn read_manifest(archive: &mut ZipArchive<fs::File>) -> Result<ArchiveManifest, String> {
+    let mut content = String::new();
+    archive
+        .by_name(MANIFEST_ENTRY)
+        .map_err(|_| "Arhiv ne vsebuje manifesta.".to_string())?
+        .read_to_string(&mut content)
+        .map_err(|e| format!("Napaka pri branju manifesta: {}", e))?;
+    let manifest: ArchiveManifest = serde_json::from_str(&content)
+        .map_err(|e| format!("Napaka pri branju manifesta: {}", e))?;
+
+    if manifest.format != ARCHIVE_FORMAT || manifest.format_version > ARCHIVE_FORMAT_VERSION {
+        return Err(format!(
+            "Nepodprta oblika arhiva: {} (verzija {}).",
+            manifest.format, manifest.format_version
+        ));
+    }
+
+    Ok(manifest)
+}
+
+/// Read the manifest of an archive without needing the password
+pub fn read_archive_manifest(path: &Path) -> Result<ArchiveManifest, String> {
+    read_manifest(&mut open_archive(path)?)
+}
+
+/// Read one entry and check it against the manifest checksum
+fn read_verified_entry(
+    archive: &mut ZipArchive<fs::File>,
+    file: &ArchiveFile,
+    password: Option<&str>,
+) -> Result<Vec<u8>, String> {
+    let entry = match password {
+        Some(password) => archive.by_name_decrypt(&file.path, password.as_bytes()),
+        None => archive.by_name(&file.path),
+    };
+    let mut entry = entry.map_err(|e| match e {
+        zip::result::ZipError::InvalidPassword => "Napačno geslo za arhiv.".to_string(),
+        zip::result::ZipError::UnsupportedArchive(zip::result::ZipError::PASSWORD_REQUIRED) => {
+            "Arhiv je zaščiten z geslom.".to_string()
+        }
+        e => format!("Napaka pri branju {} iz arhiva: {}", file.path, e),
+    })?;
+
+    let mut data = Vec::with_capacity(file.size as usize);
+    entry
+        .read_to_end(&mut data)
+        .map_err(|e| format!("Napaka pri branju {} iz arhiva: {}", file.path, e))?;
+
+    if sha256_hex(&data) != file.sha256 {
+        return Err(format!("Kontrolna vsota za {} se ne ujema, arhiv je poškodovan.", file.path));
+    }
+
+    Ok(data)
+}
+
+/// Verify the database snapshot of an archive and write it to `dest_db`
+pub fn extract_archive_database(path: &Path, password: Option<&str>, dest_db: &Path) -> Result<ArchiveManifest, String> {
+    let password = password.filter(|p| !p.is_empty());
+    let mut archive = open_archive(path)?;
+    let manifest = read_manifest(&mut archive)?;
+
+    if manifest.encrypted && password.is_none() {
+        return Err("Arhiv je zaščiten z geslom.".to_string());
+    }
+
+    let file = manifest
+        .files
+        .iter()
+        .find(|file| file.path == DATABASE_ENTRY)
+        .ok_or_else(|| "Arhiv ne vsebuje baze podatkov.".to_string())?;
+    let database = read_verified_entry(&mut archive, file, password)?;
+
+    fs::write(dest_db, database)
+        .map_err(|e| format!("Napaka pri zapisovanju datoteke {}: {}", dest_db.display(), e))?;
+
+    Ok(manifest)
+}
+
+/// Replace the database at `db_path` with the snapshot from a `.tskpay` archive.
+/// Returns the path of the safety copy of the previous database.
+pub fn import_archive_into(
+    db_path: &Path,
+    backups_dir: &Path,
+    path: &Path,
+    password: Option<&str>,
+) -> Result<String, String> {
+    let extracted = db_path.with_file_name("tskpay-archive-import.db.partial");
+    let result = extract_archive_database(path, password, &extracted)
+        .and_then(|_| replace_database_file(db_path, backups_dir, &extracted, "before-import"));
+    let _ = fs::remove_file(&extracted);
+    result
+}
+
+/// Show the manifest of an archive (club, versions, date) before importing it
+#[tauri::command]
+pub fn archive_manifest(path: String) -> Result<ArchiveManifest, String> {
+    read_archive_manifest(Path::new(&path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::store_attachment;
+    use rusqlite::Connection;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tskpay-archive-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn snapshot_with_attachment(dir: &Path) -> PathBuf {
+        let path = dir.join("snapshot.db");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(include_str!("schema.sql")).unwrap();
+        conn.execute("INSERT INTO parents (id, first_name, last_name) VALUES ('par-1', 'Mojca', 'Novak')", [])
+            .unwrap();
+        store_attachment(&conn, "parents", "par-1", "medical_certificate", "zdravniško-potrdilo.pdf", b"%PDF-1.4").unwrap();
+        path
+    }
+
+    #[test]
+    fn encrypted_archive_shows_only_the_club_name() {
+        let dir = scratch_dir("encrypted");
+        let snapshot = snapshot_with_attachment(&dir);
+        let dest = dir.join("varnostna.tskpay");
+        write_archive_from_snapshot("1.0.0", &snapshot, &dest, Some("TSK JUB Dol"), Some("geslo")).unwrap();
+
+        let mut archive = open_archive(&dest).unwrap();
+        assert_eq!(archive.len(), 2, "manifest and database only");
+        let mut manifest_json = String::new();
+        archive.by_name(MANIFEST_ENTRY).unwrap().read_to_string(&mut manifest_json).unwrap();
+        assert!(!manifest_json.contains("potrdilo"), "{}", manifest_json);
+        assert!(!manifest_json.contains("Novak"), "{}", manifest_json);
+        let manifest = read_archive_manifest(&dest).unwrap();
+        assert!(manifest.encrypted);
+        assert_eq!(manifest.club_name, "TSK JUB Dol");
+
+        let restored = dir.join("restored.db");
+        assert!(extract_archive_database(&dest, Some("napačno"), &restored).is_err());
+        extract_archive_database(&dest, Some("geslo"), &restored).unwrap();
+        let conn = Connection::open(&restored).unwrap();
+        let data: Vec<u8> = conn
+            .query_row(
+                "SELECT b.data FROM attachments a JOIN attachment_blobs b ON b.sha256 = a.sha256",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(data, b"%PDF-1.4");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn plain_archive_names_the_club() {
+        let dir = scratch_dir("plain");
+        let snapshot = snapshot_with_attachment(&dir);
+        let dest = dir.join("varnostna.tskpay");
+        let manifest = write_archive_from_snapshot("1.0.0", &snapshot, &dest, Some("TSK JUB Dol"), None).unwrap();
+        assert_eq!(manifest.club_name, "TSK JUB Dol");
+        assert!(!manifest.encrypted);
+        assert_eq!(read_archive_manifest(&dest).unwrap().files.len(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,398 @@
+use crate::database::introspection::list_tables;
+use crate::database::{emit_change, generate_id, get_connection, now_timestamp, record_change, table_columns};
+use rusqlite::{Connection, OptionalExtension, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::{DialogExt, FilePath};
+use tokio::sync::mpsc;
+
+/// Metadata of a stored file. The contents live in `attachment_blobs`, keyed by SHA-256,
+/// so the same file attached twice is stored once and every backup of the database
+/// carries the attachments with it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub id: String,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub kind: String,
+    pub file_name: String,
+    pub mime_type: Option<String>,
+    pub size: i64,
+    pub sha256: String,
+    pub notes: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+const ATTACHMENT_COLUMNS: &str =
+    "id, entity_type, entity_id, kind, file_name, mime_type, size, sha256, notes, created_at, updated_at";
+
+fn row_to_attachment(row: &rusqlite::Row) -> Result<Attachment> {
+    Ok(Attachment {
+        id: row.get(0)?,
+        entity_type: row.get(1)?,
+        entity_id: row.get(2)?,
+        kind: row.get(3)?,
+        file_name: row.get(4)?,
+        mime_type: row.get(5)?,
+        size: row.get(6)?,
+        sha256: row.get(7)?,
+        notes: row.get::<_, Option<String>>(8)?.unwrap_or_default(),
+        created_at: row.get(9)?,
+        updated_at: row.get(10)?,
+    })
+}
+
+/// Hex-encoded SHA-256 of a byte slice
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Guess the MIME type from the file extension
+fn guess_mime_type(file_name: &str) -> Option<String> {
+    let extension = file_name.rsplit_once('.')?.1.to_lowercase();
+    let mime = match extension.as_str() {
+        "pdf" => "application/pdf",
+        "xml" => "application/xml",
+        "csv" => "text/csv",
+        "txt" | "sta" | "mt940" => "text/plain",
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "heic" => "image/heic",
+        "doc" => "application/msword",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        _ => return None,
+    };
+    Some(mime.to_string())
+}
+
+/// The last component of an attachment's file name, or `None` for names that are empty or
+/// `.`/`..`. Both `/` and `\` count as separators on every platform, so a name gives the
+/// same file everywhere. Names reach the database from merge and dump imports too, so they
+/// are checked before they become part of a path.
+pub fn plain_file_name(file_name: &str) -> Option<&str> {
+    let name = file_name.rsplit(['/', '\\']).next()?;
+    if name.is_empty() || name == "." || name == ".." || name.contains('\0') {
+        return None;
+    }
+    Some(name)
+}
+
+/// Store a file and link it to a row of any table
+pub fn store_attachment(
+    conn: &Connection,
+    entity_type: &str,
+    entity_id: &str,
+    kind: &str,
+    file_name: &str,
+    data: &[u8],
+) -> Result<Attachment> {
+    // entity_type ends up in SQL, so only accept names of existing tables
+    if !list_tables(conn)?.iter().any(|t| t == entity_type) {
+        return Err(rusqlite::Error::InvalidParameterName(entity_type.to_string()));
+    }
+    let entity_exists = conn
+        .prepare(&format!("SELECT 1 FROM {} WHERE id = ?1", entity_type))?
+        .exists([entity_id])?;
+    if !entity_exists {
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+
+    if plain_file_name(file_name) != Some(file_name) {
+        return Err(rusqlite::Error::InvalidParameterName(file_name.to_string()));
+    }
+
+    let sha256 = sha256_hex(data);
+    let now = now_timestamp();
+    conn.execute(
+        "INSERT OR IGNORE INTO attachment_blobs (sha256, data, size, created_at) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![sha256, data, data.len() as i64, now],
+    )?;
+
+    let id = generate_id("attachments");
+    conn.execute(
+        "INSERT INTO attachments (id, entity_type, entity_id, kind, file_name, mime_type, size, sha256, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9)",
+        rusqlite::params![
+            id,
+            entity_type,
+            entity_id,
+            kind,
+            file_name,
+            guess_mime_type(file_name),
+            data.len() as i64,
+            sha256,
+            now
+        ],
+    )?;
+
+    get_attachment(conn, &id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
+
+pub fn get_attachment(conn: &Connection, id: &str) -> Result<Option<Attachment>> {
+    conn.query_row(
+        &format!("SELECT {} FROM attachments WHERE id = ?1", ATTACHMENT_COLUMNS),
+        [id],
+        row_to_attachment,
+    )
+    .optional()
+}
+
+/// List attachments of one entity, newest first
+pub fn list_attachments(conn: &Connection, entity_type: &str, entity_id: &str) -> Result<Vec<Attachment>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM attachments WHERE entity_type = ?1 AND entity_id = ?2 ORDER BY created_at DESC, id DESC",
+        ATTACHMENT_COLUMNS
+    ))?;
+    let rows = stmt.query_map([entity_type, entity_id], row_to_attachment)?;
+
+    let mut attachments = Vec::new();
+    for row_result in rows {
+        attachments.push(row_result?);
+    }
+
+    Ok(attachments)
+}
+
+/// Read the stored contents of an attachment
+pub fn read_attachment_data(conn: &Connection, id: &str) -> Result<(Attachment, Vec<u8>)> {
+    let attachment = get_attachment(conn, id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+    let data: Vec<u8> = conn.query_row(
+        "SELECT data FROM attachment_blobs WHERE sha256 = ?1",
+        [&attachment.sha256],
+        |row| row.get(0),
+    )?;
+    Ok((attachment, data))
+}
+
+/// Delete attachments whose entity row no longer exists, e.g. after the entity or a row it
+/// cascades to was deleted, together with contents nothing refers to any more.
+/// Returns the ids of the deleted attachments.
+pub fn delete_orphaned_attachments(conn: &Connection) -> Result<Vec<String>> {
+    let tables = list_tables(conn)?;
+    let mut stmt = conn.prepare("SELECT DISTINCT entity_type FROM attachments")?;
+    let entity_types = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut deleted = Vec::new();
+    for entity_type in entity_types {
+        // Attachments of unknown tables are left alone; entity_type only reaches SQL when it names a table
+        if !tables.contains(&entity_type) || !table_columns(conn, &entity_type)?.iter().any(|c| c == "id") {
+            continue;
+        }
+        let mut stmt = conn.prepare(&format!(
+            "DELETE FROM attachments WHERE entity_type = ?1 AND entity_id NOT IN (SELECT id FROM {}) RETURNING id",
+            entity_type
+        ))?;
+        let ids = stmt
+            .query_map([&entity_type], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>>>()?;
+        deleted.extend(ids);
+    }
+    if !deleted.is_empty() {
+        conn.execute(
+            "DELETE FROM attachment_blobs WHERE sha256 NOT IN (SELECT sha256 FROM attachments)",
+            [],
+        )?;
+    }
+    Ok(deleted)
+}
+
+/// Delete an attachment, and its contents once no other attachment refers to them
+pub fn delete_attachment(conn: &Connection, id: &str) -> Result<()> {
+    let Some(attachment) = get_attachment(conn, id)? else {
+        return Ok(());
+    };
+    conn.execute("DELETE FROM attachments WHERE id = ?1", [id])?;
+    conn.execute(
+        "DELETE FROM attachment_blobs WHERE sha256 = ?1 AND NOT EXISTS (SELECT 1 FROM attachments WHERE sha256 = ?1)",
+        [&attachment.sha256],
+    )?;
+    Ok(())
+}
+
+async fn pick_file_path(app: &AppHandle, title: &str) -> Result<PathBuf, String> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    app.dialog()
+        .file()
+        .set_title(title)
+        .add_filter("Dokumenti", &["pdf", "xml", "jpg", "jpeg", "png", "heic", "txt", "csv", "doc", "docx"])
+        .add_filter("All Files", &["*"])
+        .pick_file(move |dialog_result| {
+            let _ = tx.send(dialog_result);
+        });
+
+    let file_path = rx.recv().await.ok_or_else(|| "Napaka pri komunikaciji z dialogom.".to_string())?;
+
+    match file_path {
+        Some(FilePath::Path(p)) => Ok(p),
+        Some(FilePath::Url(_)) => Err("Podpora za URL poti ni na voljo.".to_string()),
+        None => Err("Izbira datoteke je bila preklicana.".to_string()),
+    }
+}
+
+/// Attach a file to an entity. Without `file_path` a file dialog is shown.
+#[tauri::command]
+pub async fn attachment_upload(
+    app: AppHandle,
+    entity_type: String,
+    entity_id: String,
+    kind: Option<String>,
+    file_path: Option<String>,
+) -> Result<Attachment, String> {
+    let source_path = match file_path {
+        Some(p) => PathBuf::from(p),
+        None => pick_file_path(&app, "Izberi prilogo").await?,
+    };
+
+    let data = fs::read(&source_path)
+        .map_err(|e| format!("Napaka pri branju datoteke {}: {}", source_path.display(), e))?;
+    let file_name = source_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| "Napaka pri pretvorbi poti datoteke.".to_string())?;
+
+    let conn = get_connection(&app).map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let attachment = store_attachment(&tx, &entity_type, &entity_id, kind.as_deref().unwrap_or("other"), &file_name, &data)
+        .map_err(|e| format!("Napaka pri shranjevanju priloge: {}", e))?;
+    let change = record_change(&tx, "attachments", "insert", std::slice::from_ref(&attachment.id)).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    emit_change(&app, &change);
+
+    Ok(attachment)
+}
+
+#[tauri::command]
+pub fn attachment_list(app: AppHandle, entity_type: String, entity_id: String) -> Result<Vec<Attachment>, String> {
+    let conn = get_connection(&app).map_err(|e| e.to_string())?;
+    list_attachments(&conn, &entity_type, &entity_id).map_err(|e| e.to_string())
+}
+
+/// Open an attachment with the default application of the operating system
+#[tauri::command]
+pub fn attachment_open(app: AppHandle, id: String) -> Result<(), String> {
+    let conn = get_connection(&app).map_err(|e| e.to_string())?;
+    let (attachment, data) = read_attachment_data(&conn, &id)
+        .map_err(|e| format!("Napaka pri branju priloge: {}", e))?;
+
+    // The viewer needs a real file, so write a copy into the app cache directory
+    let cache_dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| format!("Napaka pri iskanju začasnega direktorija: {}", e))?
+        .join("attachments");
+    fs::create_dir_all(&cache_dir)
+        .map_err(|e| format!("Napaka pri ustvarjanju direktorija {}: {}", cache_dir.display(), e))?;
+    let file_name = plain_file_name(&attachment.file_name)
+        .ok_or_else(|| format!("Ime priloge »{}« ni veljavno.", attachment.file_name))?;
+    let open_path = cache_dir.join(format!("{}-{}", attachment.id, file_name));
+    fs::write(&open_path, data)
+        .map_err(|e| format!("Napaka pri zapisovanju datoteke {}: {}", open_path.display(), e))?;
+
+    open::that(&open_path).map_err(|e| format!("Napaka pri odpiranju priloge: {}", e))
+}
+
+/// Save a copy of an attachment to a user-selected location
+#[tauri::command]
+pub async fn attachment_export(app: AppHandle, id: String) -> Result<String, String> {
+    let (attachment, data) = {
+        let conn = get_connection(&app).map_err(|e| e.to_string())?;
+        read_attachment_data(&conn, &id).map_err(|e| format!("Napaka pri branju priloge: {}", e))?
+    };
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    app.dialog()
+        .file()
+        .set_title("Shrani prilogo")
+        .set_file_name(plain_file_name(&attachment.file_name).unwrap_or("priloga"))
+        .add_filter("All Files", &["*"])
+        .save_file(move |dialog_result| {
+            let _ = tx.send(dialog_result);
+        });
+
+    let file_path = rx.recv().await.ok_or_else(|| "Napaka pri komunikaciji z dialogom.".to_string())?;
+
+    let file_path = match file_path {
+        Some(path) => match path {
+            FilePath::Path(p) => p,
+            FilePath::Url(_) => return Err("Podpora za URL poti ni na voljo.".to_string()),
+        },
+        None => return Err("Izvoz je bil preklican.".to_string()),
+    };
+
+    fs::write(&file_path, data)
+        .map_err(|e| format!("Napaka pri shranjevanju datoteke: {}", e))?;
+
+    file_path
+        .to_str()
+        .ok_or_else(|| "Napaka pri pretvorbi poti datoteke.".to_string())
+        .map(|s| s.to_string())
+}
+
+#[tauri::command]
+pub fn attachment_delete(app: AppHandle, id: String) -> Result<(), String> {
+    let conn = get_connection(&app).map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    delete_attachment(&tx, &id).map_err(|e| e.to_string())?;
+    let change = record_change(&tx, "attachments", "delete", &[id]).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    emit_change(&app, &change);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("schema.sql")).unwrap();
+        conn.execute_batch(
+            "INSERT INTO parents (id, first_name, last_name) VALUES ('par-1', 'Mojca', 'Novak'), ('par-2', 'Ana', 'Horvat');",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn keeps_only_plain_file_names() {
+        assert_eq!(plain_file_name("zdravniško.pdf"), Some("zdravniško.pdf"));
+        assert_eq!(plain_file_name("../../x.desktop"), Some("x.desktop"));
+        assert_eq!(plain_file_name("..\\..\\x.desktop"), Some("x.desktop"));
+        assert_eq!(plain_file_name("C:\\Users\\ana\\"), None);
+        assert_eq!(plain_file_name(".."), None);
+        assert_eq!(plain_file_name(""), None);
+
+        let conn = database();
+        assert!(store_attachment(&conn, "parents", "par-1", "other", "../x.desktop", b"x").is_err());
+        assert!(store_attachment(&conn, "parents", "par-1", "other", "potrdilo.pdf", b"x").is_ok());
+    }
+
+    #[test]
+    fn deletes_attachments_of_deleted_entities() {
+        let conn = database();
+        let kept = store_attachment(&conn, "parents", "par-1", "other", "a.pdf", b"same").unwrap();
+        let orphan = store_attachment(&conn, "parents", "par-2", "other", "b.pdf", b"same").unwrap();
+        let orphan_only = store_attachment(&conn, "parents", "par-2", "other", "c.pdf", b"own").unwrap();
+        conn.execute("DELETE FROM parents WHERE id = 'par-2'", []).unwrap();
+
+        let mut deleted = delete_orphaned_attachments(&conn).unwrap();
+        deleted.sort();
+        let mut expected = vec![orphan.id, orphan_only.id];
+        expected.sort();
+        assert_eq!(deleted, expected);
+
+        // Contents still used by another attachment stay
+        assert!(read_attachment_data(&conn, &kept.id).is_ok());
+        let blobs: i64 = conn.query_row("SELECT COUNT(*) FROM attachment_blobs", [], |row| row.get(0)).unwrap();
+        assert_eq!(blobs, 1);
+    }
+}
@@ -0,0 +1,469 @@
+use crate::database::{copy_verified, get_db_path, snapshot_database};
+use chrono::{Datelike, Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+const SETTINGS_FILENAME: &str = "backup-settings.json";
+/// Mirror warnings of the last automatic backup, kept for views opened after the event was emitted
+const WARNINGS_FILENAME: &str = "backup-warnings.json";
+const BACKUPS_DIRNAME: &str = "backups";
+const AUTO_BACKUP_PREFIX: &str = "tskpay-auto-";
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d-%H%M%S";
+/// How often the background job checks whether the daily backup is due
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const DAILY_INTERVAL_HOURS: i64 = 24;
+/// Tauri event emitted when a backup could not be copied to a mirror directory
+pub const BACKUP_WARNING_EVENT: &str = "backup-warning";
+
+/// Automatic backup configuration, stored as JSON next to the database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BackupSettings {
+    pub enabled: bool,
+    /// Number of most recent days that keep their newest backup
+    pub keep_daily: u32,
+    /// Number of most recent ISO weeks that keep their newest backup
+    pub keep_weekly: u32,
+    /// Number of most recent months that keep their newest backup
+    pub keep_monthly: u32,
+    /// Extra directories (USB stick, network share) every automatic backup is copied to
+    pub mirror_dirs: Vec<String>,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            keep_daily: 7,
+            keep_weekly: 4,
+            keep_monthly: 12,
+            mirror_dirs: Vec::new(),
+        }
+    }
+}
+
+/// Why an automatic backup was taken; ends up in the file name
+#[derive(Debug, Clone, Copy)]
+pub enum BackupTrigger {
+    Startup,
+    Shutdown,
+    Daily,
+    Manual,
+}
+
+impl BackupTrigger {
+    fn as_str(self) -> &'static str {
+        match self {
+            BackupTrigger::Startup => "startup",
+            BackupTrigger::Shutdown => "shutdown",
+            BackupTrigger::Daily => "daily",
+            BackupTrigger::Manual => "manual",
+        }
+    }
+}
+
+/// Outcome of copying a backup to one mirror directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MirrorCopy {
+    pub dir: String,
+    /// Path of the verified copy, when it succeeded
+    pub path: Option<String>,
+    pub error: Option<String>,
+    /// The copy succeeded, but old backups in the mirror directory could not be pruned
+    pub prune_error: Option<String>,
+}
+
+impl MirrorCopy {
+    fn is_warning(&self) -> bool {
+        self.error.is_some() || self.prune_error.is_some()
+    }
+}
+
+/// A finished automatic backup and its mirror copies
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupRun {
+    pub path: String,
+    pub mirrors: Vec<MirrorCopy>,
+}
+
+/// Directory with automatic backups, next to the database file
+pub fn get_backups_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let db_path = get_db_path(app).map_err(|e| e.to_string())?;
+    let parent = db_path
+        .parent()
+        .ok_or_else(|| "Napaka pri iskanju direktorija baze podatkov.".to_string())?;
+    Ok(parent.join(BACKUPS_DIRNAME))
+}
+
+fn get_warnings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let db_path = get_db_path(app).map_err(|e| e.to_string())?;
+    Ok(db_path.with_file_name(WARNINGS_FILENAME))
+}
+
+/// Mirror copies of the last automatic backup that failed or could not be pruned
+pub fn load_backup_warnings(app: &AppHandle) -> Result<Vec<MirrorCopy>, String> {
+    let path = get_warnings_path(app)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Napaka pri branju opozoril {}: {}", path.display(), e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Napaka pri branju opozoril {}: {}", path.display(), e))
+}
+
+/// Replace the stored warnings with those of the latest backup, so a clean run clears them
+fn save_backup_warnings(app: &AppHandle, mirrors: &[MirrorCopy]) -> Result<(), String> {
+    let path = get_warnings_path(app)?;
+    let warnings: Vec<&MirrorCopy> = mirrors.iter().filter(|m| m.is_warning()).collect();
+    let content = serde_json::to_string_pretty(&warnings)
+        .map_err(|e| format!("Napaka pri pretvorbi opozoril: {}", e))?;
+    fs::write(&path, content)
+        .map_err(|e| format!("Napaka pri shranjevanju opozoril {}: {}", path.display(), e))
+}
+
+fn get_settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let db_path = get_db_path(app).map_err(|e| e.to_string())?;
+    Ok(db_path.with_file_name(SETTINGS_FILENAME))
+}
+
+/// Load backup settings, falling back to the defaults when none were saved yet
+pub fn load_backup_settings(app: &AppHandle) -> Result<BackupSettings, String> {
+    let path = get_settings_path(app)?;
+    if !path.exists() {
+        return Ok(BackupSettings::default());
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Napaka pri branju nastavitev {}: {}", path.display(), e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Napaka pri branju nastavitev {}: {}", path.display(), e))
+}
+
+pub fn save_backup_settings(app: &AppHandle, settings: &BackupSettings) -> Result<(), String> {
+    let path = get_settings_path(app)?;
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Napaka pri pretvorbi nastavitev: {}", e))?;
+    fs::write(&path, content)
+        .map_err(|e| format!("Napaka pri shranjevanju nastavitev {}: {}", path.display(), e))
+}
+
+/// Parse the creation time from an automatic backup file name
+/// (`tskpay-auto-2025-01-31-183000-daily.db`)
+fn parse_auto_backup_time(file_name: &str) -> Option<NaiveDateTime> {
+    let rest = file_name.strip_prefix(AUTO_BACKUP_PREFIX)?;
+    let timestamp = rest.get(..17)?;
+    NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()
+}
+
+/// All automatic backups in the backups directory, newest first
+pub fn list_auto_backups(dir: &Path) -> Vec<(PathBuf, NaiveDateTime)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut backups: Vec<(PathBuf, NaiveDateTime)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.ends_with(".db") {
+                return None;
+            }
+            parse_auto_backup_time(&name).map(|time| (entry.path(), time))
+        })
+        .collect();
+    backups.sort_by_key(|(_, time)| std::cmp::Reverse(*time));
+    backups
+}
+
+/// Pick the backups to keep under a grandfather-father-son rule: the newest backup of each
+/// of the last `keep_daily` days, `keep_weekly` weeks and `keep_monthly` months. The newest
+/// backup is always kept, so pruning never removes the one just taken.
+/// `backups` must be sorted newest first.
+fn select_retained(backups: &[(PathBuf, NaiveDateTime)], settings: &BackupSettings) -> HashSet<PathBuf> {
+    let mut keep: HashSet<PathBuf> = backups.first().map(|(path, _)| path.clone()).into_iter().collect();
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    let mut months = HashSet::new();
+
+    for (path, time) in backups {
+        let date = time.date();
+        if days.len() < settings.keep_daily as usize && days.insert(date) {
+            keep.insert(path.clone());
+        }
+        let week = date.iso_week();
+        if weeks.len() < settings.keep_weekly as usize && weeks.insert((week.year(), week.week())) {
+            keep.insert(path.clone());
+        }
+        if months.len() < settings.keep_monthly as usize && months.insert((date.year(), date.month())) {
+            keep.insert(path.clone());
+        }
+    }
+
+    keep
+}
+
+/// Delete automatic backups that fall outside the retention policy, returns the deleted paths
+pub fn prune_auto_backups(dir: &Path, settings: &BackupSettings) -> Result<Vec<PathBuf>, String> {
+    let backups = list_auto_backups(dir);
+    let keep = select_retained(&backups, settings);
+
+    let mut deleted = Vec::new();
+    for (path, _) in backups {
+        if !keep.contains(&path) {
+            fs::remove_file(&path)
+                .map_err(|e| format!("Napaka pri brisanju backup-a {}: {}", path.display(), e))?;
+            deleted.push(path);
+        }
+    }
+
+    Ok(deleted)
+}
+
+/// Copy a backup to every mirror directory and apply the retention policy there as well.
+/// A failing mirror doesn't stop the others; failures, and copies whose pruning failed, are
+/// reported and emitted as warning events.
+fn mirror_backup(app: &AppHandle, path: &Path, settings: &BackupSettings) -> Vec<MirrorCopy> {
+    let mut mirrors = Vec::new();
+    for dir in settings.mirror_dirs.iter().filter(|d| !d.trim().is_empty()) {
+        let mirror_dir = PathBuf::from(dir);
+        let mirror = match copy_verified(path, &mirror_dir) {
+            Ok(copy) => MirrorCopy {
+                dir: dir.clone(),
+                path: Some(copy.to_string_lossy().to_string()),
+                error: None,
+                prune_error: prune_auto_backups(&mirror_dir, settings).err(),
+            },
+            Err(e) => MirrorCopy {
+                dir: dir.clone(),
+                path: None,
+                error: Some(e),
+                prune_error: None,
+            },
+        };
+        if mirror.is_warning() {
+            eprintln!(
+                "Backup mirror {}: {}",
+                dir,
+                mirror.error.as_deref().or(mirror.prune_error.as_deref()).unwrap_or_default()
+            );
+            let _ = app.emit(BACKUP_WARNING_EVENT, &mirror);
+        }
+        mirrors.push(mirror);
+    }
+    mirrors
+}
+
+/// Write a timestamped snapshot into the backups directory, copy it to the mirror
+/// directories and apply the retention policy
+pub fn run_auto_backup(app: &AppHandle, trigger: BackupTrigger) -> Result<BackupRun, String> {
+    let settings = load_backup_settings(app)?;
+    let dir = get_backups_dir(app)?;
+    let timestamp = Local::now().format(TIMESTAMP_FORMAT);
+    let path = dir.join(format!("{}{}-{}.db", AUTO_BACKUP_PREFIX, timestamp, trigger.as_str()));
+
+    snapshot_database(app, &path)?;
+    let mirrors = mirror_backup(app, &path, &settings);
+    // The startup backup runs before the window listens for warning events
+    if let Err(e) = save_backup_warnings(app, &mirrors) {
+        eprintln!("{}", e);
+    }
+    prune_auto_backups(&dir, &settings)?;
+
+    Ok(BackupRun {
+        path: path.to_string_lossy().to_string(),
+        mirrors,
+    })
+}
+
+/// Take a backup if automatic backups are enabled; failures are logged, never fatal
+pub fn run_scheduled_backup(app: &AppHandle, trigger: BackupTrigger) {
+    match load_backup_settings(app) {
+        Ok(settings) if !settings.enabled => return,
+        Err(e) => {
+            eprintln!("Automatic {} backup skipped, settings could not be loaded: {}", trigger.as_str(), e);
+            return;
+        }
+        Ok(_) => {}
+    }
+
+    if let Err(e) = run_auto_backup(app, trigger) {
+        eprintln!("Automatic {} backup failed: {}", trigger.as_str(), e);
+    }
+}
+
+/// Back up on startup, then check every hour whether the daily backup is due
+pub fn start_backup_scheduler(app: AppHandle) {
+    std::thread::spawn(move || {
+        run_scheduled_backup(&app, BackupTrigger::Startup);
+
+        loop {
+            std::thread::sleep(CHECK_INTERVAL);
+
+            let Ok(dir) = get_backups_dir(&app) else {
+                continue;
+            };
+            let due = match list_auto_backups(&dir).first() {
+                Some((_, newest)) => {
+                    Local::now().naive_local() - *newest >= chrono::Duration::hours(DAILY_INTERVAL_HOURS)
+                }
+                None => true,
+            };
+            if due {
+                run_scheduled_backup(&app, BackupTrigger::Daily);
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_backup_settings(app: AppHandle) -> Result<BackupSettings, String> {
+    load_backup_settings(&app)
+}
+
+/// Mirror warnings of the last automatic backup, including ones emitted before the window opened
+#[tauri::command]
+pub fn backup_warnings(app: AppHandle) -> Result<Vec<MirrorCopy>, String> {
+    load_backup_warnings(&app)
+}
+
+/// Save backup settings and immediately apply the new retention policy
+#[tauri::command]
+pub fn set_backup_settings(app: AppHandle, settings: BackupSettings) -> Result<BackupSettings, String> {
+    if settings.keep_daily == 0 && settings.keep_weekly == 0 && settings.keep_monthly == 0 {
+        return Err("Ohraniti je treba vsaj en dnevni, tedenski ali mesečni backup.".to_string());
+    }
+    save_backup_settings(&app, &settings)?;
+    prune_auto_backups(&get_backups_dir(&app)?, &settings)?;
+    Ok(settings)
+}
+
+/// Take an automatic-style backup right now, including its mirror copies
+#[tauri::command]
+pub fn run_backup_now(app: AppHandle) -> Result<BackupRun, String> {
+    run_auto_backup(&app, BackupTrigger::Manual)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backups(times: &[&str]) -> Vec<(PathBuf, NaiveDateTime)> {
+        let mut backups: Vec<(PathBuf, NaiveDateTime)> = times
+            .iter()
+            .map(|time| {
+                let time = NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M").unwrap();
+                let name = format!("{}{}-daily.db", AUTO_BACKUP_PREFIX, time.format(TIMESTAMP_FORMAT));
+                (PathBuf::from(name), time)
+            })
+            .collect();
+        backups.sort_by_key(|(_, time)| std::cmp::Reverse(*time));
+        backups
+    }
+
+    fn retained(backups: &[(PathBuf, NaiveDateTime)], daily: u32, weekly: u32, monthly: u32) -> Vec<String> {
+        let settings = BackupSettings {
+            keep_daily: daily,
+            keep_weekly: weekly,
+            keep_monthly: monthly,
+            ..BackupSettings::default()
+        };
+        let keep = select_retained(backups, &settings);
+        let mut times: Vec<String> = backups
+            .iter()
+            .filter(|(path, _)| keep.contains(path))
+            .map(|(_, time)| time.format("%Y-%m-%d %H:%M").to_string())
+            .collect();
+        times.sort();
+        times
+    }
+
+    #[test]
+    fn parses_the_time_from_the_file_name() {
+        let time = parse_auto_backup_time("tskpay-auto-2025-01-31-183000-daily.db").unwrap();
+        assert_eq!(time.format("%Y-%m-%d %H:%M:%S").to_string(), "2025-01-31 18:30:00");
+        assert!(parse_auto_backup_time("tskpay-2025-01-31-183000.db").is_none());
+        assert!(parse_auto_backup_time("tskpay-auto-zadnji.db").is_none());
+    }
+
+    #[test]
+    fn keeps_the_newest_backup_of_each_day() {
+        let all = backups(&["2025-03-12 08:00", "2025-03-12 20:00", "2025-03-13 08:00", "2025-03-14 08:00", "2025-03-14 20:00"]);
+        assert_eq!(retained(&all, 2, 0, 0), vec!["2025-03-13 08:00", "2025-03-14 20:00"]);
+    }
+
+    #[test]
+    fn counts_weeks_by_iso_week() {
+        // Sunday 2025-03-09 ends ISO week 10, Monday 2025-03-10 starts week 11
+        let all = backups(&["2025-03-08 10:00", "2025-03-09 10:00", "2025-03-10 10:00", "2025-03-16 10:00"]);
+        assert_eq!(retained(&all, 0, 2, 0), vec!["2025-03-09 10:00", "2025-03-16 10:00"]);
+
+        // 2024-12-30 already belongs to ISO week 1 of 2025, together with 2025-01-05
+        let all = backups(&["2024-12-29 10:00", "2024-12-30 10:00", "2025-01-05 10:00"]);
+        assert_eq!(retained(&all, 0, 2, 0), vec!["2024-12-29 10:00", "2025-01-05 10:00"]);
+    }
+
+    #[test]
+    fn counts_months_by_calendar_month() {
+        let all = backups(&["2025-01-31 23:00", "2025-02-01 00:30", "2025-02-28 12:00", "2025-03-01 09:00"]);
+        assert_eq!(retained(&all, 0, 0, 2), vec!["2025-02-28 12:00", "2025-03-01 09:00"]);
+        assert_eq!(retained(&all, 0, 0, 3), vec!["2025-01-31 23:00", "2025-02-28 12:00", "2025-03-01 09:00"]);
+    }
+
+    #[test]
+    fn combines_daily_weekly_and_monthly() {
+        // Two days, the newest of weeks 11 and 10, and January as the third month
+        let all = backups(&[
+            "2025-01-15 10:00",
+            "2025-02-20 10:00",
+            "2025-03-03 10:00",
+            "2025-03-12 10:00",
+            "2025-03-13 10:00",
+            "2025-03-14 10:00",
+        ]);
+        assert_eq!(
+            retained(&all, 2, 2, 3),
+            vec!["2025-01-15 10:00", "2025-02-20 10:00", "2025-03-03 10:00", "2025-03-13 10:00", "2025-03-14 10:00"]
+        );
+    }
+
+    #[test]
+    fn always_keeps_the_newest_backup() {
+        let all = backups(&["2025-03-13 08:00", "2025-03-14 08:00"]);
+        assert_eq!(retained(&all, 0, 0, 0), vec!["2025-03-14 08:00"]);
+        assert!(retained(&[], 0, 0, 0).is_empty());
+    }
+
+    #[test]
+    fn prunes_backups_outside_the_policy() {
+        let dir = std::env::temp_dir().join(format!("tskpay-auto-backup-prune-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for name in [
+            "tskpay-auto-2025-03-13-080000-daily.db",
+            "tskpay-auto-2025-03-14-080000-startup.db",
+            "tskpay-auto-2025-03-14-200000-shutdown.db",
+            "tskpay-rocni.db",
+        ] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+
+        let settings = BackupSettings {
+            keep_daily: 0,
+            keep_weekly: 0,
+            keep_monthly: 0,
+            ..BackupSettings::default()
+        };
+        let deleted = prune_auto_backups(&dir, &settings).unwrap();
+        assert_eq!(deleted.len(), 2);
+        assert!(dir.join("tskpay-auto-2025-03-14-200000-shutdown.db").exists());
+        assert!(dir.join("tskpay-rocni.db").exists(), "manual backups are not pruned");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
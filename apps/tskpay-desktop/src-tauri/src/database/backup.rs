@@ -1,185 +1,645 @@
-use crate::database::get_db_path;
+use crate::database::archive::{import_archive_into, is_archive_path, write_archive_file, ARCHIVE_EXTENSION};
+use crate::database::{checkpoint_database, get_backups_dir, get_db_path, migrate_database, open_database, wal_sidecar_paths, REQUIRED_TABLES, SCHEMA_VERSION};
+use rusqlite::{Connection, OpenFlags};
+use sha2::{Digest, Sha256};
 use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use tauri::AppHandle;
 use tauri_plugin_dialog::{DialogExt, FilePath};
 use tokio::sync::mpsc;
 
-/// Export database to a user-selected location
-#[tauri::command]
-pub async fn export_database(app: AppHandle) -> Result<String, String> {
-    let db_path = get_db_path(&app).map_err(|e| e.to_string())?;
+/// Run `PRAGMA integrity_check` on a database file, opened read-only
+pub fn verify_database_integrity(path: &Path) -> Result<(), String> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Napaka pri odpiranju baze podatkov {}: {}", path.display(), e))?;
+    let mut stmt = conn
+        .prepare("PRAGMA integrity_check")
+        .map_err(|e| format!("Napaka pri preverjanju baze podatkov {}: {}", path.display(), e))?;
+    let messages = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<String>>>())
+        .map_err(|e| format!("Napaka pri preverjanju baze podatkov {}: {}", path.display(), e))?;
 
-    // Check if database exists
+    if messages.len() == 1 && messages[0] == "ok" {
+        Ok(())
+    } else {
+        Err(format!(
+            "Baza podatkov {} ni celovita: {}",
+            path.display(),
+            messages.join("; ")
+        ))
+    }
+}
+
+/// Write a consistent, compacted copy of the app database to `dest`.
+///
+/// Uses `VACUUM INTO`, which reads the database inside a single read transaction, so
+/// concurrent writes can't tear the copy. The snapshot is written to a temporary file,
+/// checked with `integrity_check` and only then renamed to `dest`.
+pub fn snapshot_database(app: &AppHandle, dest: &Path) -> Result<(), String> {
+    let db_path = get_db_path(app).map_err(|e| e.to_string())?;
+    snapshot_database_file(&db_path, dest)
+}
+
+/// [`snapshot_database`] of the database file at `db_path`
+pub fn snapshot_database_file(db_path: &Path, dest: &Path) -> Result<(), String> {
     if !db_path.exists() {
         return Err("Baza podatkov ne obstaja.".to_string());
     }
 
-    // Generate default filename with timestamp
-    let timestamp = chrono::Local::now().format("%Y-%m-%d-%H%M%S");
-    let default_filename = format!("tskpay-backup-{}.db", timestamp);
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Napaka pri ustvarjanju direktorija {}: {}", parent.display(), e))?;
+    }
+
+    let mut tmp_name = dest.as_os_str().to_os_string();
+    tmp_name.push(".partial");
+    let tmp_path = PathBuf::from(tmp_name);
+    if tmp_path.exists() {
+        fs::remove_file(&tmp_path)
+            .map_err(|e| format!("Napaka pri brisanju datoteke {}: {}", tmp_path.display(), e))?;
+    }
+    let tmp_str = tmp_path
+        .to_str()
+        .ok_or_else(|| "Napaka pri pretvorbi poti datoteke.".to_string())?;
+
+    let conn = open_database(db_path).map_err(|e| e.to_string())?;
+    conn.execute("VACUUM INTO ?1", [tmp_str])
+        .map_err(|e| format!("Napaka pri kopiranju baze podatkov od {} do {}: {}", db_path.display(), dest.display(), e))?;
+    drop(conn);
+
+    if let Err(e) = verify_database_integrity(&tmp_path) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+
+    fs::rename(&tmp_path, dest)
+        .map_err(|e| format!("Napaka pri premikanju datoteke {} v {}: {}", tmp_path.display(), dest.display(), e))?;
+
+    Ok(())
+}
+
+/// Hex-encoded SHA-256 of a file, read in chunks
+pub fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = fs::File::open(path)
+        .map_err(|e| format!("Napaka pri branju datoteke {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)
+        .map_err(|e| format!("Napaka pri branju datoteke {}: {}", path.display(), e))?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Copy a file into `dest_dir` and check the copy against the SHA-256 of the original.
+///
+/// The copy is written under a temporary name and only renamed once the checksums match,
+/// so an interrupted or corrupted copy never looks like a valid backup. The directory must
+/// already exist: a missing mount point should not be recreated on the local disk.
+pub fn copy_verified(source: &Path, dest_dir: &Path) -> Result<PathBuf, String> {
+    if !dest_dir.is_dir() {
+        return Err(format!("Direktorij {} ni dosegljiv.", dest_dir.display()));
+    }
+    let file_name = source
+        .file_name()
+        .ok_or_else(|| "Napaka pri pretvorbi poti datoteke.".to_string())?;
+    let dest = dest_dir.join(file_name);
+    let mut tmp_name = dest.as_os_str().to_os_string();
+    tmp_name.push(".partial");
+    let tmp_path = PathBuf::from(tmp_name);
 
+    let expected = sha256_file(source)?;
+    let copied = fs::copy(source, &tmp_path)
+        .map_err(|e| format!("Napaka pri kopiranju v {}: {}", dest_dir.display(), e))
+        .and_then(|_| sha256_file(&tmp_path));
+    match copied {
+        Ok(actual) if actual == expected => {}
+        Ok(_) => {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(format!("Kontrolna vsota kopije v {} se ne ujema z izvirnikom.", dest_dir.display()));
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+    }
+
+    fs::rename(&tmp_path, &dest)
+        .map_err(|e| format!("Napaka pri premikanju datoteke {} v {}: {}", tmp_path.display(), dest.display(), e))?;
+    Ok(dest)
+}
+
+/// Show a save dialog and wait for the chosen path
+pub async fn ask_save_path(
+    app: &AppHandle,
+    title: &str,
+    default_filename: &str,
+    filters: &[(&str, &[&str])],
+    cancelled_message: &str,
+) -> Result<PathBuf, String> {
     // Use mpsc channel for async communication
     let (tx, mut rx) = mpsc::unbounded_channel();
 
-    // Open file save dialog
-    app.dialog()
-        .file()
-        .set_title("Shrani izvoz baze podatkov")
-        .set_file_name(&default_filename)
-        .add_filter("SQLite Database", &["db", "sqlite"])
-        .add_filter("All Files", &["*"])
-        .save_file(move |dialog_result| {
-            let _ = tx.send(dialog_result);
-        });
+    let mut dialog = app.dialog().file().set_title(title).set_file_name(default_filename);
+    for (name, extensions) in filters {
+        dialog = dialog.add_filter(*name, extensions);
+    }
+    dialog.save_file(move |dialog_result| {
+        let _ = tx.send(dialog_result);
+    });
 
     // Wait for dialog result asynchronously (non-blocking)
     let file_path = rx.recv().await.ok_or_else(|| "Napaka pri komunikaciji z dialogom.".to_string())?;
 
-    let file_path = match file_path {
-        Some(path) => match path {
-            FilePath::Path(p) => p,
-            FilePath::Url(_) => return Err("Podpora za URL poti ni na voljo.".to_string()),
-        },
-        None => return Err("Izvoz je bil preklican.".to_string()),
-    };
+    match file_path {
+        Some(FilePath::Path(p)) => Ok(p),
+        Some(FilePath::Url(_)) => Err("Podpora za URL poti ni na voljo.".to_string()),
+        None => Err(cancelled_message.to_string()),
+    }
+}
+
+/// Show an open dialog and wait for the chosen path
+pub async fn ask_open_path(
+    app: &AppHandle,
+    title: &str,
+    filters: &[(&str, &[&str])],
+    cancelled_message: &str,
+) -> Result<PathBuf, String> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
 
-    // Copy database file to selected location
-    fs::copy(&db_path, &file_path)
-        .map_err(|e| format!("Napaka pri kopiranju baze podatkov: {}", e))?;
+    let mut dialog = app.dialog().file().set_title(title);
+    for (name, extensions) in filters {
+        dialog = dialog.add_filter(*name, extensions);
+    }
+    dialog.pick_file(move |dialog_result| {
+        let _ = tx.send(dialog_result);
+    });
 
-    // Return the path as string
-    file_path
-        .to_str()
+    let file_path = rx.recv().await.ok_or_else(|| "Napaka pri komunikaciji z dialogom.".to_string())?;
+
+    match file_path {
+        Some(FilePath::Path(p)) => Ok(p),
+        Some(FilePath::Url(_)) => Err("Podpora za URL poti ni na voljo.".to_string()),
+        None => Err(cancelled_message.to_string()),
+    }
+}
+
+/// Show a folder picker and wait for the chosen directory
+pub async fn ask_folder_path(app: &AppHandle, title: &str, cancelled_message: &str) -> Result<PathBuf, String> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    app.dialog().file().set_title(title).pick_folder(move |dialog_result| {
+        let _ = tx.send(dialog_result);
+    });
+
+    let folder_path = rx.recv().await.ok_or_else(|| "Napaka pri komunikaciji z dialogom.".to_string())?;
+
+    match folder_path {
+        Some(FilePath::Path(p)) => Ok(p),
+        Some(FilePath::Url(_)) => Err("Podpora za URL poti ni na voljo.".to_string()),
+        None => Err(cancelled_message.to_string()),
+    }
+}
+
+/// Check that a file can be written at `path`: a file name in an existing directory
+pub fn check_output_path(path: &Path) -> Result<(), String> {
+    if path.file_name().is_none() {
+        return Err(format!("Pot {} ne vsebuje imena datoteke.", path.display()));
+    }
+    match path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        Some(parent) if !parent.is_dir() => Err(format!("Direktorij {} ne obstaja.", parent.display())),
+        _ => Ok(()),
+    }
+}
+
+/// Check that `path` is an existing file
+pub fn check_input_path(path: &Path) -> Result<(), String> {
+    if !path.is_file() {
+        return Err(format!("Datoteka {} ne obstaja.", path.display()));
+    }
+    Ok(())
+}
+
+fn path_to_string(path: &Path) -> Result<String, String> {
+    path.to_str()
         .ok_or_else(|| "Napaka pri pretvorbi poti datoteke.".to_string())
         .map(|s| s.to_string())
 }
 
-/// Import database from a user-selected file
+/// Export the database to `path`.
+/// A `.tskpay` file name produces an archive (optionally password-protected), any other a plain SQLite copy.
+pub fn export_database_to_path(
+    app: &AppHandle,
+    path: &Path,
+    password: Option<&str>,
+    club_name: Option<&str>,
+) -> Result<String, String> {
+    let db_path = get_db_path(app).map_err(|e| e.to_string())?;
+    let app_version = app.package_info().version.to_string();
+    export_database_file(&db_path, path, password, club_name, &app_version)
+}
+
+/// [`export_database_to_path`] of the database file at `db_path`
+pub fn export_database_file(
+    db_path: &Path,
+    path: &Path,
+    password: Option<&str>,
+    club_name: Option<&str>,
+    app_version: &str,
+) -> Result<String, String> {
+    // Check if database exists
+    if !db_path.exists() {
+        return Err("Baza podatkov ne obstaja.".to_string());
+    }
+    check_output_path(path)?;
+
+    if is_archive_path(path) {
+        write_archive_file(db_path, path, club_name, password, app_version)?;
+    } else {
+        // Copy database file to selected location
+        snapshot_database_file(db_path, path)?;
+    }
+
+    path_to_string(path)
+}
+
+/// Import a plain SQLite copy or `.tskpay` archive from `path`.
+/// Returns the path of the safety copy of the previous database.
+pub fn import_database_from_path(app: &AppHandle, path: &Path, password: Option<&str>) -> Result<String, String> {
+    let db_path = get_db_path(app).map_err(|e| e.to_string())?;
+    import_database_file_into(&db_path, &get_backups_dir(app)?, path, password)
+}
+
+/// [`import_database_from_path`] into the database file at `db_path`, with the safety copy in `backups_dir`
+pub fn import_database_file_into(
+    db_path: &Path,
+    backups_dir: &Path,
+    path: &Path,
+    password: Option<&str>,
+) -> Result<String, String> {
+    check_input_path(path)?;
+
+    if is_archive_path(path) {
+        import_archive_into(db_path, backups_dir, path, password)
+    } else {
+        replace_database_file(db_path, backups_dir, path, "before-import")
+    }
+}
+
+/// Export database to a user-selected location, see [`export_database_to_path`]
 #[tauri::command]
-pub async fn import_database(app: AppHandle) -> Result<String, String> {
-    let db_path = get_db_path(&app).map_err(|e| e.to_string())?;
+pub async fn export_database(app: AppHandle, password: Option<String>, club_name: Option<String>) -> Result<String, String> {
+    // Check if database exists
+    if !get_db_path(&app).map_err(|e| e.to_string())?.exists() {
+        return Err("Baza podatkov ne obstaja.".to_string());
+    }
 
-    // Use mpsc channel for async communication
-    let (tx, mut rx) = mpsc::unbounded_channel();
+    // Generate default filename with timestamp
+    let timestamp = chrono::Local::now().format("%Y-%m-%d-%H%M%S");
+    let default_filename = format!("tskpay-backup-{}.{}", timestamp, ARCHIVE_EXTENSION);
 
-    // Open file open dialog
-    app.dialog()
-        .file()
-        .set_title("Izberi datoteko baze podatkov za uvoz")
-        .add_filter("SQLite Database", &["db", "sqlite"])
-        .add_filter("All Files", &["*"])
-        .pick_file(move |dialog_result| {
-            let _ = tx.send(dialog_result);
-        });
+    let file_path = ask_save_path(
+        &app,
+        "Shrani izvoz baze podatkov",
+        &default_filename,
+        &[
+            ("tskpay arhiv", &[ARCHIVE_EXTENSION]),
+            ("SQLite Database", &["db", "sqlite"]),
+            ("All Files", &["*"]),
+        ],
+        "Izvoz je bil preklican.",
+    )
+    .await?;
 
-    // Wait for dialog result asynchronously (non-blocking)
-    let file_path = rx.recv().await.ok_or_else(|| "Napaka pri komunikaciji z dialogom.".to_string())?;
+    export_database_to_path(&app, &file_path, password.as_deref(), club_name.as_deref())
+}
 
-    let import_file_path = match file_path {
-        Some(path) => match path {
-            FilePath::Path(p) => p,
-            FilePath::Url(_) => return Err("Podpora za URL poti ni na voljo.".to_string()),
-        },
-        None => return Err("Uvoz je bil preklican.".to_string()),
-    };
+/// Export database to an explicit path, without a dialog
+#[tauri::command]
+pub fn export_database_to(
+    app: AppHandle,
+    path: String,
+    password: Option<String>,
+    club_name: Option<String>,
+) -> Result<String, String> {
+    export_database_to_path(&app, Path::new(&path), password.as_deref(), club_name.as_deref())
+}
+
+/// Import database from a user-selected file, see [`import_database_from_path`]
+#[tauri::command]
+pub async fn import_database(app: AppHandle, password: Option<String>) -> Result<String, String> {
+    let file_path = ask_open_path(
+        &app,
+        "Izberi datoteko baze podatkov za uvoz",
+        &[
+            ("tskpay arhiv ali SQLite", &[ARCHIVE_EXTENSION, "db", "sqlite"]),
+            ("All Files", &["*"]),
+        ],
+        "Uvoz je bil preklican.",
+    )
+    .await?;
+
+    import_database_from_path(&app, &file_path, password.as_deref())
+}
+
+/// Import database from an explicit path, without a dialog
+#[tauri::command]
+pub fn import_database_from(app: AppHandle, path: String, password: Option<String>) -> Result<String, String> {
+    import_database_from_path(&app, Path::new(&path), password.as_deref())
+}
+
+/// Check the 16-byte SQLite header ("SQLite format 3\0")
+fn check_sqlite_header(path: &Path) -> Result<(), String> {
+    let mut file = fs::File::open(path)
+        .map_err(|e| format!("Napaka pri branju datoteke {}: {}", path.display(), e))?;
 
-    // Validate that the selected file is a valid SQLite database
-    // Check SQLite magic bytes (first 16 bytes should start with "SQLite format 3\000")
-    let mut file = fs::File::open(&import_file_path)
-        .map_err(|e| format!("Napaka pri branju datoteke {}: {}", import_file_path.display(), e))?;
-    
     let mut header = [0u8; 16];
-    use std::io::Read;
     file.read_exact(&mut header)
-        .map_err(|e| format!("Napaka pri branju header datoteke {}: {}", import_file_path.display(), e))?;
-
-    // SQLite magic bytes: "SQLite format 3\000" (16 bytes)
-    // Compare first 16 bytes
-    let sqlite_magic: &[u8; 16] = b"SQLite format 3\0";
-    if &header != sqlite_magic {
-        // Try to provide more detailed error - check if it starts with "SQLite"
-        let header_str = String::from_utf8_lossy(&header);
-        if !header_str.starts_with("SQLite") {
-            return Err(format!(
-                "Izbrana datoteka ni veljavna SQLite baza podatkov. Datoteka ne vsebuje SQLite magic bytes. Prebrano: {:?}",
-                header_str
-            ));
-        } else {
-            // Header starts with SQLite but doesn't match exactly - might be valid, skip strict validation
-            // This allows importing valid SQLite files with slight header variations
+        .map_err(|e| format!("Napaka pri branju header datoteke {}: {}", path.display(), e))?;
+
+    if &header != b"SQLite format 3\0" {
+        return Err(format!(
+            "Izbrana datoteka ni veljavna SQLite baza podatkov. Datoteka ne vsebuje SQLite magic bytes. Prebrano: {:?}",
+            String::from_utf8_lossy(&header)
+        ));
+    }
+
+    Ok(())
+}
+
+/// Check that a file is a tskpay database this version of the app can open, without modifying it.
+/// Returns its schema version.
+pub fn validate_tskpay_database(path: &Path) -> Result<i32, String> {
+    check_sqlite_header(path)?;
+
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Napaka pri odpiranju baze podatkov {}: {}", path.display(), e))?;
+
+    let mut missing = Vec::new();
+    for table in REQUIRED_TABLES.iter().chain(std::iter::once(&"schema_version")) {
+        let exists = conn
+            .prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1")
+            .and_then(|mut stmt| stmt.exists([table]))
+            .map_err(|e| format!("Napaka pri branju baze podatkov {}: {}", path.display(), e))?;
+        if !exists {
+            missing.push(*table);
+        }
+    }
+    if !missing.is_empty() {
+        return Err(format!(
+            "Datoteka ni tskpay baza podatkov. Manjkajoče tabele: {}",
+            missing.join(", ")
+        ));
+    }
+
+    let version: i32 = conn
+        .query_row("SELECT MAX(version) FROM schema_version", [], |row| row.get::<_, Option<i32>>(0))
+        .map_err(|e| format!("Napaka pri branju verzije sheme: {}", e))?
+        .ok_or_else(|| "Baza podatkov nima zapisane verzije sheme.".to_string())?;
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "Baza podatkov je iz novejše različice aplikacije (verzija sheme {}, podprta {}). Posodobite aplikacijo.",
+            version, SCHEMA_VERSION
+        ));
+    }
+
+    Ok(version)
+}
+
+/// Copy a validated database to `dest`, bring it to the current schema version and check its integrity.
+/// The source file is only read.
+pub fn prepare_import_copy(source: &Path, dest: &Path) -> Result<(), String> {
+    if dest.exists() {
+        fs::remove_file(dest)
+            .map_err(|e| format!("Napaka pri brisanju datoteke {}: {}", dest.display(), e))?;
+    }
+    let dest_str = dest
+        .to_str()
+        .ok_or_else(|| "Napaka pri pretvorbi poti datoteke.".to_string())?;
+
+    let source_conn = Connection::open_with_flags(source, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Napaka pri odpiranju baze podatkov {}: {}", source.display(), e))?;
+    source_conn
+        .execute("VACUUM INTO ?1", [dest_str])
+        .map_err(|e| format!("Napaka pri kopiranju baze podatkov {}: {}", source.display(), e))?;
+    drop(source_conn);
+
+    let conn = Connection::open(dest)
+        .map_err(|e| format!("Napaka pri odpiranju baze podatkov {}: {}", dest.display(), e))?;
+    migrate_database(&conn)
+        .map_err(|e| format!("Napaka pri posodabljanju sheme uvožene baze: {}", e))?;
+    drop(conn);
+
+    verify_database_integrity(dest)
+}
+
+/// Atomically move a prepared database file over the app database
+fn swap_into_place(prepared: &Path, db_path: &Path) -> Result<(), String> {
+    if db_path.exists() {
+        // Empty the WAL first so nothing from the old database is left to replay
+        open_database(db_path)
+            .and_then(|conn| checkpoint_database(&conn))
+            .map_err(|e| format!("Napaka pri pripravi baze podatkov za zamenjavo: {}", e))?;
+    }
+
+    // The old WAL and shared-memory files belong to the replaced database; SQLite would
+    // otherwise replay them on top of the new file
+    for sidecar in wal_sidecar_paths(db_path) {
+        if sidecar.exists() {
+            fs::remove_file(&sidecar)
+                .map_err(|e| format!("Napaka pri brisanju datoteke {}: {}", sidecar.display(), e))?;
         }
     }
 
+    fs::rename(prepared, db_path)
+        .map_err(|e| format!("Napaka pri zamenjavi baze podatkov {}: {}", db_path.display(), e))
+}
+
+/// Replace the app database with another tskpay database file.
+///
+/// The candidate is validated read-only, copied and migrated in a temporary file next to the
+/// database, integrity-checked and only then renamed into place. A safety copy of the current
+/// database (`tskpay-backup-<label>-<timestamp>.db` in the backups folder) is taken first and
+/// restored if anything goes wrong after the swap. Returns the path of the safety copy.
+pub fn replace_database(app: &AppHandle, import_file_path: &Path, safety_copy_label: &str) -> Result<String, String> {
+    let db_path = get_db_path(app).map_err(|e| e.to_string())?;
+    replace_database_file(&db_path, &get_backups_dir(app)?, import_file_path, safety_copy_label)
+}
+
+/// [`replace_database`] for the database file at `db_path`, with the safety copy in `backups_dir`
+pub fn replace_database_file(
+    db_path: &Path,
+    backups_dir: &Path,
+    import_file_path: &Path,
+    safety_copy_label: &str,
+) -> Result<String, String> {
+    validate_tskpay_database(import_file_path)?;
+
+    // Ensure database directory exists
+    if let Some(parent) = db_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Napaka pri ustvarjanju direktorija za bazo {}: {}", parent.display(), e))?;
+    }
+
+    let prepared_path = db_path.with_file_name("tskpay-import.db.partial");
+    if let Err(e) = prepare_import_copy(import_file_path, &prepared_path) {
+        let _ = fs::remove_file(&prepared_path);
+        return Err(e);
+    }
+
     // Create backup of existing database if it exists
     let backup_path = if db_path.exists() {
         let timestamp = chrono::Local::now().format("%Y-%m-%d-%H%M%S");
-        let backup_filename = format!("tskpay-backup-before-import-{}.db", timestamp);
-        let mut backup_path = db_path.clone();
-        backup_path.set_file_name(&backup_filename);
-        
-        // Ensure backup directory exists
-        if let Some(parent) = backup_path.parent() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("Napaka pri ustvarjanju direktorija za backup {}: {}", parent.display(), e))?;
+        let backup_filename = format!("tskpay-backup-{}-{}.db", safety_copy_label, timestamp);
+        let backup_path = backups_dir.join(&backup_filename);
+
+        if let Err(e) = snapshot_database_file(db_path, &backup_path) {
+            let _ = fs::remove_file(&prepared_path);
+            return Err(format!("Napaka pri ustvarjanju backup-a: {}", e));
         }
-        
-        fs::copy(&db_path, &backup_path)
-            .map_err(|e| format!("Napaka pri ustvarjanju backup-a od {} do {}: {}", db_path.display(), backup_path.display(), e))?;
-        
-        Some(backup_path.to_str().ok_or_else(|| "Napaka pri pretvorbi backup poti v string".to_string())?.to_string())
+
+        Some(backup_path)
     } else {
         None
     };
 
-    // Ensure database directory exists
-    if let Some(parent) = db_path.parent() {
-        fs::create_dir_all(parent)
-            .map_err(|e| format!("Napaka pri ustvarjanju direktorija za bazo {}: {}", parent.display(), e))?;
-    }
+    let swapped = swap_into_place(&prepared_path, db_path)
+        .and_then(|_| open_database(db_path).map(|_| ()).map_err(|e| e.to_string()));
+
+    if let Err(e) = swapped {
+        let _ = fs::remove_file(&prepared_path);
+        let Some(backup_path) = &backup_path else {
+            return Err(e);
+        };
 
-    // Copy imported file to database location
-    fs::copy(&import_file_path, &db_path)
-        .map_err(|e| format!("Napaka pri kopiranju baze podatkov od {} do {}: {}", import_file_path.display(), db_path.display(), e))?;
+        // Put the safety copy back in place
+        let restore_path = db_path.with_file_name("tskpay-restore.db.partial");
+        let restored = fs::copy(backup_path, &restore_path)
+            .map_err(|e| e.to_string())
+            .and_then(|_| swap_into_place(&restore_path, db_path));
+        return match restored {
+            Ok(()) => Err(format!("Uvoz ni uspel, prejšnja baza podatkov je obnovljena: {}", e)),
+            Err(restore_error) => Err(format!(
+                "Uvoz ni uspel ({}), obnova iz {} prav tako ni uspela: {}",
+                e,
+                backup_path.display(),
+                restore_error
+            )),
+        };
+    }
 
     // Return backup path if created
-    Ok(backup_path.unwrap_or_else(|| "Nobena obstoječa baza ni bila zamenjana.".to_string()))
+    match backup_path {
+        Some(path) => path
+            .to_str()
+            .ok_or_else(|| "Napaka pri pretvorbi backup poti v string".to_string())
+            .map(|s| s.to_string()),
+        None => Ok("Nobena obstoječa baza ni bila zamenjana.".to_string()),
+    }
+}
+
+/// Write a text file to `path`
+pub fn save_text_file_to_path(path: &Path, content: &str) -> Result<String, String> {
+    check_output_path(path)?;
+
+    // Write content to file
+    fs::write(path, content)
+        .map_err(|e| format!("Napaka pri shranjevanju datoteke: {}", e))?;
+
+    path_to_string(path)
 }
 
 /// Save text file to a user-selected location
 #[tauri::command]
 pub async fn save_text_file(app: AppHandle, content: String, default_filename: String) -> Result<String, String> {
-    // Use mpsc channel for async communication
-    let (tx, mut rx) = mpsc::unbounded_channel();
+    let file_path = ask_save_path(
+        &app,
+        "Shrani datoteko",
+        &default_filename,
+        &[("Text Files", &["txt"]), ("All Files", &["*"])],
+        "Shranjevanje je bilo preklicano.",
+    )
+    .await?;
+
+    save_text_file_to_path(&file_path, &content)
+}
 
-    // Open file save dialog
-    app.dialog()
-        .file()
-        .set_title("Shrani datoteko")
-        .set_file_name(&default_filename)
-        .add_filter("Text Files", &["txt"])
-        .add_filter("All Files", &["*"])
-        .save_file(move |dialog_result| {
-            let _ = tx.send(dialog_result);
-        });
+/// Save text file to an explicit path, without a dialog
+#[tauri::command]
+pub fn save_text_file_to(path: String, content: String) -> Result<String, String> {
+    save_text_file_to_path(Path::new(&path), &content)
+}
 
-    // Wait for dialog result asynchronously (non-blocking)
-    let file_path = rx.recv().await.ok_or_else(|| "Napaka pri komunikaciji z dialogom.".to_string())?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let file_path = match file_path {
-        Some(path) => match path {
-            FilePath::Path(p) => p,
-            FilePath::Url(_) => return Err("Podpora za URL poti ni na voljo.".to_string()),
-        },
-        None => return Err("Shranjevanje je bilo preklicano.".to_string()),
-    };
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tskpay-backup-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("backups")).unwrap();
+        dir
+    }
 
-    // Write content to file
-    fs::write(&file_path, content)
-        .map_err(|e| format!("Napaka pri shranjevanju datoteke: {}", e))?;
+    fn parent_names(db_path: &Path) -> Vec<String> {
+        let conn = Connection::open(db_path).unwrap();
+        let mut stmt = conn.prepare("SELECT first_name FROM parents ORDER BY id").unwrap();
+        let names = stmt.query_map([], |row| row.get(0)).unwrap().collect::<rusqlite::Result<_>>().unwrap();
+        names
+    }
 
-    // Return the path as string
-    file_path
-        .to_str()
-        .ok_or_else(|| "Napaka pri pretvorbi poti datoteke.".to_string())
-        .map(|s| s.to_string())
+    #[test]
+    fn exports_and_imports_back_through_a_path() {
+        let dir = scratch_dir("roundtrip");
+        let db_path = dir.join("tskpay.db");
+        let backups_dir = dir.join("backups");
+        open_database(&db_path)
+            .unwrap()
+            .execute("INSERT INTO parents (id, first_name, last_name) VALUES ('par-1', 'Mojca', 'Novak')", [])
+            .unwrap();
+
+        for (file_name, password) in [("izvoz.db", None), ("izvoz.tskpay", Some("geslo"))] {
+            let export_path = dir.join(file_name);
+            export_database_file(&db_path, &export_path, password, Some("TSK JUB Dol"), "1.0.0").unwrap();
+
+            open_database(&db_path)
+                .unwrap()
+                .execute("UPDATE parents SET first_name = 'Spremenjeno'", [])
+                .unwrap();
+            let safety_copy = import_database_file_into(&db_path, &backups_dir, &export_path, password).unwrap();
+
+            assert_eq!(parent_names(&db_path), ["Mojca"], "{}", file_name);
+            assert_eq!(parent_names(Path::new(&safety_copy)), ["Spremenjeno"], "{}", file_name);
+            let version: i32 = Connection::open(&db_path)
+                .unwrap()
+                .query_row("SELECT MAX(version) FROM schema_version", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(version, SCHEMA_VERSION);
+            assert!(!db_path.with_file_name("tskpay-import.db.partial").exists());
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rejects_a_foreign_database_and_keeps_the_original() {
+        let dir = scratch_dir("foreign");
+        let db_path = dir.join("tskpay.db");
+        let backups_dir = dir.join("backups");
+        open_database(&db_path)
+            .unwrap()
+            .execute("INSERT INTO parents (id, first_name, last_name) VALUES ('par-1', 'Mojca', 'Novak')", [])
+            .unwrap();
+
+        let foreign_path = dir.join("tuja.db");
+        Connection::open(&foreign_path)
+            .unwrap()
+            .execute_batch("CREATE TABLE recepti (id TEXT PRIMARY KEY, naziv TEXT);")
+            .unwrap();
+
+        let error = import_database_file_into(&db_path, &backups_dir, &foreign_path, None).unwrap_err();
+        assert!(error.contains("parents"), "{}", error);
+        assert_eq!(parent_names(&db_path), ["Mojca"]);
+        assert!(!db_path.with_file_name("tskpay-import.db.partial").exists());
+        assert_eq!(fs::read_dir(&backups_dir).unwrap().count(), 0, "no safety copy for a rejected file");
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
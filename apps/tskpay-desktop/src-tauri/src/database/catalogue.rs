@@ -0,0 +1,177 @@
+use crate::database::{get_backups_dir, get_db_path, replace_database, REQUIRED_TABLES};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// A local backup file with the details needed to decide whether to restore it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    pub path: String,
+    pub file_name: String,
+    /// `auto`, `before-import`, `before-restore`, `before-merge` or `other`
+    pub kind: String,
+    pub created_at: String,
+    pub size_bytes: u64,
+    pub schema_version: Option<i32>,
+    /// Row count per domain table
+    pub record_counts: BTreeMap<String, i64>,
+    /// Set when the file could not be read as a tskpay database
+    pub error: Option<String>,
+}
+
+fn backup_kind(file_name: &str) -> &'static str {
+    if file_name.starts_with("tskpay-auto-") {
+        "auto"
+    } else if file_name.starts_with("tskpay-backup-before-import-") {
+        "before-import"
+    } else if file_name.starts_with("tskpay-backup-before-restore-") {
+        "before-restore"
+    } else if file_name.starts_with("tskpay-backup-before-merge-") {
+        "before-merge"
+    } else {
+        "other"
+    }
+}
+
+/// Creation time from the `YYYY-MM-DD-HHMMSS` part of the file name, else the modification time
+fn backup_created_at(path: &Path, metadata: &fs::Metadata) -> String {
+    let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let from_name = file_name
+        .char_indices()
+        .filter(|(_, c)| c.is_ascii_digit())
+        .find_map(|(i, _)| {
+            let candidate = file_name.get(i..i + 17)?;
+            NaiveDateTime::parse_from_str(candidate, "%Y-%m-%d-%H%M%S").ok()
+        })
+        .and_then(|naive| Local.from_local_datetime(&naive).single());
+
+    from_name
+        .or_else(|| metadata.modified().ok().map(DateTime::<Local>::from))
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_default()
+}
+
+/// Schema version and row counts of a backup, read without modifying it
+fn read_backup_contents(path: &Path) -> Result<(Option<i32>, BTreeMap<String, i64>), String> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| e.to_string())?;
+
+    let schema_version = conn
+        .query_row("SELECT MAX(version) FROM schema_version", [], |row| row.get::<_, Option<i32>>(0))
+        .map_err(|e| e.to_string())?;
+
+    let mut record_counts = BTreeMap::new();
+    for table in REQUIRED_TABLES {
+        let count: i64 = conn
+            .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        record_counts.insert(table.to_string(), count);
+    }
+
+    Ok((schema_version, record_counts))
+}
+
+fn describe_backup(path: &Path) -> Option<BackupInfo> {
+    let metadata = fs::metadata(path).ok()?;
+    let file_name = path.file_name()?.to_string_lossy().to_string();
+    let (schema_version, record_counts, error) = match read_backup_contents(path) {
+        Ok((version, counts)) => (version, counts, None),
+        Err(e) => (None, BTreeMap::new(), Some(e)),
+    };
+
+    Some(BackupInfo {
+        path: path.to_string_lossy().to_string(),
+        kind: backup_kind(&file_name).to_string(),
+        file_name,
+        created_at: backup_created_at(path, &metadata),
+        size_bytes: metadata.len(),
+        schema_version,
+        record_counts,
+        error,
+    })
+}
+
+/// Backup files in the backups folder, plus older pre-import copies kept next to the database
+fn find_backup_files(app: &AppHandle) -> Result<Vec<PathBuf>, String> {
+    let backups_dir = get_backups_dir(app)?;
+    let db_path = get_db_path(app).map_err(|e| e.to_string())?;
+
+    let mut files = Vec::new();
+    if let Ok(entries) = fs::read_dir(&backups_dir) {
+        files.extend(
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "db")),
+        );
+    }
+    if let Some(Ok(entries)) = db_path.parent().map(fs::read_dir) {
+        files.extend(
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                    name.starts_with("tskpay-backup-") && name.ends_with(".db")
+                }),
+        );
+    }
+
+    Ok(files)
+}
+
+/// All local backups, newest first
+pub fn list_backups(app: &AppHandle) -> Result<Vec<BackupInfo>, String> {
+    let mut backups: Vec<BackupInfo> = find_backup_files(app)?
+        .iter()
+        .filter_map(|path| describe_backup(path))
+        .collect();
+    backups.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(backups)
+}
+
+/// Resolve a path coming from the frontend to one of the catalogued backup files
+fn find_catalogued_backup(app: &AppHandle, path: &str) -> Result<PathBuf, String> {
+    let requested = fs::canonicalize(path)
+        .map_err(|e| format!("Backup {} ne obstaja: {}", path, e))?;
+    find_backup_files(app)?
+        .into_iter()
+        .find(|candidate| fs::canonicalize(candidate).is_ok_and(|c| c == requested))
+        .ok_or_else(|| format!("Datoteka {} ni lokalni backup.", path))
+}
+
+#[tauri::command]
+pub fn backup_list(app: AppHandle) -> Result<Vec<BackupInfo>, String> {
+    list_backups(&app)
+}
+
+/// Restore a local backup. The current database is saved as a `before-restore` backup first.
+/// Returns the path of that safety copy.
+#[tauri::command]
+pub fn backup_restore(app: AppHandle, path: String) -> Result<String, String> {
+    let backup_path = find_catalogued_backup(&app, &path)?;
+    replace_database(&app, &backup_path, "before-restore")
+}
+
+#[tauri::command]
+pub fn backup_delete(app: AppHandle, path: String) -> Result<(), String> {
+    let backup_path = find_catalogued_backup(&app, &path)?;
+    fs::remove_file(&backup_path)
+        .map_err(|e| format!("Napaka pri brisanju backup-a {}: {}", backup_path.display(), e))
+}
+
+/// Open the backups folder in the system file manager
+#[tauri::command]
+pub fn backup_reveal_folder(app: AppHandle) -> Result<String, String> {
+    let backups_dir = get_backups_dir(&app)?;
+    fs::create_dir_all(&backups_dir)
+        .map_err(|e| format!("Napaka pri ustvarjanju direktorija {}: {}", backups_dir.display(), e))?;
+    open::that(&backups_dir)
+        .map_err(|e| format!("Napaka pri odpiranju direktorija {}: {}", backups_dir.display(), e))?;
+    Ok(backups_dir.to_string_lossy().to_string())
+}
@@ -0,0 +1,88 @@
+use rusqlite::{Connection, Result};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+/// Name of the Tauri event emitted after every committed write
+pub const CHANGE_EVENT: &str = "db-change";
+
+/// A single committed write, as stored in `change_log` and pushed to the frontend
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeEvent {
+    pub seq: i64,
+    pub table: String,
+    pub operation: String,
+    pub ids: Vec<String>,
+    pub changed_at: String,
+}
+
+/// Append a change to `change_log` and return it with its sequence number.
+///
+/// Call this inside the same transaction as the write so the sequence number only
+/// exists if the write is committed.
+pub fn record_change(conn: &Connection, table: &str, operation: &str, ids: &[String]) -> Result<ChangeEvent> {
+    insert_change(conn, table, operation, ids, "local")
+}
+
+/// Like [`record_change`], for rows written by a pull from the sync server.
+///
+/// Views are notified the same way, but the sync engine does not push these changes back.
+pub fn record_sync_change(conn: &Connection, table: &str, operation: &str, ids: &[String]) -> Result<ChangeEvent> {
+    insert_change(conn, table, operation, ids, "sync")
+}
+
+fn insert_change(conn: &Connection, table: &str, operation: &str, ids: &[String], source: &str) -> Result<ChangeEvent> {
+    let ids_json = serde_json::to_string(ids).unwrap_or_else(|_| "[]".to_string());
+    conn.execute(
+        "INSERT INTO change_log (table_name, operation, row_ids, source) VALUES (?1, ?2, ?3, ?4)",
+        [table, operation, ids_json.as_str(), source],
+    )?;
+    let seq = conn.last_insert_rowid();
+    let changed_at: String = conn.query_row(
+        "SELECT changed_at FROM change_log WHERE seq = ?1",
+        [seq],
+        |row| row.get(0),
+    )?;
+
+    Ok(ChangeEvent {
+        seq,
+        table: table.to_string(),
+        operation: operation.to_string(),
+        ids: ids.to_vec(),
+        changed_at,
+    })
+}
+
+/// Get all changes with a sequence number greater than `since`, oldest first
+pub fn get_changes_since(conn: &Connection, since: i64) -> Result<Vec<ChangeEvent>> {
+    let mut stmt = conn.prepare(
+        "SELECT seq, table_name, operation, row_ids, changed_at FROM change_log WHERE seq > ?1 ORDER BY seq"
+    )?;
+    let rows = stmt.query_map([since], |row| {
+        let ids_json: String = row.get(3)?;
+        Ok(ChangeEvent {
+            seq: row.get(0)?,
+            table: row.get(1)?,
+            operation: row.get(2)?,
+            ids: serde_json::from_str(&ids_json).unwrap_or_default(),
+            changed_at: row.get(4)?,
+        })
+    })?;
+
+    let mut changes = Vec::new();
+    for row_result in rows {
+        changes.push(row_result?);
+    }
+
+    Ok(changes)
+}
+
+/// Notify the frontend about a committed change.
+///
+/// Emitting is best effort: the write already succeeded, and views that miss an
+/// event catch up through `db_get_changes_since`.
+pub fn emit_change(app: &AppHandle, change: &ChangeEvent) {
+    if let Err(e) = app.emit(CHANGE_EVENT, change) {
+        eprintln!("Failed to emit {} event: {}", CHANGE_EVENT, e);
+    }
+}
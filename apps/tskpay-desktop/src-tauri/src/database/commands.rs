@@ -1,4 +1,5 @@
-use crate::database::{get_connection, get_all, get_by_id, create, update, delete, get_member_parents, set_member_parents, get_parent_members};
+use crate::database::{get_connection, get_all, get_by_id, create, update, delete, delete_actions, get_member_parents, set_member_parents, get_parent_members, record_change, emit_change, get_changes_since, delete_orphaned_attachments, ChangeEvent};
+use crate::database::introspection::{get_schema, TableSchema};
 use serde_json::Value;
 use std::collections::HashMap;
 use tauri::AppHandle;
@@ -20,19 +21,65 @@ pub fn db_get_by_id(app: AppHandle, table: String, id: String) -> Result<Option<
 #[tauri::command]
 pub fn db_create(app: AppHandle, table: String, data: HashMap<String, Value>) -> Result<HashMap<String, Value>, String> {
     let conn = get_connection(&app).map_err(|e| e.to_string())?;
-    create(&conn, &table, &data).map_err(|e| e.to_string())
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let created = create(&tx, &table, &data).map_err(|e| e.to_string())?;
+    let ids: Vec<String> = created.get("id").and_then(|id| id.as_str()).map(|id| vec![id.to_string()]).unwrap_or_default();
+    let change = record_change(&tx, &table, "insert", &ids).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    emit_change(&app, &change);
+    Ok(created)
 }
 
 #[tauri::command]
 pub fn db_update(app: AppHandle, table: String, id: String, data: HashMap<String, Value>) -> Result<(), String> {
     let conn = get_connection(&app).map_err(|e| e.to_string())?;
-    update(&conn, &table, &id, &data).map_err(|e| e.to_string())
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    if update(&tx, &table, &id, &data).map_err(|e| e.to_string())? == 0 {
+        return Ok(());
+    }
+    let change = record_change(&tx, &table, "update", &[id]).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    emit_change(&app, &change);
+    Ok(())
 }
 
 #[tauri::command]
 pub fn db_delete(app: AppHandle, table: String, id: String) -> Result<(), String> {
     let conn = get_connection(&app).map_err(|e| e.to_string())?;
-    delete(&conn, &table, &id).map_err(|e| e.to_string())
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    // Foreign key actions are looked up first, the rows are gone after the delete
+    let cascaded = delete_actions(&tx, &table, &id).map_err(|e| e.to_string())?;
+    if delete(&tx, &table, &id).map_err(|e| e.to_string())? == 0 {
+        return Ok(());
+    }
+    let mut changes = vec![record_change(&tx, &table, "delete", &[id]).map_err(|e| e.to_string())?];
+    for (child_table, operation, ids) in &cascaded {
+        changes.push(record_change(&tx, child_table, operation, ids).map_err(|e| e.to_string())?);
+    }
+    // Attachments go with their entity, including rows the delete cascaded to
+    let attachments = delete_orphaned_attachments(&tx).map_err(|e| e.to_string())?;
+    if !attachments.is_empty() {
+        changes.push(record_change(&tx, "attachments", "delete", &attachments).map_err(|e| e.to_string())?);
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    for change in &changes {
+        emit_change(&app, change);
+    }
+    Ok(())
+}
+
+/// Get all committed changes after the given sequence number, so views can refresh incrementally
+#[tauri::command]
+pub fn db_get_changes_since(app: AppHandle, since: i64) -> Result<Vec<ChangeEvent>, String> {
+    let conn = get_connection(&app).map_err(|e| e.to_string())?;
+    get_changes_since(&conn, since).map_err(|e| e.to_string())
+}
+
+/// Describe all tables, columns and foreign keys of the live database
+#[tauri::command]
+pub fn db_schema(app: AppHandle) -> Result<Vec<TableSchema>, String> {
+    let conn = get_connection(&app).map_err(|e| e.to_string())?;
+    get_schema(&conn).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -52,7 +99,13 @@ pub fn db_get_member_parents(app: AppHandle, member_id: String) -> Result<Vec<St
 #[tauri::command]
 pub fn db_set_member_parents(app: AppHandle, member_id: String, parent_ids: Vec<String>) -> Result<(), String> {
     let conn = get_connection(&app).map_err(|e: rusqlite::Error| e.to_string())?;
-    set_member_parents(&conn, &member_id, &parent_ids).map_err(|e: rusqlite::Error| e.to_string())
+    let tx = conn.unchecked_transaction().map_err(|e: rusqlite::Error| e.to_string())?;
+    set_member_parents(&tx, &member_id, &parent_ids).map_err(|e: rusqlite::Error| e.to_string())?;
+    // The pivot rows are replaced wholesale, so the change is reported against the member
+    let change = record_change(&tx, "member_parents", "update", &[member_id]).map_err(|e: rusqlite::Error| e.to_string())?;
+    tx.commit().map_err(|e: rusqlite::Error| e.to_string())?;
+    emit_change(&app, &change);
+    Ok(())
 }
 
 #[tauri::command]
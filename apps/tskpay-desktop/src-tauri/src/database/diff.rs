@@ -0,0 +1,407 @@
+use crate::database::introspection::get_table_schema;
+use crate::database::{dump_tables, get_connection, row_to_map, validate_tskpay_database, DumpRow};
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use tauri::AppHandle;
+
+/// Tables left out of a diff: attachment contents are covered by the `attachments` rows
+const IGNORED_TABLES: &[&str] = &["attachment_blobs"];
+
+/// A single column that differs between the two versions of a record
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldChange {
+    pub column: String,
+    pub before: serde_json::Value,
+    pub after: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordDiff {
+    pub id: String,
+    /// Human-readable name of the record (person, title, payer...)
+    pub label: String,
+    /// The record as it is in the newer database, or in the older one for removed records
+    pub row: DumpRow,
+    /// Changed columns; empty for added and removed records
+    pub fields: Vec<FieldChange>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableDiff {
+    pub table: String,
+    pub label: String,
+    pub added: Vec<RecordDiff>,
+    pub removed: Vec<RecordDiff>,
+    pub changed: Vec<RecordDiff>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseDiff {
+    pub from: String,
+    pub to: String,
+    /// Only tables with at least one difference
+    pub tables: Vec<TableDiff>,
+    /// One line per table in domain terms, e.g. "Plačila — dodano: 0, odstranjeno: 3, spremenjeno: 1"
+    pub summary: Vec<String>,
+}
+
+/// Slovenian name of a table for summaries
+fn table_label(table: &str) -> String {
+    match table {
+        "parents" => "Starši",
+        "coaches" => "Trenerji",
+        "groups" => "Skupine",
+        "members" => "Člani",
+        "member_parents" => "Povezave član–starš",
+        "cost_types" => "Vrste stroškov",
+        "costs" => "Stroški",
+        "payments" => "Plačila",
+        "bank_statements" => "Bančni izpiski",
+        "bank_transactions" => "Bančne transakcije",
+        "payment_allocations" => "Razporeditve plačil",
+        "audit_log" => "Revizijska sled",
+        "attachments" => "Priloge",
+        "csv_import_profiles" => "Profili uvoza CSV",
+        other => other,
+    }
+    .to_string()
+}
+
+fn text_field<'a>(row: &'a DumpRow, column: &str) -> Option<&'a str> {
+    row.get(column).and_then(|value| value.as_str()).filter(|s| !s.is_empty())
+}
+
+/// Best human-readable name for a record, falling back to its id
+fn record_label(row: &DumpRow, id: &str) -> String {
+    if let (Some(first), Some(last)) = (text_field(row, "first_name"), text_field(row, "last_name")) {
+        return format!("{} {}", first, last);
+    }
+    if let Some(payer) = text_field(row, "payer_name") {
+        return match row.get("amount") {
+            Some(amount) => format!("{} ({} €)", payer, amount),
+            None => payer.to_string(),
+        };
+    }
+    ["name", "title", "file_name", "description"]
+        .iter()
+        .find_map(|column| text_field(row, column))
+        .unwrap_or(id)
+        .to_string()
+}
+
+/// Primary key column of a table, when it has exactly one
+pub fn primary_key_column(conn: &Connection, table: &str) -> rusqlite::Result<Option<String>> {
+    let schema = get_table_schema(conn, table)?;
+    let mut keys = schema.columns.iter().filter(|c| c.primary_key);
+    match (keys.next(), keys.next()) {
+        (Some(key), None) => Ok(Some(key.name.clone())),
+        _ => Ok(None),
+    }
+}
+
+fn key_string(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// All rows of a table, keyed by primary key
+pub fn read_keyed_rows(conn: &Connection, table: &str, key: &str) -> rusqlite::Result<BTreeMap<String, DumpRow>> {
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {}", table))?;
+    let rows = stmt.query_map([], |row| row_to_map(row).map(|map| map.into_iter().collect::<DumpRow>()))?;
+
+    let mut keyed = BTreeMap::new();
+    for row_result in rows {
+        let row = row_result?;
+        let id = row.get(key).map(key_string).unwrap_or_default();
+        keyed.insert(id, row);
+    }
+    Ok(keyed)
+}
+
+/// Columns whose values differ; columns missing on one side (older schema) are not compared
+pub fn changed_fields(before: &DumpRow, after: &DumpRow) -> Vec<FieldChange> {
+    after
+        .iter()
+        .filter_map(|(column, after_value)| {
+            let before_value = before.get(column)?;
+            (before_value != after_value).then(|| FieldChange {
+                column: column.clone(),
+                before: before_value.clone(),
+                after: after_value.clone(),
+            })
+        })
+        .collect()
+}
+
+fn diff_table(from: &Connection, to: &Connection, table: &str) -> rusqlite::Result<Option<TableDiff>> {
+    let Some(key) = primary_key_column(to, table)? else {
+        return Ok(None);
+    };
+    let from_rows = read_keyed_rows(from, table, &key)?;
+    let to_rows = read_keyed_rows(to, table, &key)?;
+
+    let mut diff = TableDiff {
+        table: table.to_string(),
+        label: table_label(table),
+        added: Vec::new(),
+        removed: Vec::new(),
+        changed: Vec::new(),
+    };
+
+    for (id, row) in &to_rows {
+        match from_rows.get(id) {
+            None => diff.added.push(RecordDiff {
+                id: id.clone(),
+                label: record_label(row, id),
+                row: row.clone(),
+                fields: Vec::new(),
+            }),
+            Some(before) => {
+                let fields = changed_fields(before, row);
+                if !fields.is_empty() {
+                    diff.changed.push(RecordDiff {
+                        id: id.clone(),
+                        label: record_label(row, id),
+                        row: row.clone(),
+                        fields,
+                    });
+                }
+            }
+        }
+    }
+    for (id, row) in from_rows.iter().filter(|(id, _)| !to_rows.contains_key(*id)) {
+        diff.removed.push(RecordDiff {
+            id: id.clone(),
+            label: record_label(row, id),
+            row: row.clone(),
+            fields: Vec::new(),
+        });
+    }
+
+    if diff.added.is_empty() && diff.removed.is_empty() && diff.changed.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(diff))
+}
+
+/// Summary line of a table, with status transitions spelled out where the table has a status
+fn summarize_table(diff: &TableDiff) -> String {
+    let mut line = format!(
+        "{} — dodano: {}, odstranjeno: {}, spremenjeno: {}",
+        diff.label,
+        diff.added.len(),
+        diff.removed.len(),
+        diff.changed.len()
+    );
+
+    let mut transitions: BTreeMap<String, usize> = BTreeMap::new();
+    for record in &diff.changed {
+        if let Some(field) = record.fields.iter().find(|f| f.column == "status") {
+            let transition = format!("{} → {}", key_string(&field.before), key_string(&field.after));
+            *transitions.entry(transition).or_default() += 1;
+        }
+    }
+    if !transitions.is_empty() {
+        let total: usize = transitions.values().sum();
+        let details: Vec<String> = transitions
+            .iter()
+            .map(|(transition, count)| format!("{}: {}", transition, count))
+            .collect();
+        line.push_str(&format!("; spremenjen status: {} ({})", total, details.join(", ")));
+    }
+
+    line
+}
+
+/// Compare two tskpay databases table by table. `from` is the older state, `to` the newer one.
+pub fn diff_databases(from: &Connection, to: &Connection, from_name: &str, to_name: &str) -> rusqlite::Result<DatabaseDiff> {
+    let from_tables = dump_tables(from)?;
+
+    let mut tables = Vec::new();
+    for table in dump_tables(to)? {
+        if IGNORED_TABLES.contains(&table.as_str()) || !from_tables.contains(&table) {
+            continue;
+        }
+        if let Some(diff) = diff_table(from, to, &table)? {
+            tables.push(diff);
+        }
+    }
+
+    Ok(DatabaseDiff {
+        from: from_name.to_string(),
+        to: to_name.to_string(),
+        summary: tables.iter().map(summarize_table).collect(),
+        tables,
+    })
+}
+
+fn format_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => "∅".to_string(),
+        serde_json::Value::String(s) => format!("\"{}\"", s),
+        other => other.to_string(),
+    }
+}
+
+/// Plain-text report of a diff, for saving or printing
+pub fn format_diff_report(diff: &DatabaseDiff) -> String {
+    let mut report = String::new();
+    report.push_str("Primerjava baz podatkov\n");
+    report.push_str(&format!("Prej:  {}\n", diff.from));
+    report.push_str(&format!("Potem: {}\n\n", diff.to));
+
+    if diff.tables.is_empty() {
+        report.push_str("Baza podatkov je nespremenjena.\n");
+        return report;
+    }
+
+    report.push_str("Povzetek\n");
+    for line in &diff.summary {
+        report.push_str(&format!("  {}\n", line));
+    }
+
+    for table in &diff.tables {
+        report.push_str(&format!("\n{} ({})\n", table.label, table.table));
+        for record in &table.added {
+            report.push_str(&format!("  + {} [{}]\n", record.label, record.id));
+        }
+        for record in &table.removed {
+            report.push_str(&format!("  - {} [{}]\n", record.label, record.id));
+        }
+        for record in &table.changed {
+            report.push_str(&format!("  ~ {} [{}]\n", record.label, record.id));
+            for field in &record.fields {
+                report.push_str(&format!(
+                    "      {}: {} → {}\n",
+                    field.column,
+                    format_value(&field.before),
+                    format_value(&field.after)
+                ));
+            }
+        }
+    }
+
+    report
+}
+
+/// Open one side of a comparison: a database file read-only, or the app database when `path` is `None`
+fn open_side(app: &AppHandle, path: Option<&str>) -> Result<(Connection, String), String> {
+    match path {
+        Some(path) => {
+            let path = Path::new(path);
+            validate_tskpay_database(path)?;
+            let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+                .map_err(|e| format!("Napaka pri odpiranju baze podatkov {}: {}", path.display(), e))?;
+            Ok((conn, path.to_string_lossy().to_string()))
+        }
+        None => {
+            let conn = get_connection(app).map_err(|e| e.to_string())?;
+            Ok((conn, "trenutna baza podatkov".to_string()))
+        }
+    }
+}
+
+fn compare(app: &AppHandle, from: Option<&str>, to: Option<&str>) -> Result<DatabaseDiff, String> {
+    let (from_conn, from_name) = open_side(app, from)?;
+    let (to_conn, to_name) = open_side(app, to)?;
+    diff_databases(&from_conn, &to_conn, &from_name, &to_name)
+        .map_err(|e| format!("Napaka pri primerjavi baz podatkov: {}", e))
+}
+
+/// Compare two databases; a missing path stands for the current app database.
+/// To see what restoring a backup would lose, pass the backup as `to` and leave `from` empty.
+#[tauri::command]
+pub fn database_diff(app: AppHandle, from: Option<String>, to: Option<String>) -> Result<DatabaseDiff, String> {
+    compare(&app, from.as_deref(), to.as_deref())
+}
+
+/// The same comparison as [`database_diff`], formatted as a text report
+#[tauri::command]
+pub fn database_diff_report(app: AppHandle, from: Option<String>, to: Option<String>) -> Result<String, String> {
+    compare(&app, from.as_deref(), to.as_deref()).map(|diff| format_diff_report(&diff))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PEOPLE: &str = "
+        INSERT INTO parents (id, first_name, last_name, created_at, updated_at)
+          VALUES ('par-1', 'Mojca', 'Novak', '2025-01-01', '2025-01-01'),
+                 ('par-2', 'Janez', 'Kos', '2025-01-01', '2025-01-01');
+        INSERT INTO payments (id, amount, payment_date, payment_method, status, payer_name, updated_at)
+          VALUES ('pay-1', 45, '2025-03-14', 'bank_transfer', 'pending', 'NOVAK MOJCA', '2025-03-14'),
+                 ('pay-2', 30, '2025-03-15', 'cash', 'pending', 'KOS JANEZ', '2025-03-15');";
+
+    fn database(changes: &str) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("schema.sql")).unwrap();
+        conn.execute_batch(PEOPLE).unwrap();
+        conn.execute_batch(changes).unwrap();
+        conn
+    }
+
+    #[test]
+    fn reports_added_removed_and_changed_records() {
+        let from = database("");
+        let to = database(
+            "DELETE FROM parents WHERE id = 'par-2';
+             INSERT INTO parents (id, first_name, last_name, created_at, updated_at)
+               VALUES ('par-3', 'Ana', 'Zupan', '2025-04-01', '2025-04-01');
+             UPDATE payments SET status = 'confirmed', updated_at = '2025-04-02' WHERE id = 'pay-1';
+             UPDATE payments SET status = 'confirmed', notes = 'Vadnina', updated_at = '2025-04-02' WHERE id = 'pay-2';",
+        );
+
+        let diff = diff_databases(&from, &to, "marec.db", "april.db").unwrap();
+        let tables: Vec<&str> = diff.tables.iter().map(|t| t.table.as_str()).collect();
+        assert_eq!(tables, vec!["parents", "payments"]);
+        assert_eq!(
+            summarize_table(&diff.tables[1]),
+            "Plačila — dodano: 0, odstranjeno: 0, spremenjeno: 2; spremenjen status: 2 (pending → confirmed: 2)"
+        );
+
+        let report = format_diff_report(&diff);
+        assert_eq!(
+            report,
+            "Primerjava baz podatkov
+Prej:  marec.db
+Potem: april.db
+
+Povzetek
+  Starši — dodano: 1, odstranjeno: 1, spremenjeno: 0
+  Plačila — dodano: 0, odstranjeno: 0, spremenjeno: 2; spremenjen status: 2 (pending → confirmed: 2)
+
+Starši (parents)
+  + Ana Zupan [par-3]
+  - Janez Kos [par-2]
+
+Plačila (payments)
+  ~ NOVAK MOJCA (45.0 €) [pay-1]
+      status: \"pending\" → \"confirmed\"
+      updated_at: \"2025-03-14\" → \"2025-04-02\"
+  ~ KOS JANEZ (30.0 €) [pay-2]
+      notes: \"\" → \"Vadnina\"
+      status: \"pending\" → \"confirmed\"
+      updated_at: \"2025-03-15\" → \"2025-04-02\"
+"
+        );
+    }
+
+    #[test]
+    fn reports_an_unchanged_database() {
+        let diff = diff_databases(&database(""), &database(""), "a.db", "b.db").unwrap();
+        assert!(diff.tables.is_empty() && diff.summary.is_empty());
+        assert_eq!(
+            format_diff_report(&diff),
+            "Primerjava baz podatkov\nPrej:  a.db\nPotem: b.db\n\nBaza podatkov je nespremenjena.\n"
+        );
+    }
+}
@@ -0,0 +1,336 @@
+use crate::database::introspection::{get_table_schema, list_tables};
+use crate::database::{
+    ask_open_path, ask_save_path, check_input_path, get_connection, get_db_path, replace_database, row_to_map,
+    save_text_file_to_path, SCHEMA_VERSION,
+};
+use base64::Engine;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+
+const DUMP_FORMAT: &str = "tskpay-dump";
+const DUMP_FORMAT_VERSION: u32 = 1;
+/// Bookkeeping tables that are rebuilt by the app or belong to one device, not part of the club data
+const EXCLUDED_TABLES: &[&str] = &["schema_version", "change_log", "sync_state", "sync_conflicts"];
+
+pub type DumpRow = BTreeMap<String, serde_json::Value>;
+
+/// Plain JSON copy of every data table. Tables are keyed by name, rows are ordered by
+/// primary key and columns by name, so two dumps of the same data are byte-identical
+/// apart from `createdAt`. BLOB values are base64 encoded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseDump {
+    pub format: String,
+    pub format_version: u32,
+    pub schema_version: i32,
+    pub created_at: String,
+    pub tables: BTreeMap<String, Vec<DumpRow>>,
+}
+
+/// Tables included in a dump, sorted by name
+pub fn dump_tables(conn: &Connection) -> rusqlite::Result<Vec<String>> {
+    Ok(list_tables(conn)?
+        .into_iter()
+        .filter(|table| !EXCLUDED_TABLES.contains(&table.as_str()))
+        .collect())
+}
+
+/// `ORDER BY` clause for a table: its primary key columns, or all columns when it has none
+pub fn order_by_columns(conn: &Connection, table: &str) -> rusqlite::Result<String> {
+    let schema = get_table_schema(conn, table)?;
+    let mut columns: Vec<&str> = schema
+        .columns
+        .iter()
+        .filter(|c| c.primary_key)
+        .map(|c| c.name.as_str())
+        .collect();
+    if columns.is_empty() {
+        columns = schema.columns.iter().map(|c| c.name.as_str()).collect();
+    }
+    Ok(columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", "))
+}
+
+/// Read every data table into a [`DatabaseDump`]
+pub fn dump_database(conn: &Connection) -> rusqlite::Result<DatabaseDump> {
+    let schema_version: i32 = conn
+        .query_row("SELECT MAX(version) FROM schema_version", [], |row| row.get::<_, Option<i32>>(0))?
+        .unwrap_or(SCHEMA_VERSION);
+
+    let mut tables = BTreeMap::new();
+    for table in dump_tables(conn)? {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT * FROM {} ORDER BY {}",
+            table,
+            order_by_columns(conn, &table)?
+        ))?;
+        let rows = stmt.query_map([], |row| row_to_map(row).map(|map| map.into_iter().collect::<DumpRow>()))?;
+
+        let mut dumped = Vec::new();
+        for row_result in rows {
+            dumped.push(row_result?);
+        }
+        tables.insert(table, dumped);
+    }
+
+    Ok(DatabaseDump {
+        format: DUMP_FORMAT.to_string(),
+        format_version: DUMP_FORMAT_VERSION,
+        schema_version,
+        created_at: chrono::Local::now().to_rfc3339(),
+        tables,
+    })
+}
+
+/// Convert a dumped JSON value back into an SQLite value for a column of the given declared type
+pub fn json_to_sql(value: &serde_json::Value, data_type: &str) -> Result<rusqlite::types::Value, String> {
+    use rusqlite::types::Value;
+
+    Ok(match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Integer(*b as i64),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Real(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s) if data_type.eq_ignore_ascii_case("BLOB") => Value::Blob(
+            base64::engine::general_purpose::STANDARD
+                .decode(s)
+                .map_err(|e| format!("Neveljavna base64 vrednost: {}", e))?,
+        ),
+        serde_json::Value::String(s) => Value::Text(s.clone()),
+        other => Value::Text(other.to_string()),
+    })
+}
+
+/// Write the rows of a dump into a freshly created database.
+///
+/// Triggers are dropped while the rows are inserted and recreated afterwards, so the
+/// restored rows keep their dumped `updated_at` and `status` values exactly. Tables are
+/// filled in name order, not in reference order, so foreign key checks are off.
+fn load_dump(conn: &Connection, dump: &DatabaseDump) -> Result<(), String> {
+    let known_tables = dump_tables(conn).map_err(|e| e.to_string())?;
+    if let Some(unknown) = dump.tables.keys().find(|t| !known_tables.contains(t)) {
+        return Err(format!("Izvoz vsebuje neznano tabelo: {}", unknown));
+    }
+
+    conn.execute("PRAGMA foreign_keys = OFF", []).map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+
+    let mut stmt = tx
+        .prepare("SELECT name, sql FROM sqlite_master WHERE type = 'trigger' AND sql IS NOT NULL ORDER BY name")
+        .map_err(|e| e.to_string())?;
+    let triggers = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| e.to_string())?;
+    drop(stmt);
+    for (name, _) in &triggers {
+        tx.execute(&format!("DROP TRIGGER \"{}\"", name), []).map_err(|e| e.to_string())?;
+    }
+
+    // The new schema comes with seed rows; the dump is the complete data set
+    for table in &known_tables {
+        tx.execute(&format!("DELETE FROM {}", table), []).map_err(|e| e.to_string())?;
+    }
+
+    for (table, rows) in &dump.tables {
+        let schema = get_table_schema(&tx, table).map_err(|e| e.to_string())?;
+        for row in rows {
+            let mut columns = Vec::with_capacity(row.len());
+            let mut values = Vec::with_capacity(row.len());
+            for (column, value) in row {
+                let column_schema = schema
+                    .columns
+                    .iter()
+                    .find(|c| &c.name == column)
+                    .ok_or_else(|| format!("Izvoz vsebuje neznan stolpec {}.{}", table, column))?;
+                columns.push(format!("\"{}\"", column));
+                values.push(json_to_sql(value, &column_schema.data_type).map_err(|e| format!("{}.{}: {}", table, column, e))?);
+            }
+
+            let placeholders = (1..=columns.len()).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(", ");
+            tx.execute(
+                &format!("INSERT INTO {} ({}) VALUES ({})", table, columns.join(", "), placeholders),
+                rusqlite::params_from_iter(values),
+            )
+            .map_err(|e| format!("Napaka pri uvozu vrstice v tabelo {}: {}", table, e))?;
+        }
+    }
+
+    for (_, sql) in &triggers {
+        tx.execute_batch(sql).map_err(|e| e.to_string())?;
+    }
+
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// Build a new database file at `dest` from a dump
+pub fn build_database_from_dump(dump: &DatabaseDump, dest: &Path) -> Result<(), String> {
+    if dump.format != DUMP_FORMAT || dump.format_version > DUMP_FORMAT_VERSION {
+        return Err(format!(
+            "Nepodprta oblika izvoza: {} (verzija {}).",
+            dump.format, dump.format_version
+        ));
+    }
+    if dump.schema_version > SCHEMA_VERSION {
+        return Err(format!(
+            "Izvoz je iz novejše različice aplikacije (verzija sheme {}, podprta {}). Posodobite aplikacijo.",
+            dump.schema_version, SCHEMA_VERSION
+        ));
+    }
+
+    if dest.exists() {
+        fs::remove_file(dest)
+            .map_err(|e| format!("Napaka pri brisanju datoteke {}: {}", dest.display(), e))?;
+    }
+    let conn = Connection::open(dest)
+        .map_err(|e| format!("Napaka pri odpiranju baze podatkov {}: {}", dest.display(), e))?;
+    super::initialize_schema(&conn)
+        .and_then(|_| super::ensure_schema_version(&conn))
+        .map_err(|e| format!("Napaka pri ustvarjanju baze podatkov: {}", e))?;
+
+    load_dump(&conn, dump)
+}
+
+/// Replace the app database with the contents of a dump.
+/// Returns the path of the safety copy of the previous database.
+pub fn restore_dump(app: &AppHandle, dump: &DatabaseDump) -> Result<String, String> {
+    let built = get_db_path(app)
+        .map_err(|e| e.to_string())?
+        .with_file_name("tskpay-json-import.db.partial");
+    let result = build_database_from_dump(dump, &built)
+        .and_then(|_| replace_database(app, &built, "before-import"));
+    let _ = fs::remove_file(&built);
+    result
+}
+
+/// Write a JSON dump of all club data to `path`
+pub fn export_json_dump_to_path(app: &AppHandle, path: &Path) -> Result<String, String> {
+    let content = {
+        let conn = get_connection(app).map_err(|e| e.to_string())?;
+        let dump = dump_database(&conn).map_err(|e| format!("Napaka pri branju baze podatkov: {}", e))?;
+        serde_json::to_string_pretty(&dump).map_err(|e| format!("Napaka pri pretvorbi v JSON: {}", e))?
+    };
+
+    save_text_file_to_path(path, &content)
+}
+
+/// Rebuild the database from the JSON dump at `path`.
+/// Returns the path of the safety copy of the previous database.
+pub fn import_json_dump_from_path(app: &AppHandle, path: &Path) -> Result<String, String> {
+    check_input_path(path)?;
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Napaka pri branju datoteke {}: {}", path.display(), e))?;
+    let dump: DatabaseDump = serde_json::from_str(&content)
+        .map_err(|e| format!("Datoteka {} ni veljaven JSON izvoz: {}", path.display(), e))?;
+
+    restore_dump(app, &dump)
+}
+
+/// Export all club data as a JSON dump to a user-selected location
+#[tauri::command]
+pub async fn export_json_dump(app: AppHandle) -> Result<String, String> {
+    let timestamp = chrono::Local::now().format("%Y-%m-%d-%H%M%S");
+    let default_filename = format!("tskpay-dump-{}.json", timestamp);
+
+    let file_path = ask_save_path(
+        &app,
+        "Shrani JSON izvoz podatkov",
+        &default_filename,
+        &[("JSON", &["json"]), ("All Files", &["*"])],
+        "Izvoz je bil preklican.",
+    )
+    .await?;
+
+    export_json_dump_to_path(&app, &file_path)
+}
+
+/// Export all club data as a JSON dump to an explicit path, without a dialog
+#[tauri::command]
+pub fn export_json_dump_to(app: AppHandle, path: String) -> Result<String, String> {
+    export_json_dump_to_path(&app, Path::new(&path))
+}
+
+/// Rebuild the database from a user-selected JSON dump, see [`import_json_dump_from_path`]
+#[tauri::command]
+pub async fn import_json_dump(app: AppHandle) -> Result<String, String> {
+    let file_path = ask_open_path(
+        &app,
+        "Izberi JSON izvoz za uvoz",
+        &[("JSON", &["json"]), ("All Files", &["*"])],
+        "Uvoz je bil preklican.",
+    )
+    .await?;
+
+    import_json_dump_from_path(&app, &file_path)
+}
+
+/// Rebuild the database from the JSON dump at an explicit path, without a dialog
+#[tauri::command]
+pub fn import_json_dump_from(app: AppHandle, path: String) -> Result<String, String> {
+    import_json_dump_from_path(&app, Path::new(&path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::database::initialize_schema(&conn).unwrap();
+        crate::database::ensure_schema_version(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn dump_rebuilds_the_same_database() {
+        let conn = database();
+        conn.execute_batch(
+            "INSERT INTO parents (id, first_name, last_name, email, created_at, updated_at)
+               VALUES ('par-1', 'Mojca', 'Novak', NULL, '2024-09-01 10:00:00', '2024-09-02 11:30:00');
+             INSERT INTO coaches (id, name) VALUES ('coa-1', 'Trener');
+             INSERT INTO groups (id, name, coach_id) VALUES ('grp-1', 'Začetniki', 'coa-1');
+             INSERT INTO members (id, first_name, last_name, date_of_birth, status, parent_id, group_id)
+               VALUES ('mem-1', 'Ana', 'Novak', '2015-05-05', 'active', 'par-1', 'grp-1');
+             INSERT INTO payments (id, parent_id, amount, payment_date, payment_method, status, notes, updated_at)
+               VALUES ('pay-1', 'par-1', 45.5, '2025-03-14', 'cash', 'confirmed', 'Vadnina marec', '2025-03-15 08:00:00');
+             INSERT INTO attachment_blobs (sha256, data, size) VALUES ('abc', X'00FF2A', 3);
+             INSERT INTO attachments (id, entity_type, entity_id, file_name, size, sha256)
+               VALUES ('att-1', 'payments', 'pay-1', 'račun.pdf', 3, 'abc');",
+        )
+        .unwrap();
+        let dump = dump_database(&conn).unwrap();
+        assert_eq!(dump.tables["payments"][0]["amount"], 45.5);
+        assert_eq!(dump.tables["attachment_blobs"][0]["data"], "AP8q");
+        assert!(!dump.tables.contains_key("change_log"));
+
+        let dest = std::env::temp_dir().join(format!("tskpay-dump-roundtrip-{}.db", std::process::id()));
+        let json = serde_json::to_string(&dump).unwrap();
+        build_database_from_dump(&serde_json::from_str(&json).unwrap(), &dest).unwrap();
+        let rebuilt = Connection::open(&dest).unwrap();
+        let again = dump_database(&rebuilt).unwrap();
+        let blob: Vec<u8> = rebuilt
+            .query_row("SELECT data FROM attachment_blobs WHERE sha256 = 'abc'", [], |row| row.get(0))
+            .unwrap();
+        drop(rebuilt);
+        let _ = fs::remove_file(&dest);
+
+        assert_eq!(again.schema_version, dump.schema_version);
+        assert_eq!(serde_json::to_string(&again.tables).unwrap(), serde_json::to_string(&dump.tables).unwrap());
+        assert_eq!(blob, vec![0x00, 0xff, 0x2a]);
+    }
+
+    #[test]
+    fn rejects_a_dump_from_a_newer_schema() {
+        let mut dump = dump_database(&database()).unwrap();
+        dump.schema_version = SCHEMA_VERSION + 1;
+        let dest = std::env::temp_dir().join(format!("tskpay-dump-newer-{}.db", std::process::id()));
+        let error = build_database_from_dump(&dump, &dest).unwrap_err();
+        assert!(error.contains("novejše različice"), "{}", error);
+        assert!(!dest.exists());
+    }
+}
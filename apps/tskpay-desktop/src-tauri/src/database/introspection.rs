@@ -0,0 +1,172 @@
+use regex::Regex;
+use rusqlite::{Connection, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Description of a table as found in the live database
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableSchema {
+    pub name: String,
+    pub columns: Vec<ColumnSchema>,
+    pub foreign_keys: Vec<ForeignKeySchema>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnSchema {
+    pub name: String,
+    pub data_type: String,
+    pub not_null: bool,
+    pub default_value: Option<String>,
+    pub primary_key: bool,
+    /// Values allowed by a `CHECK (column IN (...))` constraint, if the column has one
+    pub allowed_values: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForeignKeySchema {
+    pub column: String,
+    pub references_table: String,
+    pub references_column: String,
+    pub on_update: String,
+    pub on_delete: String,
+}
+
+/// List the user tables of the database (SQLite internal tables excluded), sorted by name
+pub fn list_tables(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name"
+    )?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+
+    let mut tables = Vec::new();
+    for row_result in rows {
+        tables.push(row_result?);
+    }
+
+    Ok(tables)
+}
+
+/// Describe every user table of the database
+pub fn get_schema(conn: &Connection) -> Result<Vec<TableSchema>> {
+    let mut tables = Vec::new();
+    for table in list_tables(conn)? {
+        tables.push(get_table_schema(conn, &table)?);
+    }
+    Ok(tables)
+}
+
+/// Describe a single table: columns, CHECK-enumerated values and foreign keys
+pub fn get_table_schema(conn: &Connection, table: &str) -> Result<TableSchema> {
+    let create_sql: String = conn.query_row(
+        "SELECT COALESCE(sql, '') FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        [table],
+        |row| row.get(0),
+    )?;
+    let mut enumerations = parse_check_enumerations(&create_sql);
+
+    let mut stmt = conn.prepare("SELECT name, type, \"notnull\", dflt_value, pk FROM pragma_table_info(?1) ORDER BY cid")?;
+    let rows = stmt.query_map([table], |row| {
+        Ok(ColumnSchema {
+            name: row.get(0)?,
+            data_type: row.get(1)?,
+            not_null: row.get::<_, i64>(2)? != 0,
+            default_value: row.get(3)?,
+            primary_key: row.get::<_, i64>(4)? != 0,
+            allowed_values: None,
+        })
+    })?;
+
+    let mut columns = Vec::new();
+    for row_result in rows {
+        let mut column = row_result?;
+        column.allowed_values = enumerations.remove(&column.name);
+        columns.push(column);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT \"from\", \"table\", COALESCE(\"to\", 'id'), on_update, on_delete FROM pragma_foreign_key_list(?1) ORDER BY id, seq"
+    )?;
+    let rows = stmt.query_map([table], |row| {
+        Ok(ForeignKeySchema {
+            column: row.get(0)?,
+            references_table: row.get(1)?,
+            references_column: row.get(2)?,
+            on_update: row.get(3)?,
+            on_delete: row.get(4)?,
+        })
+    })?;
+
+    let mut foreign_keys = Vec::new();
+    for row_result in rows {
+        foreign_keys.push(row_result?);
+    }
+
+    Ok(TableSchema {
+        name: table.to_string(),
+        columns,
+        foreign_keys,
+    })
+}
+
+/// Find `CHECK (column IN ('a', 'b', ...))` constraints in a CREATE TABLE statement
+fn parse_check_enumerations(create_sql: &str) -> HashMap<String, Vec<String>> {
+    let check_in = Regex::new(r#"(?i)CHECK\s*\(\s*["`\[]?(\w+)["`\]]?\s+IN\s*\(([^)]*)\)"#)
+        .expect("valid CHECK regex");
+
+    let mut enumerations = HashMap::new();
+    for captures in check_in.captures_iter(create_sql) {
+        let values = captures[2]
+            .split(',')
+            .map(|value| value.trim().trim_matches('\'').to_string())
+            .filter(|value| !value.is_empty())
+            .collect();
+        enumerations.insert(captures[1].to_string(), values);
+    }
+
+    enumerations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("schema.sql")).unwrap();
+        conn
+    }
+
+    #[test]
+    fn reads_check_lists_and_foreign_keys_of_the_schema() {
+        let conn = database();
+        let members = get_table_schema(&conn, "members").unwrap();
+
+        let status = members.columns.iter().find(|c| c.name == "status").unwrap();
+        assert_eq!(
+            status.allowed_values.as_deref(),
+            Some(&["active".to_string(), "inactive".to_string(), "archived".to_string()][..])
+        );
+        let id = members.columns.iter().find(|c| c.name == "id").unwrap();
+        assert!(id.primary_key);
+        assert_eq!(id.allowed_values, None);
+
+        let group = members.foreign_keys.iter().find(|fk| fk.column == "group_id").unwrap();
+        assert_eq!(group.references_table, "groups");
+        assert_eq!(group.references_column, "id");
+        assert_eq!(group.on_delete, "RESTRICT");
+    }
+
+    #[test]
+    fn lists_tables_without_sqlite_internals() {
+        let conn = database();
+        let tables = list_tables(&conn).unwrap();
+        assert!(tables.contains(&"parents".to_string()));
+        assert!(tables.iter().all(|t| !t.starts_with("sqlite_")), "{:?}", tables);
+        let mut sorted = tables.clone();
+        sorted.sort();
+        assert_eq!(tables, sorted);
+    }
+}
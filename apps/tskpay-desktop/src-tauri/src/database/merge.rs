@@ -0,0 +1,375 @@
+use crate::database::archive::{extract_archive_database, is_archive_path, ARCHIVE_EXTENSION};
+use crate::database::diff::{changed_fields, primary_key_column, read_keyed_rows};
+use crate::database::introspection::get_table_schema;
+use crate::database::{
+    ask_open_path, dump_tables, emit_change, generate_id, get_backups_dir, get_connection, get_db_path, json_to_sql,
+    now_timestamp, parse_timestamp, prepare_import_copy, record_change, snapshot_database, validate_tskpay_database,
+    DumpRow,
+};
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// Tables keyed by their contents; a matching key means identical data, so they never conflict
+const CONTENT_ADDRESSED_TABLES: &[&str] = &["attachment_blobs"];
+
+/// Which version of a conflicting row to keep
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeSide {
+    Local,
+    Incoming,
+}
+
+/// A row that only exists in the incoming database
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeRow {
+    pub table: String,
+    pub id: String,
+    pub row: DumpRow,
+}
+
+/// A row that exists in both databases with different values
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeConflict {
+    pub table: String,
+    pub id: String,
+    pub local: DumpRow,
+    pub incoming: DumpRow,
+    pub changed_columns: Vec<String>,
+    /// The side with the newer `updated_at`; local when the table has no `updated_at`
+    pub proposed: MergeSide,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergePreview {
+    pub source_path: String,
+    pub additions: Vec<MergeRow>,
+    pub conflicts: Vec<MergeConflict>,
+    /// Rows present in both databases with identical values
+    pub unchanged: usize,
+}
+
+/// The user's choice for one conflict
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeResolution {
+    pub table: String,
+    pub id: String,
+    pub resolution: MergeSide,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeResult {
+    pub added: usize,
+    pub updated: usize,
+    pub kept_local: usize,
+    /// Incoming rows that clashed with a unique constraint, e.g. the same member-parent link under another id
+    pub skipped: usize,
+    pub backup_path: String,
+}
+
+fn propose_side(local: &DumpRow, incoming: &DumpRow) -> MergeSide {
+    // Rows carry both `datetime('now')` and `now_timestamp()` formats, which do not compare as strings
+    let updated_at = |row: &DumpRow| row.get("updated_at").and_then(|value| value.as_str()).and_then(parse_timestamp);
+    match (updated_at(local), updated_at(incoming)) {
+        (Some(local_at), Some(incoming_at)) if incoming_at > local_at => MergeSide::Incoming,
+        _ => MergeSide::Local,
+    }
+}
+
+/// Compare the incoming database with ours by primary key.
+/// Rows that only exist locally are left alone; a merge never deletes.
+pub fn diff_for_merge(local: &Connection, incoming: &Connection, source_path: &str) -> rusqlite::Result<MergePreview> {
+    let mut preview = MergePreview {
+        source_path: source_path.to_string(),
+        additions: Vec::new(),
+        conflicts: Vec::new(),
+        unchanged: 0,
+    };
+
+    for table in dump_tables(incoming)? {
+        let Some(key) = primary_key_column(local, &table)? else {
+            continue;
+        };
+        let local_rows = read_keyed_rows(local, &table, &key)?;
+
+        for (id, incoming_row) in read_keyed_rows(incoming, &table, &key)? {
+            let Some(local_row) = local_rows.get(&id) else {
+                preview.additions.push(MergeRow {
+                    table: table.clone(),
+                    id,
+                    row: incoming_row,
+                });
+                continue;
+            };
+
+            let changed_columns: Vec<String> = changed_fields(local_row, &incoming_row)
+                .into_iter()
+                .map(|field| field.column)
+                .collect();
+            if changed_columns.is_empty() || CONTENT_ADDRESSED_TABLES.contains(&table.as_str()) {
+                preview.unchanged += 1;
+                continue;
+            }
+
+            preview.conflicts.push(MergeConflict {
+                table: table.clone(),
+                id,
+                proposed: propose_side(local_row, &incoming_row),
+                local: local_row.clone(),
+                incoming: incoming_row,
+                changed_columns,
+            });
+        }
+    }
+
+    Ok(preview)
+}
+
+/// Insert a row unless its primary key already exists; returns whether it was inserted
+pub fn insert_row(conn: &Connection, table: &str, row: &DumpRow) -> Result<bool, String> {
+    let schema = get_table_schema(conn, table).map_err(|e| e.to_string())?;
+    let mut columns = Vec::with_capacity(row.len());
+    let mut values = Vec::with_capacity(row.len());
+    for column in &schema.columns {
+        if let Some(value) = row.get(&column.name) {
+            columns.push(format!("\"{}\"", column.name));
+            values.push(json_to_sql(value, &column.data_type)?);
+        }
+    }
+
+    let placeholders = (1..=columns.len()).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(", ");
+    let inserted = conn
+        .execute(
+            &format!("INSERT OR IGNORE INTO {} ({}) VALUES ({})", table, columns.join(", "), placeholders),
+            rusqlite::params_from_iter(values),
+        )
+        .map_err(|e| format!("Napaka pri dodajanju vrstice v tabelo {}: {}", table, e))?;
+    Ok(inserted > 0)
+}
+
+/// Overwrite every column present in `row` of the record with the given key
+pub fn overwrite_row(conn: &Connection, table: &str, key: &str, id: &str, row: &DumpRow) -> Result<(), String> {
+    let schema = get_table_schema(conn, table).map_err(|e| e.to_string())?;
+    let mut assignments = Vec::new();
+    let mut values = Vec::new();
+    for column in schema.columns.iter().filter(|c| c.name != key) {
+        if let Some(value) = row.get(&column.name) {
+            values.push(json_to_sql(value, &column.data_type)?);
+            assignments.push(format!("\"{}\" = ?{}", column.name, values.len()));
+        }
+    }
+    values.push(rusqlite::types::Value::Text(id.to_string()));
+
+    conn.execute(
+        &format!("UPDATE {} SET {} WHERE \"{}\" = ?{}", table, assignments.join(", "), key, values.len()),
+        rusqlite::params_from_iter(values),
+    )
+    .map_err(|e| format!("Napaka pri posodabljanju vrstice {} v tabeli {}: {}", id, table, e))?;
+    Ok(())
+}
+
+/// Copy the file to merge from into a migrated temporary database next to ours.
+/// `.tskpay` archives are unpacked first.
+fn prepare_incoming(app: &AppHandle, path: &Path, password: Option<&str>) -> Result<PathBuf, String> {
+    let db_path = get_db_path(app).map_err(|e| e.to_string())?;
+    let prepared = db_path.with_file_name("tskpay-merge.db.partial");
+
+    let source = if is_archive_path(path) {
+        let extracted = db_path.with_file_name("tskpay-merge-archive.db.partial");
+        extract_archive_database(path, password, &extracted)?;
+        extracted
+    } else {
+        path.to_path_buf()
+    };
+
+    let result = validate_tskpay_database(&source).and_then(|_| prepare_import_copy(&source, &prepared));
+    if source.as_path() != path {
+        let _ = fs::remove_file(&source);
+    }
+    if let Err(e) = result {
+        let _ = fs::remove_file(&prepared);
+        return Err(e);
+    }
+
+    Ok(prepared)
+}
+
+/// Open the prepared incoming database and diff it against the app database
+fn preview_with(app: &AppHandle, prepared: &Path, source_path: &str) -> Result<MergePreview, String> {
+    let incoming = Connection::open_with_flags(prepared, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Napaka pri odpiranju baze podatkov {}: {}", prepared.display(), e))?;
+    let local = get_connection(app).map_err(|e| e.to_string())?;
+    diff_for_merge(&local, &incoming, source_path).map_err(|e| format!("Napaka pri primerjavi baz podatkov: {}", e))
+}
+
+/// Apply a merge preview in one transaction: add missing rows, overwrite conflicts resolved
+/// as incoming and write an audit entry
+fn apply_merge(
+    app: &AppHandle,
+    preview: &MergePreview,
+    resolutions: &[MergeResolution],
+    backup_path: String,
+) -> Result<MergeResult, String> {
+    let chosen: HashMap<(&str, &str), MergeSide> = resolutions
+        .iter()
+        .map(|r| ((r.table.as_str(), r.id.as_str()), r.resolution))
+        .collect();
+
+    let conn = get_connection(app).map_err(|e| e.to_string())?;
+    // Rows are added table by table in name order, not in reference order, and are copied
+    // as the other database holds them, so foreign key checks stay off
+    conn.execute("PRAGMA foreign_keys = OFF", []).map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+
+    let mut result = MergeResult {
+        added: 0,
+        updated: 0,
+        kept_local: 0,
+        skipped: 0,
+        backup_path,
+    };
+    let mut inserted_ids: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    let mut updated_ids: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+
+    for addition in &preview.additions {
+        if insert_row(&tx, &addition.table, &addition.row)? {
+            result.added += 1;
+            inserted_ids.entry(&addition.table).or_default().push(addition.id.clone());
+        } else {
+            result.skipped += 1;
+        }
+    }
+
+    for conflict in &preview.conflicts {
+        let side = chosen
+            .get(&(conflict.table.as_str(), conflict.id.as_str()))
+            .copied()
+            .unwrap_or(conflict.proposed);
+        if side == MergeSide::Local {
+            result.kept_local += 1;
+            continue;
+        }
+        let key = primary_key_column(&tx, &conflict.table)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Tabela {} nima primarnega ključa.", conflict.table))?;
+        overwrite_row(&tx, &conflict.table, &key, &conflict.id, &conflict.incoming)?;
+        result.updated += 1;
+        updated_ids.entry(&conflict.table).or_default().push(conflict.id.clone());
+    }
+
+    let source_name = Path::new(&preview.source_path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| preview.source_path.clone());
+    let audit_id = generate_id("audit_log");
+    let details = serde_json::json!({
+        "source": preview.source_path,
+        "added": result.added,
+        "updated": result.updated,
+        "keptLocal": result.kept_local,
+        "skipped": result.skipped,
+        "backupPath": result.backup_path,
+    });
+    tx.execute(
+        "INSERT INTO audit_log (id, action, description, timestamp, details) VALUES (?1, 'database_merged', ?2, ?3, ?4)",
+        rusqlite::params![
+            audit_id,
+            format!(
+                "Združeni podatki iz {}: dodano {}, posodobljeno {}, ohranjeno lokalno {}",
+                source_name, result.added, result.updated, result.kept_local
+            ),
+            now_timestamp(),
+            details.to_string()
+        ],
+    )
+    .map_err(|e| format!("Napaka pri zapisu v revizijsko sled: {}", e))?;
+    inserted_ids.entry("audit_log").or_default().push(audit_id);
+
+    let mut changes = Vec::new();
+    for (table, ids) in &inserted_ids {
+        changes.push(record_change(&tx, table, "insert", ids).map_err(|e| e.to_string())?);
+    }
+    for (table, ids) in &updated_ids {
+        changes.push(record_change(&tx, table, "update", ids).map_err(|e| e.to_string())?);
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    for change in &changes {
+        emit_change(app, change);
+    }
+
+    Ok(result)
+}
+
+/// Diff another tskpay database (or `.tskpay` archive) against ours without changing anything.
+/// Without `path` a file dialog is shown.
+#[tauri::command]
+pub async fn merge_preview(app: AppHandle, path: Option<String>, password: Option<String>) -> Result<MergePreview, String> {
+    let source_path = match path {
+        Some(p) => PathBuf::from(p),
+        None => ask_open_path(
+            &app,
+            "Izberi bazo podatkov za združitev",
+            &[("tskpay arhiv ali SQLite", &[ARCHIVE_EXTENSION, "db", "sqlite"]), ("All Files", &["*"])],
+            "Uvoz je bil preklican.",
+        )
+        .await?,
+    };
+
+    let prepared = prepare_incoming(&app, &source_path, password.as_deref())?;
+    let preview = preview_with(&app, &prepared, &source_path.to_string_lossy());
+    let _ = fs::remove_file(&prepared);
+    preview
+}
+
+/// Merge another tskpay database into ours. The diff is recomputed so changes made since the
+/// preview are taken into account; conflicts without a resolution use the proposed side.
+/// A `before-merge` backup is taken first.
+#[tauri::command]
+pub fn merge_apply(
+    app: AppHandle,
+    path: String,
+    password: Option<String>,
+    resolutions: Vec<MergeResolution>,
+) -> Result<MergeResult, String> {
+    let source_path = PathBuf::from(&path);
+    let prepared = prepare_incoming(&app, &source_path, password.as_deref())?;
+    let preview = preview_with(&app, &prepared, &path);
+    let _ = fs::remove_file(&prepared);
+    let preview = preview?;
+
+    let timestamp = chrono::Local::now().format("%Y-%m-%d-%H%M%S");
+    let backup_path = get_backups_dir(&app)?.join(format!("tskpay-backup-before-merge-{}.db", timestamp));
+    snapshot_database(&app, &backup_path).map_err(|e| format!("Napaka pri ustvarjanju backup-a: {}", e))?;
+
+    apply_merge(&app, &preview, &resolutions, backup_path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(updated_at: &str) -> DumpRow {
+        let mut row = DumpRow::new();
+        row.insert("updated_at".to_string(), serde_json::Value::String(updated_at.to_string()));
+        row
+    }
+
+    #[test]
+    fn proposes_the_newer_side_across_timestamp_formats() {
+        // As strings the space sorts before `T`, which would pick the older row
+        assert_eq!(propose_side(&row("2025-01-10T08:00:00.000Z"), &row("2025-01-10 09:30:00")), MergeSide::Incoming);
+        assert_eq!(propose_side(&row("2025-01-10 09:30:00"), &row("2025-01-10T08:00:00.000Z")), MergeSide::Local);
+        assert_eq!(propose_side(&row("2025-01-10 08:00:00"), &row("2025-01-10T08:00:00.000Z")), MergeSide::Local);
+        assert_eq!(propose_side(&row("2025-01-10T08:00:00.000Z"), &row("2025-01-10T08:00:01.000Z")), MergeSide::Incoming);
+    }
+}
@@ -1,17 +1,61 @@
 use rusqlite::{Connection, Result};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tauri::path::BaseDirectory;
 use tauri::Manager;
 
 pub mod queries;
+pub mod changes;
+pub mod introspection;
 mod commands;
 mod backup;
+mod attachments;
+mod auto_backup;
+mod catalogue;
+mod dump;
+mod merge;
+pub mod archive;
+pub mod diff;
+pub mod postgres;
 pub use queries::*;
+pub use changes::*;
 pub use commands::*;
 pub use backup::*;
+pub use attachments::*;
+pub use auto_backup::*;
+pub use catalogue::*;
+pub use dump::*;
+pub use merge::*;
 
 const DB_FILENAME: &str = "tskpay.db";
-const SCHEMA_VERSION: i32 = 5;
+pub const SCHEMA_VERSION: i32 = 15;
+/// Tables every tskpay database has, used to recognise foreign files on import
+pub const REQUIRED_TABLES: &[&str] = &[
+    "parents",
+    "coaches",
+    "groups",
+    "members",
+    "cost_types",
+    "costs",
+    "payments",
+    "bank_statements",
+    "bank_transactions",
+    "payment_allocations",
+    "audit_log",
+];
+/// Tables that carry an updated_at column maintained by a trigger
+const UPDATED_AT_TABLES: &[&str] = &[
+    "parents",
+    "coaches",
+    "groups",
+    "members",
+    "costs",
+    "bank_statements",
+    "bank_transactions",
+    "payments",
+];
+/// How long a connection waits for a competing writer before giving up with "database is locked"
+const BUSY_TIMEOUT_MS: u64 = 5000;
 
 /// Get the database file path in the app data directory
 pub fn get_db_path(app: &tauri::AppHandle) -> Result<PathBuf> {
@@ -37,8 +81,13 @@ pub fn get_db_path(app: &tauri::AppHandle) -> Result<PathBuf> {
 
 /// Initialize the database connection
 pub fn init_database(app: &tauri::AppHandle) -> Result<Connection> {
-    let db_path = get_db_path(app)?;
-    let conn = Connection::open(&db_path)?;
+    open_database(&get_db_path(app)?)
+}
+
+/// Open the database file at `db_path`, creating the schema or migrating it as needed
+pub fn open_database(db_path: &Path) -> Result<Connection> {
+    let conn = Connection::open(db_path)?;
+    configure_connection(&conn)?;
     
     // Enable foreign keys
     conn.execute("PRAGMA foreign_keys = ON", [])?;
@@ -56,6 +105,53 @@ pub fn init_database(app: &tauri::AppHandle) -> Result<Connection> {
     Ok(conn)
 }
 
+/// Apply journaling and durability settings to a freshly opened connection.
+///
+/// WAL lets the webview read while another command writes, the busy timeout makes
+/// concurrent writers wait instead of failing, and `synchronous = FULL` keeps every
+/// committed transaction on disk even after a power loss.
+pub fn configure_connection(conn: &Connection) -> Result<()> {
+    conn.busy_timeout(Duration::from_millis(BUSY_TIMEOUT_MS))?;
+    // journal_mode returns the resulting mode as a row, so it can't go through execute()
+    let _mode: String = conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))?;
+    conn.execute_batch("PRAGMA synchronous = FULL;")?;
+    Ok(())
+}
+
+/// Move all committed pages from the WAL back into the main database file and truncate the WAL.
+///
+/// After this returns the `.db` file alone holds the complete database, so it is safe to copy.
+pub fn checkpoint_database(conn: &Connection) -> Result<()> {
+    let (busy, _log, _checkpointed): (i32, i32, i32) = conn.query_row(
+        "PRAGMA wal_checkpoint(TRUNCATE)",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    if busy != 0 {
+        return Err(rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY),
+            Some("WAL checkpoint could not complete because the database is busy".to_string()),
+        ));
+    }
+    Ok(())
+}
+
+/// Checkpoint the app database, used on shutdown and before file-level copies
+pub fn checkpoint_app_database(app: &tauri::AppHandle) -> Result<()> {
+    let conn = get_connection(app)?;
+    checkpoint_database(&conn)
+}
+
+/// Paths of the `-wal` and `-shm` files SQLite keeps next to a database in WAL mode
+pub fn wal_sidecar_paths(db_path: &Path) -> [PathBuf; 2] {
+    let with_suffix = |suffix: &str| {
+        let mut name = db_path.as_os_str().to_os_string();
+        name.push(suffix);
+        PathBuf::from(name)
+    };
+    [with_suffix("-wal"), with_suffix("-shm")]
+}
+
 /// Check if a table exists
 fn table_exists(conn: &Connection, table_name: &str) -> Result<bool> {
     let mut stmt = conn.prepare(
@@ -85,6 +181,11 @@ fn initialize_schema(conn: &Connection) -> Result<()> {
     }
 }
 
+/// Bring an existing tskpay database to the current schema version
+pub fn migrate_database(conn: &Connection) -> Result<()> {
+    ensure_schema_version(conn)
+}
+
 /// Ensure schema version is set
 fn ensure_schema_version(conn: &Connection) -> Result<()> {
     // Create schema_version table if it doesn't exist
@@ -196,6 +297,221 @@ fn run_migrations(conn: &Connection, from_version: i32, to_version: i32) -> Resu
                     [],
                 )?;
             }
+            6 => {
+                // Migration to version 6: Add change_log for pushing committed writes to the frontend
+                conn.execute(
+                    "CREATE TABLE IF NOT EXISTS change_log (
+                        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                        table_name TEXT NOT NULL,
+                        operation TEXT NOT NULL CHECK (operation IN ('insert', 'update', 'delete')),
+                        row_ids TEXT NOT NULL DEFAULT '[]',
+                        changed_at TEXT NOT NULL DEFAULT (datetime('now'))
+                    )",
+                    [],
+                )?;
+                conn.execute(
+                    "CREATE INDEX IF NOT EXISTS idx_change_log_table ON change_log(table_name)",
+                    [],
+                )?;
+            }
+            7 => {
+                // Migration to version 7: updated_at triggers write ISO timestamps and respect explicit values
+                for table in UPDATED_AT_TABLES {
+                    conn.execute(&format!("DROP TRIGGER IF EXISTS update_{}_updated_at", table), [])?;
+                    conn.execute(
+                        &format!(
+                            "CREATE TRIGGER update_{table}_updated_at
+                              AFTER UPDATE ON {table}
+                              FOR EACH ROW
+                              WHEN NEW.updated_at IS OLD.updated_at
+                            BEGIN
+                              UPDATE {table} SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
+                            END",
+                            table = table
+                        ),
+                        [],
+                    )?;
+                }
+            }
+            8 => {
+                // Migration to version 8: Add attachments with content-addressed storage
+                conn.execute(
+                    "CREATE TABLE IF NOT EXISTS attachment_blobs (
+                        sha256 TEXT PRIMARY KEY,
+                        data BLOB NOT NULL,
+                        size INTEGER NOT NULL,
+                        created_at TEXT NOT NULL DEFAULT (datetime('now'))
+                    )",
+                    [],
+                )?;
+                conn.execute(
+                    "CREATE TABLE IF NOT EXISTS attachments (
+                        id TEXT PRIMARY KEY,
+                        entity_type TEXT NOT NULL,
+                        entity_id TEXT NOT NULL,
+                        kind TEXT NOT NULL CHECK (kind IN ('bank_statement', 'receipt', 'membership_form', 'medical_certificate', 'other')) DEFAULT 'other',
+                        file_name TEXT NOT NULL,
+                        mime_type TEXT,
+                        size INTEGER NOT NULL,
+                        sha256 TEXT NOT NULL REFERENCES attachment_blobs(sha256) ON DELETE RESTRICT,
+                        notes TEXT DEFAULT '',
+                        created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                        updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+                    )",
+                    [],
+                )?;
+                conn.execute(
+                    "CREATE INDEX IF NOT EXISTS idx_attachments_entity ON attachments(entity_type, entity_id)",
+                    [],
+                )?;
+                conn.execute(
+                    "CREATE INDEX IF NOT EXISTS idx_attachments_sha256 ON attachments(sha256)",
+                    [],
+                )?;
+                conn.execute(
+                    "CREATE TRIGGER IF NOT EXISTS update_attachments_updated_at
+                      AFTER UPDATE ON attachments
+                      FOR EACH ROW
+                      WHEN NEW.updated_at IS OLD.updated_at
+                    BEGIN
+                      UPDATE attachments SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
+                    END",
+                    [],
+                )?;
+            }
+            9 => {
+                // Migration to version 9: Allow the database_merged audit action.
+                // SQLite can't alter a CHECK constraint, so the table is rebuilt.
+                conn.execute(
+                    "CREATE TABLE audit_log_new (
+                        id TEXT PRIMARY KEY,
+                        action TEXT NOT NULL CHECK (action IN ('bulk_billing', 'import_confirmed', 'cost_cancelled', 'cost_created', 'cost_updated', 'payment_created', 'database_merged')),
+                        description TEXT NOT NULL,
+                        user_id TEXT,
+                        user_name TEXT,
+                        timestamp TEXT NOT NULL DEFAULT (datetime('now')),
+                        details TEXT DEFAULT '{}'
+                    )",
+                    [],
+                )?;
+                conn.execute(
+                    "INSERT INTO audit_log_new (id, action, description, user_id, user_name, timestamp, details)
+                     SELECT id, action, description, user_id, user_name, timestamp, details FROM audit_log",
+                    [],
+                )?;
+                conn.execute("DROP TABLE audit_log", [])?;
+                conn.execute("ALTER TABLE audit_log_new RENAME TO audit_log", [])?;
+                conn.execute("CREATE INDEX IF NOT EXISTS idx_audit_log_action ON audit_log(action)", [])?;
+                conn.execute("CREATE INDEX IF NOT EXISTS idx_audit_log_timestamp ON audit_log(timestamp)", [])?;
+                conn.execute(
+                    "CREATE INDEX IF NOT EXISTS idx_audit_log_user ON audit_log(user_id) WHERE user_id IS NOT NULL",
+                    [],
+                )?;
+            }
+            10 => {
+                // Migration to version 10: Sync bookkeeping
+                conn.execute(
+                    "ALTER TABLE change_log ADD COLUMN source TEXT NOT NULL DEFAULT 'local' CHECK (source IN ('local', 'sync'))",
+                    [],
+                )?;
+                conn.execute(
+                    "CREATE TABLE IF NOT EXISTS sync_state (
+                        key TEXT PRIMARY KEY,
+                        value TEXT NOT NULL
+                    )",
+                    [],
+                )?;
+                conn.execute(
+                    "CREATE TABLE IF NOT EXISTS sync_conflicts (
+                        id TEXT PRIMARY KEY,
+                        table_name TEXT NOT NULL,
+                        row_id TEXT NOT NULL,
+                        local_row TEXT,
+                        remote_row TEXT,
+                        winner TEXT NOT NULL CHECK (winner IN ('local', 'remote')),
+                        detected_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+                        resolution TEXT CHECK (resolution IN ('local', 'remote')),
+                        resolved_at TEXT
+                    )",
+                    [],
+                )?;
+                conn.execute(
+                    "CREATE INDEX IF NOT EXISTS idx_sync_conflicts_open ON sync_conflicts(detected_at) WHERE resolved_at IS NULL",
+                    [],
+                )?;
+            }
+            11 => {
+                // Migration to version 11: Message type and balances of imported bank statements
+                conn.execute("ALTER TABLE bank_statements ADD COLUMN message_type TEXT", [])?;
+                conn.execute("ALTER TABLE bank_statements ADD COLUMN message_id TEXT", [])?;
+                conn.execute("ALTER TABLE bank_statements ADD COLUMN account_iban TEXT", [])?;
+                conn.execute("ALTER TABLE bank_statements ADD COLUMN opening_balance REAL", [])?;
+                conn.execute("ALTER TABLE bank_statements ADD COLUMN opening_balance_date TEXT", [])?;
+                conn.execute("ALTER TABLE bank_statements ADD COLUMN closing_balance REAL", [])?;
+                conn.execute("ALTER TABLE bank_statements ADD COLUMN closing_balance_date TEXT", [])?;
+            }
+            12 => {
+                // Migration to version 12: CSV statements and saved CSV column mappings
+                rebuild_bank_statements(conn, "'pdf', 'xml', 'csv'")?;
+
+                conn.execute(
+                    "CREATE TABLE IF NOT EXISTS csv_import_profiles (
+                        id TEXT PRIMARY KEY,
+                        name TEXT NOT NULL UNIQUE,
+                        bank TEXT,
+                        account_iban TEXT NOT NULL DEFAULT '',
+                        delimiter TEXT NOT NULL DEFAULT ';',
+                        encoding TEXT NOT NULL DEFAULT 'windows-1250',
+                        decimal_comma INTEGER NOT NULL DEFAULT 1,
+                        date_format TEXT NOT NULL DEFAULT '%d.%m.%Y',
+                        skip_rows INTEGER NOT NULL DEFAULT 0,
+                        has_header INTEGER NOT NULL DEFAULT 1,
+                        columns TEXT NOT NULL DEFAULT '{}',
+                        created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                        updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+                    )",
+                    [],
+                )?;
+                conn.execute(
+                    "CREATE TRIGGER IF NOT EXISTS update_csv_import_profiles_updated_at
+                      AFTER UPDATE ON csv_import_profiles
+                      FOR EACH ROW
+                      WHEN NEW.updated_at IS OLD.updated_at
+                    BEGIN
+                      UPDATE csv_import_profiles SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
+                    END",
+                    [],
+                )?;
+            }
+            13 => {
+                // Migration to version 13: MT940 statements
+                rebuild_bank_statements(conn, "'pdf', 'xml', 'csv', 'mt940'")?;
+            }
+            14 => {
+                // Migration to version 14: File hashes and indexes for duplicate detection on import
+                conn.execute("ALTER TABLE bank_statements ADD COLUMN file_sha256 TEXT", [])?;
+                conn.execute(
+                    "CREATE INDEX IF NOT EXISTS idx_bank_statements_file_sha256 ON bank_statements(file_sha256) WHERE file_sha256 IS NOT NULL",
+                    [],
+                )?;
+                conn.execute(
+                    "CREATE INDEX IF NOT EXISTS idx_bank_transactions_bank_reference ON bank_transactions(bank_reference) WHERE bank_reference IS NOT NULL",
+                    [],
+                )?;
+            }
+            15 => {
+                // Migration to version 15: SI12/RF payment references for parents and costs
+                conn.execute("ALTER TABLE parents ADD COLUMN payment_reference TEXT", [])?;
+                conn.execute("ALTER TABLE costs ADD COLUMN payment_reference TEXT", [])?;
+                conn.execute(
+                    "CREATE UNIQUE INDEX IF NOT EXISTS idx_parents_payment_reference ON parents(payment_reference) WHERE payment_reference IS NOT NULL",
+                    [],
+                )?;
+                conn.execute(
+                    "CREATE UNIQUE INDEX IF NOT EXISTS idx_costs_payment_reference ON costs(payment_reference) WHERE payment_reference IS NOT NULL",
+                    [],
+                )?;
+            }
             _ => {
                 // Future migrations
             }
@@ -204,11 +520,75 @@ fn run_migrations(conn: &Connection, from_version: i32, to_version: i32) -> Resu
     Ok(())
 }
 
+/// Recreate `bank_statements` with the given list of allowed file types, as of schema
+/// version 11; SQLite can't change a CHECK in place. Foreign keys are off meanwhile,
+/// otherwise dropping the old table would cascade to bank_transactions.
+fn rebuild_bank_statements(conn: &Connection, file_types: &str) -> Result<()> {
+    let foreign_keys: bool = conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0))?;
+    conn.execute("PRAGMA foreign_keys = OFF", [])?;
+    conn.execute(
+        &format!(
+            "CREATE TABLE bank_statements_new (
+                id TEXT PRIMARY KEY,
+                file_name TEXT NOT NULL,
+                file_type TEXT NOT NULL CHECK (file_type IN ({})),
+                imported_at TEXT NOT NULL DEFAULT (datetime('now')),
+                status TEXT NOT NULL CHECK (status IN ('processing', 'completed', 'failed')) DEFAULT 'processing',
+                total_transactions INTEGER DEFAULT 0,
+                matched_transactions INTEGER DEFAULT 0,
+                unmatched_transactions INTEGER DEFAULT 0,
+                message_type TEXT,
+                message_id TEXT,
+                account_iban TEXT,
+                opening_balance REAL,
+                opening_balance_date TEXT,
+                closing_balance REAL,
+                closing_balance_date TEXT,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+            )",
+            file_types
+        ),
+        [],
+    )?;
+    // Upgraded databases have the version 11 columns after updated_at, so name them
+    let columns = "id, file_name, file_type, imported_at, status, total_transactions,
+        matched_transactions, unmatched_transactions, message_type, message_id, account_iban,
+        opening_balance, opening_balance_date, closing_balance, closing_balance_date,
+        created_at, updated_at";
+    conn.execute(
+        &format!("INSERT INTO bank_statements_new ({0}) SELECT {0} FROM bank_statements", columns),
+        [],
+    )?;
+    conn.execute("DROP TABLE bank_statements", [])?;
+    conn.execute("ALTER TABLE bank_statements_new RENAME TO bank_statements", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_bank_statements_status ON bank_statements(status)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_bank_statements_imported ON bank_statements(imported_at)", [])?;
+    conn.execute(
+        "CREATE TRIGGER IF NOT EXISTS update_bank_statements_updated_at
+          AFTER UPDATE ON bank_statements
+          FOR EACH ROW
+          WHEN NEW.updated_at IS OLD.updated_at
+        BEGIN
+          UPDATE bank_statements SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
+        END",
+        [],
+    )?;
+    if foreign_keys {
+        conn.execute("PRAGMA foreign_keys = ON", [])?;
+    }
+    Ok(())
+}
+
 /// Get a database connection (for use in Tauri commands)
 pub fn get_connection(app: &tauri::AppHandle) -> Result<Connection> {
     init_database(app)?;
     let db_path = get_db_path(app)?;
-    Connection::open(&db_path)
+    let conn = Connection::open(&db_path)?;
+    configure_connection(&conn)?;
+    // Off by default in SQLite; deletes rely on the schema's ON DELETE actions
+    conn.execute("PRAGMA foreign_keys = ON", [])?;
+    Ok(conn)
 }
 
 // Member-Parent relationship functions
@@ -0,0 +1,396 @@
+use crate::database::introspection::get_table_schema;
+use crate::database::{
+    ask_folder_path, ask_save_path, check_output_path, dump_tables, get_connection, order_by_columns,
+    save_text_file_to_path, SCHEMA_VERSION,
+};
+use rusqlite::types::Value;
+use rusqlite::Connection;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+
+/// Rows per INSERT statement in the SQL script
+const INSERT_BATCH_SIZE: usize = 500;
+/// SQLite INTEGER columns that hold 0/1 flags
+const BOOLEAN_COLUMNS: &[&str] = &["is_recurring", "imported_from_bank", "decimal_comma", "has_header"];
+/// SQLite TEXT columns that hold calendar dates
+const DATE_COLUMNS: &[&str] = &[
+    "date_of_birth",
+    "due_date",
+    "payment_date",
+    "transaction_date",
+    "recurring_start_date",
+    "recurring_end_date",
+    "opening_balance_date",
+    "closing_balance_date",
+];
+/// SQLite TEXT columns that hold JSON documents
+const JSON_COLUMNS: &[&str] = &["details", "columns"];
+
+/// Output of [`export_postgres`]: one SQL script, or a folder of CSV files with a psql load script
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PostgresExportFormat {
+    Sql,
+    Csv,
+}
+
+/// Postgres type a SQLite column is loaded into, matching `supabase/migrations`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PgType {
+    Text,
+    Integer,
+    Numeric,
+    Boolean,
+    Date,
+    Timestamp,
+    Json,
+    Bytea,
+}
+
+pub fn pg_type(column: &str, sqlite_type: &str) -> PgType {
+    match sqlite_type.to_uppercase().as_str() {
+        "BLOB" => PgType::Bytea,
+        "REAL" => PgType::Numeric,
+        "INTEGER" if BOOLEAN_COLUMNS.contains(&column) => PgType::Boolean,
+        "INTEGER" => PgType::Integer,
+        _ if DATE_COLUMNS.contains(&column) => PgType::Date,
+        _ if JSON_COLUMNS.contains(&column) => PgType::Json,
+        _ if column.ends_with("_at") || column == "timestamp" => PgType::Timestamp,
+        _ => PgType::Text,
+    }
+}
+
+struct ExportTable {
+    name: String,
+    columns: Vec<(String, PgType)>,
+    primary_key: Vec<String>,
+}
+
+/// Tables in an order where every referenced table comes before the tables referring to it
+fn tables_in_load_order(conn: &Connection) -> rusqlite::Result<Vec<ExportTable>> {
+    let mut pending: BTreeMap<String, (ExportTable, BTreeSet<String>)> = BTreeMap::new();
+    for table in dump_tables(conn)? {
+        let schema = get_table_schema(conn, &table)?;
+        let depends_on: BTreeSet<String> = schema
+            .foreign_keys
+            .iter()
+            .map(|fk| fk.references_table.clone())
+            .filter(|referenced| referenced != &table)
+            .collect();
+        let export_table = ExportTable {
+            name: table.clone(),
+            columns: schema
+                .columns
+                .iter()
+                .map(|c| (c.name.clone(), pg_type(&c.name, &c.data_type)))
+                .collect(),
+            primary_key: schema.columns.iter().filter(|c| c.primary_key).map(|c| c.name.clone()).collect(),
+        };
+        pending.insert(table, (export_table, depends_on));
+    }
+
+    let mut ordered = Vec::new();
+    while !pending.is_empty() {
+        let ready: Vec<String> = pending
+            .iter()
+            .filter(|(_, (_, depends_on))| depends_on.iter().all(|d| !pending.contains_key(d)))
+            .map(|(name, _)| name.clone())
+            .collect();
+        // A reference cycle can't be ordered; the load script disables FK checks anyway
+        let next = if ready.is_empty() {
+            vec![pending.keys().next().cloned().unwrap_or_default()]
+        } else {
+            ready
+        };
+        for name in next {
+            if let Some((table, _)) = pending.remove(&name) {
+                ordered.push(table);
+            }
+        }
+    }
+
+    Ok(ordered)
+}
+
+/// SQLite stores `datetime('now')` in UTC without a zone; make that explicit for TIMESTAMPTZ
+pub fn normalize_timestamp(value: &str) -> String {
+    let time_part = value.get(10..).unwrap_or("");
+    if value.len() <= 10 || value.ends_with('Z') || time_part.contains('+') || time_part.contains('-') {
+        value.to_string()
+    } else {
+        format!("{}+00", value)
+    }
+}
+
+/// Text form of a value as Postgres parses it for the column type, `None` for NULL
+fn pg_text(value: &Value, pg_type: PgType) -> Option<String> {
+    let text = match (value, pg_type) {
+        (Value::Null, _) => return None,
+        (Value::Integer(i), PgType::Boolean) => (if *i != 0 { "true" } else { "false" }).to_string(),
+        (Value::Integer(i), _) => i.to_string(),
+        (Value::Real(f), _) => f.to_string(),
+        (Value::Blob(bytes), _) => format!("\\x{}", hex::encode(bytes)),
+        (Value::Text(s), PgType::Date) => s.get(..10).unwrap_or(s).to_string(),
+        (Value::Text(s), PgType::Timestamp) => normalize_timestamp(s),
+        (Value::Text(s), PgType::Json) if serde_json::from_str::<serde_json::Value>(s).is_err() => {
+            serde_json::Value::String(s.clone()).to_string()
+        }
+        (Value::Text(s), _) => s.clone(),
+    };
+    Some(text)
+}
+
+fn sql_literal(value: &Value, pg_type: PgType) -> String {
+    let Some(text) = pg_text(value, pg_type) else {
+        return "NULL".to_string();
+    };
+    match (value, pg_type) {
+        (_, PgType::Boolean) | (Value::Integer(_), _) | (Value::Real(_), _) => text,
+        (_, PgType::Bytea) => format!("'{}'::bytea", text),
+        (_, PgType::Json) => format!("'{}'::jsonb", text.replace('\'', "''")),
+        _ => format!("'{}'", text.replace('\'', "''")),
+    }
+}
+
+fn csv_field(value: &Value, pg_type: PgType) -> String {
+    // COPY reads an unquoted empty field as NULL, so empty strings must be quoted
+    let Some(text) = pg_text(value, pg_type) else {
+        return String::new();
+    };
+    if text.is_empty() || text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+fn read_table_rows(conn: &Connection, table: &ExportTable) -> rusqlite::Result<Vec<Vec<Value>>> {
+    let columns: Vec<String> = table.columns.iter().map(|(name, _)| format!("\"{}\"", name)).collect();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM {} ORDER BY {}",
+        columns.join(", "),
+        table.name,
+        order_by_columns(conn, &table.name)?
+    ))?;
+    let rows = stmt.query_map([], |row| {
+        (0..columns.len()).map(|i| row.get::<_, Value>(i)).collect::<rusqlite::Result<Vec<Value>>>()
+    })?;
+
+    let mut result = Vec::new();
+    for row_result in rows {
+        result.push(row_result?);
+    }
+    Ok(result)
+}
+
+/// Supabase migrations that create the exported columns, in order; a test keeps the list in
+/// step with `supabase/migrations`
+const SUPABASE_MIGRATIONS: &[&str] = &[
+    "001_initial_schema.sql",
+    "002_desktop_schema_sync.sql",
+    "003_sync.sql",
+    "004_bank_statement_balances.sql",
+    "005_csv_statements.sql",
+    "006_mt940_statements.sql",
+    "007_statement_file_hashes.sql",
+    "008_payment_references.sql",
+];
+
+fn script_header(kind: &str) -> String {
+    let migrations: String = SUPABASE_MIGRATIONS
+        .iter()
+        .map(|name| format!("--   supabase/migrations/{}\n", name))
+        .collect();
+    format!(
+        "-- tskPay {} for PostgreSQL, exported {} from desktop schema version {}\n\
+         -- Load into a database migrated with:\n\
+         {}\
+         -- Existing rows with the same primary key are overwritten, so the script can be re-run.\n\n",
+        kind,
+        chrono::Local::now().to_rfc3339(),
+        SCHEMA_VERSION,
+        migrations
+    )
+}
+
+/// `ON CONFLICT` clause that overwrites an existing row with the exported one
+fn upsert_clause(table: &ExportTable) -> String {
+    let conflict_target = table.primary_key.join(", ");
+    let updates: Vec<String> = table
+        .columns
+        .iter()
+        .filter(|(name, _)| !table.primary_key.contains(name))
+        .map(|(name, _)| format!("{} = EXCLUDED.{}", name, name))
+        .collect();
+    if updates.is_empty() {
+        format!("ON CONFLICT ({}) DO NOTHING", conflict_target)
+    } else {
+        format!("ON CONFLICT ({}) DO UPDATE SET {}", conflict_target, updates.join(", "))
+    }
+}
+
+/// Build a PostgreSQL script that upserts every row of the database
+pub fn postgres_sql_script(conn: &Connection) -> rusqlite::Result<String> {
+    let mut script = script_header("data export");
+    script.push_str("BEGIN;\n");
+    // Skip FK checks and triggers, so cost status and updated_at stay exactly as exported
+    script.push_str("SET LOCAL session_replication_role = replica;\n");
+
+    for table in tables_in_load_order(conn)? {
+        let rows = read_table_rows(conn, &table)?;
+        if rows.is_empty() {
+            continue;
+        }
+
+        let column_list = table.columns.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(", ");
+        let on_conflict = upsert_clause(&table);
+
+        script.push_str(&format!("\n-- {} ({} rows)\n", table.name, rows.len()));
+        for batch in rows.chunks(INSERT_BATCH_SIZE) {
+            script.push_str(&format!("INSERT INTO {} ({}) VALUES\n", table.name, column_list));
+            let values: Vec<String> = batch
+                .iter()
+                .map(|row| {
+                    let literals: Vec<String> = row
+                        .iter()
+                        .zip(&table.columns)
+                        .map(|(value, (_, pg_type))| sql_literal(value, *pg_type))
+                        .collect();
+                    format!("  ({})", literals.join(", "))
+                })
+                .collect();
+            script.push_str(&values.join(",\n"));
+            script.push_str(&format!("\n{};\n", on_conflict));
+        }
+    }
+
+    script.push_str("\nCOMMIT;\n");
+    Ok(script)
+}
+
+/// Write one CSV file per table into `dir`, plus `import.sql` that loads them with psql `\copy`.
+/// Returns the written file names.
+pub fn write_postgres_csv(conn: &Connection, dir: &Path) -> Result<Vec<String>, String> {
+    if !dir.is_dir() {
+        return Err(format!("Direktorij {} ne obstaja.", dir.display()));
+    }
+
+    let mut files = Vec::new();
+    let mut load_script = script_header("CSV load script");
+    load_script.push_str("-- Run from this folder: psql \"$DATABASE_URL\" -f import.sql\n\n");
+    load_script.push_str("BEGIN;\n");
+    load_script.push_str("SET LOCAL session_replication_role = replica;\n");
+
+    for table in tables_in_load_order(conn).map_err(|e| e.to_string())? {
+        let rows = read_table_rows(conn, &table).map_err(|e| e.to_string())?;
+        let column_names: Vec<&str> = table.columns.iter().map(|(name, _)| name.as_str()).collect();
+
+        let mut csv = column_names.join(",");
+        csv.push('\n');
+        for row in &rows {
+            let fields: Vec<String> = row
+                .iter()
+                .zip(&table.columns)
+                .map(|(value, (_, pg_type))| csv_field(value, *pg_type))
+                .collect();
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+
+        let file_name = format!("{}.csv", table.name);
+        let path = dir.join(&file_name);
+        fs::write(&path, csv)
+            .map_err(|e| format!("Napaka pri zapisovanju datoteke {}: {}", path.display(), e))?;
+        files.push(file_name.clone());
+
+        // COPY can't upsert, so each file goes through a staging table first
+        let column_list = column_names.join(", ");
+        let staging = format!("_load_{}", table.name);
+        load_script.push_str(&format!(
+            "\nCREATE TEMP TABLE {staging} (LIKE {table} INCLUDING DEFAULTS) ON COMMIT DROP;\n\
+             \\copy {staging} ({columns}) FROM '{file}' WITH (FORMAT csv, HEADER true)\n\
+             INSERT INTO {table} ({columns}) SELECT {columns} FROM {staging} {upsert};\n",
+            staging = staging,
+            table = table.name,
+            columns = column_list,
+            file = file_name,
+            upsert = upsert_clause(&table)
+        ));
+    }
+
+    load_script.push_str("\nCOMMIT;\n");
+    let script_path = dir.join("import.sql");
+    fs::write(&script_path, load_script)
+        .map_err(|e| format!("Napaka pri zapisovanju datoteke {}: {}", script_path.display(), e))?;
+    files.push("import.sql".to_string());
+
+    Ok(files)
+}
+
+/// Export the database for PostgreSQL to `path`: a `.sql` file, or an existing folder for CSV
+pub fn export_postgres_to_path(app: &AppHandle, format: PostgresExportFormat, path: &Path) -> Result<String, String> {
+    let conn = get_connection(app).map_err(|e| e.to_string())?;
+    match format {
+        PostgresExportFormat::Sql => {
+            let script = postgres_sql_script(&conn).map_err(|e| format!("Napaka pri branju baze podatkov: {}", e))?;
+            save_text_file_to_path(path, &script)
+        }
+        PostgresExportFormat::Csv => {
+            check_output_path(&path.join("import.sql"))?;
+            write_postgres_csv(&conn, path)?;
+            Ok(path.to_string_lossy().to_string())
+        }
+    }
+}
+
+/// Export the database for PostgreSQL to a user-selected file or folder
+#[tauri::command]
+pub async fn export_postgres(app: AppHandle, format: PostgresExportFormat) -> Result<String, String> {
+    let path = match format {
+        PostgresExportFormat::Sql => {
+            let timestamp = chrono::Local::now().format("%Y-%m-%d-%H%M%S");
+            ask_save_path(
+                &app,
+                "Shrani izvoz za PostgreSQL",
+                &format!("tskpay-postgres-{}.sql", timestamp),
+                &[("SQL", &["sql"]), ("All Files", &["*"])],
+                "Izvoz je bil preklican.",
+            )
+            .await?
+        }
+        PostgresExportFormat::Csv => {
+            ask_folder_path(&app, "Izberi mapo za CSV izvoz", "Izvoz je bil preklican.").await?
+        }
+    };
+
+    export_postgres_to_path(&app, format, &path)
+}
+
+/// Export the database for PostgreSQL to an explicit path, without a dialog
+#[tauri::command]
+pub fn export_postgres_to(app: AppHandle, format: PostgresExportFormat, path: String) -> Result<String, String> {
+    export_postgres_to_path(&app, format, Path::new(&path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_lists_every_supabase_migration() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../../supabase/migrations");
+        let mut on_disk: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| name.ends_with(".sql"))
+            .collect();
+        on_disk.sort();
+        assert_eq!(on_disk, SUPABASE_MIGRATIONS);
+
+        let header = script_header("SQL export");
+        assert!(header.contains("--   supabase/migrations/008_payment_references.sql\n"), "{}", header);
+    }
+}
@@ -1,9 +1,92 @@
+use super::introspection::get_schema;
+use base64::Engine;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use rusqlite::{Connection, Result};
 use serde_json;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Id prefix per table, used when the caller does not supply an id
+const ID_PREFIXES: &[(&str, &str)] = &[
+    ("parents", "par"),
+    ("coaches", "coa"),
+    ("groups", "grp"),
+    ("members", "mem"),
+    ("member_parents", "mp"),
+    ("cost_types", "ct"),
+    ("costs", "cst"),
+    ("payments", "pay"),
+    ("bank_statements", "stmt"),
+    ("bank_transactions", "txn"),
+    ("payment_allocations", "alloc"),
+    ("audit_log", "audit"),
+    ("attachments", "att"),
+    ("sync_conflicts", "sconf"),
+    ("csv_import_profiles", "csvp"),
+];
+
+static ID_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// Generate a new id for a table, e.g. `mem-0192f3a1b2c4000a7f3e21`.
+///
+/// The part after the prefix starts with the creation time in milliseconds (fixed-width hex)
+/// followed by a per-process counter, so ids of one table sort in creation order. The random
+/// tail keeps ids created on different computers from colliding.
+pub fn generate_id(table: &str) -> String {
+    let prefix = ID_PREFIXES
+        .iter()
+        .find(|(name, _)| *name == table)
+        .map(|(_, prefix)| *prefix)
+        .unwrap_or(table);
+    let millis = chrono::Utc::now().timestamp_millis().max(0) as u64;
+    let counter = ID_COUNTER.fetch_add(1, Ordering::Relaxed) & 0xffff;
+    let random = rand::random::<u32>() & 0xffffff;
+    format!("{}-{:012x}{:04x}{:06x}", prefix, millis, counter, random)
+}
+
+/// Current time in the ISO 8601 format used for created_at/updated_at (same as JS `toISOString`)
+pub fn now_timestamp() -> String {
+    chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+/// Parse the timestamp formats found in SQLite (`datetime('now')`, ISO 8601) and in PostgREST responses
+pub fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Utc));
+    }
+    if let Ok(time) = DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f%#z") {
+        return Some(time.with_timezone(&Utc));
+    }
+    ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .map(|time| time.and_utc())
+}
+
+/// Names of the columns of a table
+pub fn table_columns(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1) ORDER BY cid")?;
+    let rows = stmt.query_map([table], |row| row.get::<_, String>(0))?;
+
+    let mut columns = Vec::new();
+    for row_result in rows {
+        columns.push(row_result?);
+    }
+
+    Ok(columns)
+}
+
+fn is_missing(data: &HashMap<String, serde_json::Value>, key: &str) -> bool {
+    match data.get(key) {
+        None | Some(serde_json::Value::Null) => true,
+        Some(serde_json::Value::String(s)) => s.is_empty(),
+        Some(_) => false,
+    }
+}
 
 // Helper to convert SQLite row to JSON-serializable HashMap
-fn row_to_map(row: &rusqlite::Row) -> Result<HashMap<String, serde_json::Value>> {
+// BLOB columns are returned as base64 strings
+pub fn row_to_map(row: &rusqlite::Row) -> Result<HashMap<String, serde_json::Value>> {
     let mut map = HashMap::new();
     let column_count = row.as_ref().column_count();
     
@@ -18,7 +101,9 @@ fn row_to_map(row: &rusqlite::Row) -> Result<HashMap<String, serde_json::Value>>
                 serde_json::Value::Number(serde_json::Number::from_f64(f).unwrap_or(serde_json::Number::from(0)))
             }
             rusqlite::types::Value::Text(s) => serde_json::Value::String(s),
-            rusqlite::types::Value::Blob(_) => serde_json::Value::String("BLOB".to_string()),
+            rusqlite::types::Value::Blob(b) => {
+                serde_json::Value::String(base64::engine::general_purpose::STANDARD.encode(b))
+            }
         };
         
         map.insert(column_name, json_value);
@@ -53,7 +138,21 @@ pub fn get_by_id(conn: &Connection, table: &str, id: &str) -> Result<Option<Hash
 }
 
 // Generic create function
+// Fills in id, created_at and updated_at when the caller leaves them out and returns the stored row
 pub fn create(conn: &Connection, table: &str, data: &HashMap<String, serde_json::Value>) -> Result<HashMap<String, serde_json::Value>> {
+    let table_cols = table_columns(conn, table)?;
+    let mut data = data.clone();
+    let now = now_timestamp();
+
+    if table_cols.iter().any(|c| c == "id") && is_missing(&data, "id") {
+        data.insert("id".to_string(), serde_json::Value::String(generate_id(table)));
+    }
+    for timestamp_col in ["created_at", "updated_at"] {
+        if table_cols.iter().any(|c| c == timestamp_col) && is_missing(&data, timestamp_col) {
+            data.insert(timestamp_col.to_string(), serde_json::Value::String(now.clone()));
+        }
+    }
+
     let columns: Vec<String> = data.keys().cloned().collect();
     let placeholders: Vec<String> = (1..=columns.len()).map(|i| format!("?{}", i)).collect();
     
@@ -89,27 +188,28 @@ pub fn create(conn: &Connection, table: &str, data: &HashMap<String, serde_json:
     
     stmt.execute(rusqlite::params_from_iter(values.iter()))?;
     
-    // Return the created record
-    if let Some(id) = data.get("id") {
-        if let Some(id_str) = id.as_str() {
-            return get_by_id(conn, table, id_str).and_then(|opt| {
-                opt.ok_or_else(|| rusqlite::Error::QueryReturnedNoRows)
-            });
-        }
-    }
-    
-    Err(rusqlite::Error::QueryReturnedNoRows)
+    // Return the created record, looked up by rowid so defaults filled in by SQLite are included
+    let rowid = conn.last_insert_rowid();
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {} WHERE rowid = ?1", table))?;
+    stmt.query_row([rowid], row_to_map)
 }
 
 // Generic update function
-pub fn update(conn: &Connection, table: &str, id: &str, data: &HashMap<String, serde_json::Value>) -> Result<()> {
+// Bumps updated_at unless the caller sets it explicitly
+/// Update the row with `id`; returns the number of rows changed, 0 when there is no such row
+pub fn update(conn: &Connection, table: &str, id: &str, data: &HashMap<String, serde_json::Value>) -> Result<usize> {
+    let mut data = data.clone();
+    if data.keys().any(|k| k != "id") && is_missing(&data, "updated_at") && table_columns(conn, table)?.iter().any(|c| c == "updated_at") {
+        data.insert("updated_at".to_string(), serde_json::Value::String(now_timestamp()));
+    }
+
     let columns: Vec<String> = data.keys()
         .filter(|k| *k != "id")
         .cloned()
         .collect();
     
     if columns.is_empty() {
-        return Ok(());
+        return Ok(0);
     }
     
     let set_clauses: Vec<String> = columns.iter()
@@ -149,17 +249,166 @@ pub fn update(conn: &Connection, table: &str, id: &str, data: &HashMap<String, s
     
     values.push(rusqlite::types::Value::Text(id.to_string()));
     
-    stmt.execute(rusqlite::params_from_iter(values.iter()))?;
-    
-    Ok(())
+    stmt.execute(rusqlite::params_from_iter(values.iter()))
 }
 
-// Generic delete function
-pub fn delete(conn: &Connection, table: &str, id: &str) -> Result<()> {
+// Generic delete function, returns the number of rows deleted
+pub fn delete(conn: &Connection, table: &str, id: &str) -> Result<usize> {
     let sql = format!("DELETE FROM {} WHERE id = ?1", table);
     let mut stmt = conn.prepare(&sql)?;
-    stmt.execute([id])?;
-    Ok(())
+    stmt.execute([id])
+}
+
+/// Rows that deleting `id` from `table` changes through `ON DELETE CASCADE` and
+/// `ON DELETE SET NULL` foreign keys, as `(table, "delete" | "update", ids)`. Call it before
+/// the delete; cascades are followed to the children of deleted children.
+pub fn delete_actions(conn: &Connection, table: &str, id: &str) -> Result<Vec<(String, &'static str, Vec<String>)>> {
+    let schema = get_schema(conn)?;
+    let mut actions: BTreeMap<(String, &'static str), BTreeSet<String>> = BTreeMap::new();
+    let mut pending = vec![(table.to_string(), vec![id.to_string()])];
+
+    while let Some((parent, ids)) = pending.pop() {
+        for child in schema.iter().filter(|t| t.columns.iter().any(|c| c.name == "id")) {
+            for foreign_key in child.foreign_keys.iter().filter(|fk| fk.references_table == parent) {
+                let operation = match foreign_key.on_delete.as_str() {
+                    "CASCADE" => "delete",
+                    "SET NULL" => "update",
+                    _ => continue,
+                };
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT id FROM \"{}\" WHERE \"{}\" IN (SELECT \"{}\" FROM \"{}\" WHERE id = ?1)",
+                    child.name, foreign_key.column, foreign_key.references_column, parent
+                ))?;
+                let affected = actions.entry((child.name.clone(), operation)).or_default();
+                let mut found = Vec::new();
+                for parent_id in &ids {
+                    for child_id in stmt.query_map([parent_id], |row| row.get::<_, String>(0))? {
+                        let child_id = child_id?;
+                        if affected.insert(child_id.clone()) {
+                            found.push(child_id);
+                        }
+                    }
+                }
+                if operation == "delete" && !found.is_empty() {
+                    pending.push((child.name.clone(), found));
+                }
+            }
+        }
+    }
+
+    // A row that is deleted by one cascade is not also reported as updated by another
+    let deleted: BTreeMap<String, BTreeSet<String>> = actions
+        .iter()
+        .filter(|((_, operation), _)| *operation == "delete")
+        .map(|((table, _), ids)| (table.clone(), ids.clone()))
+        .collect();
+    Ok(actions
+        .into_iter()
+        .map(|((table, operation), ids)| {
+            let ids: Vec<String> = match (operation, deleted.get(&table)) {
+                ("update", Some(gone)) => ids.difference(gone).cloned().collect(),
+                _ => ids.into_iter().collect(),
+            };
+            (table, operation, ids)
+        })
+        .filter(|(_, _, ids)| !ids.is_empty())
+        .collect())
 }
 
 // Note: Member-Parent relationship functions are in mod.rs
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("schema.sql")).unwrap();
+        conn.execute("PRAGMA foreign_keys = ON", []).unwrap();
+        conn
+    }
+
+    #[test]
+    fn generated_ids_carry_the_table_prefix() {
+        assert!(generate_id("parents").starts_with("par-"));
+        assert!(generate_id("members").starts_with("mem-"));
+        assert!(generate_id("bank_transactions").starts_with("txn-"));
+        assert!(generate_id("csv_import_profiles").starts_with("csvp-"));
+        assert!(generate_id("unknown_table").starts_with("unknown_table-"));
+
+        let id = generate_id("payments");
+        let tail = id.strip_prefix("pay-").unwrap();
+        assert_eq!(tail.len(), 22);
+        assert!(tail.chars().all(|c| c.is_ascii_hexdigit()), "{}", id);
+    }
+
+    #[test]
+    fn generated_ids_sort_in_creation_order_and_do_not_repeat() {
+        let ids: Vec<String> = (0..10_000).map(|_| generate_id("members")).collect();
+        for pair in ids.windows(2) {
+            assert!(pair[0] < pair[1], "{} >= {}", pair[0], pair[1]);
+        }
+        let unique: BTreeSet<&String> = ids.iter().collect();
+        assert_eq!(unique.len(), ids.len());
+    }
+
+    #[test]
+    fn create_returns_the_stored_row() {
+        let conn = database();
+        let data: HashMap<String, serde_json::Value> =
+            serde_json::from_value(serde_json::json!({ "first_name": "Mojca", "last_name": "Novak", "email": null })).unwrap();
+
+        let created = create(&conn, "parents", &data).unwrap();
+        let id = created["id"].as_str().unwrap();
+        assert!(id.starts_with("par-"));
+        assert_eq!(created["first_name"], "Mojca");
+        assert_eq!(created["email"], serde_json::Value::Null);
+        assert!(parse_timestamp(created["created_at"].as_str().unwrap()).is_some());
+        assert_eq!(created["created_at"], created["updated_at"]);
+        assert_eq!(Some(created.clone()), get_by_id(&conn, "parents", id).unwrap());
+
+        let given: HashMap<String, serde_json::Value> =
+            serde_json::from_value(serde_json::json!({ "id": "par-own", "first_name": "Ana", "last_name": "Kos" })).unwrap();
+        assert_eq!(create(&conn, "parents", &given).unwrap()["id"], "par-own");
+    }
+
+    #[test]
+    fn delete_actions_follow_cascades_before_the_delete() {
+        let conn = database();
+        conn.execute_batch(
+            "INSERT INTO bank_statements (id, file_name, file_type) VALUES ('stmt-1', 'marec.xml', 'xml');
+             INSERT INTO bank_transactions (id, bank_statement_id, transaction_date, amount, description, account_number, payer_name)
+               VALUES ('txn-1', 'stmt-1', '2025-03-14', 45, 'Vadnina', 'SI56031001000012345', 'NOVAK MOJCA'),
+                      ('txn-2', 'stmt-1', '2025-03-14', 30, 'Vadnina', 'SI56031001000012345', 'NOVAK MOJCA');
+             INSERT INTO payments (id, amount, payment_date, payment_method, imported_from_bank, bank_transaction_id)
+               VALUES ('pay-1', 45, '2025-03-14', 'bank_transfer', 1, 'txn-1');",
+        )
+        .unwrap();
+
+        let actions = delete_actions(&conn, "bank_statements", "stmt-1").unwrap();
+        assert_eq!(
+            actions,
+            vec![
+                ("bank_transactions".to_string(), "delete", vec!["txn-1".to_string(), "txn-2".to_string()]),
+                ("payments".to_string(), "update", vec!["pay-1".to_string()]),
+            ]
+        );
+
+        assert_eq!(delete(&conn, "bank_statements", "stmt-1").unwrap(), 1);
+        let transactions: i64 = conn.query_row("SELECT COUNT(*) FROM bank_transactions", [], |row| row.get(0)).unwrap();
+        assert_eq!(transactions, 0);
+        let linked: Option<String> = conn
+            .query_row("SELECT bank_transaction_id FROM payments WHERE id = 'pay-1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(linked, None);
+        assert_eq!(delete(&conn, "bank_statements", "stmt-1").unwrap(), 0);
+    }
+
+    #[test]
+    fn update_reports_a_missing_row() {
+        let conn = database();
+        let data: HashMap<String, serde_json::Value> =
+            serde_json::from_value(serde_json::json!({ "first_name": "Ana" })).unwrap();
+        assert_eq!(update(&conn, "parents", "par-none", &data).unwrap(), 0);
+    }
+}
@@ -18,12 +18,15 @@ CREATE TABLE IF NOT EXISTS parents (
   phone TEXT,
   iban TEXT,
   created_at TEXT NOT NULL DEFAULT (datetime('now')),
-  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+  updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+  -- Sklic SI12 ali RF, s katerim starš plačuje
+  payment_reference TEXT
 );
 
 CREATE INDEX IF NOT EXISTS idx_parents_name ON parents(first_name, last_name);
 CREATE INDEX IF NOT EXISTS idx_parents_email ON parents(email) WHERE email IS NOT NULL;
 CREATE INDEX IF NOT EXISTS idx_parents_iban ON parents(iban) WHERE iban IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_parents_payment_reference ON parents(payment_reference) WHERE payment_reference IS NOT NULL;
 
 -- Coaches (Trenerji)
 CREATE TABLE IF NOT EXISTS coaches (
@@ -106,30 +109,42 @@ CREATE TABLE IF NOT EXISTS costs (
   recurring_start_date TEXT,
   recurring_end_date TEXT,
   recurring_day_of_month INTEGER,
-  recurring_template_id TEXT REFERENCES costs(id) ON DELETE SET NULL
+  recurring_template_id TEXT REFERENCES costs(id) ON DELETE SET NULL,
+  -- Sklic SI12 ali RF za plačilo samo tega stroška
+  payment_reference TEXT
 );
 
 CREATE INDEX IF NOT EXISTS idx_costs_member ON costs(member_id);
 CREATE INDEX IF NOT EXISTS idx_costs_status ON costs(status);
 CREATE INDEX IF NOT EXISTS idx_costs_cost_type ON costs(cost_type_id);
 CREATE INDEX IF NOT EXISTS idx_costs_due_date ON costs(due_date) WHERE due_date IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_costs_payment_reference ON costs(payment_reference) WHERE payment_reference IS NOT NULL;
 
 -- Bank Statements (Bančni izpiski)
 CREATE TABLE IF NOT EXISTS bank_statements (
   id TEXT PRIMARY KEY,
   file_name TEXT NOT NULL,
-  file_type TEXT NOT NULL CHECK (file_type IN ('pdf', 'xml')),
+  file_type TEXT NOT NULL CHECK (file_type IN ('pdf', 'xml', 'csv', 'mt940')),
   imported_at TEXT NOT NULL DEFAULT (datetime('now')),
   status TEXT NOT NULL CHECK (status IN ('processing', 'completed', 'failed')) DEFAULT 'processing',
   total_transactions INTEGER DEFAULT 0,
   matched_transactions INTEGER DEFAULT 0,
   unmatched_transactions INTEGER DEFAULT 0,
+  message_type TEXT, -- e.g. camt.053.001.02
+  message_id TEXT,
+  account_iban TEXT,
+  opening_balance REAL, -- Account balances for reconciliation, negative when overdrawn
+  opening_balance_date TEXT,
+  closing_balance REAL,
+  closing_balance_date TEXT,
+  file_sha256 TEXT, -- SHA-256 of the imported file, to refuse importing it again
   created_at TEXT NOT NULL DEFAULT (datetime('now')),
   updated_at TEXT NOT NULL DEFAULT (datetime('now'))
 );
 
 CREATE INDEX IF NOT EXISTS idx_bank_statements_status ON bank_statements(status);
 CREATE INDEX IF NOT EXISTS idx_bank_statements_imported ON bank_statements(imported_at);
+CREATE INDEX IF NOT EXISTS idx_bank_statements_file_sha256 ON bank_statements(file_sha256) WHERE file_sha256 IS NOT NULL;
 
 -- Payments (Plačila)
 CREATE TABLE IF NOT EXISTS payments (
@@ -176,6 +191,7 @@ CREATE INDEX IF NOT EXISTS idx_bank_transactions_parent ON bank_transactions(mat
 CREATE INDEX IF NOT EXISTS idx_bank_transactions_status ON bank_transactions(status);
 CREATE INDEX IF NOT EXISTS idx_bank_transactions_date ON bank_transactions(transaction_date);
 CREATE INDEX IF NOT EXISTS idx_bank_transactions_reference ON bank_transactions(reference) WHERE reference IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_bank_transactions_bank_reference ON bank_transactions(bank_reference) WHERE bank_reference IS NOT NULL;
 CREATE INDEX IF NOT EXISTS idx_bank_transactions_account ON bank_transactions(account_number);
 CREATE INDEX IF NOT EXISTS idx_bank_transactions_payer_name ON bank_transactions(payer_name);
 
@@ -195,7 +211,7 @@ CREATE INDEX IF NOT EXISTS idx_payment_allocations_cost ON payment_allocations(c
 -- Audit Log
 CREATE TABLE IF NOT EXISTS audit_log (
   id TEXT PRIMARY KEY,
-  action TEXT NOT NULL CHECK (action IN ('bulk_billing', 'import_confirmed', 'cost_cancelled', 'cost_created', 'cost_updated', 'payment_created')),
+  action TEXT NOT NULL CHECK (action IN ('bulk_billing', 'import_confirmed', 'cost_cancelled', 'cost_created', 'cost_updated', 'payment_created', 'database_merged')),
   description TEXT NOT NULL,
   user_id TEXT,
   user_name TEXT,
@@ -207,64 +223,165 @@ CREATE INDEX IF NOT EXISTS idx_audit_log_action ON audit_log(action);
 CREATE INDEX IF NOT EXISTS idx_audit_log_timestamp ON audit_log(timestamp);
 CREATE INDEX IF NOT EXISTS idx_audit_log_user ON audit_log(user_id) WHERE user_id IS NOT NULL;
 
+-- Change Log (committed writes, pushed to the frontend as events)
+CREATE TABLE IF NOT EXISTS change_log (
+  seq INTEGER PRIMARY KEY AUTOINCREMENT,
+  table_name TEXT NOT NULL,
+  operation TEXT NOT NULL CHECK (operation IN ('insert', 'update', 'delete')),
+  row_ids TEXT NOT NULL DEFAULT '[]',
+  changed_at TEXT NOT NULL DEFAULT (datetime('now')),
+  -- 'sync' for rows written by a pull from the sync server, which must not be pushed back
+  source TEXT NOT NULL DEFAULT 'local' CHECK (source IN ('local', 'sync'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_change_log_table ON change_log(table_name);
+
+-- Sync Bookkeeping (device-local, never exported or synced)
+CREATE TABLE IF NOT EXISTS sync_state (
+  key TEXT PRIMARY KEY,
+  value TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS sync_conflicts (
+  id TEXT PRIMARY KEY,
+  table_name TEXT NOT NULL,
+  row_id TEXT NOT NULL,
+  local_row TEXT,
+  remote_row TEXT,
+  winner TEXT NOT NULL CHECK (winner IN ('local', 'remote')),
+  detected_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  resolution TEXT CHECK (resolution IN ('local', 'remote')),
+  resolved_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_sync_conflicts_open ON sync_conflicts(detected_at) WHERE resolved_at IS NULL;
+
+-- CSV Import Profiles (column mappings of online banking CSV exports)
+CREATE TABLE IF NOT EXISTS csv_import_profiles (
+  id TEXT PRIMARY KEY,
+  name TEXT NOT NULL UNIQUE,
+  bank TEXT,
+  account_iban TEXT NOT NULL DEFAULT '',
+  delimiter TEXT NOT NULL DEFAULT ';',
+  encoding TEXT NOT NULL DEFAULT 'windows-1250',
+  decimal_comma INTEGER NOT NULL DEFAULT 1,
+  date_format TEXT NOT NULL DEFAULT '%d.%m.%Y',
+  skip_rows INTEGER NOT NULL DEFAULT 0,
+  has_header INTEGER NOT NULL DEFAULT 1,
+  columns TEXT NOT NULL DEFAULT '{}', -- JSON: field -> header name or 1-based column number
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+-- Attachment Contents (one row per distinct file, addressed by SHA-256)
+CREATE TABLE IF NOT EXISTS attachment_blobs (
+  sha256 TEXT PRIMARY KEY,
+  data BLOB NOT NULL,
+  size INTEGER NOT NULL,
+  created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+-- Attachments (Priloge) linked to any entity
+CREATE TABLE IF NOT EXISTS attachments (
+  id TEXT PRIMARY KEY,
+  entity_type TEXT NOT NULL,
+  entity_id TEXT NOT NULL,
+  kind TEXT NOT NULL CHECK (kind IN ('bank_statement', 'receipt', 'membership_form', 'medical_certificate', 'other')) DEFAULT 'other',
+  file_name TEXT NOT NULL,
+  mime_type TEXT,
+  size INTEGER NOT NULL,
+  sha256 TEXT NOT NULL REFERENCES attachment_blobs(sha256) ON DELETE RESTRICT,
+  notes TEXT DEFAULT '',
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_attachments_entity ON attachments(entity_type, entity_id);
+CREATE INDEX IF NOT EXISTS idx_attachments_sha256 ON attachments(sha256);
+
 -- =============================================================================
 -- Triggers for updated_at
+-- Only bump the timestamp when the UPDATE itself did not set it
 -- =============================================================================
 
 CREATE TRIGGER IF NOT EXISTS update_parents_updated_at
   AFTER UPDATE ON parents
   FOR EACH ROW
+  WHEN NEW.updated_at IS OLD.updated_at
 BEGIN
-  UPDATE parents SET updated_at = datetime('now') WHERE id = NEW.id;
+  UPDATE parents SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
 END;
 
 CREATE TRIGGER IF NOT EXISTS update_coaches_updated_at
   AFTER UPDATE ON coaches
   FOR EACH ROW
+  WHEN NEW.updated_at IS OLD.updated_at
 BEGIN
-  UPDATE coaches SET updated_at = datetime('now') WHERE id = NEW.id;
+  UPDATE coaches SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
 END;
 
 CREATE TRIGGER IF NOT EXISTS update_groups_updated_at
   AFTER UPDATE ON groups
   FOR EACH ROW
+  WHEN NEW.updated_at IS OLD.updated_at
 BEGIN
-  UPDATE groups SET updated_at = datetime('now') WHERE id = NEW.id;
+  UPDATE groups SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
 END;
 
 CREATE TRIGGER IF NOT EXISTS update_members_updated_at
   AFTER UPDATE ON members
   FOR EACH ROW
+  WHEN NEW.updated_at IS OLD.updated_at
 BEGIN
-  UPDATE members SET updated_at = datetime('now') WHERE id = NEW.id;
+  UPDATE members SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
 END;
 
 CREATE TRIGGER IF NOT EXISTS update_costs_updated_at
   AFTER UPDATE ON costs
   FOR EACH ROW
+  WHEN NEW.updated_at IS OLD.updated_at
 BEGIN
-  UPDATE costs SET updated_at = datetime('now') WHERE id = NEW.id;
+  UPDATE costs SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
 END;
 
 CREATE TRIGGER IF NOT EXISTS update_bank_statements_updated_at
   AFTER UPDATE ON bank_statements
   FOR EACH ROW
+  WHEN NEW.updated_at IS OLD.updated_at
 BEGIN
-  UPDATE bank_statements SET updated_at = datetime('now') WHERE id = NEW.id;
+  UPDATE bank_statements SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
 END;
 
 CREATE TRIGGER IF NOT EXISTS update_bank_transactions_updated_at
   AFTER UPDATE ON bank_transactions
   FOR EACH ROW
+  WHEN NEW.updated_at IS OLD.updated_at
+BEGIN
+  UPDATE bank_transactions SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS update_csv_import_profiles_updated_at
+  AFTER UPDATE ON csv_import_profiles
+  FOR EACH ROW
+  WHEN NEW.updated_at IS OLD.updated_at
 BEGIN
-  UPDATE bank_transactions SET updated_at = datetime('now') WHERE id = NEW.id;
+  UPDATE csv_import_profiles SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
 END;
 
 CREATE TRIGGER IF NOT EXISTS update_payments_updated_at
   AFTER UPDATE ON payments
   FOR EACH ROW
+  WHEN NEW.updated_at IS OLD.updated_at
+BEGIN
+  UPDATE payments SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS update_attachments_updated_at
+  AFTER UPDATE ON attachments
+  FOR EACH ROW
+  WHEN NEW.updated_at IS OLD.updated_at
 BEGIN
-  UPDATE payments SET updated_at = datetime('now') WHERE id = NEW.id;
+  UPDATE attachments SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
 END;
 
 -- =============================================================================
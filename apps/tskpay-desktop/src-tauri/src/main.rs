@@ -1,9 +1,11 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod bank;
 mod database;
+mod sync;
 
-use database::{db_init, db_get_all, db_get_by_id, db_create, db_update, db_delete, db_get_member_parents, db_set_member_parents, db_get_parent_members, export_database, import_database, save_text_file};
+use database::{db_init, db_get_all, db_get_by_id, db_create, db_update, db_delete, db_get_changes_since, db_schema, db_get_member_parents, db_set_member_parents, db_get_parent_members, export_database, export_database_to, import_database, import_database_from, save_text_file, save_text_file_to, attachment_upload, attachment_list, attachment_open, attachment_export, attachment_delete, get_backup_settings, backup_warnings, set_backup_settings, run_backup_now, backup_list, backup_restore, backup_delete, backup_reveal_folder, export_json_dump, export_json_dump_to, import_json_dump, import_json_dump_from, merge_preview, merge_apply};
 
 fn main() {
     tauri::Builder::default()
@@ -17,20 +19,88 @@ fn main() {
             db_create,
             db_update,
             db_delete,
+            db_get_changes_since,
+            db_schema,
             db_get_member_parents,
             db_set_member_parents,
             db_get_parent_members,
             export_database,
+            export_database_to,
             import_database,
+            import_database_from,
             save_text_file,
+            save_text_file_to,
+            attachment_upload,
+            attachment_list,
+            attachment_open,
+            attachment_export,
+            attachment_delete,
+            get_backup_settings,
+            backup_warnings,
+            set_backup_settings,
+            run_backup_now,
+            backup_list,
+            backup_restore,
+            backup_delete,
+            backup_reveal_folder,
+            database::archive::archive_manifest,
+            export_json_dump,
+            export_json_dump_to,
+            import_json_dump,
+            import_json_dump_from,
+            merge_preview,
+            merge_apply,
+            database::diff::database_diff,
+            database::diff::database_diff_report,
+            database::postgres::export_postgres,
+            database::postgres::export_postgres_to,
+            sync::get_sync_settings,
+            sync::set_sync_settings,
+            sync::sync_now,
+            sync::sync_get_status,
+            sync::sync_list_conflicts,
+            sync::sync_resolve_conflict,
+            bank::bank_parse_xml,
+            bank::bank_parse_xml_from,
+            bank::bank_import_xml,
+            bank::bank_import_xml_from,
+            bank::bank_parse_pdf,
+            bank::bank_parse_pdf_from,
+            bank::bank_import_pdf,
+            bank::bank_import_pdf_from,
+            bank::bank_parse_mt940,
+            bank::bank_parse_mt940_from,
+            bank::bank_import_mt940,
+            bank::bank_import_mt940_from,
+            bank::bank_csv_preview,
+            bank::bank_import_csv,
+            bank::bank_csv_profiles,
+            bank::bank_csv_profile_save,
+            bank::bank_csv_profile_delete,
+            bank::bank_match_candidates,
+            bank::bank_match_transactions,
+            bank::bank_assign_references,
         ])
         .setup(|app| {
             // Initialize database on app startup
             if let Err(e) = database::init_database(app.handle()) {
                 eprintln!("Failed to initialize database: {}", e);
             }
+            // Startup backup, then daily backups in the background
+            database::start_backup_scheduler(app.handle().clone());
+            // Background sync, when enabled in the sync settings
+            sync::start_sync_scheduler(app.handle().clone());
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                // Fold the WAL back into tskpay.db so the file on disk is self-contained
+                if let Err(e) = database::checkpoint_app_database(app) {
+                    eprintln!("Failed to checkpoint database on shutdown: {}", e);
+                }
+                database::run_scheduled_backup(app, database::BackupTrigger::Shutdown);
+            }
+        });
 }
@@ -0,0 +1,991 @@
+use super::transport::{eq, gt, in_list, RemoteRow, SyncTransport};
+use crate::database::diff::{changed_fields, FieldChange};
+use crate::database::introspection::get_table_schema;
+use crate::database::postgres::{normalize_timestamp, pg_type, PgType};
+use crate::database::{
+    generate_id, insert_row, now_timestamp, overwrite_row, parse_timestamp, record_change, record_sync_change, row_to_map,
+    ChangeEvent, DumpRow,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Tables kept in sync, referenced tables before the tables referring to them.
+/// Attachments stay on the device that stored them.
+pub const SYNC_TABLES: &[&str] = &[
+    "parents",
+    "coaches",
+    "groups",
+    "members",
+    "member_parents",
+    "cost_types",
+    "costs",
+    "bank_statements",
+    "bank_transactions",
+    "payments",
+    "payment_allocations",
+    "audit_log",
+];
+/// Server table listing deleted rows, see `supabase/migrations/003_sync.sql`
+const TOMBSTONES_TABLE: &str = "sync_tombstones";
+/// Ids per `in.(...)` filter and rows per upsert request
+const BATCH_SIZE: usize = 200;
+/// Rows per page when pulling
+const PAGE_SIZE: usize = 1000;
+/// Pulls reach this far behind the newest `synced_at` seen, so rows of server transactions
+/// that committed out of order are not missed. Rows that are already up to date are skipped.
+const PULL_OVERLAP_SECONDS: i64 = 60;
+/// Columns that differ between copies of the same record without meaning a change
+const UNCOMPARED_COLUMNS: &[&str] = &["created_at", "synced_at"];
+const PUSHED_SEQ_KEY: &str = "pushed_seq";
+const LAST_SYNC_KEY: &str = "last_sync_at";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncSide {
+    Local,
+    Remote,
+}
+
+impl SyncSide {
+    fn as_str(self) -> &'static str {
+        match self {
+            SyncSide::Local => "local",
+            SyncSide::Remote => "remote",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "local" => Some(SyncSide::Local),
+            "remote" => Some(SyncSide::Remote),
+            _ => None,
+        }
+    }
+}
+
+/// A record changed both locally and on the server since the last sync
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConflict {
+    pub id: String,
+    pub table: String,
+    pub row_id: String,
+    /// The local version, `None` when the record was deleted locally
+    pub local: Option<DumpRow>,
+    /// The server version, `None` when the record was deleted on the server
+    pub remote: Option<DumpRow>,
+    /// Differing columns, local value before server value
+    pub fields: Vec<FieldChange>,
+    /// Version applied by last-writer-wins
+    pub winner: SyncSide,
+    pub detected_at: String,
+    /// Version kept after review; `None` while the conflict waits for review
+    pub resolution: Option<SyncSide>,
+    pub resolved_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    /// Rows created or updated on the server
+    pub pushed: usize,
+    /// Rows deleted on the server
+    pub deleted: usize,
+    /// Rows created or updated locally from the server
+    pub pulled: usize,
+    /// Rows deleted locally because they were deleted on the server
+    pub removed: usize,
+    /// New conflicts; last-writer-wins already applied, they wait for review
+    pub conflicts: usize,
+    /// Local writes made by the pull, for change events
+    #[serde(skip)]
+    pub changes: Vec<ChangeEvent>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    pub last_sync_at: Option<String>,
+    /// Records with local writes not pushed yet
+    pub pending_changes: usize,
+    pub open_conflicts: i64,
+}
+
+enum LocalWrite {
+    Upsert(DumpRow),
+    Delete(String),
+}
+
+struct NewConflict {
+    row_id: String,
+    local: Option<DumpRow>,
+    remote: Option<DumpRow>,
+    winner: SyncSide,
+}
+
+fn format_watermark(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn column_types(conn: &Connection, table: &str) -> rusqlite::Result<Vec<(String, PgType)>> {
+    let schema = get_table_schema(conn, table)?;
+    Ok(schema
+        .columns
+        .iter()
+        .map(|c| (c.name.clone(), pg_type(&c.name, &c.data_type)))
+        .collect())
+}
+
+/// Local row in the shape the server expects: booleans, JSON documents and zoned timestamps
+fn to_remote(row: &DumpRow, columns: &[(String, PgType)]) -> RemoteRow {
+    let mut remote = RemoteRow::new();
+    for (column, column_type) in columns {
+        let Some(value) = row.get(column) else {
+            continue;
+        };
+        let converted = match (value, column_type) {
+            (Value::Number(n), PgType::Boolean) => Value::Bool(n.as_i64() != Some(0)),
+            (Value::String(s), PgType::Timestamp) => Value::String(normalize_timestamp(s)),
+            (Value::String(s), PgType::Date) => Value::String(s.get(..10).unwrap_or(s).to_string()),
+            (Value::String(s), PgType::Json) => serde_json::from_str(s).unwrap_or_else(|_| value.clone()),
+            _ => value.clone(),
+        };
+        remote.insert(column.clone(), converted);
+    }
+    remote
+}
+
+/// Server row in the shape of a local row; columns the local table doesn't have are dropped
+fn to_local(row: &RemoteRow, columns: &[(String, PgType)]) -> DumpRow {
+    let mut local = DumpRow::new();
+    for (column, column_type) in columns {
+        let Some(value) = row.get(column) else {
+            continue;
+        };
+        let converted = match (value, column_type) {
+            (Value::Bool(b), _) => Value::from(*b as i64),
+            (Value::String(s), PgType::Timestamp) => parse_timestamp(s)
+                .map(|time| Value::String(time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()))
+                .unwrap_or_else(|| value.clone()),
+            (Value::Number(n), PgType::Numeric) => n.as_f64().map(Value::from).unwrap_or_else(|| value.clone()),
+            (Value::Object(_) | Value::Array(_), _) => Value::String(value.to_string()),
+            _ => value.clone(),
+        };
+        local.insert(column.clone(), converted);
+    }
+    local
+}
+
+fn values_equal(a: &Value, b: &Value, column_type: PgType) -> bool {
+    match (a, b) {
+        (Value::Null, Value::Null) => true,
+        (Value::Null, _) | (_, Value::Null) => false,
+        (Value::String(a), Value::String(b)) if column_type == PgType::Timestamp => {
+            match (parse_timestamp(a), parse_timestamp(b)) {
+                (Some(a), Some(b)) => a == b,
+                _ => a == b,
+            }
+        }
+        (Value::String(a), Value::String(b)) if column_type == PgType::Json => {
+            match (serde_json::from_str::<Value>(a), serde_json::from_str::<Value>(b)) {
+                (Ok(a), Ok(b)) => a == b,
+                _ => a == b,
+            }
+        }
+        (Value::Number(a), Value::Number(b)) => match (a.as_f64(), b.as_f64()) {
+            (Some(a), Some(b)) => (a - b).abs() < 1e-9,
+            _ => a == b,
+        },
+        _ => a == b,
+    }
+}
+
+/// Whether two local-shaped rows hold the same record; columns missing on either side are not compared
+fn rows_equal(a: &DumpRow, b: &DumpRow, columns: &[(String, PgType)]) -> bool {
+    columns
+        .iter()
+        .filter(|(column, _)| !UNCOMPARED_COLUMNS.contains(&column.as_str()))
+        .all(|(column, column_type)| match (a.get(column), b.get(column)) {
+            (Some(a), Some(b)) => values_equal(a, b, *column_type),
+            _ => true,
+        })
+}
+
+/// The more recently edited version by `updated_at`; the local one when that can't be told
+fn newer_side(local: &DumpRow, remote: &DumpRow) -> SyncSide {
+    let updated_at = |row: &DumpRow| row.get("updated_at").and_then(Value::as_str).and_then(parse_timestamp);
+    match (updated_at(local), updated_at(remote)) {
+        (Some(local), Some(remote)) if remote > local => SyncSide::Remote,
+        _ => SyncSide::Local,
+    }
+}
+
+fn row_id(row: &DumpRow) -> Option<String> {
+    match row.get("id")? {
+        Value::String(s) => Some(s.clone()),
+        Value::Null => None,
+        other => Some(other.to_string()),
+    }
+}
+
+fn state_get(conn: &Connection, key: &str) -> rusqlite::Result<Option<String>> {
+    conn.query_row("SELECT value FROM sync_state WHERE key = ?1", [key], |row| row.get(0))
+        .optional()
+}
+
+fn state_set(conn: &Connection, key: &str, value: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO sync_state (key, value) VALUES (?1, ?2) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        [key, value],
+    )?;
+    Ok(())
+}
+
+fn pulled_key(table: &str) -> String {
+    format!("pulled_at:{}", table)
+}
+
+fn read_local(conn: &Connection, table: &str, id: &str) -> rusqlite::Result<Option<DumpRow>> {
+    conn.query_row(&format!("SELECT * FROM {} WHERE id = ?1", table), [id], |row| {
+        row_to_map(row).map(|map| map.into_iter().collect::<DumpRow>())
+    })
+    .optional()
+}
+
+fn max_seq(conn: &Connection) -> rusqlite::Result<i64> {
+    conn.query_row("SELECT COALESCE(MAX(seq), 0) FROM change_log", [], |row| row.get(0))
+}
+
+/// Ids of synced tables with local writes in the change log range `(since, until]`
+fn local_changes(conn: &Connection, since: i64, until: i64) -> rusqlite::Result<BTreeMap<String, BTreeSet<String>>> {
+    let mut stmt = conn.prepare(
+        "SELECT table_name, row_ids FROM change_log WHERE seq > ?1 AND seq <= ?2 AND source = 'local' ORDER BY seq",
+    )?;
+    let rows = stmt.query_map([since, until], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+
+    let mut changes: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for row_result in rows {
+        let (table, ids_json) = row_result?;
+        if !SYNC_TABLES.contains(&table.as_str()) {
+            continue;
+        }
+        let ids: Vec<String> = serde_json::from_str(&ids_json).unwrap_or_default();
+        changes.entry(table).or_default().extend(ids);
+    }
+    Ok(changes)
+}
+
+/// Every synced record, for the first sync of a database.
+/// Member links are keyed by member, the way `db_set_member_parents` reports them.
+fn all_local_ids(conn: &Connection) -> rusqlite::Result<BTreeMap<String, BTreeSet<String>>> {
+    let mut ids = BTreeMap::new();
+    for table in SYNC_TABLES {
+        let query = match *table {
+            "member_parents" => "SELECT DISTINCT member_id FROM member_parents".to_string(),
+            _ => format!("SELECT id FROM {}", table),
+        };
+        let mut stmt = conn.prepare(&query)?;
+        let table_ids = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<BTreeSet<String>>>()?;
+        ids.insert(table.to_string(), table_ids);
+    }
+    Ok(ids)
+}
+
+/// Write pulled rows and new conflicts of one table in a single transaction
+fn apply_local(
+    conn: &Connection,
+    table: &str,
+    writes: &[LocalWrite],
+    conflicts: &[NewConflict],
+    state: Option<(&str, &str)>,
+) -> Result<Vec<ChangeEvent>, String> {
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+
+    let mut inserted = Vec::new();
+    let mut updated = Vec::new();
+    let mut deleted = Vec::new();
+    for write in writes {
+        match write {
+            LocalWrite::Upsert(row) => {
+                let Some(id) = row_id(row) else {
+                    continue;
+                };
+                if read_local(&tx, table, &id).map_err(|e| e.to_string())?.is_some() {
+                    overwrite_row(&tx, table, "id", &id, row)?;
+                    updated.push(id);
+                } else if insert_row(&tx, table, row)? {
+                    inserted.push(id);
+                }
+            }
+            LocalWrite::Delete(id) => {
+                let count = tx
+                    .execute(&format!("DELETE FROM {} WHERE id = ?1", table), [id])
+                    .map_err(|e| format!("Napaka pri brisanju vrstice {} iz tabele {}: {}", id, table, e))?;
+                if count > 0 {
+                    deleted.push(id.clone());
+                }
+            }
+        }
+    }
+
+    for conflict in conflicts {
+        let to_json = |row: &Option<DumpRow>| row.as_ref().map(|row| serde_json::to_string(row).unwrap_or_default());
+        tx.execute(
+            "INSERT INTO sync_conflicts (id, table_name, row_id, local_row, remote_row, winner) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                generate_id("sync_conflicts"),
+                table,
+                conflict.row_id,
+                to_json(&conflict.local),
+                to_json(&conflict.remote),
+                conflict.winner.as_str(),
+            ],
+        )
+        .map_err(|e| format!("Napaka pri shranjevanju konflikta: {}", e))?;
+    }
+
+    if let Some((key, value)) = state {
+        state_set(&tx, key, value).map_err(|e| e.to_string())?;
+    }
+
+    let mut changes = Vec::new();
+    for (operation, ids) in [("insert", &inserted), ("update", &updated), ("delete", &deleted)] {
+        if !ids.is_empty() {
+            changes.push(record_sync_change(&tx, table, operation, ids).map_err(|e| e.to_string())?);
+        }
+    }
+
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(changes)
+}
+
+/// Delete rows on the server and leave tombstones so other devices delete them too
+fn delete_remote(transport: &dyn SyncTransport, table: &str, ids: &[String]) -> Result<(), String> {
+    for chunk in ids.chunks(BATCH_SIZE) {
+        transport.delete(table, &[in_list("id", chunk)])?;
+        let tombstones: Vec<RemoteRow> = chunk
+            .iter()
+            .map(|id| {
+                let mut row = RemoteRow::new();
+                row.insert("table_name".to_string(), Value::from(table));
+                row.insert("row_id".to_string(), Value::from(id.as_str()));
+                row
+            })
+            .collect();
+        transport.insert(TOMBSTONES_TABLE, &tombstones)?;
+    }
+    Ok(())
+}
+
+fn upsert_remote(
+    transport: &dyn SyncTransport,
+    table: &str,
+    rows: &[DumpRow],
+    columns: &[(String, PgType)],
+) -> Result<(), String> {
+    for chunk in rows.chunks(BATCH_SIZE) {
+        let remote: Vec<RemoteRow> = chunk.iter().map(|row| to_remote(row, columns)).collect();
+        transport.upsert(table, &remote)?;
+    }
+    Ok(())
+}
+
+/// Push the changed records of one table. A record that also changed on the server since
+/// the last pull is a conflict: the newer edit wins and both versions are kept for review.
+/// Returns the ids to delete on the server, which happens after all upserts.
+fn push_table(
+    conn: &Connection,
+    transport: &dyn SyncTransport,
+    table: &str,
+    ids: &BTreeSet<String>,
+    report: &mut SyncReport,
+) -> Result<Vec<String>, String> {
+    let columns = column_types(conn, table).map_err(|e| e.to_string())?;
+    let watermark = state_get(conn, &pulled_key(table))
+        .map_err(|e| e.to_string())?
+        .and_then(|w| parse_timestamp(&w));
+
+    let mut upserts = Vec::new();
+    let mut deletes = Vec::new();
+    let mut writes = Vec::new();
+    let mut conflicts = Vec::new();
+
+    let ids: Vec<String> = ids.iter().cloned().collect();
+    for chunk in ids.chunks(BATCH_SIZE) {
+        let mut remote_rows = BTreeMap::new();
+        for row in transport.select(table, &[in_list("id", chunk)], &["id"], chunk.len(), 0)? {
+            let synced_at = row.get("synced_at").and_then(Value::as_str).and_then(parse_timestamp);
+            let changed_remotely = match (synced_at, watermark) {
+                (Some(synced_at), Some(watermark)) => synced_at > watermark,
+                _ => true,
+            };
+            let local_shape = to_local(&row, &columns);
+            if let Some(id) = row_id(&local_shape) {
+                remote_rows.insert(id, (local_shape, changed_remotely));
+            }
+        }
+
+        for id in chunk {
+            let local = read_local(conn, table, id).map_err(|e| e.to_string())?;
+            match (local, remote_rows.remove(id)) {
+                (Some(local), None) => upserts.push(local),
+                (Some(local), Some((remote, changed_remotely))) => {
+                    if rows_equal(&local, &remote, &columns) {
+                        continue;
+                    }
+                    if !changed_remotely {
+                        upserts.push(local);
+                        continue;
+                    }
+                    let winner = newer_side(&local, &remote);
+                    conflicts.push(NewConflict {
+                        row_id: id.clone(),
+                        local: Some(local.clone()),
+                        remote: Some(remote.clone()),
+                        winner,
+                    });
+                    match winner {
+                        SyncSide::Local => upserts.push(local),
+                        SyncSide::Remote => writes.push(LocalWrite::Upsert(remote)),
+                    }
+                }
+                // Deleted here but edited on the server: keep the edit rather than lose it
+                (None, Some((remote, true))) => {
+                    conflicts.push(NewConflict {
+                        row_id: id.clone(),
+                        local: None,
+                        remote: Some(remote.clone()),
+                        winner: SyncSide::Remote,
+                    });
+                    writes.push(LocalWrite::Upsert(remote));
+                }
+                (None, Some((_, false))) => deletes.push(id.clone()),
+                (None, None) => {}
+            }
+        }
+    }
+
+    upsert_remote(transport, table, &upserts, &columns)?;
+    report.pushed += upserts.len();
+    report.conflicts += conflicts.len();
+    report.changes.extend(apply_local(conn, table, &writes, &conflicts, None)?);
+    Ok(deletes)
+}
+
+/// Push member links. `db_set_member_parents` replaces all links of a member and logs
+/// the member id, so the server's links of each member are replaced the same way.
+fn push_member_links(
+    conn: &Connection,
+    transport: &dyn SyncTransport,
+    keys: &BTreeSet<String>,
+    report: &mut SyncReport,
+) -> Result<(), String> {
+    const TABLE: &str = "member_parents";
+    let columns = column_types(conn, TABLE).map_err(|e| e.to_string())?;
+
+    for key in keys {
+        // Links whose member or parent is gone locally would be rejected by the server
+        let mut stmt = conn
+            .prepare(
+                "SELECT * FROM member_parents mp
+                 WHERE (mp.member_id = ?1 OR mp.id = ?1)
+                   AND EXISTS (SELECT 1 FROM members WHERE id = mp.member_id)
+                   AND EXISTS (SELECT 1 FROM parents WHERE id = mp.parent_id)",
+            )
+            .map_err(|e| e.to_string())?;
+        let local: Vec<DumpRow> = stmt
+            .query_map([key], |row| row_to_map(row).map(|map| map.into_iter().collect::<DumpRow>()))
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| e.to_string())?;
+
+        let mut remote: BTreeMap<String, DumpRow> = BTreeMap::new();
+        for filter in [eq("member_id", key), eq("id", key)] {
+            for row in transport.select(TABLE, &[filter], &["id"], PAGE_SIZE, 0)? {
+                let row = to_local(&row, &columns);
+                if let Some(id) = row_id(&row) {
+                    remote.insert(id, row);
+                }
+            }
+        }
+
+        // Stale links go first, a reordered link may reuse the (member, parent) pair
+        let local_ids: BTreeSet<String> = local.iter().filter_map(row_id).collect();
+        let stale: Vec<String> = remote.keys().filter(|id| !local_ids.contains(*id)).cloned().collect();
+        delete_remote(transport, TABLE, &stale)?;
+        report.deleted += stale.len();
+
+        let changed: Vec<DumpRow> = local
+            .into_iter()
+            .filter(|row| {
+                row_id(row)
+                    .and_then(|id| remote.get(&id))
+                    .is_none_or(|existing| !rows_equal(row, existing, &columns))
+            })
+            .collect();
+        upsert_remote(transport, TABLE, &changed, &columns)?;
+        report.pushed += changed.len();
+    }
+    Ok(())
+}
+
+/// Push all local writes not pushed yet; returns the last change log entry covered
+fn push(conn: &Connection, transport: &dyn SyncTransport, report: &mut SyncReport) -> Result<i64, String> {
+    let until = max_seq(conn).map_err(|e| e.to_string())?;
+    let pushed_seq = state_get(conn, PUSHED_SEQ_KEY)
+        .map_err(|e| e.to_string())?
+        .and_then(|seq| seq.parse::<i64>().ok());
+    let pending = match pushed_seq {
+        Some(since) => local_changes(conn, since, until),
+        None => all_local_ids(conn),
+    }
+    .map_err(|e| e.to_string())?;
+
+    let mut deletes = Vec::new();
+    for table in SYNC_TABLES {
+        let Some(ids) = pending.get(*table) else {
+            continue;
+        };
+        if *table == "member_parents" {
+            push_member_links(conn, transport, ids, report)?;
+        } else {
+            deletes.push((*table, push_table(conn, transport, table, ids, report)?));
+        }
+    }
+    // Referring rows are deleted before the rows they refer to
+    for (table, ids) in deletes.iter().rev() {
+        delete_remote(transport, table, ids)?;
+        report.deleted += ids.len();
+    }
+
+    state_set(conn, PUSHED_SEQ_KEY, &until.to_string()).map_err(|e| e.to_string())?;
+    Ok(until)
+}
+
+/// Server rows written since the last pull of `table`, oldest first
+fn fetch_changed(conn: &Connection, transport: &dyn SyncTransport, table: &str) -> Result<Vec<RemoteRow>, String> {
+    let watermark = state_get(conn, &pulled_key(table))
+        .map_err(|e| e.to_string())?
+        .and_then(|w| parse_timestamp(&w));
+    let filters = match watermark {
+        Some(watermark) => vec![gt(
+            "synced_at",
+            &format_watermark(watermark - chrono::Duration::seconds(PULL_OVERLAP_SECONDS)),
+        )],
+        None => Vec::new(),
+    };
+
+    // Rows written in one server transaction share `synced_at`; the id keeps the pages apart
+    let mut rows = Vec::new();
+    loop {
+        let page = transport.select(table, &filters, &["synced_at", "id"], PAGE_SIZE, rows.len())?;
+        let complete = page.len() < PAGE_SIZE;
+        rows.extend(page);
+        if complete {
+            return Ok(rows);
+        }
+    }
+}
+
+/// New pull position for a table: the newest row seen, but before any row that was skipped
+/// because it is being edited locally, so that row is looked at again next time
+fn next_watermark(rows: &[(DateTime<Utc>, bool)]) -> Option<String> {
+    let newest = rows.iter().map(|(synced_at, _)| *synced_at).max()?;
+    let first_skipped = rows.iter().filter(|(_, skipped)| *skipped).map(|(synced_at, _)| *synced_at).min();
+    Some(format_watermark(match first_skipped {
+        Some(skipped) => skipped - chrono::Duration::microseconds(1),
+        None => newest,
+    }))
+}
+
+fn synced_at(row: &RemoteRow) -> Option<DateTime<Utc>> {
+    row.get("synced_at").and_then(Value::as_str).and_then(parse_timestamp)
+}
+
+/// Apply server deletes. Records with local writes since the push are left alone.
+fn pull_tombstones(
+    conn: &Connection,
+    transport: &dyn SyncTransport,
+    busy: &BTreeMap<String, BTreeSet<String>>,
+    report: &mut SyncReport,
+) -> Result<(), String> {
+    let rows = fetch_changed(conn, transport, TOMBSTONES_TABLE)?;
+
+    let mut deletes: BTreeMap<&str, Vec<LocalWrite>> = BTreeMap::new();
+    let mut seen = Vec::new();
+    for row in &rows {
+        let table = row.get("table_name").and_then(Value::as_str).unwrap_or_default();
+        let id = row.get("row_id").and_then(Value::as_str).unwrap_or_default();
+        let Some(table) = SYNC_TABLES.iter().find(|t| **t == table) else {
+            continue;
+        };
+        let skipped = busy.get(*table).is_some_and(|ids| ids.contains(id));
+        if let Some(synced_at) = synced_at(row) {
+            seen.push((synced_at, skipped));
+        }
+        if !skipped {
+            deletes.entry(table).or_default().push(LocalWrite::Delete(id.to_string()));
+        }
+    }
+
+    for (table, writes) in &deletes {
+        let changes = apply_local(conn, table, writes, &[], None)?;
+        report.removed += changes.iter().map(|change| change.ids.len()).sum::<usize>();
+        report.changes.extend(changes);
+    }
+    if let Some(watermark) = next_watermark(&seen) {
+        state_set(conn, &pulled_key(TOMBSTONES_TABLE), &watermark).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Apply server rows written since the last pull. Records with local writes since the push
+/// are left alone; the next sync pushes them and compares then.
+fn pull_table(
+    conn: &Connection,
+    transport: &dyn SyncTransport,
+    table: &str,
+    busy: &BTreeMap<String, BTreeSet<String>>,
+    report: &mut SyncReport,
+) -> Result<(), String> {
+    let columns = column_types(conn, table).map_err(|e| e.to_string())?;
+    let rows = fetch_changed(conn, transport, table)?;
+    let busy_ids = busy.get(table);
+    let is_busy = |row: &DumpRow, column: &str| {
+        let value = row.get(column).and_then(Value::as_str);
+        busy_ids.is_some_and(|ids| value.is_some_and(|value| ids.contains(value)))
+    };
+
+    let mut writes = Vec::new();
+    let mut seen = Vec::new();
+    for row in &rows {
+        let remote = to_local(row, &columns);
+        let Some(id) = row_id(&remote) else {
+            continue;
+        };
+        let skipped = is_busy(&remote, "id") || (table == "member_parents" && is_busy(&remote, "member_id"));
+        if let Some(synced_at) = synced_at(row) {
+            seen.push((synced_at, skipped));
+        }
+        if skipped {
+            continue;
+        }
+        let local = read_local(conn, table, &id).map_err(|e| e.to_string())?;
+        if local.is_some_and(|local| rows_equal(&local, &remote, &columns)) {
+            continue;
+        }
+        writes.push(LocalWrite::Upsert(remote));
+    }
+
+    let key = pulled_key(table);
+    let watermark = next_watermark(&seen);
+    let changes = apply_local(conn, table, &writes, &[], watermark.as_deref().map(|w| (key.as_str(), w)))?;
+    report.pulled += changes.iter().map(|change| change.ids.len()).sum::<usize>();
+    report.changes.extend(changes);
+    Ok(())
+}
+
+/// One sync run against a Supabase-compatible REST backend.
+///
+/// The local database stays usable offline. A run pushes the records named in `change_log`
+/// since the last push, then pulls the server rows written since the last pull. Records
+/// edited on both sides are resolved by `updated_at` (last writer wins) and kept in
+/// `sync_conflicts` for review.
+pub fn sync_database(conn: &Connection, transport: &dyn SyncTransport) -> Result<SyncReport, String> {
+    let mut report = SyncReport::default();
+
+    let pushed_until = push(conn, transport, &mut report)?;
+
+    // Writes made while the push was running are pushed next time
+    let busy = local_changes(conn, pushed_until, i64::MAX).map_err(|e| e.to_string())?;
+    pull_tombstones(conn, transport, &busy, &mut report)?;
+    for table in SYNC_TABLES {
+        pull_table(conn, transport, table, &busy, &mut report)?;
+    }
+
+    state_set(conn, LAST_SYNC_KEY, &now_timestamp()).map_err(|e| e.to_string())?;
+    Ok(report)
+}
+
+pub fn sync_status(conn: &Connection) -> rusqlite::Result<SyncStatus> {
+    let pushed_seq = state_get(conn, PUSHED_SEQ_KEY)?
+        .and_then(|seq| seq.parse::<i64>().ok())
+        .unwrap_or(0);
+    let pending_changes = local_changes(conn, pushed_seq, i64::MAX)?.values().map(BTreeSet::len).sum();
+    let open_conflicts = conn.query_row(
+        "SELECT COUNT(*) FROM sync_conflicts WHERE resolved_at IS NULL",
+        [],
+        |row| row.get(0),
+    )?;
+
+    Ok(SyncStatus {
+        last_sync_at: state_get(conn, LAST_SYNC_KEY)?,
+        pending_changes,
+        open_conflicts,
+    })
+}
+
+fn conflict_from_row(row: &rusqlite::Row) -> rusqlite::Result<SyncConflict> {
+    let parse_row = |json: Option<String>| json.and_then(|json| serde_json::from_str::<DumpRow>(&json).ok());
+    let local = parse_row(row.get(3)?);
+    let remote = parse_row(row.get(4)?);
+    let fields = match (&local, &remote) {
+        (Some(local), Some(remote)) => changed_fields(local, remote)
+            .into_iter()
+            .filter(|field| !UNCOMPARED_COLUMNS.contains(&field.column.as_str()))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    Ok(SyncConflict {
+        id: row.get(0)?,
+        table: row.get(1)?,
+        row_id: row.get(2)?,
+        local,
+        remote,
+        fields,
+        winner: SyncSide::parse(&row.get::<_, String>(5)?).unwrap_or(SyncSide::Local),
+        detected_at: row.get(6)?,
+        resolution: row.get::<_, Option<String>>(7)?.as_deref().and_then(SyncSide::parse),
+        resolved_at: row.get(8)?,
+    })
+}
+
+const CONFLICT_COLUMNS: &str =
+    "id, table_name, row_id, local_row, remote_row, winner, detected_at, resolution, resolved_at";
+
+/// Conflicts, newest first; resolved ones only when asked for
+pub fn list_conflicts(conn: &Connection, include_resolved: bool) -> rusqlite::Result<Vec<SyncConflict>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM sync_conflicts WHERE ?1 OR resolved_at IS NULL ORDER BY detected_at DESC, id DESC",
+        CONFLICT_COLUMNS
+    ))?;
+    let rows = stmt.query_map([include_resolved], conflict_from_row)?;
+    rows.collect()
+}
+
+/// Close a conflict after review. Keeping the version that lost writes it back as a new
+/// local edit, so the next sync pushes it. Returns that write, if any.
+pub fn resolve_conflict(conn: &Connection, id: &str, keep: SyncSide) -> Result<Option<ChangeEvent>, String> {
+    let conflict = conn
+        .query_row(
+            &format!("SELECT {} FROM sync_conflicts WHERE id = ?1", CONFLICT_COLUMNS),
+            [id],
+            conflict_from_row,
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Konflikt {} ne obstaja.", id))?;
+    if conflict.resolution.is_some() {
+        return Err("Konflikt je že razrešen.".to_string());
+    }
+
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let table = conflict.table.as_str();
+    let row_id = std::slice::from_ref(&conflict.row_id);
+
+    let change = if keep == conflict.winner {
+        None
+    } else {
+        let version = match keep {
+            SyncSide::Local => conflict.local,
+            SyncSide::Remote => conflict.remote,
+        };
+        let change = match version {
+            Some(mut row) => {
+                // Newer than both versions, so it also wins on the other devices
+                if row.contains_key("updated_at") {
+                    row.insert("updated_at".to_string(), Value::from(now_timestamp()));
+                }
+                if read_local(&tx, table, &conflict.row_id).map_err(|e| e.to_string())?.is_some() {
+                    overwrite_row(&tx, table, "id", &conflict.row_id, &row)?;
+                    record_change(&tx, table, "update", row_id)
+                } else {
+                    insert_row(&tx, table, &row)?;
+                    record_change(&tx, table, "insert", row_id)
+                }
+            }
+            None => {
+                tx.execute(&format!("DELETE FROM {} WHERE id = ?1", table), [&conflict.row_id])
+                    .map_err(|e| e.to_string())?;
+                record_change(&tx, table, "delete", row_id)
+            }
+        };
+        Some(change.map_err(|e| e.to_string())?)
+    };
+
+    tx.execute(
+        "UPDATE sync_conflicts SET resolution = ?1, resolved_at = ?2 WHERE id = ?3",
+        [keep.as_str(), now_timestamp().as_str(), id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(change)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::set_member_parents;
+    use crate::sync::transport::MemoryTransport;
+    use serde_json::json;
+
+    fn local_database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../database/schema.sql")).unwrap();
+        conn
+    }
+
+    fn dump_row(value: Value) -> DumpRow {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn create(conn: &Connection, table: &str, row: Value) {
+        let row = dump_row(row);
+        insert_row(conn, table, &row).unwrap();
+        record_change(conn, table, "insert", &[row_id(&row).unwrap()]).unwrap();
+    }
+
+    fn update(conn: &Connection, table: &str, id: &str, row: Value) {
+        overwrite_row(conn, table, "id", id, &dump_row(row)).unwrap();
+        record_change(conn, table, "update", &[id.to_string()]).unwrap();
+    }
+
+    fn local_value(conn: &Connection, table: &str, id: &str, column: &str) -> Option<Value> {
+        read_local(conn, table, id).unwrap().and_then(|row| row.get(column).cloned())
+    }
+
+    fn parent(id: &str, first_name: &str, updated_at: &str) -> Value {
+        json!({ "id": id, "first_name": first_name, "last_name": "Novak", "updated_at": updated_at })
+    }
+
+    #[test]
+    fn first_sync_pushes_everything_in_server_types() {
+        let conn = local_database();
+        let server = MemoryTransport::new();
+        create(&conn, "parents", parent("par-1", "Ana", "2025-01-10 08:00:00"));
+
+        let report = sync_database(&conn, &server).unwrap();
+
+        let pushed = server.get("parents", "par-1").unwrap();
+        assert_eq!(pushed["first_name"], "Ana");
+        assert_eq!(pushed["updated_at"], "2025-01-10 08:00:00+00");
+        // Seed rows go up as well
+        assert_eq!(server.get("cost_types", "ct-vadnine").unwrap()["name"], "Vadnine");
+        assert!(report.pushed >= 1);
+        assert_eq!(report.conflicts, 0);
+
+        // Nothing changed since, so the second run moves nothing
+        let again = sync_database(&conn, &server).unwrap();
+        assert_eq!((again.pushed, again.pulled, again.deleted), (0, 0, 0));
+    }
+
+    #[test]
+    fn pulls_server_rows_without_pushing_them_back() {
+        let conn = local_database();
+        let server = MemoryTransport::new();
+        sync_database(&conn, &server).unwrap();
+
+        server.put("parents", parent("par-2", "Bor", "2025-02-01T10:00:00+00:00"));
+        let report = sync_database(&conn, &server).unwrap();
+
+        assert_eq!(report.pulled, 1);
+        assert_eq!(local_value(&conn, "parents", "par-2", "first_name"), Some(json!("Bor")));
+        assert_eq!(local_value(&conn, "parents", "par-2", "updated_at"), Some(json!("2025-02-01T10:00:00.000Z")));
+        assert_eq!(report.changes.len(), 1);
+        assert_eq!(sync_status(&conn).unwrap().pending_changes, 0);
+    }
+
+    #[test]
+    fn deletes_travel_both_ways_through_tombstones() {
+        let conn = local_database();
+        let server = MemoryTransport::new();
+        create(&conn, "parents", parent("par-1", "Ana", "2025-01-10 08:00:00"));
+        sync_database(&conn, &server).unwrap();
+
+        conn.execute("DELETE FROM parents WHERE id = 'par-1'", []).unwrap();
+        record_change(&conn, "parents", "delete", &["par-1".to_string()]).unwrap();
+        let report = sync_database(&conn, &server).unwrap();
+        assert_eq!(report.deleted, 1);
+        assert!(server.get("parents", "par-1").is_none());
+
+        // Another device deleted a row this one has
+        server.put("parents", parent("par-3", "Cene", "2025-03-01T10:00:00+00:00"));
+        sync_database(&conn, &server).unwrap();
+        server.delete("parents", &[eq("id", "par-3")]).unwrap();
+        server.insert("sync_tombstones", &[json!({ "table_name": "parents", "row_id": "par-3" }).as_object().unwrap().clone()]).unwrap();
+
+        let report = sync_database(&conn, &server).unwrap();
+        assert_eq!(report.removed, 1);
+        assert!(read_local(&conn, "parents", "par-3").unwrap().is_none());
+    }
+
+    #[test]
+    fn concurrent_edits_are_resolved_by_last_writer_and_kept_for_review() {
+        let conn = local_database();
+        let server = MemoryTransport::new();
+        create(&conn, "parents", parent("par-1", "Ana", "2025-01-10 08:00:00"));
+        sync_database(&conn, &server).unwrap();
+
+        update(&conn, "parents", "par-1", json!({ "first_name": "Anica", "updated_at": "2025-01-11T08:00:00.000Z" }));
+        server.put("parents", parent("par-1", "Anja", "2025-01-12T08:00:00+00:00"));
+
+        let report = sync_database(&conn, &server).unwrap();
+        assert_eq!(report.conflicts, 1);
+        assert_eq!(local_value(&conn, "parents", "par-1", "first_name"), Some(json!("Anja")));
+        assert_eq!(server.get("parents", "par-1").unwrap()["first_name"], "Anja");
+
+        let conflicts = list_conflicts(&conn, false).unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].winner, SyncSide::Remote);
+        assert!(conflicts[0].fields.iter().any(|f| f.column == "first_name" && f.before == "Anica" && f.after == "Anja"));
+
+        // On review the local edit is kept after all, and the next sync pushes it
+        let change = resolve_conflict(&conn, &conflicts[0].id, SyncSide::Local).unwrap();
+        assert!(change.is_some());
+        assert!(list_conflicts(&conn, false).unwrap().is_empty());
+        assert_eq!(list_conflicts(&conn, true).unwrap()[0].resolution, Some(SyncSide::Local));
+
+        let report = sync_database(&conn, &server).unwrap();
+        assert_eq!(report.conflicts, 0);
+        assert_eq!(server.get("parents", "par-1").unwrap()["first_name"], "Anica");
+    }
+
+    #[test]
+    fn member_links_are_replaced_per_member() {
+        let conn = local_database();
+        let server = MemoryTransport::new();
+        create(&conn, "parents", parent("par-1", "Ana", "2025-01-10 08:00:00"));
+        create(&conn, "parents", parent("par-2", "Bor", "2025-01-10 08:00:00"));
+        create(
+            &conn,
+            "members",
+            json!({ "id": "mem-1", "first_name": "Eva", "last_name": "Novak", "date_of_birth": "2015-04-01",
+                    "status": "active", "parent_id": "par-1", "group_id": "grp-samo-clani" }),
+        );
+        set_member_parents(&conn, "mem-1", &["par-1".to_string()]).unwrap();
+        sync_database(&conn, &server).unwrap();
+        assert!(server.get("member_parents", "mem-1_par-1_0").is_some());
+
+        set_member_parents(&conn, "mem-1", &["par-2".to_string(), "par-1".to_string()]).unwrap();
+        record_change(&conn, "member_parents", "update", &["mem-1".to_string()]).unwrap();
+        sync_database(&conn, &server).unwrap();
+
+        assert_eq!(server.get("member_parents", "mem-1_par-2_0").unwrap()["parent_id"], "par-2");
+        assert_eq!(server.get("member_parents", "mem-1_par-1_1").unwrap()["parent_id"], "par-1");
+        assert!(server.get("member_parents", "mem-1_par-1_0").is_none());
+    }
+
+    #[test]
+    fn values_compare_across_sqlite_and_postgres_formats() {
+        assert!(values_equal(&json!("2025-01-10 08:00:00"), &json!("2025-01-10T08:00:00.000Z"), PgType::Timestamp));
+        assert!(values_equal(&json!(45), &json!(45.0), PgType::Numeric));
+        assert!(values_equal(&json!("{\"a\": 1}"), &json!("{\"a\":1}"), PgType::Json));
+        assert!(!values_equal(&json!(null), &json!(""), PgType::Text));
+    }
+}
@@ -0,0 +1,184 @@
+mod engine;
+mod transport;
+pub use engine::*;
+pub use transport::*;
+
+use crate::database::{emit_change, get_connection, get_db_path};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+const SETTINGS_FILENAME: &str = "sync-settings.json";
+/// How often the background job checks whether a sync is due
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// Tauri event emitted after every background sync run
+pub const SYNC_EVENT: &str = "sync-finished";
+
+/// Held for the duration of a sync run, so a manual sync can't overlap the background one
+static SYNC_LOCK: Mutex<()> = Mutex::new(());
+
+/// Sync configuration, stored as JSON next to the database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SyncSettings {
+    pub enabled: bool,
+    /// REST endpoint, e.g. `https://<project>.supabase.co` or a local PostgREST at `http://localhost:3000`
+    pub url: String,
+    /// Project API key (the Supabase `anon` key), sent as the `apikey` header
+    pub api_key: String,
+    /// JWT of the signed-in user; Supabase policies only admit the `authenticated` role
+    pub access_token: Option<String>,
+    /// Minutes between background syncs, 0 to sync only on request
+    pub interval_minutes: u32,
+}
+
+impl Default for SyncSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: String::new(),
+            api_key: String::new(),
+            access_token: None,
+            interval_minutes: 15,
+        }
+    }
+}
+
+/// Outcome of a background sync run, sent with [`SYNC_EVENT`]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncRun {
+    pub report: Option<SyncReport>,
+    pub error: Option<String>,
+}
+
+fn get_settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let db_path = get_db_path(app).map_err(|e| e.to_string())?;
+    Ok(db_path.with_file_name(SETTINGS_FILENAME))
+}
+
+/// Load sync settings, falling back to the defaults (sync off) when none were saved yet
+pub fn load_sync_settings(app: &AppHandle) -> Result<SyncSettings, String> {
+    let path = get_settings_path(app)?;
+    if !path.exists() {
+        return Ok(SyncSettings::default());
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Napaka pri branju nastavitev {}: {}", path.display(), e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Napaka pri branju nastavitev {}: {}", path.display(), e))
+}
+
+pub fn save_sync_settings(app: &AppHandle, settings: &SyncSettings) -> Result<(), String> {
+    let path = get_settings_path(app)?;
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Napaka pri pretvorbi nastavitev: {}", e))?;
+    fs::write(&path, content)
+        .map_err(|e| format!("Napaka pri shranjevanju nastavitev {}: {}", path.display(), e))
+}
+
+/// Run one sync with the saved settings and notify views about pulled rows
+pub fn run_sync(app: &AppHandle) -> Result<SyncReport, String> {
+    let settings = load_sync_settings(app)?;
+    if !settings.enabled || settings.url.trim().is_empty() {
+        return Err("Sinhronizacija ni vklopljena.".to_string());
+    }
+    let _guard = SYNC_LOCK
+        .try_lock()
+        .map_err(|_| "Sinhronizacija že poteka.".to_string())?;
+
+    let transport = PostgrestTransport::new(&settings.url, &settings.api_key, settings.access_token.as_deref());
+    let conn = get_connection(app).map_err(|e| e.to_string())?;
+    // Server rows and deletes arrive table by table, not in reference order, and the server
+    // already enforces the references, so local foreign key checks and actions stay off
+    conn.execute("PRAGMA foreign_keys = OFF", []).map_err(|e| e.to_string())?;
+    let report = sync_database(&conn, &transport)?;
+    for change in &report.changes {
+        emit_change(app, change);
+    }
+    Ok(report)
+}
+
+/// Sync in the background every `interval_minutes` while sync is enabled
+pub fn start_sync_scheduler(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut last_run: Option<Instant> = None;
+
+        loop {
+            std::thread::sleep(CHECK_INTERVAL);
+
+            let Ok(settings) = load_sync_settings(&app) else {
+                continue;
+            };
+            if !settings.enabled || settings.interval_minutes == 0 {
+                continue;
+            }
+            let interval = Duration::from_secs(u64::from(settings.interval_minutes) * 60);
+            if last_run.is_some_and(|last| last.elapsed() < interval) {
+                continue;
+            }
+            last_run = Some(Instant::now());
+
+            let run = match run_sync(&app) {
+                Ok(report) => SyncRun { report: Some(report), error: None },
+                Err(e) => {
+                    eprintln!("Background sync failed: {}", e);
+                    SyncRun { report: None, error: Some(e) }
+                }
+            };
+            if let Err(e) = app.emit(SYNC_EVENT, &run) {
+                eprintln!("Failed to emit {} event: {}", SYNC_EVENT, e);
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_sync_settings(app: AppHandle) -> Result<SyncSettings, String> {
+    load_sync_settings(&app)
+}
+
+#[tauri::command]
+pub fn set_sync_settings(app: AppHandle, settings: SyncSettings) -> Result<SyncSettings, String> {
+    let url = settings.url.trim();
+    if settings.enabled && !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err("Naslov strežnika se mora začeti s http:// ali https://.".to_string());
+    }
+    save_sync_settings(&app, &settings)?;
+    Ok(settings)
+}
+
+/// Push local changes and pull server changes now
+#[tauri::command]
+pub async fn sync_now(app: AppHandle) -> Result<SyncReport, String> {
+    // HTTP requests would block the main thread, so the run goes to a worker thread
+    tauri::async_runtime::spawn_blocking(move || run_sync(&app))
+        .await
+        .map_err(|e| format!("Napaka pri sinhronizaciji: {}", e))?
+}
+
+/// Time of the last sync, unpushed records and conflicts waiting for review
+#[tauri::command]
+pub fn sync_get_status(app: AppHandle) -> Result<SyncStatus, String> {
+    let conn = get_connection(&app).map_err(|e| e.to_string())?;
+    sync_status(&conn).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn sync_list_conflicts(app: AppHandle, include_resolved: Option<bool>) -> Result<Vec<SyncConflict>, String> {
+    let conn = get_connection(&app).map_err(|e| e.to_string())?;
+    list_conflicts(&conn, include_resolved.unwrap_or(false)).map_err(|e| e.to_string())
+}
+
+/// Close a conflict, keeping the local or the server version, see [`resolve_conflict`]
+#[tauri::command]
+pub fn sync_resolve_conflict(app: AppHandle, id: String, keep: SyncSide) -> Result<(), String> {
+    let conn = get_connection(&app).map_err(|e| e.to_string())?;
+    if let Some(change) = resolve_conflict(&conn, &id, keep)? {
+        emit_change(&app, &change);
+    }
+    Ok(())
+}
@@ -0,0 +1,487 @@
+use serde_json::{Map, Value};
+use std::time::Duration;
+
+/// A row as sent to and received from the server, keyed by column name
+pub type RemoteRow = Map<String, Value>;
+
+/// A PostgREST filter: column name and operator with value, e.g. `("synced_at", "gt.2025-01-31T18:30:00Z")`
+pub type Filter = (String, String);
+
+/// How long a single request may take before the sync run is abandoned
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub fn eq(column: &str, value: &str) -> Filter {
+    (column.to_string(), format!("eq.{}", value))
+}
+
+pub fn gt(column: &str, value: &str) -> Filter {
+    (column.to_string(), format!("gt.{}", value))
+}
+
+/// `column IN (values)`; values are quoted so ids with commas or parentheses stay intact
+pub fn in_list(column: &str, values: &[String]) -> Filter {
+    let quoted: Vec<String> = values
+        .iter()
+        .map(|value| format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect();
+    (column.to_string(), format!("in.({})", quoted.join(",")))
+}
+
+/// The HTTP side of the sync engine, in PostgREST terms.
+///
+/// [`PostgrestTransport`] talks to a real server; the engine tests use an in-memory implementation.
+pub trait SyncTransport {
+    /// Rows matching all filters, sorted ascending by the `order` columns. Pages taken with
+    /// `offset` only line up when the order is unique, so end it with the primary key.
+    fn select(&self, table: &str, filters: &[Filter], order: &[&str], limit: usize, offset: usize) -> Result<Vec<RemoteRow>, String>;
+    /// Insert rows, overwriting rows whose primary key already exists
+    fn upsert(&self, table: &str, rows: &[RemoteRow]) -> Result<(), String>;
+    fn insert(&self, table: &str, rows: &[RemoteRow]) -> Result<(), String>;
+    /// Delete rows matching all filters; at least one filter is required
+    fn delete(&self, table: &str, filters: &[Filter]) -> Result<(), String>;
+}
+
+/// [`SyncTransport`] for a PostgREST server, e.g. the REST API of a Supabase project
+pub struct PostgrestTransport {
+    base_url: String,
+    api_key: String,
+    access_token: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl PostgrestTransport {
+    /// `url` is the REST endpoint; a bare Supabase project URL gets `/rest/v1` appended.
+    /// Requests authenticate with `access_token` when given, otherwise with the API key.
+    pub fn new(url: &str, api_key: &str, access_token: Option<&str>) -> Self {
+        let mut base_url = url.trim().trim_end_matches('/').to_string();
+        if base_url.ends_with(".supabase.co") {
+            base_url.push_str("/rest/v1");
+        }
+        Self {
+            base_url,
+            api_key: api_key.to_string(),
+            access_token: access_token.filter(|t| !t.is_empty()).map(str::to_string),
+            agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+        }
+    }
+
+    fn request(&self, method: &str, table: &str, filters: &[Filter]) -> ureq::Request {
+        let mut request = self
+            .agent
+            .request(method, &format!("{}/{}", self.base_url, table))
+            .set("Accept", "application/json");
+        if !self.api_key.is_empty() {
+            request = request.set("apikey", &self.api_key);
+        }
+        let token = self.access_token.as_deref().unwrap_or(&self.api_key);
+        if !token.is_empty() {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+        for (column, condition) in filters {
+            request = request.query(column, condition);
+        }
+        request
+    }
+
+    fn send(&self, table: &str, result: Result<ureq::Response, ureq::Error>) -> Result<ureq::Response, String> {
+        result.map_err(|e| match e {
+            ureq::Error::Status(code, response) => {
+                let body = response.into_string().unwrap_or_default();
+                format!("Strežnik je zavrnil zahtevo za tabelo {} (HTTP {}): {}", table, code, body.trim())
+            }
+            ureq::Error::Transport(transport) => format!("Napaka pri povezavi s strežnikom: {}", transport),
+        })
+    }
+}
+
+impl SyncTransport for PostgrestTransport {
+    fn select(&self, table: &str, filters: &[Filter], order: &[&str], limit: usize, offset: usize) -> Result<Vec<RemoteRow>, String> {
+        let order: Vec<String> = order.iter().map(|column| format!("{}.asc", column)).collect();
+        let request = self
+            .request("GET", table, filters)
+            .query("select", "*")
+            .query("order", &order.join(","))
+            .query("limit", &limit.to_string())
+            .query("offset", &offset.to_string());
+        self.send(table, request.call())?
+            .into_json()
+            .map_err(|e| format!("Neveljaven odgovor strežnika za tabelo {}: {}", table, e))
+    }
+
+    fn upsert(&self, table: &str, rows: &[RemoteRow]) -> Result<(), String> {
+        let request = self
+            .request("POST", table, &[])
+            .set("Prefer", "resolution=merge-duplicates,return=minimal");
+        self.send(table, request.send_json(rows)).map(|_| ())
+    }
+
+    fn insert(&self, table: &str, rows: &[RemoteRow]) -> Result<(), String> {
+        let request = self.request("POST", table, &[]).set("Prefer", "return=minimal");
+        self.send(table, request.send_json(rows)).map(|_| ())
+    }
+
+    fn delete(&self, table: &str, filters: &[Filter]) -> Result<(), String> {
+        if filters.is_empty() {
+            return Err(format!("Brisanje vseh vrstic tabele {} ni dovoljeno.", table));
+        }
+        let request = self.request("DELETE", table, filters).set("Prefer", "return=minimal");
+        self.send(table, request.call()).map(|_| ())
+    }
+}
+
+/// In-memory stand-in for a PostgREST server, with the `synced_at` stamping of
+/// `supabase/migrations/003_sync.sql`. Supports the `eq`, `gt` and `in` filters.
+#[cfg(test)]
+pub struct MemoryTransport {
+    pub tables: std::cell::RefCell<std::collections::BTreeMap<String, Vec<RemoteRow>>>,
+    clock: std::cell::Cell<i64>,
+}
+
+#[cfg(test)]
+impl MemoryTransport {
+    pub fn new() -> Self {
+        Self {
+            tables: Default::default(),
+            clock: std::cell::Cell::new(0),
+        }
+    }
+
+    /// Server-side write by another client
+    pub fn put(&self, table: &str, row: Value) {
+        let Value::Object(row) = row else {
+            panic!("row must be a JSON object");
+        };
+        self.upsert(table, &[row]).unwrap();
+    }
+
+    pub fn get(&self, table: &str, id: &str) -> Option<RemoteRow> {
+        self.tables
+            .borrow()
+            .get(table)
+            .and_then(|rows| rows.iter().find(|row| row.get("id").and_then(Value::as_str) == Some(id)).cloned())
+    }
+
+    fn tick(&self) -> Value {
+        self.clock.set(self.clock.get() + 1);
+        let time = chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+            + chrono::Duration::microseconds(self.clock.get());
+        Value::String(time.to_rfc3339_opts(chrono::SecondsFormat::Micros, true))
+    }
+
+    fn matches(row: &RemoteRow, (column, condition): &Filter) -> bool {
+        let value = match row.get(column) {
+            Some(Value::String(s)) => s.clone(),
+            Some(Value::Null) | None => return false,
+            Some(other) => other.to_string(),
+        };
+        if let Some(expected) = condition.strip_prefix("eq.") {
+            value == expected
+        } else if let Some(bound) = condition.strip_prefix("gt.") {
+            match (chrono::DateTime::parse_from_rfc3339(&value), chrono::DateTime::parse_from_rfc3339(bound)) {
+                (Ok(value), Ok(bound)) => value > bound,
+                _ => value.as_str() > bound,
+            }
+        } else if let Some(list) = condition.strip_prefix("in.(").and_then(|c| c.strip_suffix(')')) {
+            list.split(',').any(|item| item.trim_matches('"') == value)
+        } else {
+            panic!("unsupported filter {}", condition)
+        }
+    }
+}
+
+#[cfg(test)]
+impl SyncTransport for MemoryTransport {
+    fn select(&self, table: &str, filters: &[Filter], order: &[&str], limit: usize, offset: usize) -> Result<Vec<RemoteRow>, String> {
+        let tables = self.tables.borrow();
+        let mut rows: Vec<RemoteRow> = tables
+            .get(table)
+            .map(|rows| rows.iter().filter(|row| filters.iter().all(|f| Self::matches(row, f))).cloned().collect())
+            .unwrap_or_default();
+        rows.sort_by_key(|row| {
+            order
+                .iter()
+                .map(|column| row.get(*column).map(|v| v.to_string()).unwrap_or_default())
+                .collect::<Vec<_>>()
+        });
+        Ok(rows.into_iter().skip(offset).take(limit).collect())
+    }
+
+    fn upsert(&self, table: &str, rows: &[RemoteRow]) -> Result<(), String> {
+        for row in rows {
+            let mut row = row.clone();
+            row.insert("synced_at".to_string(), self.tick());
+            let mut tables = self.tables.borrow_mut();
+            let stored = tables.entry(table.to_string()).or_default();
+            match stored.iter_mut().find(|existing| existing.get("id") == row.get("id")) {
+                Some(existing) => existing.extend(row),
+                None => stored.push(row),
+            }
+        }
+        Ok(())
+    }
+
+    fn insert(&self, table: &str, rows: &[RemoteRow]) -> Result<(), String> {
+        for row in rows {
+            let mut row = row.clone();
+            row.insert("synced_at".to_string(), self.tick());
+            self.tables.borrow_mut().entry(table.to_string()).or_default().push(row);
+        }
+        Ok(())
+    }
+
+    fn delete(&self, table: &str, filters: &[Filter]) -> Result<(), String> {
+        assert!(!filters.is_empty());
+        if let Some(rows) = self.tables.borrow_mut().get_mut(table) {
+            rows.retain(|row| !filters.iter().all(|f| Self::matches(row, f)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::parse_timestamp;
+    use crate::sync::SYNC_TABLES;
+    use serde_json::json;
+    use std::path::Path;
+    use std::sync::mpsc;
+
+    /// A request as the server received it
+    struct Received {
+        method: String,
+        url: String,
+        headers: Vec<(String, String)>,
+        body: String,
+    }
+
+    impl Received {
+        fn header(&self, name: &str) -> Option<&str> {
+            self.headers
+                .iter()
+                .find(|(field, _)| field.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        }
+
+        fn path(&self) -> &str {
+            self.url.split('?').next().unwrap_or_default()
+        }
+
+        /// Query parameters, percent-decoded
+        fn query(&self) -> Vec<(String, String)> {
+            let Some((_, query)) = self.url.split_once('?') else {
+                return Vec::new();
+            };
+            query
+                .split('&')
+                .map(|pair| {
+                    let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                    (decode(name), decode(value))
+                })
+                .collect()
+        }
+    }
+
+    fn decode(text: &str) -> String {
+        let mut bytes = Vec::new();
+        let mut i = 0;
+        while i < text.len() {
+            match text.as_bytes()[i] {
+                b'+' => bytes.push(b' '),
+                b'%' => {
+                    bytes.push(u8::from_str_radix(&text[i + 1..i + 3], 16).unwrap());
+                    i += 2;
+                }
+                byte => bytes.push(byte),
+            }
+            i += 1;
+        }
+        String::from_utf8(bytes).unwrap()
+    }
+
+    /// Local HTTP server that answers `requests` requests with `respond` and hands over what
+    /// it received. Returns the server's URL.
+    fn serve(
+        requests: usize,
+        mut respond: impl FnMut(&Received) -> (u16, String) + Send + 'static,
+    ) -> (String, mpsc::Receiver<Received>) {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for mut request in server.incoming_requests().take(requests) {
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                let received = Received {
+                    method: request.method().to_string(),
+                    url: request.url().to_string(),
+                    headers: request
+                        .headers()
+                        .iter()
+                        .map(|header| (header.field.to_string(), header.value.to_string()))
+                        .collect(),
+                    body,
+                };
+                let (status, response) = respond(&received);
+                let _ = sender.send(received);
+                let _ = request.respond(tiny_http::Response::from_string(response).with_status_code(status));
+            }
+        });
+        (url, receiver)
+    }
+
+    fn row(value: Value) -> RemoteRow {
+        match value {
+            Value::Object(row) => row,
+            _ => panic!("row must be a JSON object"),
+        }
+    }
+
+    #[test]
+    fn appends_the_rest_path_to_a_supabase_project_url() {
+        assert_eq!(PostgrestTransport::new(" https://abc.supabase.co/ ", "", None).base_url, "https://abc.supabase.co/rest/v1");
+        assert_eq!(PostgrestTransport::new("http://localhost:3000/", "", None).base_url, "http://localhost:3000");
+    }
+
+    #[test]
+    fn encodes_filters_order_and_paging_in_the_query() {
+        let (url, received) = serve(1, |_| (200, "[]".to_string()));
+        let transport = PostgrestTransport::new(&url, "anon", None);
+        let ids = ["par-1".to_string(), "par,(2)".to_string(), "par \"3\"".to_string()];
+        let rows = transport
+            .select(
+                "parents",
+                &[in_list("id", &ids), gt("synced_at", "2025-01-31T18:30:00.123456+00:00")],
+                &["synced_at", "id"],
+                1000,
+                2000,
+            )
+            .unwrap();
+        assert!(rows.is_empty());
+
+        let request = received.recv().unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path(), "/parents");
+        // A `+` left as is would reach PostgREST as a space and shift the watermark
+        assert!(request.url.contains("%2B00"), "{}", request.url);
+        let query = request.query();
+        let expected = [
+            ("id", r#"in.("par-1","par,(2)","par \"3\"")"#),
+            ("synced_at", "gt.2025-01-31T18:30:00.123456+00:00"),
+            ("select", "*"),
+            ("order", "synced_at.asc,id.asc"),
+            ("limit", "1000"),
+            ("offset", "2000"),
+        ];
+        assert_eq!(query.len(), expected.len(), "{:?}", query);
+        for (name, value) in expected {
+            assert!(query.contains(&(name.to_string(), value.to_string())), "{} in {:?}", name, query);
+        }
+        // Without a user session the API key is the bearer token too
+        assert_eq!(request.header("apikey"), Some("anon"));
+        assert_eq!(request.header("Authorization"), Some("Bearer anon"));
+    }
+
+    #[test]
+    fn sends_writes_with_the_right_preferences() {
+        let (url, received) = serve(3, |_| (201, String::new()));
+        let transport = PostgrestTransport::new(&url, "anon", Some("user-jwt"));
+        let rows = [row(json!({ "id": "par-1", "first_name": "Mojca" }))];
+
+        transport.upsert("parents", &rows).unwrap();
+        transport.insert("audit_log", &rows).unwrap();
+        assert!(transport.delete("parents", &[]).is_err(), "deleting a whole table is refused");
+        transport.delete("parents", &[eq("id", "par-1")]).unwrap();
+
+        let upsert = received.recv().unwrap();
+        assert_eq!((upsert.method.as_str(), upsert.path()), ("POST", "/parents"));
+        assert_eq!(upsert.header("Prefer"), Some("resolution=merge-duplicates,return=minimal"));
+        assert_eq!(upsert.header("Authorization"), Some("Bearer user-jwt"));
+        assert_eq!(upsert.header("apikey"), Some("anon"));
+        assert_eq!(serde_json::from_str::<Value>(&upsert.body).unwrap(), json!([{ "id": "par-1", "first_name": "Mojca" }]));
+
+        let insert = received.recv().unwrap();
+        assert_eq!((insert.method.as_str(), insert.path()), ("POST", "/audit_log"));
+        assert_eq!(insert.header("Prefer"), Some("return=minimal"));
+
+        let delete = received.recv().unwrap();
+        assert_eq!((delete.method.as_str(), delete.path()), ("DELETE", "/parents"));
+        assert_eq!(delete.query(), [("id".to_string(), "eq.par-1".to_string())]);
+    }
+
+    #[test]
+    fn reports_server_and_connection_errors() {
+        let (url, _received) = serve(2, |request| match request.method.as_str() {
+            "GET" => (200, "<html>Bad gateway</html>".to_string()),
+            _ => (409, r#"{"code":"23505","message":"duplicate key value"}"#.to_string()),
+        });
+        let transport = PostgrestTransport::new(&url, "anon", None);
+
+        let error = transport.upsert("parents", &[row(json!({ "id": "par-1" }))]).unwrap_err();
+        assert!(error.starts_with("Strežnik je zavrnil zahtevo za tabelo parents (HTTP 409)"), "{}", error);
+        assert!(error.contains("23505"), "{}", error);
+
+        let error = transport.select("parents", &[], &["id"], 10, 0).unwrap_err();
+        assert!(error.starts_with("Neveljaven odgovor strežnika za tabelo parents"), "{}", error);
+
+        // Nothing listens on a port that was just released
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let offline = PostgrestTransport::new(&format!("http://127.0.0.1:{}", port), "anon", None);
+        let error = offline.select("parents", &[], &["id"], 10, 0).unwrap_err();
+        assert!(error.starts_with("Napaka pri povezavi s strežnikom"), "{}", error);
+    }
+
+    #[test]
+    fn migration_stamps_synced_at_on_every_synced_table() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../../supabase/migrations/003_sync.sql");
+        let migration = std::fs::read_to_string(path).unwrap();
+        assert!(migration.contains("NEW.synced_at = clock_timestamp();"));
+        assert!(migration.contains("BEFORE INSERT OR UPDATE ON %I FOR EACH ROW EXECUTE FUNCTION set_synced_at()"));
+
+        let start = migration.find("FOREACH synced_table IN ARRAY ARRAY[").unwrap();
+        let end = start + migration[start..].find(']').unwrap();
+        let mut stamped: Vec<&str> = migration[start..end]
+            .split('\'')
+            .skip(1)
+            .step_by(2)
+            .collect();
+        stamped.sort();
+        let mut synced = SYNC_TABLES.to_vec();
+        synced.sort();
+        assert_eq!(stamped, synced);
+    }
+
+    #[test]
+    fn pulls_rows_by_the_server_stamped_synced_at() {
+        // The server side of migration 003: every write gets the server clock, whatever the client sent
+        let (url, _received) = serve(2, {
+            let mut stored: Vec<RemoteRow> = Vec::new();
+            move |request| match request.method.as_str() {
+                "POST" => {
+                    for mut row in serde_json::from_str::<Vec<RemoteRow>>(&request.body).unwrap() {
+                        row.insert("synced_at".to_string(), json!("2025-03-14 16:00:00.123456+00"));
+                        stored.push(row);
+                    }
+                    (201, String::new())
+                }
+                _ => {
+                    let query = request.query();
+                    let bound = query.iter().find(|(name, _)| name == "synced_at").unwrap();
+                    assert_eq!(bound.1, "gt.2025-03-14T15:59:00.000000Z");
+                    (200, Value::Array(stored.iter().cloned().map(Value::Object).collect()).to_string())
+                }
+            }
+        });
+        let transport = PostgrestTransport::new(&url, "anon", None);
+        transport
+            .upsert("parents", &[row(json!({ "id": "par-1", "synced_at": "2020-01-01T00:00:00Z" }))])
+            .unwrap();
+
+        let rows = transport
+            .select("parents", &[gt("synced_at", "2025-03-14T15:59:00.000000Z")], &["synced_at", "id"], 10, 0)
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        let synced_at = rows[0].get("synced_at").and_then(Value::as_str).and_then(parse_timestamp).unwrap();
+        assert_eq!(synced_at, parse_timestamp("2025-03-14T16:00:00.123456Z").unwrap());
+    }
+}
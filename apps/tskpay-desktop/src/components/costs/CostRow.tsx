@@ -130,6 +130,11 @@ export function CostRow({
             {cost.description}
           </div>
         )}
+        {cost.paymentReference && (
+          <div className="text-xs text-slate-500 dark:text-slate-400 mt-1 font-mono">
+            Sklic: {cost.paymentReference}
+          </div>
+        )}
       </td>
       {showMemberColumn && (
         <td className="px-4 py-3">
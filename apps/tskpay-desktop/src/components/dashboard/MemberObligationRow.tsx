@@ -62,6 +62,11 @@ export function MemberObligationRow({
         >
           {obligation.parentName}
         </button>
+        {obligation.parentReference && (
+          <div className="text-xs text-slate-500 dark:text-slate-400 mt-1 font-mono">
+            Sklic: {obligation.parentReference}
+          </div>
+        )}
       </td>
       <td className="px-4 py-3 text-sm text-slate-600 dark:text-slate-400">
         {obligation.groupName}
@@ -1,26 +1,35 @@
 import { useState, useMemo } from 'react'
 import type { Parent, Member } from '@/types'
+import type { ReferenceModel } from '@/data/database'
 import { ParentRow } from './ParentRow'
-import { Button, Input } from '@/components/ui'
+import { Button, Input, Select, Checkbox } from '@/components/ui'
 
 export interface ParentListProps {
   parents: Parent[]
   members?: Member[]
   searchFilter?: string
+  isAssigningReferences?: boolean
   onEditParent?: (id: string) => void
   onDeleteParent?: (id: string) => void
   onSearchChange?: (search: string) => void
+  /** Give parents, and with `includeCosts` open costs, without a payment reference the next free one */
+  onAssignReferences?: (model: ReferenceModel, includeCosts: boolean) => void
 }
 
 export function ParentList({
   parents,
   members = [],
   searchFilter = '',
+  isAssigningReferences = false,
   onEditParent,
   onDeleteParent,
   onSearchChange,
+  onAssignReferences,
 }: ParentListProps) {
   const [localSearch, setLocalSearch] = useState(searchFilter)
+  const [referenceModel, setReferenceModel] = useState<ReferenceModel>('si12')
+  const [includeCosts, setIncludeCosts] = useState(false)
+  const parentsWithoutReference = parents.filter((p) => !p.paymentReference).length
 
   const membersByParent = useMemo(
     () => {
@@ -49,7 +58,8 @@ export function ParentList({
           p.firstName.toLowerCase().includes(searchLower) ||
           p.lastName.toLowerCase().includes(searchLower) ||
           p.email.toLowerCase().includes(searchLower) ||
-          p.phone.includes(localSearch)
+          p.phone.includes(localSearch) ||
+          (p.paymentReference ?? '').toLowerCase().includes(searchLower)
       )
     }
 
@@ -67,12 +77,41 @@ export function ParentList({
         <div className="flex flex-col sm:flex-row gap-4">
           <div className="flex-1">
             <Input
-              placeholder="Išči po imenu, priimku, e-pošti ali telefonu..."
+              placeholder="Išči po imenu, priimku, e-pošti, telefonu ali sklicu..."
               value={localSearch}
               onChange={(e) => handleSearchChange(e.target.value)}
               className="w-full"
             />
           </div>
+          {onAssignReferences && (
+            <div className="flex items-center gap-2">
+              <Select
+                value={referenceModel}
+                onValueChange={(value) => setReferenceModel(value as ReferenceModel)}
+                className="min-w-[120px]"
+                disabled={isAssigningReferences}
+              >
+                <option value="si12">SI12</option>
+                <option value="rf">RF</option>
+              </Select>
+              <label className="flex items-center gap-2 cursor-pointer whitespace-nowrap">
+                <Checkbox
+                  checked={includeCosts}
+                  onCheckedChange={(checked) => setIncludeCosts(checked)}
+                />
+                <span className="text-sm text-slate-700 dark:text-slate-300">Tudi odprtim stroškom</span>
+              </label>
+              <Button
+                variant="outline"
+                onClick={() => onAssignReferences(referenceModel, includeCosts)}
+                disabled={isAssigningReferences || (parentsWithoutReference === 0 && !includeCosts)}
+                title="Staršem brez sklica dodeli naslednji prosti sklic; obstoječi sklici ostanejo"
+                className="whitespace-nowrap"
+              >
+                Dodeli sklice ({parentsWithoutReference})
+              </Button>
+            </div>
+          )}
         </div>
       </div>
 
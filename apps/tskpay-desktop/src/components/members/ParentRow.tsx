@@ -36,6 +36,11 @@ export function ParentRow({
                 {memberCount} {memberCount === 1 ? 'član' : 'članov'}
               </div>
             )}
+            {parent.paymentReference && (
+              <div className="text-xs text-slate-500 dark:text-slate-400 font-mono">
+                Sklic: {parent.paymentReference}
+              </div>
+            )}
           </div>
         </div>
       </td>
@@ -1,20 +1,37 @@
+import { useState } from 'react'
 import type { BankStatement } from '@/types'
-import { Badge, Button } from '@/components/ui'
-import { FileText, CheckCircle2, AlertCircle, Clock, Upload, Trash2 } from 'lucide-react'
+import type { CsvProfile } from '@/data/database'
+import { Badge, Button, Select } from '@/components/ui'
+import { FileText, CheckCircle2, AlertCircle, Clock, Upload, Trash2, Plus, Settings2 } from 'lucide-react'
+
+/** File formats the backend imports statements from */
+export type StatementFormat = 'xml' | 'pdf' | 'mt940' | 'csv'
 
 export interface BankStatementListProps {
   statements: BankStatement[]
+  /** Saved CSV profiles; CSV files can only be imported with one of them */
+  csvProfiles?: CsvProfile[]
+  isImporting?: boolean
   onViewStatement?: (statementId: string) => void
-  onUploadStatement?: (file: File) => void
+  /** The user picks the file in the backend's dialog */
+  onImportStatement?: (format: StatementFormat, csvProfile?: CsvProfile) => void
+  /** Open the profile editor; null for a new profile */
+  onEditCsvProfile?: (csvProfile: CsvProfile | null) => void
   onDeleteStatement?: (statementId: string) => void
 }
 
 export function BankStatementList({
   statements,
+  csvProfiles = [],
+  isImporting = false,
   onViewStatement,
-  onUploadStatement,
+  onImportStatement,
+  onEditCsvProfile,
   onDeleteStatement,
 }: BankStatementListProps) {
+  // 'xml', 'pdf', 'mt940' or 'csv:<profile id>'
+  const [source, setSource] = useState('xml')
+
   const formatDate = (dateString: string) => {
     const date = new Date(dateString)
     return date.toLocaleDateString('sl-SI', {
@@ -54,10 +71,18 @@ export function BankStatementList({
     }
   }
 
-  const handleFileUpload = (e: React.ChangeEvent<HTMLInputElement>) => {
-    const file = e.target.files?.[0]
-    if (file && onUploadStatement) {
-      onUploadStatement(file)
+  const selectedCsvProfile = source.startsWith('csv:')
+    ? csvProfiles.find((p) => p.id === source.slice('csv:'.length))
+    : undefined
+
+  const handleImport = () => {
+    if (source.startsWith('csv:')) {
+      const profile = selectedCsvProfile
+      if (profile) {
+        onImportStatement?.('csv', profile)
+      }
+    } else {
+      onImportStatement?.(source as StatementFormat)
     }
   }
 
@@ -65,20 +90,45 @@ export function BankStatementList({
     <div className="space-y-4">
       <div className="flex items-center justify-between">
         <h2 className="text-2xl font-semibold text-slate-900 dark:text-slate-100">Bančni izpiski</h2>
-        <div>
-          <input
-            type="file"
-            id="file-upload"
-            accept=".pdf,.xml"
-            onChange={handleFileUpload}
-            className="hidden"
-          />
+        <div className="flex items-center gap-2">
+          <Select value={source} onValueChange={setSource} className="min-w-[220px]" disabled={isImporting}>
+            <option value="xml">XML (camt.052, camt.053, camt.054)</option>
+            <option value="pdf">PDF (NLB, NKBM/OTP, SKB, DH)</option>
+            <option value="mt940">MT940</option>
+            {csvProfiles.map((profile) => (
+              <option key={profile.id} value={`csv:${profile.id}`}>
+                CSV – {profile.name}
+              </option>
+            ))}
+          </Select>
+          {onEditCsvProfile && selectedCsvProfile && (
+            <Button
+              variant="outline"
+              onClick={() => onEditCsvProfile(selectedCsvProfile)}
+              disabled={isImporting}
+              title="Uredi profil CSV"
+            >
+              <Settings2 className="w-4 h-4" />
+            </Button>
+          )}
+          {onEditCsvProfile && (
+            <Button
+              variant="outline"
+              onClick={() => onEditCsvProfile(null)}
+              disabled={isImporting}
+              title="Nov profil za uvoz CSV"
+            >
+              <Plus className="w-4 h-4 mr-2" />
+              Profil CSV
+            </Button>
+          )}
           <Button
-            onClick={() => document.getElementById('file-upload')?.click()}
+            onClick={handleImport}
+            disabled={isImporting}
             className="bg-blue-600 hover:bg-blue-700"
           >
             <Upload className="w-4 h-4 mr-2" />
-            Naloži izpisek
+            {isImporting ? 'Uvažanje ...' : 'Uvozi izpisek'}
           </Button>
         </div>
       </div>
@@ -119,11 +169,12 @@ export function BankStatementList({
                       <p className="text-slate-500 dark:text-slate-400">Ni uvoženih izpiskov</p>
                       <p className="text-sm text-slate-400">Naložite prvi bančni izpisek, da začnete</p>
                       <Button
-                        onClick={() => document.getElementById('file-upload')?.click()}
+                        onClick={handleImport}
+                        disabled={isImporting}
                         className="mt-4"
                       >
                         <Upload className="w-4 h-4 mr-2" />
-                        Naloži izpisek
+                        Uvozi izpisek
                       </Button>
                     </div>
                   </td>
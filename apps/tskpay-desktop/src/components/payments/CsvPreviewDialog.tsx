@@ -0,0 +1,164 @@
+import type { CsvPreview, CsvProfile } from '@/data/database'
+import { Button } from '@/components/ui'
+import { Dialog, DialogContent, DialogHeader, DialogTitle, DialogClose } from '@/components/ui/Dialog'
+import { AlertCircle, Upload } from 'lucide-react'
+
+export interface CsvPreviewDialogProps {
+  preview: CsvPreview | null
+  profile: CsvProfile | null
+  open: boolean
+  isImporting?: boolean
+  onOpenChange: (open: boolean) => void
+  /** Import the previewed file with the same profile */
+  onConfirm?: () => void
+}
+
+export function CsvPreviewDialog({
+  preview,
+  profile,
+  open,
+  isImporting = false,
+  onOpenChange,
+  onConfirm,
+}: CsvPreviewDialogProps) {
+  if (!preview || !profile) return null
+
+  const formatDate = (dateString: string) => {
+    const date = new Date(dateString)
+    return date.toLocaleDateString('sl-SI')
+  }
+
+  const fileName = preview.sourcePath.split(/[\\/]/).pop()
+  const columnCount = Math.max(preview.headers.length, ...preview.rows.map((row) => row.length))
+
+  return (
+    <Dialog open={open} onOpenChange={onOpenChange}>
+      <DialogContent className="max-w-4xl max-h-[90vh] overflow-y-auto">
+        <DialogClose onClose={() => onOpenChange(false)} />
+        <DialogHeader>
+          <DialogTitle>Predogled uvoza CSV</DialogTitle>
+          <p className="text-sm text-slate-600 dark:text-slate-400">
+            {fileName} · profil {profile.name} · {preview.totalRows} vrstic, prebranih {preview.totalTransactions} transakcij
+          </p>
+        </DialogHeader>
+
+        <div className="space-y-6 px-6 pb-6">
+          {/* Raw rows, to check the column mapping */}
+          <div className="space-y-2">
+            <div className="text-sm font-medium text-slate-900 dark:text-slate-200">
+              Prve vrstice datoteke ({preview.rows.length} od {preview.totalRows})
+            </div>
+            <div className="overflow-x-auto rounded-lg border border-slate-200 dark:border-slate-700">
+              <table className="w-full text-xs">
+                {preview.headers.length > 0 && (
+                  <thead className="bg-slate-50 dark:bg-slate-900/50">
+                    <tr>
+                      {preview.headers.map((header, index) => (
+                        <th key={index} className="px-2 py-1 text-left font-medium text-slate-700 dark:text-slate-300 whitespace-nowrap">
+                          <span className="text-slate-400 mr-1">{index + 1}</span>
+                          {header}
+                        </th>
+                      ))}
+                    </tr>
+                  </thead>
+                )}
+                <tbody>
+                  {preview.rows.map((row, rowIndex) => (
+                    <tr key={rowIndex} className="border-t border-slate-200 dark:border-slate-700">
+                      {Array.from({ length: columnCount }, (_, index) => (
+                        <td key={index} className="px-2 py-1 text-slate-600 dark:text-slate-400 whitespace-nowrap">
+                          {row[index] ?? ''}
+                        </td>
+                      ))}
+                    </tr>
+                  ))}
+                </tbody>
+              </table>
+            </div>
+          </div>
+
+          {/* Transactions as the profile reads them */}
+          <div className="space-y-2">
+            <div className="text-sm font-medium text-slate-900 dark:text-slate-200">
+              Prebrane transakcije ({preview.transactions.length} od {preview.totalTransactions})
+            </div>
+            {preview.transactions.length === 0 ? (
+              <p className="text-sm text-slate-500 dark:text-slate-400">
+                S tem profilom iz datoteke ni bilo mogoče prebrati nobene transakcije.
+              </p>
+            ) : (
+              <div className="overflow-x-auto rounded-lg border border-slate-200 dark:border-slate-700">
+                <table className="w-full text-sm">
+                  <thead className="bg-slate-50 dark:bg-slate-900/50">
+                    <tr>
+                      <th className="px-3 py-2 text-left font-medium text-slate-700 dark:text-slate-300">Datum</th>
+                      <th className="px-3 py-2 text-right font-medium text-slate-700 dark:text-slate-300">Znesek</th>
+                      <th className="px-3 py-2 text-left font-medium text-slate-700 dark:text-slate-300">Plačnik</th>
+                      <th className="px-3 py-2 text-left font-medium text-slate-700 dark:text-slate-300">Sklic</th>
+                      <th className="px-3 py-2 text-left font-medium text-slate-700 dark:text-slate-300">Namen</th>
+                    </tr>
+                  </thead>
+                  <tbody>
+                    {preview.transactions.map((transaction) => (
+                      <tr key={transaction.id} className="border-t border-slate-200 dark:border-slate-700">
+                        <td className="px-3 py-2 text-slate-600 dark:text-slate-400 whitespace-nowrap">
+                          {formatDate(transaction.bookingDate)}
+                        </td>
+                        <td
+                          className={
+                            transaction.isCredit
+                              ? 'px-3 py-2 text-right font-medium text-green-600 dark:text-green-400 whitespace-nowrap'
+                              : 'px-3 py-2 text-right font-medium text-red-600 dark:text-red-400 whitespace-nowrap'
+                          }
+                        >
+                          {transaction.isCredit ? '' : '−'}
+                          {transaction.amount.toFixed(2)} {transaction.currency}
+                        </td>
+                        <td className="px-3 py-2 text-slate-900 dark:text-slate-100">{transaction.payerName || '—'}</td>
+                        <td className="px-3 py-2 text-slate-600 dark:text-slate-400">{transaction.reference || '—'}</td>
+                        <td className="px-3 py-2 text-slate-600 dark:text-slate-400">{transaction.description || '—'}</td>
+                      </tr>
+                    ))}
+                  </tbody>
+                </table>
+              </div>
+            )}
+            <p className="text-xs text-slate-500">Uvoženi bodo le prilivi; odlivi se ne shranijo.</p>
+          </div>
+
+          {preview.unparsedLines.length > 0 && (
+            <div className="rounded-lg border p-3 space-y-1 bg-amber-50 border-amber-200 dark:bg-amber-950/20 dark:border-amber-800">
+              <div className="flex items-center gap-2 text-sm text-slate-700 dark:text-slate-300">
+                <AlertCircle className="w-4 h-4 text-amber-600 dark:text-amber-400" />
+                Vrstice, ki jih ni bilo mogoče prebrati ({preview.unparsedLines.length}):
+              </div>
+              <ul className="text-xs text-slate-600 dark:text-slate-400 space-y-0.5">
+                {preview.unparsedLines.map((line) => (
+                  <li key={line.lineNumber}>
+                    <span className="font-medium">{line.lineNumber}. vrstica:</span>{' '}
+                    <span className="font-mono">{line.text}</span> — {line.reason}
+                  </li>
+                ))}
+              </ul>
+            </div>
+          )}
+
+          {/* Actions */}
+          <div className="flex justify-end gap-3 pt-4 border-t border-slate-200 dark:border-slate-700">
+            <Button type="button" variant="outline" onClick={() => onOpenChange(false)} disabled={isImporting}>
+              Prekliči
+            </Button>
+            <Button
+              onClick={onConfirm}
+              disabled={isImporting || preview.totalTransactions === 0}
+              className="bg-blue-600 hover:bg-blue-700"
+            >
+              <Upload className="w-4 h-4 mr-2" />
+              {isImporting ? 'Uvažanje ...' : 'Uvozi izpisek'}
+            </Button>
+          </div>
+        </div>
+      </DialogContent>
+    </Dialog>
+  )
+}
@@ -0,0 +1,247 @@
+import { useState, useEffect } from 'react'
+import type { CsvColumns, CsvProfile } from '@/data/database'
+import { Button, Checkbox, Input, Label, Select } from '@/components/ui'
+import { Dialog, DialogContent, DialogHeader, DialogTitle, DialogClose } from '@/components/ui/Dialog'
+import { Trash2 } from 'lucide-react'
+
+export interface CsvProfileFormProps {
+  /** null for a new profile */
+  profile?: CsvProfile | null
+  open: boolean
+  onOpenChange: (open: boolean) => void
+  /** Closes the form itself once the profile is stored */
+  onSave?: (profile: CsvProfile) => void
+  onDelete?: (profileId: string) => void
+}
+
+const EMPTY_COLUMNS: CsvColumns = {
+  bookingDate: null,
+  valueDate: null,
+  amount: null,
+  credit: null,
+  debit: null,
+  currency: null,
+  payerName: null,
+  payerIban: null,
+  description: null,
+  reference: null,
+  bankReference: null,
+}
+
+const NEW_PROFILE: CsvProfile = {
+  id: null,
+  name: '',
+  bank: null,
+  accountIban: '',
+  delimiter: ';',
+  encoding: 'windows-1250',
+  decimalComma: true,
+  dateFormat: '%d.%m.%Y',
+  skipRows: 0,
+  hasHeader: true,
+  columns: EMPTY_COLUMNS,
+}
+
+const COLUMN_FIELDS: { key: keyof CsvColumns; label: string; required?: boolean }[] = [
+  { key: 'bookingDate', label: 'Datum knjiženja', required: true },
+  { key: 'valueDate', label: 'Datum valute' },
+  { key: 'amount', label: 'Znesek (s predznakom)' },
+  { key: 'credit', label: 'Dobro' },
+  { key: 'debit', label: 'Breme' },
+  { key: 'currency', label: 'Valuta' },
+  { key: 'payerName', label: 'Plačnik' },
+  { key: 'payerIban', label: 'IBAN plačnika' },
+  { key: 'description', label: 'Namen' },
+  { key: 'reference', label: 'Sklic' },
+  { key: 'bankReference', label: 'Referenca banke' },
+]
+
+export function CsvProfileForm({ profile, open, onOpenChange, onSave, onDelete }: CsvProfileFormProps) {
+  const [draft, setDraft] = useState<CsvProfile>(NEW_PROFILE)
+
+  useEffect(() => {
+    if (open) {
+      setDraft(profile ? { ...profile, columns: { ...EMPTY_COLUMNS, ...profile.columns } } : NEW_PROFILE)
+    }
+  }, [profile, open])
+
+  const setField = <K extends keyof CsvProfile>(key: K, value: CsvProfile[K]) => {
+    setDraft((current) => ({ ...current, [key]: value }))
+  }
+
+  const setColumn = (key: keyof CsvColumns, value: string) => {
+    setDraft((current) => ({ ...current, columns: { ...current.columns, [key]: value.trim() ? value : null } }))
+  }
+
+  const hasAmountColumn = !!(draft.columns.amount || draft.columns.credit || draft.columns.debit)
+
+  const handleSubmit = (e: React.FormEvent) => {
+    e.preventDefault()
+
+    if (!draft.name.trim() || !draft.accountIban.trim() || !draft.columns.bookingDate || !hasAmountColumn) {
+      return
+    }
+
+    onSave?.({ ...draft, bank: draft.bank?.trim() || null })
+  }
+
+  return (
+    <Dialog open={open} onOpenChange={onOpenChange}>
+      <DialogContent className="max-w-2xl max-h-[90vh] overflow-y-auto">
+        <DialogClose onClose={() => onOpenChange(false)} />
+        <DialogHeader>
+          <DialogTitle>{profile ? 'Uredi profil CSV' : 'Nov profil CSV'}</DialogTitle>
+        </DialogHeader>
+
+        <form onSubmit={handleSubmit} className="space-y-6 p-6">
+          {/* Profile and account */}
+          <div className="grid grid-cols-1 sm:grid-cols-2 gap-4">
+            <div className="space-y-2">
+              <Label htmlFor="csvName">
+                Ime profila <span className="text-red-500">*</span>
+              </Label>
+              <Input
+                id="csvName"
+                value={draft.name}
+                onChange={(e) => setField('name', e.target.value)}
+                placeholder="npr. NLB Klik"
+                required
+              />
+            </div>
+            <div className="space-y-2">
+              <Label htmlFor="csvBank">Banka</Label>
+              <Input
+                id="csvBank"
+                value={draft.bank ?? ''}
+                onChange={(e) => setField('bank', e.target.value)}
+                placeholder="npr. NLB"
+              />
+            </div>
+          </div>
+
+          <div className="space-y-2">
+            <Label htmlFor="csvAccountIban">
+              IBAN računa kluba <span className="text-red-500">*</span>
+            </Label>
+            <Input
+              id="csvAccountIban"
+              value={draft.accountIban}
+              onChange={(e) => setField('accountIban', e.target.value)}
+              placeholder="SI56 ..."
+              required
+            />
+            <p className="text-xs text-slate-500">
+              Izvozi CSV računa običajno ne vsebujejo, po njem pa se iščejo že uvožena plačila.
+            </p>
+          </div>
+
+          {/* File format */}
+          <div className="grid grid-cols-1 sm:grid-cols-3 gap-4">
+            <div className="space-y-2">
+              <Label htmlFor="csvDelimiter">Ločilo</Label>
+              <Select id="csvDelimiter" value={draft.delimiter} onValueChange={(value) => setField('delimiter', value)}>
+                <option value=";">Podpičje (;)</option>
+                <option value=",">Vejica (,)</option>
+                <option value="\t">Tabulator</option>
+                <option value="|">Navpičnica (|)</option>
+              </Select>
+            </div>
+            <div className="space-y-2">
+              <Label htmlFor="csvEncoding">Kodiranje</Label>
+              <Select id="csvEncoding" value={draft.encoding} onValueChange={(value) => setField('encoding', value)}>
+                <option value="windows-1250">Windows-1250</option>
+                <option value="utf-8">UTF-8</option>
+                <option value="iso-8859-2">ISO-8859-2</option>
+              </Select>
+            </div>
+            <div className="space-y-2">
+              <Label htmlFor="csvDateFormat">Oblika datuma</Label>
+              <Select id="csvDateFormat" value={draft.dateFormat} onValueChange={(value) => setField('dateFormat', value)}>
+                <option value="%d.%m.%Y">31.12.2025</option>
+                <option value="%d. %m. %Y">31. 12. 2025</option>
+                <option value="%Y-%m-%d">2025-12-31</option>
+                <option value="%d/%m/%Y">31/12/2025</option>
+                <option value="%d.%m.%y">31.12.25</option>
+              </Select>
+            </div>
+          </div>
+
+          <div className="grid grid-cols-1 sm:grid-cols-3 gap-4 items-end">
+            <div className="space-y-2">
+              <Label htmlFor="csvSkipRows">Vrstice pred glavo</Label>
+              <Input
+                id="csvSkipRows"
+                type="number"
+                min="0"
+                value={draft.skipRows}
+                onChange={(e) => setField('skipRows', Math.max(0, parseInt(e.target.value, 10) || 0))}
+              />
+            </div>
+            <label className="flex items-center gap-2 cursor-pointer h-10">
+              <Checkbox checked={draft.hasHeader} onCheckedChange={(checked) => setField('hasHeader', checked)} />
+              <span className="text-sm text-slate-700 dark:text-slate-300">Prva vrstica je glava</span>
+            </label>
+            <label className="flex items-center gap-2 cursor-pointer h-10">
+              <Checkbox checked={draft.decimalComma} onCheckedChange={(checked) => setField('decimalComma', checked)} />
+              <span className="text-sm text-slate-700 dark:text-slate-300">Decimalna vejica (1.234,56)</span>
+            </label>
+          </div>
+
+          {/* Column mapping */}
+          <div className="space-y-3">
+            <div>
+              <div className="text-sm font-medium text-slate-900 dark:text-slate-200">Stolpci</div>
+              <p className="text-xs text-slate-500">
+                Vpišite besedilo glave stolpca ali njegovo zaporedno številko (1 je prvi stolpec). Znesek je v enem stolpcu
+                s predznakom ali v stolpcih dobro in breme.
+              </p>
+            </div>
+            <div className="grid grid-cols-1 sm:grid-cols-2 gap-3">
+              {COLUMN_FIELDS.map(({ key, label, required }) => (
+                <div key={key} className="space-y-1">
+                  <Label htmlFor={`csvColumn-${key}`}>
+                    {label} {required && <span className="text-red-500">*</span>}
+                  </Label>
+                  <Input
+                    id={`csvColumn-${key}`}
+                    value={draft.columns[key] ?? ''}
+                    onChange={(e) => setColumn(key, e.target.value)}
+                    required={required}
+                  />
+                </div>
+              ))}
+            </div>
+            {!hasAmountColumn && (
+              <p className="text-xs text-red-600 dark:text-red-400">Določite stolpec z zneskom ali stolpca dobro in breme.</p>
+            )}
+          </div>
+
+          {/* Actions */}
+          <div className="flex justify-between gap-3 pt-4 border-t border-slate-200 dark:border-slate-700">
+            <div>
+              {profile?.id && onDelete && (
+                <Button
+                  type="button"
+                  variant="outline"
+                  onClick={() => onDelete(profile.id as string)}
+                  className="text-red-600 hover:text-red-700 hover:bg-red-50 dark:text-red-400 dark:hover:text-red-300 dark:hover:bg-red-950/30"
+                >
+                  <Trash2 className="w-4 h-4 mr-2" />
+                  Izbriši profil
+                </Button>
+              )}
+            </div>
+            <div className="flex gap-3">
+              <Button type="button" variant="outline" onClick={() => onOpenChange(false)}>
+                Prekliči
+              </Button>
+              <Button type="submit" className="bg-blue-600 hover:bg-blue-700" disabled={!hasAmountColumn}>
+                {profile ? 'Shrani spremembe' : 'Dodaj profil'}
+              </Button>
+            </div>
+          </div>
+        </form>
+      </DialogContent>
+    </Dialog>
+  )
+}
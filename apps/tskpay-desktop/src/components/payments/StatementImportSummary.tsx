@@ -0,0 +1,82 @@
+import type { StatementImport } from '@/data/database'
+import { Button } from '@/components/ui'
+import { CheckCircle2, AlertCircle, X } from 'lucide-react'
+
+export interface StatementImportSummaryProps {
+  result: StatementImport
+  onClose?: () => void
+}
+
+export function StatementImportSummary({ result, onClose }: StatementImportSummaryProps) {
+  const formatDate = (dateString: string) => {
+    const date = new Date(dateString)
+    return date.toLocaleDateString('sl-SI')
+  }
+
+  const hasWarnings = result.skippedDuplicates.length > 0 || result.unparsedLines.length > 0
+
+  return (
+    <div
+      className={
+        hasWarnings
+          ? 'rounded-lg border p-4 space-y-3 bg-amber-50 border-amber-200 dark:bg-amber-950/20 dark:border-amber-800'
+          : 'rounded-lg border p-4 space-y-3 bg-green-50 border-green-200 dark:bg-green-950/20 dark:border-green-800'
+      }
+    >
+      <div className="flex items-start justify-between gap-4">
+        <div className="flex items-center gap-2 text-sm font-medium text-slate-900 dark:text-slate-100">
+          {hasWarnings ? (
+            <AlertCircle className="w-4 h-4 text-amber-600 dark:text-amber-400" />
+          ) : (
+            <CheckCircle2 className="w-4 h-4 text-green-600 dark:text-green-400" />
+          )}
+          Uvoženih {result.imported} prilivov ({result.messageType}), od tega {result.matched} povezanih s starši
+          {result.skippedDebits > 0 && `, ${result.skippedDebits} odlivov ni uvoženih`}
+        </div>
+        {onClose && (
+          <Button size="sm" variant="outline" onClick={onClose} title="Zapri">
+            <X className="w-3 h-3" />
+          </Button>
+        )}
+      </div>
+
+      {result.skippedDuplicates.length > 0 && (
+        <div>
+          <div className="text-sm text-slate-700 dark:text-slate-300 mb-1">
+            Že uvoženi prilivi, ki niso bili uvoženi ponovno ({result.skippedDuplicates.length}):
+          </div>
+          <ul className="text-xs text-slate-600 dark:text-slate-400 space-y-0.5">
+            {result.skippedDuplicates.map((duplicate) => (
+              <li key={`${duplicate.existingId}-${duplicate.bookingDate}-${duplicate.amount}`}>
+                {formatDate(duplicate.bookingDate)} · {duplicate.amount.toFixed(2)} € ·{' '}
+                {duplicate.payerName || 'Neznan plačnik'}
+                {duplicate.reference && ` · ${duplicate.reference}`} —{' '}
+                {duplicate.matchedOn === 'id'
+                  ? 'že uvožen iz tega računa'
+                  : duplicate.matchedOn === 'bankReference'
+                    ? 'enaka bančna referenca'
+                    : 'enak datum, znesek, plačnik in sklic'}
+              </li>
+            ))}
+          </ul>
+        </div>
+      )}
+
+      {result.unparsedLines.length > 0 && (
+        <div>
+          <div className="text-sm text-slate-700 dark:text-slate-300 mb-1">
+            Vrstice, ki jih ni bilo mogoče prebrati in jih preverite ročno ({result.unparsedLines.length}):
+          </div>
+          <ul className="text-xs text-slate-600 dark:text-slate-400 space-y-0.5">
+            {result.unparsedLines.map((line) => (
+              <li key={line.lineNumber}>
+                <span className="font-medium">{line.lineNumber}. vrstica:</span>{' '}
+                <span className="font-mono">{line.text}</span> — {line.reason}
+              </li>
+            ))}
+          </ul>
+        </div>
+      )}
+    </div>
+  )
+}
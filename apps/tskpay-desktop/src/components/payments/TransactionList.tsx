@@ -1,8 +1,9 @@
 import { useMemo } from 'react'
 import type { BankStatement, BankTransaction, Parent } from '@/types'
+import type { MatchCandidate } from '@/data/database'
 import { TransactionRow } from './TransactionRow'
 import { Button, Select, DateInput, Checkbox } from '@/components/ui'
-import { ArrowLeft, Filter } from 'lucide-react'
+import { ArrowLeft, Filter, Repeat } from 'lucide-react'
 
 export interface TransactionListProps {
   bankStatements: BankStatement[]
@@ -16,8 +17,16 @@ export interface TransactionListProps {
   dateTo?: string
   hideConfirmed?: boolean
   onCloseStatement?: () => void
-  onUpdateTransactionMatch?: (transactionId: string, parentId: string | null) => void
+  onUpdateTransactionMatch?: (
+    transactionId: string,
+    parentId: string | null,
+    confidence?: BankTransaction['matchConfidence']
+  ) => void
   onConfirmTransaction?: (transactionId: string) => void
+  /** Parents the matching engine proposes for a transaction, with the reasons */
+  onLoadMatchCandidates?: (transactionId: string) => Promise<MatchCandidate[]>
+  /** Run the matching engine again over the unmatched transactions of the statement */
+  onRematchStatement?: () => void
   onTransactionStatusFilterChange?: (status: 'matched' | 'unmatched' | 'confirmed' | 'all') => void
   onStatementFilterChange?: (statementId: string | undefined) => void
   onParentFilterChange?: (parentId: string | undefined) => void
@@ -40,6 +49,8 @@ export function TransactionList({
   onCloseStatement,
   onUpdateTransactionMatch,
   onConfirmTransaction,
+  onLoadMatchCandidates,
+  onRematchStatement,
   onTransactionStatusFilterChange,
   onStatementFilterChange,
   onParentFilterChange,
@@ -122,6 +133,12 @@ export function TransactionList({
             )}
           </div>
         </div>
+        {selectedStatement && onRematchStatement && (
+          <Button variant="outline" onClick={onRematchStatement} title="Ponovno poišči starše za neujemajoče transakcije">
+            <Repeat className="w-4 h-4 mr-2" />
+            Poveži neujemajoče
+          </Button>
+        )}
       </div>
 
       <div className="grid grid-cols-2 md:grid-cols-4 gap-4">
@@ -305,6 +322,7 @@ export function TransactionList({
                     parents={parents}
                     onUpdateMatch={onUpdateTransactionMatch}
                     onConfirm={onConfirmTransaction}
+                    onLoadCandidates={onLoadMatchCandidates}
                   />
                 ))
               )}
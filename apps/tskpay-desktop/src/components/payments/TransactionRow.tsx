@@ -1,13 +1,21 @@
+import { useState } from 'react'
 import type { BankTransaction, Parent } from '@/types'
+import type { MatchCandidate } from '@/data/database'
 import { Badge, Button, Select } from '@/components/ui'
-import { CheckCircle2, AlertCircle } from 'lucide-react'
+import { CheckCircle2, AlertCircle, Search } from 'lucide-react'
 import { cn } from '@/lib/utils'
 
 interface TransactionRowProps {
   transaction: BankTransaction
   parents?: Parent[]
-  onUpdateMatch?: (transactionId: string, parentId: string | null) => void
+  onUpdateMatch?: (
+    transactionId: string,
+    parentId: string | null,
+    confidence?: BankTransaction['matchConfidence']
+  ) => void
   onConfirm?: (transactionId: string) => void
+  /** Parents the matching engine proposes for the transaction, best first */
+  onLoadCandidates?: (transactionId: string) => Promise<MatchCandidate[]>
 }
 
 export function TransactionRow({
@@ -15,8 +23,31 @@ export function TransactionRow({
   parents = [],
   onUpdateMatch,
   onConfirm,
+  onLoadCandidates,
 }: TransactionRowProps) {
   const matchedParent = parents.find((p) => p.id === transaction.matchedParentId)
+  const [candidates, setCandidates] = useState<MatchCandidate[] | null>(null)
+  const [isLoadingCandidates, setIsLoadingCandidates] = useState(false)
+
+  const toggleCandidates = async () => {
+    if (candidates) {
+      setCandidates(null)
+      return
+    }
+    if (!onLoadCandidates) return
+    setIsLoadingCandidates(true)
+    try {
+      setCandidates(await onLoadCandidates(transaction.id))
+    } catch (error) {
+      console.error('Error loading match candidates:', error)
+      alert(`Napaka pri iskanju predlogov: ${typeof error === 'string' ? error : 'Neznana napaka'}`)
+    } finally {
+      setIsLoadingCandidates(false)
+    }
+  }
+
+  const confidenceLabel = (confidence: MatchCandidate['confidence']) =>
+    confidence === 'high' ? 'Visoka' : confidence === 'medium' ? 'Srednja' : 'Nizka'
 
   const getStatusBadge = () => {
     switch (transaction.status) {
@@ -76,66 +107,121 @@ export function TransactionRow({
   }
 
   return (
-    <tr className={cn('border-b border-slate-200 dark:border-slate-800 hover:bg-slate-50 dark:hover:bg-slate-900/50 transition-colors', getRowBgColor())}>
-      <td className="px-4 py-3 text-sm">
-        {formatDate(transaction.transactionDate)}
-      </td>
-      <td className="px-4 py-3 text-sm font-medium">
-        {formatAmount(transaction.amount)}
-      </td>
-      <td className="px-4 py-3 text-sm text-slate-600 dark:text-slate-400">
-        {transaction.description}
-      </td>
-      <td className="px-4 py-3 text-sm text-slate-600 dark:text-slate-300">
-        {transaction.payerName || 'Neznan'}
-      </td>
-      <td className="px-4 py-3">
-        <div className="flex items-center gap-2">
-          <Select
-            value={transaction.matchedParentId || ''}
-            onValueChange={(value) => {
-              onUpdateMatch?.(transaction.id, value || null)
-            }}
-            className="min-w-[180px]"
-            disabled={transaction.status === 'confirmed'}
-          >
-            <option value="">-- Izberi starša --</option>
-            {parents.map((parent) => (
-              <option key={parent.id} value={parent.id}>
-                {parent.firstName} {parent.lastName}
-              </option>
-            ))}
-          </Select>
-          {matchedParent && transaction.matchConfidence && (
-            <span className="text-xs text-slate-500 dark:text-slate-400">
-              ({transaction.matchConfidence === 'high' ? 'Visoka' : transaction.matchConfidence === 'medium' ? 'Srednja' : 'Nizka'} zaupanja)
-            </span>
-          )}
-        </div>
-      </td>
-      <td className="px-4 py-3">
-        {getStatusBadge()}
-      </td>
-      <td className="px-4 py-3">
-        <div className="flex items-center gap-2">
-          {transaction.status !== 'confirmed' && (
-            <Button
-              size="sm"
-              variant="outline"
-              onClick={() => onConfirm?.(transaction.id)}
-              disabled={!transaction.matchedParentId}
+    <>
+      <tr className={cn('border-b border-slate-200 dark:border-slate-800 hover:bg-slate-50 dark:hover:bg-slate-900/50 transition-colors', getRowBgColor())}>
+        <td className="px-4 py-3 text-sm">
+          {formatDate(transaction.transactionDate)}
+        </td>
+        <td className="px-4 py-3 text-sm font-medium">
+          {formatAmount(transaction.amount)}
+        </td>
+        <td className="px-4 py-3 text-sm text-slate-600 dark:text-slate-400">
+          {transaction.description}
+        </td>
+        <td className="px-4 py-3 text-sm text-slate-600 dark:text-slate-300">
+          {transaction.payerName || 'Neznan'}
+        </td>
+        <td className="px-4 py-3">
+          <div className="flex items-center gap-2">
+            <Select
+              value={transaction.matchedParentId || ''}
+              onValueChange={(value) => {
+                onUpdateMatch?.(transaction.id, value || null)
+              }}
+              className="min-w-[180px]"
+              disabled={transaction.status === 'confirmed'}
             >
-              <CheckCircle2 className="w-4 h-4 mr-1" />
-              Potrdi
-            </Button>
-          )}
-          {transaction.status === 'confirmed' && (
-            <span className="text-xs text-slate-500 dark:text-slate-400">
-              Potrjeno
-            </span>
-          )}
-        </div>
-      </td>
-    </tr>
+              <option value="">-- Izberi starša --</option>
+              {parents.map((parent) => (
+                <option key={parent.id} value={parent.id}>
+                  {parent.firstName} {parent.lastName}
+                </option>
+              ))}
+            </Select>
+            {matchedParent && transaction.matchConfidence && (
+              <span className="text-xs text-slate-500 dark:text-slate-400">
+                ({confidenceLabel(transaction.matchConfidence)} zaupanja)
+              </span>
+            )}
+          </div>
+        </td>
+        <td className="px-4 py-3">
+          {getStatusBadge()}
+        </td>
+        <td className="px-4 py-3">
+          <div className="flex items-center gap-2">
+            {transaction.status !== 'confirmed' && onLoadCandidates && (
+              <Button
+                size="sm"
+                variant="outline"
+                onClick={toggleCandidates}
+                disabled={isLoadingCandidates}
+                title="Predlagani starši z razlogi"
+              >
+                <Search className="w-4 h-4 mr-1" />
+                Predlogi
+              </Button>
+            )}
+            {transaction.status !== 'confirmed' && (
+              <Button
+                size="sm"
+                variant="outline"
+                onClick={() => onConfirm?.(transaction.id)}
+                disabled={!transaction.matchedParentId}
+              >
+                <CheckCircle2 className="w-4 h-4 mr-1" />
+                Potrdi
+              </Button>
+            )}
+            {transaction.status === 'confirmed' && (
+              <span className="text-xs text-slate-500 dark:text-slate-400">
+                Potrjeno
+              </span>
+            )}
+          </div>
+        </td>
+      </tr>
+      {candidates && (
+        <tr className="border-b border-slate-200 dark:border-slate-800 bg-slate-50 dark:bg-slate-900/50">
+          <td colSpan={7} className="px-4 py-3">
+            {candidates.length === 0 ? (
+              <p className="text-sm text-slate-500 dark:text-slate-400">Ni predlogov za to transakcijo.</p>
+            ) : (
+              <ul className="space-y-2">
+                {candidates.map((candidate) => (
+                  <li key={`${candidate.parentId}-${candidate.memberId ?? ''}`} className="flex items-start justify-between gap-4">
+                    <div className="text-sm">
+                      <div className="font-medium text-slate-900 dark:text-slate-100">
+                        {candidate.parentName}
+                        {candidate.memberName && ` (${candidate.memberName})`}
+                        <span className="ml-2 text-xs font-normal text-slate-500 dark:text-slate-400">
+                          {candidate.score} % · {confidenceLabel(candidate.confidence)} zaupanja
+                        </span>
+                      </div>
+                      <ul className="text-xs text-slate-600 dark:text-slate-400 list-disc ml-4">
+                        {candidate.reasons.map((reason) => (
+                          <li key={reason}>{reason}</li>
+                        ))}
+                      </ul>
+                    </div>
+                    <Button
+                      size="sm"
+                      variant="outline"
+                      onClick={() => {
+                        onUpdateMatch?.(transaction.id, candidate.parentId, candidate.confidence)
+                        setCandidates(null)
+                      }}
+                      disabled={candidate.parentId === transaction.matchedParentId}
+                    >
+                      Izberi
+                    </Button>
+                  </li>
+                ))}
+              </ul>
+            )}
+          </td>
+        </tr>
+      )}
+    </>
   )
 }
@@ -1,9 +1,15 @@
 export { TransactionList } from './TransactionList'
 export { TransactionRow } from './TransactionRow'
 export { BankStatementList } from './BankStatementList'
+export { StatementImportSummary } from './StatementImportSummary'
+export { CsvProfileForm } from './CsvProfileForm'
+export { CsvPreviewDialog } from './CsvPreviewDialog'
 export { PaymentForm } from './PaymentForm'
 export { PaymentAllocationDialog } from './PaymentAllocationDialog'
 export type { TransactionListProps } from './TransactionList'
-export type { BankStatementListProps } from './BankStatementList'
+export type { BankStatementListProps, StatementFormat } from './BankStatementList'
+export type { StatementImportSummaryProps } from './StatementImportSummary'
+export type { CsvProfileFormProps } from './CsvProfileForm'
+export type { CsvPreviewDialogProps } from './CsvPreviewDialog'
 export type { PaymentFormProps } from './PaymentForm'
 export type { PaymentAllocationDialogProps } from './PaymentAllocationDialog'
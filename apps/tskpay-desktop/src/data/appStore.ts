@@ -207,6 +207,12 @@ export const appStore = {
     await initializeStore()
   },
 
+  // Load everything again after the backend wrote to the database itself, e.g. a bank import
+  async reload(): Promise<void> {
+    isInitialized = false
+    await initializeStore()
+  },
+
   // Get current state
   getState(): AppState {
     ensureInitialized()
@@ -1,4 +1,5 @@
 import { invoke } from '@tauri-apps/api/core'
+import { listen, type UnlistenFn } from '@tauri-apps/api/event'
 
 export type EntityType =
   | 'parents'
@@ -15,6 +16,379 @@ export type EntityType =
 
 export type EntityData = Record<string, unknown>
 
+/**
+ * Table description derived from the live database
+ */
+export interface TableSchema {
+  name: string
+  columns: {
+    name: string
+    dataType: string
+    notNull: boolean
+    defaultValue: string | null
+    primaryKey: boolean
+    allowedValues: string[] | null
+  }[]
+  foreignKeys: {
+    column: string
+    referencesTable: string
+    referencesColumn: string
+    onUpdate: string
+    onDelete: string
+  }[]
+}
+
+/**
+ * File attached to an entity (bank statement, receipt, membership form, ...)
+ */
+export interface Attachment {
+  id: string
+  entityType: string
+  entityId: string
+  kind: 'bank_statement' | 'receipt' | 'membership_form' | 'medical_certificate' | 'other'
+  fileName: string
+  mimeType: string | null
+  size: number
+  sha256: string
+  notes: string
+  createdAt: string
+  updatedAt: string
+}
+
+/**
+ * Automatic backup schedule and grandfather-father-son retention
+ */
+export interface BackupSettings {
+  enabled: boolean
+  keepDaily: number
+  keepWeekly: number
+  keepMonthly: number
+  /** Directories (USB stick, network share) every automatic backup is also copied to */
+  mirrorDirs: string[]
+}
+
+/**
+ * Copy of a backup in one mirror directory; also the payload of the `backup-warning` event
+ */
+export interface MirrorCopy {
+  dir: string
+  path: string | null
+  error: string | null
+  /** The copy is fine, but old backups in the directory could not be deleted */
+  pruneError: string | null
+}
+
+export interface BackupRun {
+  path: string
+  mirrors: MirrorCopy[]
+}
+
+/**
+ * Local backup file from the backup catalogue
+ */
+export interface BackupInfo {
+  path: string
+  fileName: string
+  kind: 'auto' | 'before-import' | 'before-restore' | 'before-merge' | 'other'
+  createdAt: string
+  sizeBytes: number
+  schemaVersion: number | null
+  recordCounts: Record<string, number>
+  error: string | null
+}
+
+/**
+ * Manifest of a .tskpay backup archive
+ */
+export interface ArchiveManifest {
+  format: string
+  formatVersion: number
+  appVersion: string
+  schemaVersion: number
+  createdAt: string
+  /** Also readable in archives with a password */
+  clubName: string
+  encrypted: boolean
+  files: { path: string; size: number; sha256: string }[]
+}
+
+export type MergeSide = 'local' | 'incoming'
+
+/**
+ * Result of comparing another tskpay database with ours, before anything is merged
+ */
+export interface MergePreview {
+  sourcePath: string
+  additions: { table: string; id: string; row: Record<string, unknown> }[]
+  conflicts: {
+    table: string
+    id: string
+    local: Record<string, unknown>
+    incoming: Record<string, unknown>
+    changedColumns: string[]
+    proposed: MergeSide
+  }[]
+  unchanged: number
+}
+
+export interface MergeResolution {
+  table: string
+  id: string
+  resolution: MergeSide
+}
+
+export interface MergeResult {
+  added: number
+  updated: number
+  keptLocal: number
+  skipped: number
+  backupPath: string
+}
+
+export interface RecordDiff {
+  id: string
+  label: string
+  row: Record<string, unknown>
+  fields: { column: string; before: unknown; after: unknown }[]
+}
+
+/**
+ * Table-by-table comparison of two databases
+ */
+export interface DatabaseDiff {
+  from: string
+  to: string
+  tables: {
+    table: string
+    label: string
+    added: RecordDiff[]
+    removed: RecordDiff[]
+    changed: RecordDiff[]
+  }[]
+  summary: string[]
+}
+
+export interface SyncSettings {
+  enabled: boolean
+  /** REST endpoint, e.g. https://<project>.supabase.co or a local PostgREST */
+  url: string
+  apiKey: string
+  accessToken?: string | null
+  /** Minutes between background syncs, 0 to sync only on request */
+  intervalMinutes: number
+}
+
+export interface SyncReport {
+  pushed: number
+  deleted: number
+  pulled: number
+  removed: number
+  conflicts: number
+}
+
+/**
+ * Outcome of a background sync, delivered through db.sync.onFinished
+ */
+export interface SyncRun {
+  report: SyncReport | null
+  error: string | null
+}
+
+export interface SyncStatus {
+  lastSyncAt: string | null
+  pendingChanges: number
+  openConflicts: number
+}
+
+export type SyncSide = 'local' | 'remote'
+
+/**
+ * A record edited locally and on the server; the winner is already applied
+ */
+export interface SyncConflict {
+  id: string
+  table: string
+  rowId: string
+  local: Record<string, unknown> | null
+  remote: Record<string, unknown> | null
+  fields: { column: string; before: unknown; after: unknown }[]
+  winner: SyncSide
+  detectedAt: string
+  resolution: SyncSide | null
+  resolvedAt: string | null
+}
+
+/**
+ * A bank file read by the backend parser; amounts are positive, see isCredit
+ */
+export interface ParsedStatement {
+  /** Detected from the file, e.g. camt.053.001.02, or MT940 */
+  messageType: string
+  messageId: string
+  creationDateTime: string
+  accountIban: string
+  accountOwner: string | null
+  transactions: ParsedStatementTransaction[]
+  openingBalance: StatementBalance | null
+  closingBalance: StatementBalance | null
+}
+
+/** Negative when the account is overdrawn */
+export interface StatementBalance {
+  amount: number
+  currency: string
+  date: string | null
+}
+
+export interface ParsedStatementTransaction {
+  /** Deterministic: the same file always yields the same ids */
+  id: string
+  bankReference: string | null
+  amount: number
+  currency: string
+  isCredit: boolean
+  status: string | null
+  bookingDate: string
+  valueDate: string | null
+  payerName: string | null
+  payerIban: string | null
+  description: string | null
+  reference: string | null
+  bankFee: number | null
+}
+
+/**
+ * A line of a PDF or CSV statement that looked like a transaction but could not be read
+ */
+export interface UnparsedLine {
+  lineNumber: number
+  text: string
+  reason: string
+}
+
+export interface PdfStatement {
+  /** Bank whose layout was recognised, e.g. NLB */
+  bank: string
+  statement: ParsedStatement
+  unparsedLines: UnparsedLine[]
+}
+
+export interface StatementImport {
+  statementId: string
+  messageType: string
+  /** Incoming payments stored as bank transactions */
+  imported: number
+  /** Imported payments assigned to a parent by the matching engine */
+  matched: number
+  /** Outgoing payments in the file, which are not stored */
+  skippedDebits: number
+  /** Incoming payments already stored by an earlier import */
+  skippedDuplicates: SkippedDuplicate[]
+  /** PDF or CSV lines that need checking by hand */
+  unparsedLines: UnparsedLine[]
+}
+
+export interface SkippedDuplicate {
+  bookingDate: string
+  amount: number
+  payerName: string | null
+  reference: string | null
+  /** The bank transaction it repeats */
+  existingId: string
+  /** fingerprint: same date, amount, payer IBAN and reference */
+  matchedOn: 'id' | 'bankReference' | 'fingerprint'
+}
+
+/**
+ * A parent a bank transaction may come from; `reasons` explain the score
+ */
+export interface MatchCandidate {
+  parentId: string
+  parentName: string
+  memberId: string | null
+  memberName: string | null
+  /** Open cost whose amount equals the payment */
+  costId: string | null
+  /** 0–100 */
+  score: number
+  confidence: 'high' | 'medium' | 'low'
+  reasons: string[]
+}
+
+export type ReferenceModel = 'si12' | 'rf'
+
+/** References handed out by `bank.assignReferences` */
+export interface ReferenceAssignment {
+  parents: number
+  costs: number
+}
+
+export interface MatchRun {
+  matched: number
+  unmatched: number
+}
+
+/**
+ * Which CSV column holds each field: the header text, or the 1-based column number
+ */
+export interface CsvColumns {
+  bookingDate: string | null
+  valueDate: string | null
+  /** Signed amount; leave empty when the bank uses credit and debit columns */
+  amount: string | null
+  credit: string | null
+  debit: string | null
+  currency: string | null
+  payerName: string | null
+  payerIban: string | null
+  description: string | null
+  reference: string | null
+  bankReference: string | null
+}
+
+/**
+ * How to read one bank's CSV export, saved by name
+ */
+export interface CsvProfile {
+  /** null until the profile is saved */
+  id: string | null
+  name: string
+  bank: string | null
+  accountIban: string
+  /** A single character, or \t for tab */
+  delimiter: string
+  /** e.g. windows-1250 or utf-8 */
+  encoding: string
+  decimalComma: boolean
+  /** chrono format, e.g. %d.%m.%Y */
+  dateFormat: string
+  skipRows: number
+  hasHeader: boolean
+  columns: CsvColumns
+}
+
+export interface CsvPreview {
+  /** Pass to bank.importCsv to import the previewed file */
+  sourcePath: string
+  headers: string[]
+  rows: string[][]
+  transactions: ParsedStatementTransaction[]
+  totalRows: number
+  totalTransactions: number
+  unparsedLines: UnparsedLine[]
+}
+
+/**
+ * A committed write reported by the backend
+ */
+export interface ChangeEvent {
+  seq: number
+  table: string
+  operation: 'insert' | 'update' | 'delete'
+  ids: string[]
+  changedAt: string
+}
+
 /**
  * Initialize the database
  */
@@ -45,40 +419,31 @@ export async function getById<T extends EntityData = EntityData>(
 
 /**
  * Create a new entity
+ *
+ * The backend generates the id and timestamps when they are not provided.
  */
 export async function create<T extends EntityData = EntityData>(
   table: EntityType,
   data: Omit<T, 'id'> & { id?: string }
 ): Promise<T> {
-  // Generate ID if not provided
-  const id = data.id || generateId(table)
-  const entityData = {
-    ...data,
-    id,
-    created_at: data.created_at || new Date().toISOString(),
-    updated_at: data.updated_at || new Date().toISOString(),
-  }
-  
   const result = await invoke<EntityData>('db_create', {
     table,
-    data: entityData,
+    data,
   })
   return result as T
 }
 
 /**
  * Update an existing entity
+ *
+ * The backend bumps updated_at.
  */
 export async function update<T extends EntityData = EntityData>(
   table: EntityType,
   id: string,
   patch: Partial<T>
 ): Promise<void> {
-  const updateData = {
-    ...patch,
-    updated_at: new Date().toISOString(),
-  }
-  await invoke('db_update', { table, id, data: updateData })
+  await invoke('db_update', { table, id, data: patch })
 }
 
 /**
@@ -88,36 +453,6 @@ export async function remove(table: EntityType, id: string): Promise<void> {
   await invoke('db_delete', { table, id })
 }
 
-/**
- * Generate a unique ID for an entity
- */
-function generateId(table: EntityType): string {
-  const prefix = getTablePrefix(table)
-  const timestamp = Date.now()
-  const random = Math.random().toString(36).substring(2, 9)
-  return `${prefix}-${timestamp}-${random}`
-}
-
-/**
- * Get table prefix for ID generation
- */
-function getTablePrefix(table: EntityType): string {
-  const prefixes: Record<EntityType, string> = {
-    parents: 'par',
-    coaches: 'coa',
-    groups: 'grp',
-    members: 'mem',
-    cost_types: 'ct',
-    costs: 'cost',
-    payments: 'pay',
-    bank_statements: 'stmt',
-    bank_transactions: 'txn',
-    payment_allocations: 'alloc',
-    audit_log: 'audit',
-  }
-  return prefixes[table]
-}
-
 /**
  * Database service with entity-specific methods
  */
@@ -253,15 +588,220 @@ export const db = {
     },
   },
 
+  // Schema introspection
+  getSchema: async (): Promise<TableSchema[]> => {
+    return await invoke<TableSchema[]>('db_schema')
+  },
+
+  // Change notifications
+  changes: {
+    getSince: async (since: number): Promise<ChangeEvent[]> => {
+      return await invoke<ChangeEvent[]>('db_get_changes_since', { since })
+    },
+    subscribe: (handler: (change: ChangeEvent) => void): Promise<UnlistenFn> => {
+      return listen<ChangeEvent>('db-change', (event) => handler(event.payload))
+    },
+  },
+
+  // Attachments
+  attachments: {
+    upload: async (
+      entityType: EntityType,
+      entityId: string,
+      kind?: Attachment['kind'],
+      filePath?: string
+    ): Promise<Attachment> => {
+      return await invoke<Attachment>('attachment_upload', { entityType, entityId, kind, filePath })
+    },
+    list: async (entityType: EntityType, entityId: string): Promise<Attachment[]> => {
+      return await invoke<Attachment[]>('attachment_list', { entityType, entityId })
+    },
+    open: async (id: string): Promise<void> => {
+      await invoke('attachment_open', { id })
+    },
+    export: async (id: string): Promise<string> => {
+      return await invoke<string>('attachment_export', { id })
+    },
+    delete: async (id: string): Promise<void> => {
+      await invoke('attachment_delete', { id })
+    },
+  },
+
   // Database backup and restore
-  exportDatabase: async (): Promise<string> => {
-    return await invoke<string>('export_database')
+  exportDatabase: async (password?: string, clubName?: string): Promise<string> => {
+    return await invoke<string>('export_database', { password, clubName })
+  },
+  importDatabase: async (password?: string): Promise<string> => {
+    return await invoke<string>('import_database', { password })
+  },
+  exportDatabaseTo: async (path: string, password?: string, clubName?: string): Promise<string> => {
+    return await invoke<string>('export_database_to', { path, password, clubName })
+  },
+  importDatabaseFrom: async (path: string, password?: string): Promise<string> => {
+    return await invoke<string>('import_database_from', { path, password })
+  },
+  /** Compare two databases; an omitted path means the current database */
+  diffDatabases: async (from?: string, to?: string): Promise<DatabaseDiff> => {
+    return await invoke<DatabaseDiff>('database_diff', { from, to })
+  },
+  diffDatabasesReport: async (from?: string, to?: string): Promise<string> => {
+    return await invoke<string>('database_diff_report', { from, to })
+  },
+  /** Export for PostgreSQL/Supabase: one SQL script, or a folder of CSV files with a psql load script */
+  exportPostgres: async (format: 'sql' | 'csv'): Promise<string> => {
+    return await invoke<string>('export_postgres', { format })
+  },
+  exportPostgresTo: async (format: 'sql' | 'csv', path: string): Promise<string> => {
+    return await invoke<string>('export_postgres_to', { format, path })
+  },
+  getArchiveManifest: async (path: string): Promise<ArchiveManifest> => {
+    return await invoke<ArchiveManifest>('archive_manifest', { path })
+  },
+  exportJsonDump: async (): Promise<string> => {
+    return await invoke<string>('export_json_dump')
   },
-  importDatabase: async (): Promise<string> => {
-    return await invoke<string>('import_database')
+  importJsonDump: async (): Promise<string> => {
+    return await invoke<string>('import_json_dump')
+  },
+  exportJsonDumpTo: async (path: string): Promise<string> => {
+    return await invoke<string>('export_json_dump_to', { path })
+  },
+  importJsonDumpFrom: async (path: string): Promise<string> => {
+    return await invoke<string>('import_json_dump_from', { path })
+  },
+  mergePreview: async (path?: string, password?: string): Promise<MergePreview> => {
+    return await invoke<MergePreview>('merge_preview', { path, password })
+  },
+  mergeApply: async (path: string, resolutions: MergeResolution[], password?: string): Promise<MergeResult> => {
+    return await invoke<MergeResult>('merge_apply', { path, password, resolutions })
+  },
+  getBackupSettings: async (): Promise<BackupSettings> => {
+    return await invoke<BackupSettings>('get_backup_settings')
+  },
+  setBackupSettings: async (settings: BackupSettings): Promise<BackupSettings> => {
+    return await invoke<BackupSettings>('set_backup_settings', { settings })
+  },
+  runBackupNow: async (): Promise<BackupRun> => {
+    return await invoke<BackupRun>('run_backup_now')
+  },
+  /** Warnings of the last automatic backup, e.g. the startup one taken before the window listened */
+  backupWarnings: async (): Promise<MirrorCopy[]> => {
+    return await invoke<MirrorCopy[]>('backup_warnings')
+  },
+  onBackupWarning: (handler: (warning: MirrorCopy) => void): Promise<UnlistenFn> => {
+    return listen<MirrorCopy>('backup-warning', (event) => handler(event.payload))
+  },
+  sync: {
+    getSettings: async (): Promise<SyncSettings> => {
+      return await invoke<SyncSettings>('get_sync_settings')
+    },
+    setSettings: async (settings: SyncSettings): Promise<SyncSettings> => {
+      return await invoke<SyncSettings>('set_sync_settings', { settings })
+    },
+    now: async (): Promise<SyncReport> => {
+      return await invoke<SyncReport>('sync_now')
+    },
+    status: async (): Promise<SyncStatus> => {
+      return await invoke<SyncStatus>('sync_get_status')
+    },
+    conflicts: async (includeResolved = false): Promise<SyncConflict[]> => {
+      return await invoke<SyncConflict[]>('sync_list_conflicts', { includeResolved })
+    },
+    resolveConflict: async (id: string, keep: SyncSide): Promise<void> => {
+      await invoke('sync_resolve_conflict', { id, keep })
+    },
+    onFinished: (handler: (run: SyncRun) => void): Promise<UnlistenFn> => {
+      return listen<SyncRun>('sync-finished', (event) => handler(event.payload))
+    },
+  },
+  bank: {
+    /** camt.052, camt.053 or camt.054, detected from the file */
+    parseXml: async (): Promise<ParsedStatement> => {
+      return await invoke<ParsedStatement>('bank_parse_xml')
+    },
+    parseXmlFrom: async (path: string): Promise<ParsedStatement> => {
+      return await invoke<ParsedStatement>('bank_parse_xml_from', { path })
+    },
+    importXml: async (): Promise<StatementImport> => {
+      return await invoke<StatementImport>('bank_import_xml')
+    },
+    importXmlFrom: async (path: string): Promise<StatementImport> => {
+      return await invoke<StatementImport>('bank_import_xml_from', { path })
+    },
+    /** NLB, NKBM/OTP, SKB and Delavska hranilnica layouts */
+    parsePdf: async (): Promise<PdfStatement> => {
+      return await invoke<PdfStatement>('bank_parse_pdf')
+    },
+    parsePdfFrom: async (path: string): Promise<PdfStatement> => {
+      return await invoke<PdfStatement>('bank_parse_pdf_from', { path })
+    },
+    importPdf: async (): Promise<StatementImport> => {
+      return await invoke<StatementImport>('bank_import_pdf')
+    },
+    importPdfFrom: async (path: string): Promise<StatementImport> => {
+      return await invoke<StatementImport>('bank_import_pdf_from', { path })
+    },
+    /** SWIFT MT940; several statements for one account are merged */
+    parseMt940: async (): Promise<ParsedStatement> => {
+      return await invoke<ParsedStatement>('bank_parse_mt940')
+    },
+    parseMt940From: async (path: string): Promise<ParsedStatement> => {
+      return await invoke<ParsedStatement>('bank_parse_mt940_from', { path })
+    },
+    importMt940: async (): Promise<StatementImport> => {
+      return await invoke<StatementImport>('bank_import_mt940')
+    },
+    importMt940From: async (path: string): Promise<StatementImport> => {
+      return await invoke<StatementImport>('bank_import_mt940_from', { path })
+    },
+    /** Without a path the user picks the file */
+    previewCsv: async (profile: CsvProfile, path?: string, limit?: number): Promise<CsvPreview> => {
+      return await invoke<CsvPreview>('bank_csv_preview', { path, profile, limit })
+    },
+    importCsv: async (path: string, profile: CsvProfile): Promise<StatementImport> => {
+      return await invoke<StatementImport>('bank_import_csv', { path, profile })
+    },
+    csvProfiles: async (): Promise<CsvProfile[]> => {
+      return await invoke<CsvProfile[]>('bank_csv_profiles')
+    },
+    saveCsvProfile: async (profile: CsvProfile): Promise<CsvProfile> => {
+      return await invoke<CsvProfile>('bank_csv_profile_save', { profile })
+    },
+    deleteCsvProfile: async (id: string): Promise<void> => {
+      await invoke('bank_csv_profile_delete', { id })
+    },
+    /** Best candidates first, 5 unless `limit` is given */
+    matchCandidates: async (transactionId: string, limit?: number): Promise<MatchCandidate[]> => {
+      return await invoke<MatchCandidate[]>('bank_match_candidates', { transactionId, limit })
+    },
+    /** Gives parents, and with `includeCosts` open costs, without a reference the next free one */
+    assignReferences: async (model: ReferenceModel, includeCosts = false): Promise<ReferenceAssignment> => {
+      return await invoke<ReferenceAssignment>('bank_assign_references', { model, includeCosts })
+    },
+    /** Matches unmatched transactions of the statement, or of all statements */
+    matchTransactions: async (statementId?: string): Promise<MatchRun> => {
+      return await invoke<MatchRun>('bank_match_transactions', { statementId })
+    },
+  },
+  backups: {
+    list: async (): Promise<BackupInfo[]> => {
+      return await invoke<BackupInfo[]>('backup_list')
+    },
+    restore: async (path: string): Promise<string> => {
+      return await invoke<string>('backup_restore', { path })
+    },
+    delete: async (path: string): Promise<void> => {
+      await invoke('backup_delete', { path })
+    },
+    revealFolder: async (): Promise<string> => {
+      return await invoke<string>('backup_reveal_folder')
+    },
   },
   // Save text file
   saveTextFile: async (content: string, defaultFilename: string): Promise<string> => {
     return await invoke<string>('save_text_file', { content, defaultFilename })
   },
+  saveTextFileTo: async (path: string, content: string): Promise<string> => {
+    return await invoke<string>('save_text_file_to', { path, content })
+  },
 }
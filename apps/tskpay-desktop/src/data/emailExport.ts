@@ -79,7 +79,8 @@ export function generateEmailForParent(
     if (member) {
       email += `${member.firstName} ${member.lastName}:\n`
       memberCosts.forEach((cost) => {
-        email += `  - ${cost.title}: ${cost.amount.toFixed(2)} € (rok: ${formatDate(cost.dueDate)} - PREKORAČEN)\n`
+        const reference = cost.paymentReference ? `, sklic: ${cost.paymentReference}` : ''
+        email += `  - ${cost.title}: ${cost.amount.toFixed(2)} € (rok: ${formatDate(cost.dueDate)} - PREKORAČEN${reference})\n`
         totalAmount += cost.amount
       })
       email += '\n'
@@ -87,7 +88,11 @@ export function generateEmailForParent(
   })
 
   email += `Skupni znesek odprtih obveznosti: ${totalAmount.toFixed(2)} €\n\n`
-  email += `Prosimo vas, da obveznosti poravnate v najkrajšem možnem času.\n\n`
+  email += `Prosimo vas, da obveznosti poravnate v najkrajšem možnem času.\n`
+  if (parent.paymentReference) {
+    email += `Pri plačilu navedite sklic ${parent.paymentReference}, pri posameznem strošku pa njegov sklic, če ga ima.\n`
+  }
+  email += `\n`
   email += `Lep pozdrav,\n`
   email += `TSK JUB Dol\n\n`
   email += `---\n`
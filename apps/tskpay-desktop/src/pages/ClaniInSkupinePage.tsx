@@ -4,6 +4,9 @@ import type { MemberFormSaveData } from '@/components/members/MemberForm'
 import { Tabs, TabsList, TabsTrigger } from '@/components/ui'
 import type { Member, Parent, Coach } from '@/types'
 import { useMembers, useParents, useCoaches, useGroups } from '@/data/useAppStore'
+import { db } from '@/data/database'
+import type { ReferenceModel } from '@/data/database'
+import { appStore } from '@/data/appStore'
 
 export function ClaniInSkupinePage() {
   const [mode, setMode] = useState<'members' | 'parents' | 'coaches'>('members')
@@ -23,6 +26,7 @@ export function ClaniInSkupinePage() {
   const [editingParent, setEditingParent] = useState<Parent | null>(null)
   const [coachFormOpen, setCoachFormOpen] = useState(false)
   const [editingCoach, setEditingCoach] = useState<Coach | null>(null)
+  const [isAssigningReferences, setIsAssigningReferences] = useState(false)
 
   const handleCreateMember = () => {
     setEditingMember(null)
@@ -82,6 +86,25 @@ export function ClaniInSkupinePage() {
     setParentFormOpen(true)
   }
 
+  const handleAssignReferences = async (model: ReferenceModel, includeCosts: boolean) => {
+    setIsAssigningReferences(true)
+    try {
+      const assignment = await db.bank.assignReferences(model, includeCosts)
+      // The backend wrote the references itself
+      await appStore.reload()
+      alert(
+        includeCosts
+          ? `Dodeljenih ${assignment.parents} sklicev staršem in ${assignment.costs} odprtim stroškom.`
+          : `Dodeljenih ${assignment.parents} sklicev staršem.`
+      )
+    } catch (error) {
+      console.error('Error assigning references:', error)
+      alert(`Napaka pri dodeljevanju sklicev: ${typeof error === 'string' ? error : 'Neznana napaka'}`)
+    } finally {
+      setIsAssigningReferences(false)
+    }
+  }
+
   const handleDeleteParent = (id: string) => {
     const memberCount = members.filter((m) => {
       const memberParentIds = m.parentIds && m.parentIds.length > 0
@@ -255,9 +278,11 @@ export function ClaniInSkupinePage() {
             parents={parents}
             members={members}
             searchFilter={searchFilter}
+            isAssigningReferences={isAssigningReferences}
             onEditParent={handleEditParent}
             onDeleteParent={handleDeleteParent}
             onSearchChange={setSearchFilter}
+            onAssignReferences={handleAssignReferences}
           />
         )}
 
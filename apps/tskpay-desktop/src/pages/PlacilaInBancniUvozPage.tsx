@@ -1,7 +1,19 @@
-import { useState, useMemo, useRef } from 'react'
-import { TransactionList, BankStatementList, PaymentForm, PaymentAllocationDialog } from '@/components/payments'
+import { useState, useMemo, useRef, useEffect } from 'react'
+import {
+  TransactionList,
+  BankStatementList,
+  StatementImportSummary,
+  CsvProfileForm,
+  CsvPreviewDialog,
+  PaymentForm,
+  PaymentAllocationDialog,
+} from '@/components/payments'
+import type { StatementFormat } from '@/components/payments'
 import { Button, Badge, Tabs, TabsList, TabsTrigger, Select, DateInput, ConfirmDialog } from '@/components/ui'
-import type { Payment } from '@/types'
+import type { BankTransaction, Payment } from '@/types'
+import { db } from '@/data/database'
+import type { CsvPreview, CsvProfile, StatementImport } from '@/data/database'
+import { appStore } from '@/data/appStore'
 import { 
   useBankStatements, 
   useBankTransactions, 
@@ -12,13 +24,12 @@ import {
   useCosts 
 } from '@/data/useAppStore'
 import { Plus, CreditCard, FileText, Link2, CheckCircle2, Clock, AlertCircle, Trash2 } from 'lucide-react'
-import { parseXmlFile, matchTransactionToParent } from '@/lib/xmlParser'
 
 type ViewMode = 'statements' | 'payments'
 
 export function PlacilaInBancniUvozPage() {
-  const { bankStatements: statements, create: createStatement, update: updateStatement, remove: removeStatement } = useBankStatements()
-  const { bankTransactions: transactions, create: createTransaction, update: updateTransaction, remove: removeTransaction } = useBankTransactions()
+  const { bankStatements: statements, update: updateStatement, remove: removeStatement } = useBankStatements()
+  const { bankTransactions: transactions, update: updateTransaction, remove: removeTransaction } = useBankTransactions()
   const { parents } = useParents()
   const { members } = useMembers()
   const { payments, create: createPayment, update: updatePayment, deleteWithCascade: deletePaymentWithCascade } = usePayments()
@@ -54,97 +65,112 @@ export function PlacilaInBancniUvozPage() {
   // Delete confirmation dialog state
   const [deleteConfirmStatementId, setDeleteConfirmStatementId] = useState<string | null>(null)
 
-  const handleUploadStatement = async (file: File) => {
-    // Create statement record
-    const newStatement = createStatement({
-      fileName: file.name,
-      fileType: file.name.endsWith('.pdf') ? 'pdf' : 'xml',
-      importedAt: new Date().toISOString(),
-      status: 'processing',
-      totalTransactions: 0,
-      matchedTransactions: 0,
-      unmatchedTransactions: 0,
-    })
+  // Statement import state
+  const [csvProfiles, setCsvProfiles] = useState<CsvProfile[]>([])
+  const [isImporting, setIsImporting] = useState(false)
+  const [importResult, setImportResult] = useState<StatementImport | null>(null)
+  // A CSV file is imported only after its preview is confirmed
+  const [csvPreview, setCsvPreview] = useState<{ preview: CsvPreview; profile: CsvProfile } | null>(null)
+  // undefined while the profile editor is closed, null for a new profile
+  const [editingCsvProfile, setEditingCsvProfile] = useState<CsvProfile | null | undefined>(undefined)
+
+  useEffect(() => {
+    db.bank
+      .csvProfiles()
+      .then(setCsvProfiles)
+      .catch((error) => console.error('Error loading CSV profiles:', error))
+  }, [])
+
+  // Tauri invoke errors are strings
+  const errorMessage = (error: unknown) =>
+    typeof error === 'string' ? error : error instanceof Error ? error.message : 'Neznana napaka'
+
+  const runImport = async (importFile: () => Promise<StatementImport>) => {
+    setIsImporting(true)
+    try {
+      // The backend stores the statement, skips payments that are already stored and matches the rest
+      const result = await importFile()
+      await appStore.reload()
+      setImportResult(result)
+      setCsvPreview(null)
 
+      // Automatically view the imported statement
+      setSelectedStatementId(result.statementId)
+      setViewMode('statements')
+    } catch (error) {
+      const message = errorMessage(error)
+      if (message !== 'Uvoz je bil preklican.') {
+        console.error('Error importing statement:', error)
+        alert(`Napaka pri uvozu izpiska: ${message}`)
+      }
+    } finally {
+      setIsImporting(false)
+    }
+  }
+
+  const previewCsvFile = async (csvProfile: CsvProfile) => {
+    setIsImporting(true)
     try {
-      // Only process XML files
-      if (!file.name.toLowerCase().endsWith('.xml')) {
-        alert('Trenutno je podprt samo XML format (camt.052). PDF uvoz ni na voljo.')
-        updateStatement(newStatement.id, { status: 'failed' })
-        return
+      const preview = await db.bank.previewCsv(csvProfile)
+      setCsvPreview({ preview, profile: csvProfile })
+    } catch (error) {
+      const message = errorMessage(error)
+      if (message !== 'Uvoz je bil preklican.') {
+        console.error('Error reading CSV statement:', error)
+        alert(`Napaka pri branju izpiska: ${message}`)
       }
+    } finally {
+      setIsImporting(false)
+    }
+  }
 
-      // Parse the XML file
-      const parsedStatement = await parseXmlFile(file)
-      
-      // Create transactions from parsed data
-      let matchedCount = 0
-      let unmatchedCount = 0
-      let skippedCount = 0
-      
-      for (const tx of parsedStatement.transactions) {
-        // Check if transaction already exists by bankReference
-        const existingTransaction = tx.id 
-          ? transactions.find((t) => t.bankReference === tx.id)
-          : null
-        
-        // Skip if transaction already exists (confirmed or not)
-        if (existingTransaction) {
-          skippedCount++
-          continue
-        }
-        
-        // Match transaction to parent
-        const matchResult = matchTransactionToParent(tx, parents, members)
-        
-        // Create bank transaction record
-        createTransaction({
-          bankStatementId: newStatement.id,
-          transactionDate: tx.bookingDate,
-          amount: tx.amount,
-          description: tx.description || '',
-          reference: tx.reference,
-          accountNumber: tx.payerIban || '',
-          payerName: tx.payerName || 'Neznan plačnik',
-          bankReference: tx.id || null,
-          matchedParentId: matchResult.parentId,
-          matchConfidence: matchResult.confidence,
-          status: matchResult.parentId ? 'matched' : 'unmatched',
-          paymentId: null,
-        })
+  const handleImportStatement = (format: StatementFormat, csvProfile?: CsvProfile) => {
+    if (format === 'xml') return runImport(db.bank.importXml)
+    if (format === 'pdf') return runImport(db.bank.importPdf)
+    if (format === 'mt940') return runImport(db.bank.importMt940)
+    if (csvProfile) return previewCsvFile(csvProfile)
+  }
 
-        if (matchResult.parentId) {
-          matchedCount++
-        } else {
-          unmatchedCount++
-        }
-      }
-      
-      // Update statement with final counts (only newly imported transactions)
-      updateStatement(newStatement.id, {
-        status: 'completed',
-        totalTransactions: matchedCount + unmatchedCount,
-        matchedTransactions: matchedCount,
-        unmatchedTransactions: unmatchedCount,
-      })
-      
-      // Automatically view the imported statement
-      setSelectedStatementId(newStatement.id)
-      setViewMode('statements')
-      
+  const handleConfirmCsvImport = () => {
+    if (!csvPreview) return
+    const { preview, profile } = csvPreview
+    runImport(() => db.bank.importCsv(preview.sourcePath, profile))
+  }
+
+  const handleSaveCsvProfile = async (csvProfile: CsvProfile) => {
+    try {
+      const saved = await db.bank.saveCsvProfile(csvProfile)
+      setCsvProfiles((profiles) =>
+        [...profiles.filter((p) => p.id !== saved.id), saved].sort((a, b) => a.name.localeCompare(b.name, 'sl'))
+      )
+      setEditingCsvProfile(undefined)
+    } catch (error) {
+      console.error('Error saving CSV profile:', error)
+      alert(`Napaka pri shranjevanju profila: ${errorMessage(error)}`)
+    }
+  }
+
+  const handleDeleteCsvProfile = async (profileId: string) => {
+    try {
+      await db.bank.deleteCsvProfile(profileId)
+      setCsvProfiles((profiles) => profiles.filter((p) => p.id !== profileId))
+      setEditingCsvProfile(undefined)
     } catch (error) {
-      console.error('Error parsing XML file:', error)
-      alert(`Napaka pri branju XML datoteke: ${error instanceof Error ? error.message : 'Neznana napaka'}`)
-      updateStatement(newStatement.id, { status: 'failed' })
+      console.error('Error deleting CSV profile:', error)
+      alert(`Napaka pri brisanju profila: ${errorMessage(error)}`)
     }
   }
 
-  const handleUpdateTransactionMatch = (transactionId: string, parentId: string | null) => {
+  const handleUpdateTransactionMatch = (
+    transactionId: string,
+    parentId: string | null,
+    candidateConfidence?: BankTransaction['matchConfidence']
+  ) => {
     const transaction = transactions.find((t) => t.id === transactionId)
     if (!transaction) return
 
-    // Determine confidence based on whether it was manually selected
-    const confidence = parentId ? 'low' : null
+    // A proposed candidate keeps the engine's confidence, a parent picked from the list is 'low'
+    const confidence = parentId ? candidateConfidence ?? 'low' : null
 
     updateTransaction(transactionId, {
       matchedParentId: parentId,
@@ -166,6 +192,18 @@ export function PlacilaInBancniUvozPage() {
     }
   }
 
+  const handleRematchStatement = async () => {
+    if (!selectedStatementId) return
+    try {
+      const run = await db.bank.matchTransactions(selectedStatementId)
+      await appStore.reload()
+      alert(`Povezanih ${run.matched} transakcij, ${run.unmatched} jih ostaja brez starša.`)
+    } catch (error) {
+      console.error('Error matching transactions:', error)
+      alert(`Napaka pri povezovanju transakcij: ${typeof error === 'string' ? error : 'Neznana napaka'}`)
+    }
+  }
+
   const handleConfirmTransaction = (transactionId: string) => {
     const transaction = transactions.find((t) => t.id === transactionId)
     if (!transaction || !transaction.matchedParentId) return
@@ -702,36 +740,69 @@ export function PlacilaInBancniUvozPage() {
           </div>
         </div>
       ) : selectedStatementId ? (
-        <TransactionList
-          bankStatements={statements}
-          bankTransactions={transactions}
-          parents={parents}
-          selectedStatementId={selectedStatementId}
-          transactionStatusFilter={transactionStatusFilter}
-          statementFilter={statementFilter}
-          parentFilter={parentFilter}
-          dateFrom={dateFrom}
-          dateTo={dateTo}
-          hideConfirmed={hideConfirmed}
-          onCloseStatement={() => setSelectedStatementId(undefined)}
-          onUpdateTransactionMatch={handleUpdateTransactionMatch}
-          onConfirmTransaction={handleConfirmTransaction}
-          onTransactionStatusFilterChange={setTransactionStatusFilter}
-          onStatementFilterChange={setStatementFilter}
-          onParentFilterChange={setParentFilter}
-          onDateFromChange={setDateFrom}
-          onDateToChange={setDateTo}
-          onHideConfirmedChange={setHideConfirmed}
-        />
+        <div className="space-y-4">
+          {importResult?.statementId === selectedStatementId && (
+            <StatementImportSummary result={importResult} onClose={() => setImportResult(null)} />
+          )}
+          <TransactionList
+            bankStatements={statements}
+            bankTransactions={transactions}
+            parents={parents}
+            selectedStatementId={selectedStatementId}
+            transactionStatusFilter={transactionStatusFilter}
+            statementFilter={statementFilter}
+            parentFilter={parentFilter}
+            dateFrom={dateFrom}
+            dateTo={dateTo}
+            hideConfirmed={hideConfirmed}
+            onCloseStatement={() => setSelectedStatementId(undefined)}
+            onUpdateTransactionMatch={handleUpdateTransactionMatch}
+            onConfirmTransaction={handleConfirmTransaction}
+            onLoadMatchCandidates={(transactionId) => db.bank.matchCandidates(transactionId)}
+            onRematchStatement={handleRematchStatement}
+            onTransactionStatusFilterChange={setTransactionStatusFilter}
+            onStatementFilterChange={setStatementFilter}
+            onParentFilterChange={setParentFilter}
+            onDateFromChange={setDateFrom}
+            onDateToChange={setDateTo}
+            onHideConfirmedChange={setHideConfirmed}
+          />
+        </div>
       ) : (
         <BankStatementList
           statements={statements}
+          csvProfiles={csvProfiles}
+          isImporting={isImporting}
           onViewStatement={setSelectedStatementId}
-          onUploadStatement={handleUploadStatement}
+          onImportStatement={handleImportStatement}
+          onEditCsvProfile={setEditingCsvProfile}
           onDeleteStatement={handleDeleteStatementClick}
         />
       )}
 
+      {/* CSV Profile Dialog */}
+      <CsvProfileForm
+        profile={editingCsvProfile}
+        open={editingCsvProfile !== undefined}
+        onOpenChange={(open) => {
+          if (!open) setEditingCsvProfile(undefined)
+        }}
+        onSave={handleSaveCsvProfile}
+        onDelete={handleDeleteCsvProfile}
+      />
+
+      {/* CSV Import Preview Dialog */}
+      <CsvPreviewDialog
+        preview={csvPreview?.preview ?? null}
+        profile={csvPreview?.profile ?? null}
+        open={csvPreview !== null}
+        isImporting={isImporting}
+        onOpenChange={(open) => {
+          if (!open && !isImporting) setCsvPreview(null)
+        }}
+        onConfirm={handleConfirmCsvImport}
+      />
+
       {/* Payment Form Dialog */}
       <PaymentForm
         payment={editingPayment}
@@ -107,6 +107,7 @@ function calculateMemberObligations(
         memberName: `${member.firstName} ${member.lastName}`,
         parentId: firstParentId || '',
         parentName: parentNames,
+        parentReference: firstParentId ? parentsMap.get(firstParentId)?.paymentReference : null,
         groupId: member.groupId,
         groupName: group?.name || 'Neznana skupina',
         status: member.status,
@@ -26,6 +26,8 @@ export interface Parent {
   lastName: string
   email: string
   phone: string
+  /** Sklic SI12 ali RF, s katerim starš plačuje */
+  paymentReference?: string | null
 }
 
 /**
@@ -69,6 +71,8 @@ export interface Cost {
   recurringEndDate?: string | null
   recurringDayOfMonth?: number | null
   recurringTemplateId?: string | null
+  /** Sklic SI12 ali RF za plačilo samo tega stroška */
+  paymentReference?: string | null
 }
 
 /**
@@ -117,12 +121,23 @@ export interface PaymentAllocation {
 export interface BankStatement {
   id: string
   fileName: string
-  fileType: 'pdf' | 'xml'
+  fileType: 'pdf' | 'xml' | 'csv' | 'mt940'
   importedAt: string
   status: 'processing' | 'completed' | 'failed'
   totalTransactions: number
   matchedTransactions: number
   unmatchedTransactions: number
+  /** Message the statement was imported from, e.g. camt.053.001.02 or MT940 */
+  messageType?: string | null
+  messageId?: string | null
+  accountIban?: string | null
+  /** Account balances from camt.053 and MT940 statements, for reconciliation */
+  openingBalance?: number | null
+  openingBalanceDate?: string | null
+  closingBalance?: number | null
+  closingBalanceDate?: string | null
+  /** SHA-256 of the imported file; the same file is not imported twice */
+  fileSha256?: string | null
 }
 
 /**
@@ -161,6 +176,8 @@ export interface MemberObligation {
   memberName: string
   parentId: string
   parentName: string
+  /** Sklic prvega starša, ki ga navajajo opomini */
+  parentReference?: string | null
   groupId: string
   groupName: string
   status: 'active' | 'inactive' | 'archived'
@@ -223,7 +240,7 @@ export interface FinancialReport {
 
 export interface AuditLogEntry {
   id: string
-  action: 'bulk_billing' | 'import_confirmed' | 'cost_cancelled' | 'cost_created' | 'cost_updated' | 'payment_created'
+  action: 'bulk_billing' | 'import_confirmed' | 'cost_cancelled' | 'cost_created' | 'cost_updated' | 'payment_created' | 'database_merged'
   description: string
   userId: string
   userName: string
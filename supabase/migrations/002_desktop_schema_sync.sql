@@ -0,0 +1,208 @@
+-- =============================================================================
+-- tskPay: bring the Postgres schema in line with the desktop SQLite schema (v9)
+-- =============================================================================
+--
+-- The desktop app generates prefixed text ids ('par-…', 'mem-…'), so every id and
+-- reference column becomes TEXT. New ids still default to a random UUID string.
+-- After this migration the SQL/CSV export of the desktop app loads without edits.
+
+BEGIN;
+
+-- The view depends on the id columns whose type changes below
+DROP VIEW IF EXISTS member_obligations;
+
+-- =============================================================================
+-- Drop foreign keys so the id columns can change type
+-- =============================================================================
+
+ALTER TABLE groups              DROP CONSTRAINT IF EXISTS groups_coach_id_fkey;
+ALTER TABLE members             DROP CONSTRAINT IF EXISTS members_parent_id_fkey;
+ALTER TABLE members             DROP CONSTRAINT IF EXISTS members_group_id_fkey;
+ALTER TABLE costs               DROP CONSTRAINT IF EXISTS costs_member_id_fkey;
+ALTER TABLE costs               DROP CONSTRAINT IF EXISTS costs_cost_type_id_fkey;
+ALTER TABLE payments            DROP CONSTRAINT IF EXISTS payments_parent_id_fkey;
+ALTER TABLE payments            DROP CONSTRAINT IF EXISTS payments_bank_transaction_id_fkey;
+ALTER TABLE bank_transactions   DROP CONSTRAINT IF EXISTS bank_transactions_bank_statement_id_fkey;
+ALTER TABLE bank_transactions   DROP CONSTRAINT IF EXISTS bank_transactions_matched_parent_id_fkey;
+ALTER TABLE payment_allocations DROP CONSTRAINT IF EXISTS payment_allocations_payment_id_fkey;
+ALTER TABLE payment_allocations DROP CONSTRAINT IF EXISTS payment_allocations_cost_id_fkey;
+
+-- =============================================================================
+-- UUID -> TEXT
+-- =============================================================================
+
+ALTER TABLE parents             ALTER COLUMN id TYPE TEXT, ALTER COLUMN id SET DEFAULT gen_random_uuid()::text;
+ALTER TABLE coaches             ALTER COLUMN id TYPE TEXT, ALTER COLUMN id SET DEFAULT gen_random_uuid()::text;
+ALTER TABLE groups              ALTER COLUMN id TYPE TEXT, ALTER COLUMN id SET DEFAULT gen_random_uuid()::text;
+ALTER TABLE members             ALTER COLUMN id TYPE TEXT, ALTER COLUMN id SET DEFAULT gen_random_uuid()::text;
+ALTER TABLE cost_types          ALTER COLUMN id TYPE TEXT, ALTER COLUMN id SET DEFAULT gen_random_uuid()::text;
+ALTER TABLE costs               ALTER COLUMN id TYPE TEXT, ALTER COLUMN id SET DEFAULT gen_random_uuid()::text;
+ALTER TABLE bank_statements     ALTER COLUMN id TYPE TEXT, ALTER COLUMN id SET DEFAULT gen_random_uuid()::text;
+ALTER TABLE bank_transactions   ALTER COLUMN id TYPE TEXT, ALTER COLUMN id SET DEFAULT gen_random_uuid()::text;
+ALTER TABLE payments            ALTER COLUMN id TYPE TEXT, ALTER COLUMN id SET DEFAULT gen_random_uuid()::text;
+ALTER TABLE payment_allocations ALTER COLUMN id TYPE TEXT, ALTER COLUMN id SET DEFAULT gen_random_uuid()::text;
+ALTER TABLE audit_log           ALTER COLUMN id TYPE TEXT, ALTER COLUMN id SET DEFAULT gen_random_uuid()::text;
+
+ALTER TABLE groups              ALTER COLUMN coach_id TYPE TEXT;
+ALTER TABLE members             ALTER COLUMN parent_id TYPE TEXT, ALTER COLUMN group_id TYPE TEXT;
+ALTER TABLE costs               ALTER COLUMN member_id TYPE TEXT, ALTER COLUMN cost_type_id TYPE TEXT;
+ALTER TABLE payments            ALTER COLUMN parent_id TYPE TEXT, ALTER COLUMN bank_transaction_id TYPE TEXT;
+ALTER TABLE bank_transactions   ALTER COLUMN bank_statement_id TYPE TEXT, ALTER COLUMN matched_parent_id TYPE TEXT;
+ALTER TABLE payment_allocations ALTER COLUMN payment_id TYPE TEXT, ALTER COLUMN cost_id TYPE TEXT;
+ALTER TABLE audit_log           ALTER COLUMN user_id TYPE TEXT;
+
+-- =============================================================================
+-- Columns and constraints added on the desktop since 001
+-- =============================================================================
+
+-- Recurring costs
+ALTER TABLE costs ADD COLUMN IF NOT EXISTS is_recurring BOOLEAN DEFAULT FALSE;
+ALTER TABLE costs ADD COLUMN IF NOT EXISTS recurring_period TEXT CHECK (recurring_period IN ('monthly', 'yearly', 'weekly', 'quarterly'));
+ALTER TABLE costs ADD COLUMN IF NOT EXISTS recurring_start_date DATE;
+ALTER TABLE costs ADD COLUMN IF NOT EXISTS recurring_end_date DATE;
+ALTER TABLE costs ADD COLUMN IF NOT EXISTS recurring_day_of_month INTEGER;
+ALTER TABLE costs ADD COLUMN IF NOT EXISTS recurring_template_id TEXT;
+
+-- Payment status; unmatched payments have no parent yet
+ALTER TABLE payments ALTER COLUMN parent_id DROP NOT NULL;
+ALTER TABLE payments ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'allocated', 'confirmed'));
+ALTER TABLE payments ADD COLUMN IF NOT EXISTS payer_name TEXT;
+
+-- Audit actions
+ALTER TABLE audit_log DROP CONSTRAINT IF EXISTS audit_log_action_check;
+ALTER TABLE audit_log ADD CONSTRAINT audit_log_action_check
+  CHECK (action IN ('bulk_billing', 'import_confirmed', 'cost_cancelled', 'cost_created', 'cost_updated', 'payment_created', 'database_merged'));
+
+-- =============================================================================
+-- Foreign keys
+-- =============================================================================
+
+ALTER TABLE groups              ADD CONSTRAINT groups_coach_id_fkey FOREIGN KEY (coach_id) REFERENCES coaches(id) ON DELETE RESTRICT;
+ALTER TABLE members             ADD CONSTRAINT members_parent_id_fkey FOREIGN KEY (parent_id) REFERENCES parents(id) ON DELETE RESTRICT;
+ALTER TABLE members             ADD CONSTRAINT members_group_id_fkey FOREIGN KEY (group_id) REFERENCES groups(id) ON DELETE RESTRICT;
+ALTER TABLE costs               ADD CONSTRAINT costs_member_id_fkey FOREIGN KEY (member_id) REFERENCES members(id) ON DELETE RESTRICT;
+ALTER TABLE costs               ADD CONSTRAINT costs_cost_type_id_fkey FOREIGN KEY (cost_type_id) REFERENCES cost_types(id) ON DELETE RESTRICT;
+ALTER TABLE costs               ADD CONSTRAINT costs_recurring_template_id_fkey FOREIGN KEY (recurring_template_id) REFERENCES costs(id) ON DELETE SET NULL;
+ALTER TABLE payments            ADD CONSTRAINT payments_parent_id_fkey FOREIGN KEY (parent_id) REFERENCES parents(id) ON DELETE RESTRICT;
+ALTER TABLE payments            ADD CONSTRAINT payments_bank_transaction_id_fkey FOREIGN KEY (bank_transaction_id) REFERENCES bank_transactions(id) ON DELETE SET NULL;
+ALTER TABLE bank_transactions   ADD CONSTRAINT bank_transactions_bank_statement_id_fkey FOREIGN KEY (bank_statement_id) REFERENCES bank_statements(id) ON DELETE CASCADE;
+ALTER TABLE bank_transactions   ADD CONSTRAINT bank_transactions_matched_parent_id_fkey FOREIGN KEY (matched_parent_id) REFERENCES parents(id) ON DELETE SET NULL;
+ALTER TABLE payment_allocations ADD CONSTRAINT payment_allocations_payment_id_fkey FOREIGN KEY (payment_id) REFERENCES payments(id) ON DELETE CASCADE;
+ALTER TABLE payment_allocations ADD CONSTRAINT payment_allocations_cost_id_fkey FOREIGN KEY (cost_id) REFERENCES costs(id) ON DELETE RESTRICT;
+
+CREATE INDEX IF NOT EXISTS idx_payments_status ON payments(status);
+
+-- =============================================================================
+-- New tables
+-- =============================================================================
+
+-- Member-Parent Relationship (Many-to-Many)
+CREATE TABLE IF NOT EXISTS member_parents (
+  id TEXT PRIMARY KEY DEFAULT gen_random_uuid()::text,
+  member_id TEXT NOT NULL REFERENCES members(id) ON DELETE CASCADE,
+  parent_id TEXT NOT NULL REFERENCES parents(id) ON DELETE CASCADE,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  UNIQUE(member_id, parent_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_member_parents_member ON member_parents(member_id);
+CREATE INDEX IF NOT EXISTS idx_member_parents_parent ON member_parents(parent_id);
+
+-- Attachment Contents (one row per distinct file, addressed by SHA-256)
+CREATE TABLE IF NOT EXISTS attachment_blobs (
+  sha256 TEXT PRIMARY KEY,
+  data BYTEA NOT NULL,
+  size INTEGER NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Attachments (Priloge) linked to any entity
+CREATE TABLE IF NOT EXISTS attachments (
+  id TEXT PRIMARY KEY DEFAULT gen_random_uuid()::text,
+  entity_type TEXT NOT NULL,
+  entity_id TEXT NOT NULL,
+  kind TEXT NOT NULL CHECK (kind IN ('bank_statement', 'receipt', 'membership_form', 'medical_certificate', 'other')) DEFAULT 'other',
+  file_name TEXT NOT NULL,
+  mime_type TEXT,
+  size INTEGER NOT NULL,
+  sha256 TEXT NOT NULL REFERENCES attachment_blobs(sha256) ON DELETE RESTRICT,
+  notes TEXT DEFAULT '',
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_attachments_entity ON attachments(entity_type, entity_id);
+CREATE INDEX IF NOT EXISTS idx_attachments_sha256 ON attachments(sha256);
+
+-- =============================================================================
+-- Triggers
+-- =============================================================================
+
+-- 001 is missing the spaces around this statement, so it never got created
+DROP TRIGGER IF EXISTS update_bank_statements_updated_at ON bank_statements;
+CREATE TRIGGER update_bank_statements_updated_at BEFORE UPDATE ON bank_statements FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+DROP TRIGGER IF EXISTS update_attachments_updated_at ON attachments;
+CREATE TRIGGER update_attachments_updated_at BEFORE UPDATE ON attachments FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- =============================================================================
+-- Row Level Security (RLS)
+-- =============================================================================
+
+ALTER TABLE member_parents   ENABLE ROW LEVEL SECURITY;
+ALTER TABLE attachment_blobs ENABLE ROW LEVEL SECURITY;
+ALTER TABLE attachments      ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY "Enable all for authenticated users" ON member_parents
+  FOR ALL TO authenticated USING (true) WITH CHECK (true);
+
+CREATE POLICY "Enable all for authenticated users" ON attachment_blobs
+  FOR ALL TO authenticated USING (true) WITH CHECK (true);
+
+CREATE POLICY "Enable all for authenticated users" ON attachments
+  FOR ALL TO authenticated USING (true) WITH CHECK (true);
+
+-- =============================================================================
+-- Seed Data
+-- =============================================================================
+
+-- Use the fixed ids of the desktop seed rows, so exported costs keep pointing at them
+UPDATE cost_types SET id = 'ct-vadnine'       WHERE name = 'Vadnine'             AND NOT EXISTS (SELECT 1 FROM costs WHERE costs.cost_type_id = cost_types.id);
+UPDATE cost_types SET id = 'ct-oprema'        WHERE name = 'Oprema'              AND NOT EXISTS (SELECT 1 FROM costs WHERE costs.cost_type_id = cost_types.id);
+UPDATE cost_types SET id = 'ct-clanarine'     WHERE name = 'Članarine'           AND NOT EXISTS (SELECT 1 FROM costs WHERE costs.cost_type_id = cost_types.id);
+UPDATE cost_types SET id = 'ct-priprave'      WHERE name = 'Priprave'            AND NOT EXISTS (SELECT 1 FROM costs WHERE costs.cost_type_id = cost_types.id);
+UPDATE cost_types SET id = 'ct-modre-kartice' WHERE name = 'Modre kartice'       AND NOT EXISTS (SELECT 1 FROM costs WHERE costs.cost_type_id = cost_types.id);
+UPDATE cost_types SET id = 'ct-zdravniski'    WHERE name = 'Zdravniški pregledi' AND NOT EXISTS (SELECT 1 FROM costs WHERE costs.cost_type_id = cost_types.id);
+
+-- Special coach and group for club members who pay for themselves (Samo člani)
+INSERT INTO coaches (id, name) VALUES ('coa-samo-clani', 'Članstvo') ON CONFLICT (id) DO NOTHING;
+INSERT INTO groups (id, name, coach_id) VALUES ('grp-samo-clani', 'Samo člani', 'coa-samo-clani') ON CONFLICT (id) DO NOTHING;
+
+-- =============================================================================
+-- Views
+-- =============================================================================
+
+CREATE OR REPLACE VIEW member_obligations AS
+SELECT
+  m.id AS member_id,
+  m.first_name || ' ' || m.last_name AS member_name,
+  p.id AS parent_id,
+  p.first_name || ' ' || p.last_name AS parent_name,
+  g.id AS group_id,
+  g.name AS group_name,
+  m.status,
+  COALESCE(SUM(CASE WHEN c.status = 'pending' THEN c.amount ELSE 0 END), 0) AS balance,
+  COUNT(CASE WHEN c.status = 'pending' THEN 1 END) AS open_items_count,
+  COUNT(CASE WHEN c.status = 'pending' AND c.due_date < CURRENT_DATE THEN 1 END) AS overdue_items_count,
+  COALESCE(SUM(CASE WHEN c.status = 'pending' AND c.due_date < CURRENT_DATE THEN c.amount ELSE 0 END), 0) AS overdue_amount
+FROM members m
+JOIN parents p ON m.parent_id = p.id
+JOIN groups g  ON m.group_id  = g.id
+LEFT JOIN costs c ON c.member_id = m.id
+GROUP BY
+  m.id, m.first_name, m.last_name,
+  p.id, p.first_name, p.last_name,
+  g.id, g.name,
+  m.status;
+
+COMMIT;
@@ -0,0 +1,83 @@
+-- =============================================================================
+-- tskPay: server side of the desktop sync engine
+-- =============================================================================
+--
+-- Desktop clients push and pull rows through PostgREST. Pulls are incremental on
+-- `synced_at`, a server clock stamped on every insert and update, so rows pushed
+-- late by a client that was offline for a while are still picked up by the others.
+-- Deletes are recorded in `sync_tombstones` so they reach the other clients too.
+
+BEGIN;
+
+-- =============================================================================
+-- updated_at: keep the value a client sends
+-- =============================================================================
+
+-- Clients resolve conflicts by comparing updated_at, so a pushed row must keep the
+-- time of the edit on the client. Only updates that leave it unchanged are stamped.
+CREATE OR REPLACE FUNCTION update_updated_at_column()
+RETURNS TRIGGER AS $$
+BEGIN
+  IF NEW.updated_at IS NOT DISTINCT FROM OLD.updated_at THEN
+    NEW.updated_at = NOW();
+  END IF;
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- =============================================================================
+-- synced_at: server time of the last write
+-- =============================================================================
+
+CREATE OR REPLACE FUNCTION set_synced_at()
+RETURNS TRIGGER AS $$
+BEGIN
+  NEW.synced_at = clock_timestamp();
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DO $$
+DECLARE
+  synced_table TEXT;
+BEGIN
+  FOREACH synced_table IN ARRAY ARRAY[
+    'parents', 'coaches', 'groups', 'members', 'member_parents', 'cost_types', 'costs',
+    'bank_statements', 'bank_transactions', 'payments', 'payment_allocations', 'audit_log'
+  ]
+  LOOP
+    EXECUTE format('ALTER TABLE %I ADD COLUMN IF NOT EXISTS synced_at TIMESTAMPTZ NOT NULL DEFAULT clock_timestamp()', synced_table);
+    EXECUTE format('CREATE INDEX IF NOT EXISTS %I ON %I(synced_at)', 'idx_' || synced_table || '_synced_at', synced_table);
+    EXECUTE format('DROP TRIGGER IF EXISTS %I ON %I', 'set_' || synced_table || '_synced_at', synced_table);
+    EXECUTE format(
+      'CREATE TRIGGER %I BEFORE INSERT OR UPDATE ON %I FOR EACH ROW EXECUTE FUNCTION set_synced_at()',
+      'set_' || synced_table || '_synced_at',
+      synced_table
+    );
+  END LOOP;
+END;
+$$;
+
+-- =============================================================================
+-- Deleted rows
+-- =============================================================================
+
+CREATE TABLE IF NOT EXISTS sync_tombstones (
+  id BIGSERIAL PRIMARY KEY,
+  table_name TEXT NOT NULL,
+  row_id TEXT NOT NULL,
+  deleted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  synced_at TIMESTAMPTZ NOT NULL DEFAULT clock_timestamp()
+);
+
+CREATE INDEX IF NOT EXISTS idx_sync_tombstones_synced_at ON sync_tombstones(synced_at);
+
+ALTER TABLE sync_tombstones ENABLE ROW LEVEL SECURITY;
+
+DROP POLICY IF EXISTS "Enable all for authenticated users" ON sync_tombstones;
+CREATE POLICY "Enable all for authenticated users" ON sync_tombstones
+  FOR ALL TO authenticated USING (true) WITH CHECK (true);
+
+GRANT USAGE, SELECT ON SEQUENCE sync_tombstones_id_seq TO authenticated;
+
+COMMIT;
@@ -0,0 +1,19 @@
+-- =============================================================================
+-- tskPay: message type and balances of imported bank statements
+-- =============================================================================
+--
+-- The desktop importer reads camt.052 reports, camt.053 statements and camt.054
+-- notifications. It records which message a statement came from and, for
+-- camt.053, the opening and closing balance used to reconcile the account.
+
+BEGIN;
+
+ALTER TABLE bank_statements ADD COLUMN IF NOT EXISTS message_type TEXT;
+ALTER TABLE bank_statements ADD COLUMN IF NOT EXISTS message_id TEXT;
+ALTER TABLE bank_statements ADD COLUMN IF NOT EXISTS account_iban TEXT;
+ALTER TABLE bank_statements ADD COLUMN IF NOT EXISTS opening_balance DECIMAL(12, 2);
+ALTER TABLE bank_statements ADD COLUMN IF NOT EXISTS opening_balance_date DATE;
+ALTER TABLE bank_statements ADD COLUMN IF NOT EXISTS closing_balance DECIMAL(12, 2);
+ALTER TABLE bank_statements ADD COLUMN IF NOT EXISTS closing_balance_date DATE;
+
+COMMIT;
@@ -0,0 +1,39 @@
+-- =============================================================================
+-- tskPay: CSV bank exports and their column-mapping profiles
+-- =============================================================================
+--
+-- The desktop importer also reads CSV exports of online banking, stored with
+-- file_type 'csv'. How each bank's export is read is saved as a named profile.
+
+BEGIN;
+
+ALTER TABLE bank_statements DROP CONSTRAINT IF EXISTS bank_statements_file_type_check;
+ALTER TABLE bank_statements ADD CONSTRAINT bank_statements_file_type_check
+  CHECK (file_type IN ('pdf', 'xml', 'csv'));
+
+-- CSV Import Profiles (column mappings of online banking CSV exports)
+CREATE TABLE IF NOT EXISTS csv_import_profiles (
+  id TEXT PRIMARY KEY DEFAULT gen_random_uuid()::text,
+  name TEXT NOT NULL UNIQUE,
+  bank TEXT,
+  account_iban TEXT NOT NULL DEFAULT '',
+  delimiter TEXT NOT NULL DEFAULT ';',
+  encoding TEXT NOT NULL DEFAULT 'windows-1250',
+  decimal_comma BOOLEAN NOT NULL DEFAULT TRUE,
+  date_format TEXT NOT NULL DEFAULT '%d.%m.%Y',
+  skip_rows INTEGER NOT NULL DEFAULT 0,
+  has_header BOOLEAN NOT NULL DEFAULT TRUE,
+  columns JSONB NOT NULL DEFAULT '{}',
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+DROP TRIGGER IF EXISTS update_csv_import_profiles_updated_at ON csv_import_profiles;
+CREATE TRIGGER update_csv_import_profiles_updated_at BEFORE UPDATE ON csv_import_profiles FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+ALTER TABLE csv_import_profiles ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY "Enable all for authenticated users" ON csv_import_profiles
+  FOR ALL TO authenticated USING (true) WITH CHECK (true);
+
+COMMIT;
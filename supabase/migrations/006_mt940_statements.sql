@@ -0,0 +1,13 @@
+-- =============================================================================
+-- tskPay: MT940 bank statements
+-- =============================================================================
+--
+-- The desktop importer also reads SWIFT MT940 statements, stored with file_type 'mt940'.
+
+BEGIN;
+
+ALTER TABLE bank_statements DROP CONSTRAINT IF EXISTS bank_statements_file_type_check;
+ALTER TABLE bank_statements ADD CONSTRAINT bank_statements_file_type_check
+  CHECK (file_type IN ('pdf', 'xml', 'csv', 'mt940'));
+
+COMMIT;
@@ -0,0 +1,16 @@
+-- =============================================================================
+-- tskPay: hashes of imported bank statement files
+-- =============================================================================
+--
+-- The desktop importer stores the SHA-256 of every imported file and refuses to
+-- import the same file again. Transactions already stored by an overlapping
+-- report are skipped by bank reference, so that lookup gets an index too.
+
+BEGIN;
+
+ALTER TABLE bank_statements ADD COLUMN IF NOT EXISTS file_sha256 TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_bank_statements_file_sha256 ON bank_statements(file_sha256) WHERE file_sha256 IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_bank_transactions_bank_reference ON bank_transactions(bank_reference) WHERE bank_reference IS NOT NULL;
+
+COMMIT;
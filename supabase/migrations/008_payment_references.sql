@@ -0,0 +1,17 @@
+-- =============================================================================
+-- tskPay: structured payment references
+-- =============================================================================
+--
+-- Every parent, and optionally every cost, gets a "sklic" in the SI12 model
+-- (mod-11 check digit) or an ISO 11649 RF reference. Incoming payments that
+-- quote it are matched back to the parent or cost.
+
+BEGIN;
+
+ALTER TABLE parents ADD COLUMN IF NOT EXISTS payment_reference TEXT;
+ALTER TABLE costs ADD COLUMN IF NOT EXISTS payment_reference TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_parents_payment_reference ON parents(payment_reference) WHERE payment_reference IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_costs_payment_reference ON costs(payment_reference) WHERE payment_reference IS NOT NULL;
+
+COMMIT;